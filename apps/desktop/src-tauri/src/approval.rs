@@ -0,0 +1,735 @@
+// ======================================================================
+// SUPERVISOR APPROVAL
+// Otorisasi supervisor (PIN/OTP) untuk void, refund dan aksi sensitif
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+use base64::{
+    engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD as BASE64_URL},
+    Engine,
+};
+use hmac::{Hmac, Mac};
+use log::{info, warn};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha1::Sha1;
+use sha2::Sha256;
+use std::{collections::HashMap, path::PathBuf};
+
+use crate::{secrets::SecretStore, utils};
+
+/// Supervisor names and PIN hashes, in the app data directory; TOTP keys live in the secrets store
+pub const SUPERVISORS_FILE: &str = "supervisors.json";
+
+/// Failed attempts and lockouts, next to the supervisors file so a restart does not reset them
+pub const ATTEMPTS_FILE: &str = "supervisor_attempts.json";
+
+/// `IpcGuard::derive_key` purpose for the approval signing key; the web app derives the same
+pub const APPROVAL_KEY_PURPOSE: &str = "supervisor-approval";
+
+/// Long enough to submit the void, short enough that a token is useless later
+pub const APPROVAL_TTL_SECONDS: i64 = 120;
+
+/// Consecutive failures before a supervisor is locked out
+const MAX_FAILURES: u32 = 5;
+
+/// Failures across all supervisor ids before the whole till is locked out
+const MAX_TERMINAL_FAILURES: u32 = 15;
+
+const LOCKOUT_SECONDS: i64 = 5 * 60;
+
+const TERMINAL_KEY: &str = "*";
+
+/// Secrets store account prefix for a supervisor's TOTP key
+const TOTP_ACCOUNT_PREFIX: &str = "supervisor-totp:";
+
+const TOTP_STEP_SECONDS: i64 = 30;
+
+const TOTP_DIGITS: u32 = 6;
+
+/// Steps either side of now that are still accepted, for clock drift
+const TOTP_SKEW: i64 = 1;
+
+/// Largest approval context, and largest result detail `record_audit_event` accepts
+pub const MAX_CONTEXT_BYTES: usize = 2048;
+
+/// How long after the token expires the void or refund it approved can still be recorded
+const RECORD_GRACE_SECONDS: i64 = 5 * 60;
+
+type HmacSha1 = Hmac<Sha1>;
+type HmacSha256 = Hmac<Sha256>;
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalAction {
+    Void,
+    Refund,
+    /// Checked by the shell itself, never sent to the web API
+    LeaveKiosk,
+    Quit,
+    Unlock,
+}
+
+/// What the supervisor typed; never logged
+#[derive(Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum ApprovalCredential {
+    Pin { supervisor_id: String, pin: String },
+    Otp { supervisor_id: String, code: String },
+}
+
+impl ApprovalCredential {
+    pub fn supervisor_id(&self) -> &str {
+        match self {
+            Self::Pin { supervisor_id, .. } | Self::Otp { supervisor_id, .. } => supervisor_id,
+        }
+    }
+
+    pub fn method(&self) -> &'static str {
+        match self {
+            Self::Pin { .. } => "pin",
+            Self::Otp { .. } => "otp",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SupervisorRecord {
+    id: String,
+    name: String,
+    /// Argon2id PHC string
+    pin_hash: Option<String>,
+    /// The TOTP key itself is in the secrets store under `supervisor-totp:<id>`
+    #[serde(default)]
+    has_totp: bool,
+    /// Raw TOTP key, base64, as older versions stored it; moved to the secrets store on load
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    totp_secret: Option<String>,
+    /// Last TOTP step accepted, so a code cannot be replayed within its window
+    #[serde(default)]
+    last_totp_step: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SupervisorInfo {
+    pub id: String,
+    pub name: String,
+    pub has_pin: bool,
+    pub has_totp: bool,
+}
+
+impl From<&SupervisorRecord> for SupervisorInfo {
+    fn from(record: &SupervisorRecord) -> Self {
+        Self {
+            id: record.id.clone(),
+            name: record.name.clone(),
+            has_pin: record.pin_hash.is_some(),
+            has_totp: record.has_totp,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TotpEnrollment {
+    pub supervisor_id: String,
+    /// Base32, for typing into an authenticator app
+    pub secret: String,
+    /// `otpauth://` URI, for a QR code
+    pub uri: String,
+}
+
+/// Signed into the approval token the web API checks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalClaims {
+    /// Single use; the web API records it in `idempotency_keys`
+    pub jti: String,
+    pub action: ApprovalAction,
+    /// What was approved, e.g. `{ "transaction_id": "trx_001" }`
+    pub context: Value,
+    pub supervisor_id: String,
+    pub supervisor_name: String,
+    /// Session user of the window that asked
+    pub requested_by: Option<String>,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Approval {
+    /// `base64url(claims).base64url(hmac_sha256(key, base64url(claims)))`
+    pub token: String,
+    pub claims: ApprovalClaims,
+    pub expires_at: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Attempts {
+    failures: u32,
+    /// Unix seconds
+    locked_until: Option<i64>,
+}
+
+// ======================================================================
+// SUPERVISOR AUTHORIZER
+// ======================================================================
+
+pub struct SupervisorAuthorizer {
+    path: PathBuf,
+    attempts_path: PathBuf,
+    key: [u8; 32],
+    secrets: &'static SecretStore,
+    /// Serialises read-modify-write of the supervisors file
+    lock: parking_lot::Mutex<()>,
+    /// Attempts per supervisor id, plus the whole till under `*`; mirrors the attempts file
+    attempts: parking_lot::Mutex<HashMap<String, Attempts>>,
+    /// Approval ids already recorded as done, until they are past the grace period
+    recorded: parking_lot::Mutex<HashMap<String, i64>>,
+}
+
+impl SupervisorAuthorizer {
+    pub fn open_default(key: [u8; 32], secrets: &'static SecretStore) -> Self {
+        Self::open(utils::get_app_data_dir().join(SUPERVISORS_FILE), key, secrets)
+    }
+
+    pub fn open(path: PathBuf, key: [u8; 32], secrets: &'static SecretStore) -> Self {
+        let attempts_path = path.with_file_name(ATTEMPTS_FILE);
+        let attempts = match std::fs::read(&attempts_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                // Fail closed: a mangled file locks the till out rather than forgetting the count
+                warn!("Invalid {:?} ({}); locking supervisor approval", attempts_path, e);
+                HashMap::from([(
+                    TERMINAL_KEY.to_string(),
+                    Attempts {
+                        failures: MAX_TERMINAL_FAILURES,
+                        locked_until: Some(chrono::Utc::now().timestamp() + LOCKOUT_SECONDS),
+                    },
+                )])
+            }),
+            Err(_) => HashMap::new(),
+        };
+
+        Self {
+            path,
+            attempts_path,
+            key,
+            secrets,
+            lock: parking_lot::Mutex::new(()),
+            attempts: parking_lot::Mutex::new(attempts),
+            recorded: parking_lot::Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn list(&self) -> Result<Vec<SupervisorInfo>> {
+        let _guard = self.lock.lock();
+        Ok(self.load()?.iter().map(SupervisorInfo::from).collect())
+    }
+
+    /// Add a supervisor or replace their PIN
+    pub fn set_pin(&self, id: &str, name: &str, pin: &str) -> Result<SupervisorInfo> {
+        let id = id.trim();
+        if id.is_empty() || id == TERMINAL_KEY {
+            return Err(anyhow!("Supervisor id is required"));
+        }
+        validate_pin(pin)?;
+
+        let salt = SaltString::generate(&mut OsRng);
+        let pin_hash = pin_hasher()
+            .hash_password(pin.as_bytes(), &salt)
+            .map_err(|e| anyhow!("Failed to hash PIN: {}", e))?
+            .to_string();
+
+        let _guard = self.lock.lock();
+        let mut records = self.load()?;
+        let index = match records.iter().position(|record| record.id == id) {
+            Some(index) => index,
+            None => {
+                records.push(SupervisorRecord {
+                    id: id.to_string(),
+                    name: String::new(),
+                    pin_hash: None,
+                    has_totp: false,
+                    totp_secret: None,
+                    last_totp_step: 0,
+                });
+                records.len() - 1
+            }
+        };
+        let record = &mut records[index];
+        if !name.trim().is_empty() {
+            record.name = name.trim().to_string();
+        }
+        record.pin_hash = Some(pin_hash);
+
+        let info = SupervisorInfo::from(&*record);
+        self.save(&records)?;
+        self.clear_attempts(&[id]);
+        info!("Supervisor PIN set for {}", id);
+        Ok(info)
+    }
+
+    /// New TOTP key for an existing supervisor; replaces any earlier one
+    pub fn enroll_totp(&self, id: &str) -> Result<TotpEnrollment> {
+        let mut secret = [0u8; 20];
+        OsRng.fill_bytes(&mut secret);
+
+        let _guard = self.lock.lock();
+        let mut records = self.load()?;
+        let record = records
+            .iter_mut()
+            .find(|record| record.id == id)
+            .ok_or_else(|| anyhow!("Unknown supervisor {}", id))?;
+        self.secrets.set_internal(&totp_account(id), &BASE64.encode(secret))?;
+        record.has_totp = true;
+        record.last_totp_step = 0;
+
+        let label = if record.name.is_empty() { record.id.clone() } else { record.name.clone() };
+        self.save(&records)?;
+        info!("TOTP enrolled for supervisor {}", id);
+
+        let secret = base32_encode(&secret);
+        let uri = format!(
+            "otpauth://totp/POS%20Kasir:{}?secret={}&issuer=POS%20Kasir&digits={}&period={}",
+            percent_encode(&label),
+            secret,
+            TOTP_DIGITS,
+            TOTP_STEP_SECONDS
+        );
+        Ok(TotpEnrollment {
+            supervisor_id: id.to_string(),
+            secret,
+            uri,
+        })
+    }
+
+    pub fn remove(&self, id: &str) -> Result<()> {
+        let _guard = self.lock.lock();
+        let mut records = self.load()?;
+        let before = records.len();
+        records.retain(|record| record.id != id);
+        if records.len() == before {
+            return Err(anyhow!("Unknown supervisor {}", id));
+        }
+        self.save(&records)?;
+        self.secrets.delete_internal(&totp_account(id))?;
+        self.clear_attempts(&[id]);
+        Ok(())
+    }
+
+    /// Check the credential and sign an approval for `action` on `context`.
+    /// Runs Argon2, so call it off the async runtime.
+    pub fn approve(
+        &self,
+        action: ApprovalAction,
+        context: Value,
+        credential: &ApprovalCredential,
+        requested_by: Option<String>,
+    ) -> Result<Approval> {
+        if !context.is_object() {
+            return Err(anyhow!("Approval context must be an object"));
+        }
+        if serde_json::to_vec(&context)?.len() > MAX_CONTEXT_BYTES {
+            return Err(anyhow!("Approval context is larger than {} bytes", MAX_CONTEXT_BYTES));
+        }
+        // The web API caps the refund at the approved amount, so an open-ended approval is refused
+        if action == ApprovalAction::Refund && !context["amount"].as_f64().is_some_and(|amount| amount >= 0.0) {
+            return Err(anyhow!("A refund approval needs the approved amount in its context"));
+        }
+
+        let supervisor_id = credential.supervisor_id().trim();
+        // Counted before verifying, so parallel guesses cannot all slip in under the limit
+        self.reserve_attempt(supervisor_id)?;
+
+        let supervisor_name = self.verify_credential(supervisor_id, credential)?;
+        self.clear_attempts(&[supervisor_id, TERMINAL_KEY]);
+
+        let now = chrono::Utc::now().timestamp();
+        let claims = ApprovalClaims {
+            jti: uuid::Uuid::new_v4().to_string(),
+            action,
+            context,
+            supervisor_id: supervisor_id.to_string(),
+            supervisor_name,
+            requested_by,
+            iat: now,
+            exp: now + APPROVAL_TTL_SECONDS,
+        };
+        info!("Supervisor {} approved {:?} ({})", claims.supervisor_id, claims.action, claims.jti);
+
+        Ok(Approval {
+            token: self.sign(&claims)?,
+            expires_at: chrono::DateTime::from_timestamp(claims.exp, 0)
+                .map(|at| at.to_rfc3339())
+                .unwrap_or_default(),
+            claims,
+        })
+    }
+
+    /// Claims of an approval token this shell signed for `action`, for recording the void or
+    /// refund it allowed. Each token is accepted once and only from the user who asked for it.
+    pub fn redeem_for_record(
+        &self,
+        token: &str,
+        action: ApprovalAction,
+        user_id: Option<&str>,
+    ) -> Result<ApprovalClaims> {
+        let (payload, signature) = token.split_once('.').ok_or_else(|| anyhow!("Malformed approval token"))?;
+        let signature = BASE64_URL.decode(signature).map_err(|_| anyhow!("Malformed approval token"))?;
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(payload.as_bytes());
+        mac.verify_slice(&signature).map_err(|_| anyhow!("Approval token signature is invalid"))?;
+
+        let payload = BASE64_URL.decode(payload).map_err(|_| anyhow!("Malformed approval token"))?;
+        let claims: ApprovalClaims = serde_json::from_slice(&payload)?;
+        if claims.action != action {
+            return Err(anyhow!("Approval was for {:?}, not {:?}", claims.action, action));
+        }
+        if claims.requested_by.as_deref() != user_id {
+            return Err(anyhow!("Approval was requested by another user"));
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        if claims.exp + RECORD_GRACE_SECONDS < now {
+            return Err(anyhow!("Approval has expired"));
+        }
+        let mut recorded = self.recorded.lock();
+        recorded.retain(|_, exp| *exp + RECORD_GRACE_SECONDS >= now);
+        if recorded.insert(claims.jti.clone(), claims.exp).is_some() {
+            return Err(anyhow!("Approval {} was already recorded", claims.jti));
+        }
+        Ok(claims)
+    }
+
+    // ======================================================================
+    // PRIVATE METHODS
+    // ======================================================================
+
+    /// Call with `lock` held; moves TOTP keys left in the file by older versions into the secrets store
+    fn load(&self) -> Result<Vec<SupervisorRecord>> {
+        let mut records: Vec<SupervisorRecord> = match std::fs::read(&self.path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| anyhow!("Invalid {:?}: {}", self.path, e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(anyhow!("Failed to read {:?}: {}", self.path, e)),
+        };
+
+        let mut migrated = false;
+        for record in records.iter_mut() {
+            if let Some(secret) = record.totp_secret.take() {
+                self.secrets.set_internal(&totp_account(&record.id), &secret)?;
+                record.has_totp = true;
+                migrated = true;
+            }
+        }
+        if migrated {
+            self.save(&records)?;
+            info!("Moved supervisor TOTP keys into the secrets store");
+        }
+        Ok(records)
+    }
+
+    fn save(&self, records: &[SupervisorRecord]) -> Result<()> {
+        utils::write_private(&self.path, &serde_json::to_vec_pretty(records)?).map_err(|e| anyhow!(e))
+    }
+
+    /// The supervisor's name when the PIN or code is right
+    fn verify_credential(&self, supervisor_id: &str, credential: &ApprovalCredential) -> Result<String> {
+        let _guard = self.lock.lock();
+        let mut records = self.load()?;
+        let Some(record) = records.iter_mut().find(|record| record.id == supervisor_id) else {
+            warn!("Approval attempt for unknown supervisor {:?}", supervisor_id);
+            return Err(anyhow!("Wrong supervisor or PIN"));
+        };
+
+        match credential {
+            ApprovalCredential::Pin { pin, .. } => {
+                let hash = record.pin_hash.as_deref().ok_or_else(|| anyhow!("Supervisor has no PIN set"))?;
+                let hash = PasswordHash::new(hash).map_err(|e| anyhow!("Corrupt PIN hash: {}", e))?;
+                pin_hasher()
+                    .verify_password(pin.as_bytes(), &hash)
+                    .map_err(|_| anyhow!("Wrong supervisor or PIN"))?;
+            }
+            ApprovalCredential::Otp { code, .. } => {
+                let secret = match record.has_totp {
+                    true => self.secrets.get_internal(&totp_account(&record.id))?,
+                    false => None,
+                }
+                .and_then(|secret| BASE64.decode(secret.expose()).ok())
+                .ok_or_else(|| anyhow!("Supervisor has no authenticator enrolled"))?;
+
+                let now_step = chrono::Utc::now().timestamp() / TOTP_STEP_SECONDS;
+                let step = (now_step - TOTP_SKEW..=now_step + TOTP_SKEW)
+                    .filter(|step| *step > record.last_totp_step)
+                    .find(|step| totp(&secret, *step) == code.trim())
+                    .ok_or_else(|| anyhow!("Wrong or already used code"))?;
+
+                record.last_totp_step = step;
+                let name = record.name.clone();
+                self.save(&records)?;
+                return Ok(name);
+            }
+        }
+
+        Ok(record.name.clone())
+    }
+
+    /// Refuse while locked out, otherwise count this attempt as failed and persist it before the credential is checked
+    fn reserve_attempt(&self, supervisor_id: &str) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        let mut attempts = self.attempts.lock();
+        for key in [TERMINAL_KEY, supervisor_id] {
+            if let Some(until) = attempts.get(key).and_then(|attempt| attempt.locked_until) {
+                if until > now {
+                    let minutes = (until - now) / 60 + 1;
+                    return Err(anyhow!("Too many wrong attempts; try again in {} minute(s)", minutes));
+                }
+            }
+        }
+
+        for (key, limit) in [(supervisor_id, MAX_FAILURES), (TERMINAL_KEY, MAX_TERMINAL_FAILURES)] {
+            let attempt = attempts.entry(key.to_string()).or_default();
+            if attempt.locked_until.is_some_and(|until| until <= now) {
+                *attempt = Attempts::default();
+            }
+            attempt.failures += 1;
+            if attempt.failures >= limit {
+                warn!("Supervisor approval locked for {:?} after {} attempts", key, attempt.failures);
+                attempt.locked_until = Some(now + LOCKOUT_SECONDS);
+            }
+        }
+        self.save_attempts(&attempts)
+    }
+
+    fn clear_attempts(&self, keys: &[&str]) {
+        let mut attempts = self.attempts.lock();
+        let before = attempts.len();
+        for key in keys {
+            attempts.remove(*key);
+        }
+        if attempts.len() != before {
+            if let Err(e) = self.save_attempts(&attempts) {
+                warn!("Failed to save supervisor attempts: {}", e);
+            }
+        }
+    }
+
+    fn save_attempts(&self, attempts: &HashMap<String, Attempts>) -> Result<()> {
+        utils::write_private(&self.attempts_path, &serde_json::to_vec(attempts)?).map_err(|e| anyhow!(e))
+    }
+
+    fn sign(&self, claims: &ApprovalClaims) -> Result<String> {
+        let payload = BASE64_URL.encode(serde_json::to_vec(claims)?);
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(payload.as_bytes());
+        Ok(format!("{}.{}", payload, BASE64_URL.encode(mac.finalize().into_bytes())))
+    }
+}
+
+// ======================================================================
+// HELPER FUNCTIONS
+// ======================================================================
+
+/// Argon2id at the OWASP minimum; the parameters are kept in each PHC string
+pub(crate) fn pin_hasher() -> Argon2<'static> {
+    let params = Params::new(19 * 1024, 2, 1, None).expect("valid Argon2 parameters");
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+}
+
+pub(crate) fn validate_pin(pin: &str) -> Result<()> {
+    if !(4..=12).contains(&pin.len()) || !pin.chars().all(|c| c.is_ascii_digit()) {
+        return Err(anyhow!("PIN must be 4 to 12 digits"));
+    }
+    let digits = pin.as_bytes();
+    let ascending = digits.windows(2).all(|pair| pair[1] == pair[0] + 1);
+    let repeated = digits.iter().all(|digit| *digit == digits[0]);
+    if ascending || repeated {
+        return Err(anyhow!("PIN is too easy to guess"));
+    }
+    Ok(())
+}
+
+fn totp_account(supervisor_id: &str) -> String {
+    format!("{}{}", TOTP_ACCOUNT_PREFIX, supervisor_id)
+}
+
+/// RFC 6238 TOTP (HMAC-SHA1, as authenticator apps expect) for one time step
+fn totp(secret: &[u8], step: i64) -> String {
+    let mut mac = HmacSha1::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&(step as u64).to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[19] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([digest[offset] & 0x7f, digest[offset + 1], digest[offset + 2], digest[offset + 3]]);
+    format!("{:0width$}", binary % 10u32.pow(TOTP_DIGITS), width = TOTP_DIGITS as usize)
+}
+
+/// RFC 4648 base32 without padding
+fn base32_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+    let mut out = String::new();
+    let (mut buffer, mut bits) = (0u32, 0u32);
+    for byte in bytes {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            out.push(ALPHABET[((buffer >> (bits - 5)) & 0x1f) as usize] as char);
+            bits -= 5;
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secrets::SecretBackend;
+    use serde_json::json;
+
+    fn authorizer() -> SupervisorAuthorizer {
+        let dir = std::env::temp_dir().join(format!("pos-approval-{}", uuid::Uuid::new_v4()));
+        reopen(&dir.join(SUPERVISORS_FILE))
+    }
+
+    fn reopen(path: &std::path::Path) -> SupervisorAuthorizer {
+        let dir = path.parent().unwrap().to_path_buf();
+        let secrets = Box::leak(Box::new(SecretStore::with_backend(SecretBackend::EncryptedFile, dir)));
+        SupervisorAuthorizer::open(path.to_path_buf(), [7u8; 32], secrets)
+    }
+
+    fn pin(supervisor_id: &str, pin: &str) -> ApprovalCredential {
+        ApprovalCredential::Pin {
+            supervisor_id: supervisor_id.to_string(),
+            pin: pin.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_totp_and_base32() {
+        // RFC 6238 appendix B, truncated to six digits
+        let secret = b"12345678901234567890";
+        assert_eq!(totp(secret, 59 / 30), "287082");
+        assert_eq!(totp(secret, 1111111109 / 30), "081804");
+        assert_eq!(totp(secret, 20000000000 / 30), "353130");
+
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(base32_encode(secret), "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+
+        assert!(validate_pin("4821").is_ok());
+        for weak in ["123", "1234", "0000", "12a4", "1234567890123"] {
+            assert!(validate_pin(weak).is_err(), "{}", weak);
+        }
+    }
+
+    #[test]
+    fn test_pin_approval_and_lockout() {
+        let authorizer = authorizer();
+        authorizer.set_pin("sup_001", "Manager Toko", "4821").unwrap();
+        assert!(authorizer.list().unwrap()[0].has_pin);
+
+        let context = json!({ "transaction_id": "trx_001", "amount": 11000 });
+        let approval = authorizer
+            .approve(ApprovalAction::Void, context.clone(), &pin("sup_001", "4821"), Some("usr_001".to_string()))
+            .unwrap();
+        assert_eq!(approval.claims.supervisor_name, "Manager Toko");
+        assert_eq!(approval.claims.exp - approval.claims.iat, APPROVAL_TTL_SECONDS);
+
+        // The signature covers the claims and verifies with the shared key only
+        let (payload, signature) = approval.token.split_once('.').unwrap();
+        let mut mac = HmacSha256::new_from_slice(&[7u8; 32]).unwrap();
+        mac.update(payload.as_bytes());
+        assert!(mac.verify_slice(&BASE64_URL.decode(signature).unwrap()).is_ok());
+        let claims: ApprovalClaims = serde_json::from_slice(&BASE64_URL.decode(payload).unwrap()).unwrap();
+        assert_eq!(claims.context, context);
+        assert_eq!(claims.requested_by.as_deref(), Some("usr_001"));
+
+        assert!(authorizer.approve(ApprovalAction::Void, json!("trx_001"), &pin("sup_001", "4821"), None).is_err());
+        let unbounded = json!({ "transaction_id": "trx_001" });
+        assert!(authorizer.approve(ApprovalAction::Refund, unbounded, &pin("sup_001", "4821"), None).is_err());
+
+        // Recording the void needs the same user and action, once
+        let redeem = |token: &str, action, user| authorizer.redeem_for_record(token, action, user);
+        assert!(redeem(&approval.token, ApprovalAction::Refund, Some("usr_001")).is_err());
+        assert!(redeem(&approval.token, ApprovalAction::Void, Some("usr_002")).is_err());
+        assert!(redeem(&approval.token.replacen('.', "x.", 1), ApprovalAction::Void, Some("usr_001")).is_err());
+        assert_eq!(redeem(&approval.token, ApprovalAction::Void, Some("usr_001")).unwrap().jti, claims.jti);
+        assert!(redeem(&approval.token, ApprovalAction::Void, Some("usr_001")).is_err());
+
+        // Five wrong PINs lock the supervisor out, even for the right one
+        for _ in 0..MAX_FAILURES {
+            assert!(authorizer.approve(ApprovalAction::Refund, context.clone(), &pin("sup_001", "9999"), None).is_err());
+        }
+        let locked = authorizer.approve(ApprovalAction::Refund, context.clone(), &pin("sup_001", "4821"), None);
+        assert!(locked.unwrap_err().to_string().contains("Too many"));
+
+        // Restarting the app does not lift the lockout
+        let reopened = reopen(&authorizer.path);
+        let locked = reopened.approve(ApprovalAction::Refund, context.clone(), &pin("sup_001", "4821"), None);
+        assert!(locked.unwrap_err().to_string().contains("Too many"));
+
+        // Spraying unknown ids locks the whole till
+        for n in 0..MAX_TERMINAL_FAILURES {
+            let _ = authorizer.approve(ApprovalAction::Void, context.clone(), &pin(&format!("x{}", n), "4821"), None);
+        }
+        authorizer.set_pin("sup_002", "", "5937").unwrap();
+        assert!(authorizer.approve(ApprovalAction::Void, context, &pin("sup_002", "5937"), None).is_err());
+
+        let _ = std::fs::remove_dir_all(authorizer.path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_otp_is_single_use() {
+        let authorizer = authorizer();
+        authorizer.set_pin("sup_001", "Manager Toko", "4821").unwrap();
+        let enrollment = authorizer.enroll_totp("sup_001").unwrap();
+        assert!(enrollment.uri.starts_with("otpauth://totp/POS%20Kasir:Manager%20Toko?secret="));
+        assert!(authorizer.enroll_totp("nobody").is_err());
+
+        // The key is in the secrets store, not the supervisors file
+        let file = std::fs::read_to_string(&authorizer.path).unwrap();
+        assert!(!file.contains("totp_secret") && file.contains("\"has_totp\": true"));
+        let secret = authorizer.secrets.get_internal("supervisor-totp:sup_001").unwrap().unwrap();
+        let secret = BASE64.decode(secret.expose()).unwrap();
+        let code = totp(&secret, chrono::Utc::now().timestamp() / TOTP_STEP_SECONDS);
+        let otp = ApprovalCredential::Otp {
+            supervisor_id: "sup_001".to_string(),
+            code,
+        };
+
+        let context = json!({ "transaction_id": "trx_001", "amount": 11000 });
+        assert!(authorizer.approve(ApprovalAction::Refund, context.clone(), &otp, None).is_ok());
+        assert!(authorizer.approve(ApprovalAction::Refund, context, &otp, None).is_err());
+
+        // A key left in the file by an older version moves to the secrets store on load
+        let legacy = r#"[{ "id": "sup_002", "name": "", "pin_hash": null, "totp_secret": "c2VjcmV0" }]"#;
+        std::fs::write(&authorizer.path, legacy).unwrap();
+        assert!(authorizer.list().unwrap()[0].has_totp);
+        assert!(!std::fs::read_to_string(&authorizer.path).unwrap().contains("c2VjcmV0"));
+        assert_eq!(authorizer.secrets.get_internal("supervisor-totp:sup_002").unwrap().unwrap().expose(), "c2VjcmV0");
+
+        let _ = std::fs::remove_dir_all(authorizer.path.parent().unwrap());
+    }
+}
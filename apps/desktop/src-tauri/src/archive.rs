@@ -0,0 +1,437 @@
+// ======================================================================
+// BACKUP ARCHIVE
+// Arsip backup terenkripsi (passphrase) untuk disalin ke flashdisk
+// ======================================================================
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use anyhow::{anyhow, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+/// Extension of exported archives
+pub const ARCHIVE_EXTENSION: &str = ".posbak";
+
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+const MAGIC: &[u8; 8] = b"POSBAK01";
+const CHUNK_SIZE: usize = 1024 * 1024;
+const TAG_SIZE: usize = 16;
+const MAX_MANIFEST_LEN: u32 = 64 * 1024;
+
+/// Upper bounds on the Argon2id cost an archive may ask for, so a crafted header can't exhaust the till
+const MAX_KDF_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_KDF_ITERATIONS: u32 = 10;
+const MAX_KDF_PARALLELISM: u32 = 8;
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+/// Argon2id cost, stored in the archive so it can be raised later
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+        }
+    }
+}
+
+impl KdfParams {
+    fn check_limits(&self) -> Result<()> {
+        if self.memory_kib > MAX_KDF_MEMORY_KIB
+            || self.iterations > MAX_KDF_ITERATIONS
+            || self.parallelism > MAX_KDF_PARALLELISM
+        {
+            return Err(anyhow!(
+                "Archive asks for key derivation beyond the allowed cost (memory {} KiB, {} iterations, parallelism {})",
+                self.memory_kib,
+                self.iterations,
+                self.parallelism
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Describes the dump inside; readable without the passphrase
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveManifest {
+    pub branch_id: Option<String>,
+    pub branch_code: Option<String>,
+    pub terminal_code: Option<String>,
+    /// Name of the dump in the backup folder
+    pub backup_file: String,
+    pub backup_created_at: String,
+    pub exported_at: String,
+    pub app_version: String,
+    pub schema_version: String,
+    pub dump_size: u64,
+    /// SHA-256 of the plain dump, hex
+    pub dump_sha256: String,
+}
+
+/// Manifest plus the encryption parameters, stored in clear and authenticated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchiveHeader {
+    manifest: ArchiveManifest,
+    kdf: KdfParams,
+    /// base64
+    salt: String,
+    /// base64, 7 bytes; a 4-byte chunk counter and a last-chunk flag follow
+    nonce_prefix: String,
+}
+
+// ======================================================================
+// ARCHIVE FUNCTIONS
+// ======================================================================
+
+/// Encrypt `dump` into `archive`; `manifest.dump_size` and `dump_sha256` are filled in
+pub fn write_archive(
+    dump: &Path,
+    archive: &Path,
+    mut manifest: ArchiveManifest,
+    passphrase: &str,
+    kdf: KdfParams,
+) -> Result<ArchiveManifest> {
+    check_passphrase(passphrase)?;
+
+    let (dump_size, dump_sha256) = hash_file(dump)?;
+    manifest.dump_size = dump_size;
+    manifest.dump_sha256 = dump_sha256;
+
+    let mut salt = [0u8; 16];
+    let mut nonce_prefix = [0u8; 7];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce_prefix);
+
+    let header = ArchiveHeader {
+        manifest: manifest.clone(),
+        kdf,
+        salt: BASE64.encode(salt),
+        nonce_prefix: BASE64.encode(nonce_prefix),
+    };
+    let header_bytes = serde_json::to_vec(&header)?;
+    let aad = Sha256::digest(&header_bytes);
+    let cipher = derive_cipher(passphrase, &salt, kdf)?;
+
+    let mut writer = BufWriter::new(File::create(archive)?);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&(header_bytes.len() as u32).to_le_bytes())?;
+    writer.write_all(&header_bytes)?;
+
+    let mut reader = BufReader::new(File::open(dump)?);
+    let mut chunk = vec![0u8; CHUNK_SIZE];
+    let mut filled = read_full(&mut reader, &mut chunk)?;
+    let mut counter = 0u32;
+
+    // Look one chunk ahead so the final chunk can be flagged
+    loop {
+        let mut next = vec![0u8; CHUNK_SIZE];
+        let next_filled = if filled == CHUNK_SIZE { read_full(&mut reader, &mut next)? } else { 0 };
+        let last = next_filled == 0;
+
+        let nonce = chunk_nonce(&nonce_prefix, counter, last);
+        let sealed = cipher
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: &chunk[..filled], aad: &aad })
+            .map_err(|_| anyhow!("Encryption failed"))?;
+
+        writer.write_all(&(sealed.len() as u32).to_le_bytes())?;
+        writer.write_all(&sealed)?;
+
+        if last {
+            break;
+        }
+        chunk = next;
+        filled = next_filled;
+        counter = counter.checked_add(1).ok_or_else(|| anyhow!("Dump is too large"))?;
+    }
+
+    let file = writer.into_inner().map_err(|e| anyhow!("Failed to write archive: {}", e))?;
+    file.sync_all()?;
+
+    Ok(manifest)
+}
+
+/// Read the manifest without decrypting anything
+pub fn read_manifest(archive: &Path) -> Result<ArchiveManifest> {
+    let mut reader = BufReader::new(File::open(archive)?);
+    Ok(read_header(&mut reader)?.0.manifest)
+}
+
+/// Decrypt `archive` into `out`, checking every chunk, the length and the digest
+pub fn read_archive<W: Write>(archive: &Path, passphrase: &str, out: &mut W) -> Result<ArchiveManifest> {
+    let mut reader = BufReader::new(File::open(archive)?);
+    let (header, header_bytes) = read_header(&mut reader)?;
+
+    let salt = BASE64.decode(&header.salt).map_err(|_| anyhow!("Corrupt archive header"))?;
+    let nonce_prefix: [u8; 7] = BASE64
+        .decode(&header.nonce_prefix)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| anyhow!("Corrupt archive header"))?;
+
+    let aad = Sha256::digest(&header_bytes);
+    header.kdf.check_limits()?;
+    let cipher = derive_cipher(passphrase, &salt, header.kdf)?;
+
+    let mut hasher = Sha256::new();
+    let mut size = 0u64;
+    let mut counter = 0u32;
+
+    loop {
+        let mut len_bytes = [0u8; 4];
+        if read_full(&mut reader, &mut len_bytes)? != 4 {
+            return Err(anyhow!("Archive is truncated"));
+        }
+        let len = u32::from_le_bytes(len_bytes) as usize;
+        if !(TAG_SIZE..=CHUNK_SIZE + TAG_SIZE).contains(&len) {
+            return Err(anyhow!("Archive is corrupted"));
+        }
+
+        let mut sealed = vec![0u8; len];
+        if read_full(&mut reader, &mut sealed)? != len {
+            return Err(anyhow!("Archive is truncated"));
+        }
+
+        // A chunk only opens with its own position and last flag
+        let opened = [false, true].into_iter().find_map(|last| {
+            let nonce = chunk_nonce(&nonce_prefix, counter, last);
+            cipher
+                .decrypt(Nonce::from_slice(&nonce), Payload { msg: &sealed, aad: &aad })
+                .ok()
+                .map(|plain| (plain, last))
+        });
+        let Some((plain, last)) = opened else {
+            return Err(if counter == 0 {
+                anyhow!("Wrong passphrase or corrupted archive")
+            } else {
+                anyhow!("Archive is corrupted")
+            });
+        };
+
+        hasher.update(&plain);
+        size += plain.len() as u64;
+        out.write_all(&plain)?;
+
+        if last {
+            break;
+        }
+        counter = counter.checked_add(1).ok_or_else(|| anyhow!("Archive is corrupted"))?;
+    }
+
+    let mut trailing = [0u8; 1];
+    if reader.read(&mut trailing)? != 0 {
+        return Err(anyhow!("Archive has trailing data"));
+    }
+
+    let digest = hex(&hasher.finalize());
+    if size != header.manifest.dump_size || digest != header.manifest.dump_sha256 {
+        return Err(anyhow!("Archive content does not match its manifest"));
+    }
+
+    out.flush()?;
+    Ok(header.manifest)
+}
+
+/// Size and SHA-256 (hex) of a file
+pub fn hash_file(path: &Path) -> Result<(u64, String)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let size = std::io::copy(&mut reader, &mut hasher)?;
+    Ok((size, hex(&hasher.finalize())))
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+pub fn check_passphrase(passphrase: &str) -> Result<()> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(anyhow!("Passphrase must be at least {} characters", MIN_PASSPHRASE_LEN));
+    }
+    Ok(())
+}
+
+// ======================================================================
+// PRIVATE FUNCTIONS
+// ======================================================================
+
+fn read_header<R: Read>(reader: &mut R) -> Result<(ArchiveHeader, Vec<u8>)> {
+    let mut magic = [0u8; 8];
+    if read_full(reader, &mut magic)? != magic.len() || &magic != MAGIC {
+        return Err(anyhow!("Not a POS backup archive"));
+    }
+
+    let mut len_bytes = [0u8; 4];
+    if read_full(reader, &mut len_bytes)? != 4 {
+        return Err(anyhow!("Archive is truncated"));
+    }
+    let len = u32::from_le_bytes(len_bytes);
+    if len > MAX_MANIFEST_LEN {
+        return Err(anyhow!("Archive header is too large"));
+    }
+
+    let mut header_bytes = vec![0u8; len as usize];
+    if read_full(reader, &mut header_bytes)? != header_bytes.len() {
+        return Err(anyhow!("Archive is truncated"));
+    }
+
+    let header = serde_json::from_slice(&header_bytes).map_err(|e| anyhow!("Corrupt archive header: {}", e))?;
+    Ok((header, header_bytes))
+}
+
+fn derive_cipher(passphrase: &str, salt: &[u8], kdf: KdfParams) -> Result<Aes256Gcm> {
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|e| anyhow!("Invalid key derivation parameters: {}", e))?;
+
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+
+    Aes256Gcm::new_from_slice(&key).map_err(|_| anyhow!("Invalid key length"))
+}
+
+fn chunk_nonce(prefix: &[u8; 7], counter: u32, last: bool) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[..7].copy_from_slice(prefix);
+    nonce[7..11].copy_from_slice(&counter.to_be_bytes());
+    nonce[11] = last as u8;
+    nonce
+}
+
+/// Like `read_exact`, but returns the count read when the input ends early
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(filled)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_KDF: KdfParams = KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    fn manifest() -> ArchiveManifest {
+        ArchiveManifest {
+            branch_id: None,
+            branch_code: Some("JKT01".to_string()),
+            terminal_code: Some("T01".to_string()),
+            backup_file: "pos-backup-20240501-020000.dump".to_string(),
+            backup_created_at: "2024-05-01T02:00:00+07:00".to_string(),
+            exported_at: "2024-05-02T09:00:00+07:00".to_string(),
+            app_version: "1.0.0".to_string(),
+            schema_version: "test".to_string(),
+            dump_size: 0,
+            dump_sha256: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_archive_round_trip_and_tamper_detection() {
+        let dir = std::env::temp_dir().join(format!("archive-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dump = dir.join("pos-backup-20240501-020000.dump");
+        let archive = dir.join(format!("backup{}", ARCHIVE_EXTENSION));
+
+        // Spans several chunks with a partial one at the end
+        let content: Vec<u8> = (0..CHUNK_SIZE * 2 + 1234).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&dump, &content).unwrap();
+
+        assert!(write_archive(&dump, &archive, manifest(), "short", TEST_KDF).is_err());
+        let written = write_archive(&dump, &archive, manifest(), "kasir rahasia", TEST_KDF).unwrap();
+        assert_eq!(written.dump_size, content.len() as u64);
+
+        let listed = read_manifest(&archive).unwrap();
+        assert_eq!(listed.branch_code.as_deref(), Some("JKT01"));
+        assert_eq!(listed.dump_sha256, written.dump_sha256);
+
+        let mut restored = Vec::new();
+        read_archive(&archive, "kasir rahasia", &mut restored).unwrap();
+        assert_eq!(restored, content);
+
+        let err = read_archive(&archive, "wrong passphrase", &mut Vec::new()).unwrap_err();
+        assert!(err.to_string().contains("Wrong passphrase"));
+
+        let bytes = std::fs::read(&archive).unwrap();
+
+        // Flipped ciphertext bit
+        let mut flipped = bytes.clone();
+        let last = flipped.len() - 1;
+        flipped[last] ^= 1;
+        std::fs::write(&archive, &flipped).unwrap();
+        assert!(read_archive(&archive, "kasir rahasia", &mut Vec::new()).is_err());
+
+        // Dropped final chunk
+        let final_chunk = 4 + 1234 + TAG_SIZE;
+        std::fs::write(&archive, &bytes[..bytes.len() - final_chunk]).unwrap();
+        assert!(read_archive(&archive, "kasir rahasia", &mut Vec::new()).is_err());
+
+        // Edited manifest
+        let mut edited_bytes = bytes.clone();
+        let at = bytes.windows(5).position(|window| window == b"JKT01").unwrap();
+        edited_bytes[at..at + 5].copy_from_slice(b"BDG01");
+        std::fs::write(&archive, &edited_bytes).unwrap();
+        assert!(read_archive(&archive, "kasir rahasia", &mut Vec::new()).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_kdf_limits() {
+        assert!(KdfParams::default().check_limits().is_ok());
+        assert!(TEST_KDF.check_limits().is_ok());
+
+        let too_much = [
+            KdfParams { memory_kib: MAX_KDF_MEMORY_KIB + 1, ..TEST_KDF },
+            KdfParams { iterations: MAX_KDF_ITERATIONS + 1, ..TEST_KDF },
+            KdfParams { parallelism: MAX_KDF_PARALLELISM + 1, ..TEST_KDF },
+        ];
+        for kdf in too_much {
+            assert!(kdf.check_limits().is_err(), "{:?}", kdf);
+        }
+    }
+}
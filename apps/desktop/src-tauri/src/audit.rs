@@ -0,0 +1,601 @@
+// ======================================================================
+// AUDIT TRAIL
+// Catatan append-only berantai hash untuk aksi sensitif dan percobaan yang ditolak
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+};
+
+use crate::utils;
+
+/// One JSON event per line, in the app data directory
+pub const AUDIT_FILE: &str = "audit.log";
+
+/// Sequence number and hash of the last entry, so truncating the log shows up
+pub const AUDIT_HEAD_FILE: &str = "audit.head";
+
+/// Entries written before the log was chained are moved here
+pub const AUDIT_LEGACY_FILE: &str = "audit.legacy.log";
+
+/// Recorded when the log does not end where the head file says it should
+pub const AUDIT_TAMPERED_ACTION: &str = "audit_tampered";
+
+/// `prev_hash` of the first entry
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Spliced in as the last key of every line; the hash covers the line without it
+const HASH_KEY: &str = ",\"hash\":\"";
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Allowed,
+    Denied,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEvent {
+    /// Position in the chain, from 1; set when the event is appended
+    #[serde(default)]
+    pub seq: u64,
+    pub timestamp: String,
+    /// What was attempted, e.g. `open_external_url`
+    pub action: String,
+    pub outcome: AuditOutcome,
+    /// Label of the calling window
+    pub window: Option<String>,
+    /// User of the window's session, when there is one
+    pub user_id: Option<String>,
+    pub detail: Value,
+    /// Hash of the previous entry
+    #[serde(default)]
+    pub prev_hash: String,
+    /// SHA-256 of this entry's line without the hash itself
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub hash: String,
+}
+
+impl AuditEvent {
+    pub fn new(action: &str, outcome: AuditOutcome, detail: Value) -> Self {
+        Self {
+            seq: 0,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            action: action.to_string(),
+            outcome,
+            window: None,
+            user_id: None,
+            detail,
+            prev_hash: String::new(),
+            hash: String::new(),
+        }
+    }
+
+    pub fn caller(mut self, window: &str, user_id: Option<String>) -> Self {
+        self.window = Some(window.to_string());
+        self.user_id = user_id;
+        self
+    }
+}
+
+/// Filter for `query`; dates are calendar days in WIB, both ends inclusive
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditQuery {
+    pub action: Option<String>,
+    /// Session user (cashier or supervisor) the event was recorded for
+    pub user_id: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    /// Newest events first; all of them when unset
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    fn matches(&self, event: &AuditEvent) -> bool {
+        if self.action.as_deref().is_some_and(|action| action != event.action) {
+            return false;
+        }
+        if self.user_id.is_some() && self.user_id != event.user_id {
+            return false;
+        }
+        if self.from.is_none() && self.to.is_none() {
+            return true;
+        }
+
+        let Some(date) = wib_time(&event.timestamp).map(|at| at.date_naive()) else {
+            return false;
+        };
+        self.from.map_or(true, |from| date >= from) && self.to.map_or(true, |to| date <= to)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+struct ChainHead {
+    seq: u64,
+    hash: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditVerification {
+    pub valid: bool,
+    pub entries: u64,
+    /// First line (1-based) that does not fit the chain
+    pub first_invalid_line: Option<u64>,
+    pub error: Option<String>,
+}
+
+// ======================================================================
+// AUDIT LOG
+// ======================================================================
+
+pub struct AuditLog {
+    path: PathBuf,
+    head_path: PathBuf,
+    /// Last entry, loaded on the first append; also keeps appends from interleaving
+    head: parking_lot::Mutex<Option<ChainHead>>,
+}
+
+impl AuditLog {
+    pub fn open_default() -> Self {
+        Self::open(utils::get_app_data_dir().join(AUDIT_FILE))
+    }
+
+    pub fn open(path: PathBuf) -> Self {
+        Self {
+            head_path: path.with_file_name(AUDIT_HEAD_FILE),
+            path,
+            head: parking_lot::Mutex::new(None),
+        }
+    }
+
+    /// Append an event; a failed write is logged but never fails the caller
+    pub fn record(&self, event: AuditEvent) {
+        if let Err(e) = self.append(event.clone()) {
+            error!("Failed to write audit event {:?}: {}", event, e);
+        }
+    }
+
+    /// Matching events, newest first
+    pub fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEvent>> {
+        let _guard = self.head.lock();
+
+        let mut events: Vec<AuditEvent> = self
+            .read_lines()?
+            .iter()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .filter(|event| query.matches(event))
+            .collect();
+
+        events.reverse();
+        if let Some(limit) = query.limit {
+            events.truncate(limit);
+        }
+        Ok(events)
+    }
+
+    /// Walk the chain from the first entry and compare its end with the head file
+    pub fn verify(&self) -> Result<AuditVerification> {
+        let _guard = self.head.lock();
+
+        let lines = self.read_lines()?;
+        let mut head = ChainHead {
+            seq: 0,
+            hash: GENESIS_HASH.to_string(),
+        };
+
+        for (index, line) in lines.iter().enumerate() {
+            match check_link(line, &head) {
+                Ok(next) => head = next,
+                Err(e) => {
+                    return Ok(AuditVerification {
+                        valid: false,
+                        entries: index as u64,
+                        first_invalid_line: Some(index as u64 + 1),
+                        error: Some(e.to_string()),
+                    })
+                }
+            }
+        }
+
+        let error = match self.read_head()? {
+            Some(saved) if saved.seq > head.seq => Some(format!(
+                "Log ends at entry {} but entry {} was written; entries were removed",
+                head.seq, saved.seq
+            )),
+            Some(saved) if saved != head => Some(format!("Entry {} does not match the recorded head", head.seq)),
+            None if head.seq > 0 => Some("The head file is missing".to_string()),
+            _ => None,
+        };
+
+        Ok(AuditVerification {
+            valid: error.is_none(),
+            entries: head.seq,
+            first_invalid_line: None,
+            error,
+        })
+    }
+
+    // ======================================================================
+    // PRIVATE METHODS
+    // ======================================================================
+
+    fn append(&self, event: AuditEvent) -> Result<()> {
+        let mut head_guard = self.head.lock();
+        if let Some(parent) = self.path.parent() {
+            utils::ensure_directory_exists(parent).map_err(|e| anyhow!(e))?;
+        }
+
+        let mut head = match head_guard.take() {
+            Some(head) => head,
+            None => {
+                let (head, tampered) = self.load_head()?;
+                match tampered {
+                    Some(detail) => {
+                        error!("Audit log {:?} does not match its head: {}", self.path, detail);
+                        let event = AuditEvent::new(AUDIT_TAMPERED_ACTION, AuditOutcome::Denied, detail);
+                        self.write_entry(head, event)?
+                    }
+                    None => head,
+                }
+            }
+        };
+
+        head = self.write_entry(head, event)?;
+        *head_guard = Some(head);
+        Ok(())
+    }
+
+    /// Chain `event` after `head` and return the new head
+    fn write_entry(&self, head: ChainHead, mut event: AuditEvent) -> Result<ChainHead> {
+        event.seq = head.seq + 1;
+        event.prev_hash = head.hash;
+        event.hash = String::new();
+
+        let body = serde_json::to_string(&event)?;
+        let hash = entry_hash(&body);
+        let mut line = format!("{}{}{}\"}}", &body[..body.len() - 1], HASH_KEY, hash);
+        line.push('\n');
+
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+
+        let head = ChainHead { seq: event.seq, hash };
+        utils::write_file_atomic(&self.head_path, &serde_json::to_vec(&head)?).map_err(|e| anyhow!(e))?;
+        Ok(head)
+    }
+
+    /// Where the next entry links to, plus what was wrong when the log and `audit.head` disagree.
+    /// The chain continues from the recorded head, so a cut or edited tail keeps failing `verify`;
+    /// only a log with no head file and no chained entry at all is set aside as legacy.
+    fn load_head(&self) -> Result<(ChainHead, Option<Value>)> {
+        let genesis = ChainHead {
+            seq: 0,
+            hash: GENESIS_HASH.to_string(),
+        };
+
+        let (saved, head_error) = match self.read_head() {
+            Ok(saved) => (saved, None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let lines = self.read_lines()?;
+        let tail = lines.iter().rev().find_map(|line| chained_head(line));
+        let follows = |head: &ChainHead| lines.last().and_then(|line| check_link(line, head).ok());
+
+        match (saved, tail) {
+            (None, None) if head_error.is_none() => {
+                if !lines.is_empty() {
+                    let legacy = self.path.with_file_name(AUDIT_LEGACY_FILE);
+                    warn!("Audit log {:?} is not hash-chained; moving it to {:?}", self.path, legacy);
+                    std::fs::rename(&self.path, legacy)?;
+                }
+                Ok((genesis, None))
+            }
+            (Some(saved), Some(tail)) if saved == tail => Ok((saved, None)),
+            // The app stopped between writing the entry and the head file
+            (Some(saved), Some(tail)) if follows(&saved).as_ref() == Some(&tail) => Ok((tail, None)),
+            (Some(saved), tail) => {
+                let detail = json!({
+                    "reason": "log does not end at the recorded head",
+                    "head_seq": saved.seq,
+                    "log_seq": tail.map(|tail| tail.seq),
+                });
+                Ok((saved, Some(detail)))
+            }
+            (None, tail) => {
+                let detail = json!({
+                    "reason": head_error.unwrap_or_else(|| "the head file is missing".to_string()),
+                    "log_seq": tail.as_ref().map(|tail| tail.seq),
+                });
+                Ok((tail.unwrap_or(genesis), Some(detail)))
+            }
+        }
+    }
+
+    fn read_head(&self) -> Result<Option<ChainHead>> {
+        match std::fs::read(&self.head_path) {
+            Ok(bytes) => Ok(Some(
+                serde_json::from_slice(&bytes).map_err(|e| anyhow!("Corrupt audit head {:?}: {}", self.head_path, e))?,
+            )),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(anyhow!("Failed to read audit head {:?}: {}", self.head_path, e)),
+        }
+    }
+
+    fn read_lines(&self) -> Result<Vec<String>> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(anyhow!("Failed to open audit log {:?}: {}", self.path, e)),
+        };
+
+        let mut lines = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                lines.push(line);
+            }
+        }
+        Ok(lines)
+    }
+}
+
+// ======================================================================
+// HELPER FUNCTIONS
+// ======================================================================
+
+/// CSV with WIB timestamps, for `export_audit_events`
+pub fn to_csv(events: &[AuditEvent]) -> String {
+    let mut csv = String::from("seq,waktu_wib,action,outcome,window,user_id,detail,hash\n");
+    for event in events {
+        let time = wib_time(&event.timestamp)
+            .map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| event.timestamp.clone());
+        let outcome = match event.outcome {
+            AuditOutcome::Allowed => "allowed",
+            AuditOutcome::Denied => "denied",
+        };
+
+        let fields = [
+            event.seq.to_string(),
+            time,
+            event.action.clone(),
+            outcome.to_string(),
+            event.window.clone().unwrap_or_default(),
+            event.user_id.clone().unwrap_or_default(),
+            event.detail.to_string(),
+            event.hash.clone(),
+        ];
+        let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// The next head when `line` correctly follows `head`
+fn check_link(line: &str, head: &ChainHead) -> Result<ChainHead> {
+    let (body, hash) = split_hash(line).ok_or_else(|| anyhow!("Entry has no hash"))?;
+    let event: AuditEvent = serde_json::from_str(line).map_err(|e| anyhow!("Entry is not valid JSON: {}", e))?;
+
+    if event.seq != head.seq + 1 {
+        return Err(anyhow!("Expected entry {}, found {}", head.seq + 1, event.seq));
+    }
+    if event.prev_hash != head.hash {
+        return Err(anyhow!("Entry {} does not link to the entry before it", event.seq));
+    }
+    if entry_hash(&body) != hash {
+        return Err(anyhow!("Entry {} was modified", event.seq));
+    }
+
+    Ok(ChainHead {
+        seq: event.seq,
+        hash: hash.to_string(),
+    })
+}
+
+fn chained_head(line: &str) -> Option<ChainHead> {
+    let (_, hash) = split_hash(line)?;
+    let event: AuditEvent = serde_json::from_str(line).ok()?;
+    (event.seq > 0).then(|| ChainHead {
+        seq: event.seq,
+        hash: hash.to_string(),
+    })
+}
+
+/// `{...,"hash":"<hex>"}` → (`{...}`, `<hex>`)
+fn split_hash(line: &str) -> Option<(String, &str)> {
+    let start = line.rfind(HASH_KEY)?;
+    let hash = line[start + HASH_KEY.len()..].strip_suffix("\"}")?;
+    if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some((format!("{}}}", &line[..start]), hash))
+}
+
+fn entry_hash(body: &str) -> String {
+    Sha256::digest(body.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn wib_time(timestamp: &str) -> Option<DateTime<FixedOffset>> {
+    let wib = FixedOffset::east_opt(7 * 3600)?;
+    DateTime::parse_from_rfc3339(timestamp).ok().map(|at| at.with_timezone(&wib))
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn temp_log() -> (AuditLog, PathBuf) {
+        let path = std::env::temp_dir().join(format!("pos-audit-{}", uuid::Uuid::new_v4())).join(AUDIT_FILE);
+        (AuditLog::open(path.clone()), path)
+    }
+
+    fn latest(limit: usize) -> AuditQuery {
+        AuditQuery {
+            limit: Some(limit),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_append_and_read_back() {
+        let (log, path) = temp_log();
+        assert!(log.query(&latest(10)).unwrap().is_empty());
+
+        log.record(AuditEvent::new("open_external_url", AuditOutcome::Denied, json!({ "url": "file:///etc/passwd" })));
+        log.record(AuditEvent::new("open_external_url", AuditOutcome::Allowed, json!({ "url": "https://qris.id/" })));
+        log.record(
+            AuditEvent::new("save_app_config", AuditOutcome::Denied, json!({}))
+                .caller("main", Some("u-1".to_string())),
+        );
+
+        let events = log.query(&latest(2)).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].action, "save_app_config");
+        assert_eq!(events[0].user_id.as_deref(), Some("u-1"));
+        assert_eq!(events[1].outcome, AuditOutcome::Allowed);
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 3);
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_chain_detects_tampering() {
+        let (log, path) = temp_log();
+        for n in 0..4 {
+            let detail = json!({ "transaction_id": format!("trx_{}", n) });
+            log.record(AuditEvent::new("void", AuditOutcome::Allowed, detail));
+        }
+        let verification = log.verify().unwrap();
+        assert!(verification.valid, "{:?}", verification.error);
+        assert_eq!(verification.entries, 4);
+
+        // A fresh handle continues the same chain
+        AuditLog::open(path.clone()).record(AuditEvent::new("refund", AuditOutcome::Allowed, json!({})));
+        assert!(log.verify().unwrap().valid);
+        let original = std::fs::read_to_string(&path).unwrap();
+
+        // Edited detail
+        std::fs::write(&path, original.replacen("trx_1", "trx_9", 1)).unwrap();
+        assert_eq!(log.verify().unwrap().first_invalid_line, Some(2));
+
+        // Deleted entry in the middle
+        let lines: Vec<&str> = original.lines().collect();
+        let without_second = [&lines[..1], &lines[2..]].concat().join("\n");
+        std::fs::write(&path, without_second).unwrap();
+        assert_eq!(log.verify().unwrap().first_invalid_line, Some(2));
+
+        // Last entry dropped: the chain is intact but shorter than the head
+        std::fs::write(&path, lines[..4].join("\n") + "\n").unwrap();
+        let verification = log.verify().unwrap();
+        assert!(!verification.valid);
+        assert_eq!(verification.first_invalid_line, None);
+
+        // Appending after the cut records it and keeps linking to the recorded head
+        let reopened = AuditLog::open(path.clone());
+        reopened.record(AuditEvent::new("void", AuditOutcome::Allowed, json!({})));
+        let events = reopened.query(&latest(2)).unwrap();
+        assert_eq!(events[1].action, AUDIT_TAMPERED_ACTION);
+        assert_eq!(events[1].detail["head_seq"], 5);
+        assert_eq!(events[0].seq, 7);
+        assert_eq!(reopened.verify().unwrap().first_invalid_line, Some(5));
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_damaged_first_line_is_not_legacy() {
+        let (log, path) = temp_log();
+        for _ in 0..2 {
+            log.record(AuditEvent::new("void", AuditOutcome::Allowed, json!({})));
+        }
+        let original = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, original.replacen("\"seq\":1", "\"seq\":x", 1)).unwrap();
+
+        let reopened = AuditLog::open(path.clone());
+        reopened.record(AuditEvent::new("refund", AuditOutcome::Allowed, json!({})));
+        assert!(!path.with_file_name(AUDIT_LEGACY_FILE).exists());
+        let events = reopened.query(&latest(10)).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!((events[0].seq, events[0].action.as_str()), (3, "refund"));
+        assert_eq!(reopened.verify().unwrap().first_invalid_line, Some(1));
+
+        // A missing head file is recorded too, and the chain carries on from the log
+        std::fs::remove_file(path.with_file_name(AUDIT_HEAD_FILE)).unwrap();
+        let reopened = AuditLog::open(path.clone());
+        reopened.record(AuditEvent::new("refund", AuditOutcome::Allowed, json!({})));
+        let events = reopened.query(&latest(2)).unwrap();
+        assert_eq!((events[1].seq, events[1].action.as_str()), (4, AUDIT_TAMPERED_ACTION));
+        assert_eq!(events[0].seq, 5);
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_query_filters_and_legacy_log() {
+        let (log, path) = temp_log();
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "{\"timestamp\":\"2024-05-01T00:00:00Z\",\"action\":\"old\"}\n").unwrap();
+
+        let mut late = AuditEvent::new("void", AuditOutcome::Allowed, json!({}))
+            .caller("main", Some("kasir-1".into()));
+        // 23:30 UTC on 1 May is 06:30 WIB on 2 May
+        late.timestamp = "2024-05-01T23:30:00Z".to_string();
+        log.record(late);
+        log.record(
+            AuditEvent::new("refund", AuditOutcome::Allowed, json!({ "note": "a,\"b\"" }))
+                .caller("main", Some("kasir-2".into())),
+        );
+
+        assert!(path.with_file_name(AUDIT_LEGACY_FILE).exists());
+        assert!(log.verify().unwrap().valid);
+
+        let day = |d: u32| NaiveDate::from_ymd_opt(2024, 5, d);
+        let by_day = AuditQuery { from: day(2), to: day(2), ..Default::default() };
+        assert_eq!(log.query(&by_day).unwrap().len(), 1);
+        let by_day = AuditQuery { from: day(1), to: day(1), ..Default::default() };
+        assert!(log.query(&by_day).unwrap().is_empty());
+
+        let by_cashier = AuditQuery { user_id: Some("kasir-2".into()), ..Default::default() };
+        let events = log.query(&by_cashier).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].action, "refund");
+        let by_action = AuditQuery { action: Some("void".into()), ..Default::default() };
+        assert_eq!(log.query(&by_action).unwrap()[0].user_id.as_deref(), Some("kasir-1"));
+
+        let csv = to_csv(&log.query(&AuditQuery::default()).unwrap());
+        assert!(csv.contains(",2024-05-02 06:30:00,void,allowed,main,kasir-1,{},"));
+        assert!(csv.contains("\"{\"\"note\"\":\"\"a,\\\"\"b\\\"\"\"\"}\""));
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,1001 @@
+// ======================================================================
+// BACKUP MANAGER
+// Backup terjadwal PostgreSQL (pg_dump) dengan verifikasi dan restore
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, FixedOffset, TimeZone, Timelike, Utc};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::Arc,
+};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    process::Command,
+    sync::{Mutex, RwLock},
+    task::JoinHandle,
+};
+
+use crate::{
+    archive::{self, ArchiveManifest, KdfParams, ARCHIVE_EXTENSION},
+    migrations::MIGRATIONS,
+    secrets, utils,
+};
+
+/// Folder for dumps inside the app data directory
+pub const BACKUP_DIR: &str = "backups";
+
+const BACKUP_PREFIX: &str = "pos-backup-";
+const BACKUP_EXTENSION: &str = ".dump";
+const PARTIAL_EXTENSION: &str = ".partial";
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    pub enabled: bool,
+    /// Five-field cron expression evaluated in WIB, e.g. `0 2 * * *`
+    pub schedule: String,
+    /// Folder holding `pg_dump` / `pg_restore`; `PATH` is used when unset
+    pub pg_bin_dir: Option<PathBuf>,
+    /// Defaults to `backups` in the app data directory
+    pub backup_dir: Option<PathBuf>,
+    /// Always keep at least this many of the newest dumps
+    pub keep_last: usize,
+    /// Older dumps beyond `keep_last` are removed
+    pub max_age_days: Option<u32>,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            schedule: "0 2 * * *".to_string(),
+            pg_bin_dir: None,
+            backup_dir: None,
+            keep_last: 7,
+            max_age_days: Some(30),
+        }
+    }
+}
+
+impl BackupConfig {
+    pub fn dir(&self) -> PathBuf {
+        self.backup_dir
+            .clone()
+            .unwrap_or_else(|| utils::get_app_data_dir().join(BACKUP_DIR))
+    }
+
+    fn tool(&self, name: &str) -> PathBuf {
+        let file = if cfg!(windows) { format!("{}.exe", name) } else { name.to_string() };
+        match &self.pg_bin_dir {
+            Some(dir) => dir.join(file),
+            None => PathBuf::from(file),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackupEntry {
+    pub file_name: String,
+    pub size_bytes: u64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupOperation {
+    Backup,
+    Restore,
+    Export,
+    Import,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupStage {
+    Dumping,
+    Verifying,
+    Pruning,
+    Restoring,
+    Encrypting,
+    Decrypting,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupProgress {
+    pub operation: BackupOperation,
+    pub stage: BackupStage,
+    pub file_name: Option<String>,
+    /// Restore progress over the archive's table of contents
+    pub percent: Option<u8>,
+    pub message: Option<String>,
+    pub timestamp: String,
+}
+
+/// Where an exported archive came from
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackupOrigin {
+    pub branch_id: Option<String>,
+    pub branch_code: Option<String>,
+    pub terminal_code: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedBackup {
+    pub path: PathBuf,
+    pub manifest: ArchiveManifest,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedBackup {
+    pub entry: BackupEntry,
+    pub manifest: ArchiveManifest,
+    /// False when the archive predates or postdates this app's schema
+    pub schema_matches: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackupStatus {
+    pub enabled: bool,
+    pub scheduled: bool,
+    pub busy: bool,
+    pub next_run: Option<String>,
+    pub last_backup: Option<BackupEntry>,
+    pub last_error: Option<String>,
+}
+
+// ======================================================================
+// SCHEDULE
+// ======================================================================
+
+/// Minimal cron: `*`, `*/n`, `a`, `a-b`, `a-b/n` and comma lists
+#[derive(Debug, Clone)]
+pub struct CronSchedule {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(anyhow!("Cron schedule needs 5 fields, got {:?}", expression));
+        }
+
+        let mut weekdays = parse_field(fields[4], 0, 7)?;
+        // Both 0 and 7 mean Sunday
+        if weekdays[7] {
+            weekdays[0] = true;
+        }
+        weekdays.truncate(7);
+
+        Ok(Self {
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            weekdays,
+            days_restricted: fields[2] != "*",
+            weekdays_restricted: fields[4] != "*",
+        })
+    }
+
+    /// First matching minute strictly after `after`, evaluated in WIB
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let wib = FixedOffset::east_opt(7 * 3600).expect("valid offset");
+        let start = after.with_timezone(&wib);
+        let mut candidate = wib
+            .with_ymd_and_hms(start.year(), start.month(), start.day(), start.hour(), start.minute(), 0)
+            .single()?
+            + ChronoDuration::minutes(1);
+
+        // A year and a day covers every valid expression
+        for _ in 0..(366 * 24 * 60) {
+            if self.matches(&candidate) {
+                return Some(candidate.with_timezone(&Utc));
+            }
+            candidate += ChronoDuration::minutes(1);
+        }
+        None
+    }
+
+    fn matches(&self, at: &DateTime<FixedOffset>) -> bool {
+        let day = self.days[at.day() as usize];
+        let weekday = self.weekdays[at.weekday().num_days_from_sunday() as usize];
+
+        // Classic cron: when both day fields are restricted either may match
+        let day_matches = match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        };
+
+        self.minutes[at.minute() as usize]
+            && self.hours[at.hour() as usize]
+            && self.months[at.month() as usize]
+            && day_matches
+    }
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<Vec<bool>> {
+    let mut allowed = vec![false; max as usize + 1];
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| anyhow!("Invalid step in {:?}", part))?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(anyhow!("Step must be positive in {:?}", part));
+        }
+
+        let (from, to) = if range == "*" {
+            (min, max)
+        } else if let Some((from, to)) = range.split_once('-') {
+            (parse_value(from, part)?, parse_value(to, part)?)
+        } else {
+            let value = parse_value(range, part)?;
+            (value, if part.contains('/') { max } else { value })
+        };
+
+        if from < min || to > max || from > to {
+            return Err(anyhow!("{:?} is outside {}-{}", part, min, max));
+        }
+        for value in (from..=to).step_by(step as usize) {
+            allowed[value as usize] = true;
+        }
+    }
+
+    Ok(allowed)
+}
+
+fn parse_value(value: &str, part: &str) -> Result<u32> {
+    value.parse().map_err(|_| anyhow!("Invalid value in {:?}", part))
+}
+
+// ======================================================================
+// BACKUP FILES
+// ======================================================================
+
+/// Verified dumps in `dir`, newest first
+pub fn list_backups(dir: &Path) -> Result<Vec<BackupEntry>> {
+    let read_dir = match std::fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(anyhow!("Failed to read backup folder {:?}: {}", dir, e)),
+    };
+
+    let mut entries = Vec::new();
+    for item in read_dir.flatten() {
+        let file_name = item.file_name().to_string_lossy().to_string();
+        let Some(created_at) = backup_timestamp(&file_name) else {
+            continue;
+        };
+
+        let entry = BackupEntry {
+            size_bytes: item.metadata().map(|meta| meta.len()).unwrap_or(0),
+            created_at: created_at.to_rfc3339(),
+            file_name,
+        };
+        entries.push((created_at, entry));
+    }
+
+    entries.sort_by(|(a_at, a), (b_at, b)| b_at.cmp(a_at).then_with(|| b.file_name.cmp(&a.file_name)));
+    Ok(entries.into_iter().map(|(_, entry)| entry).collect())
+}
+
+/// Dumps to delete under the retention policy; the newest is always kept
+pub fn expired_backups(
+    entries: &[BackupEntry],
+    keep_last: usize,
+    max_age_days: Option<u32>,
+    now: DateTime<Utc>,
+) -> Vec<&BackupEntry> {
+    entries
+        .iter()
+        .enumerate()
+        .filter(|(index, entry)| {
+            if *index == 0 || *index < keep_last {
+                return false;
+            }
+
+            match max_age_days {
+                Some(days) => DateTime::parse_from_rfc3339(&entry.created_at)
+                    .map(|created| now.signed_duration_since(created) > ChronoDuration::days(days as i64))
+                    .unwrap_or(false),
+                None => true,
+            }
+        })
+        .map(|(_, entry)| entry)
+        .collect()
+}
+
+/// Milliseconds keep two backups within the same second apart
+fn backup_file_name(at: DateTime<Utc>) -> String {
+    let wib = FixedOffset::east_opt(7 * 3600).expect("valid offset");
+    format!(
+        "{}{}{}",
+        BACKUP_PREFIX,
+        at.with_timezone(&wib).format("%Y%m%d-%H%M%S-%3f"),
+        BACKUP_EXTENSION
+    )
+}
+
+/// Also reads names from before milliseconds were added
+fn backup_timestamp(file_name: &str) -> Option<DateTime<FixedOffset>> {
+    let stamp = file_name.strip_prefix(BACKUP_PREFIX)?.strip_suffix(BACKUP_EXTENSION)?;
+    let naive = chrono::NaiveDateTime::parse_from_str(stamp, "%Y%m%d-%H%M%S-%3f")
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(stamp, "%Y%m%d-%H%M%S"))
+        .ok()?;
+    FixedOffset::east_opt(7 * 3600)?.from_local_datetime(&naive).single()
+}
+
+// ======================================================================
+// BACKUP MANAGER
+// ======================================================================
+
+pub struct BackupManager {
+    config: Arc<RwLock<BackupConfig>>,
+    database_url: Arc<RwLock<Option<String>>>,
+    busy: Arc<Mutex<()>>,
+    status: Arc<RwLock<BackupStatus>>,
+    task_handle: Option<JoinHandle<()>>,
+    progress_callback: Option<Arc<dyn Fn(BackupProgress) + Send + Sync>>,
+}
+
+impl BackupManager {
+    pub fn new() -> Self {
+        Self {
+            config: Arc::new(RwLock::new(BackupConfig::default())),
+            database_url: Arc::new(RwLock::new(None)),
+            busy: Arc::new(Mutex::new(())),
+            status: Arc::new(RwLock::new(BackupStatus::default())),
+            task_handle: None,
+            progress_callback: None,
+        }
+    }
+
+    /// Register the handler invoked for each backup/restore step
+    pub fn set_progress_callback<F>(&mut self, callback: F)
+    where
+        F: Fn(BackupProgress) + Send + Sync + 'static,
+    {
+        self.progress_callback = Some(Arc::new(callback));
+    }
+
+    /// Apply settings and (re)start the scheduler when enabled
+    pub async fn start(&mut self, config: BackupConfig, database_url: Option<String>) -> Result<()> {
+        self.stop().await;
+
+        let schedule = CronSchedule::parse(&config.schedule)?;
+        let enabled = config.enabled && database_url.is_some();
+        *self.config.write().await = config;
+        *self.database_url.write().await = database_url;
+        self.status.write().await.enabled = enabled;
+
+        if !enabled {
+            debug!("Scheduled backups are disabled");
+            return Ok(());
+        }
+
+        info!("Starting backup scheduler");
+        let worker = self.worker();
+        self.task_handle = Some(tokio::spawn(async move {
+            worker.run(schedule).await;
+        }));
+
+        Ok(())
+    }
+
+    pub async fn stop(&mut self) {
+        if let Some(task) = self.task_handle.take() {
+            info!("Stopping backup scheduler");
+            task.abort();
+            let _ = task.await;
+        }
+        self.status.write().await.next_run = None;
+    }
+
+    pub async fn list_backups(&self) -> Result<Vec<BackupEntry>> {
+        list_backups(&self.config.read().await.dir())
+    }
+
+    pub async fn get_status(&self) -> BackupStatus {
+        let mut status = self.status.read().await.clone();
+        status.scheduled = self.task_handle.as_ref().is_some_and(|task| !task.is_finished());
+        status.busy = self.busy.try_lock().is_err();
+        status.last_backup = self.list_backups().await.ok().and_then(|entries| entries.into_iter().next());
+        status
+    }
+
+    /// Handle for running one operation without holding the manager lock
+    pub fn worker(&self) -> BackupWorker {
+        BackupWorker {
+            config: Arc::clone(&self.config),
+            database_url: Arc::clone(&self.database_url),
+            busy: Arc::clone(&self.busy),
+            status: Arc::clone(&self.status),
+            callback: self.progress_callback.clone(),
+        }
+    }
+}
+
+// ======================================================================
+// BACKUP WORKER
+// ======================================================================
+
+#[derive(Clone)]
+pub struct BackupWorker {
+    config: Arc<RwLock<BackupConfig>>,
+    database_url: Arc<RwLock<Option<String>>>,
+    busy: Arc<Mutex<()>>,
+    status: Arc<RwLock<BackupStatus>>,
+    callback: Option<Arc<dyn Fn(BackupProgress) + Send + Sync>>,
+}
+
+impl BackupWorker {
+    async fn run(&self, schedule: CronSchedule) {
+        loop {
+            let Some(next) = schedule.next_after(Utc::now()) else {
+                error!("Backup schedule never fires; scheduler stopped");
+                return;
+            };
+            self.status.write().await.next_run = Some(next.to_rfc3339());
+            debug!("Next backup at {}", next);
+
+            let wait = (next - Utc::now()).to_std().unwrap_or_default();
+            tokio::time::sleep(wait).await;
+
+            if let Err(e) = self.backup().await {
+                error!("Scheduled backup failed: {}", e);
+            }
+        }
+    }
+
+    /// Dump, verify and prune right now
+    pub async fn backup(&self) -> Result<BackupEntry> {
+        let result = self.try_backup().await;
+        self.status.write().await.last_error = result.as_ref().err().map(|e| e.to_string());
+
+        if let Err(e) = &result {
+            self.emit(BackupOperation::Backup, BackupStage::Failed, None, None, Some(e.to_string()));
+        }
+        result
+    }
+
+    async fn try_backup(&self) -> Result<BackupEntry> {
+        let _guard = self
+            .busy
+            .try_lock()
+            .map_err(|_| anyhow!("Another backup or restore is running"))?;
+
+        let config = self.config.read().await.clone();
+        let database_url = self.database_url().await?;
+        let dir = config.dir();
+        utils::ensure_directory_exists(&dir).map_err(|e| anyhow!(e))?;
+
+        let mut at = Utc::now();
+        while dir.join(backup_file_name(at)).exists() {
+            at += ChronoDuration::milliseconds(1);
+        }
+        let file_name = backup_file_name(at);
+        let partial = dir.join(format!("{}{}", file_name, PARTIAL_EXTENSION));
+        let target = dir.join(&file_name);
+
+        info!("Backing up database to {:?}", target);
+        self.emit(BackupOperation::Backup, BackupStage::Dumping, Some(&file_name), None, None);
+
+        let output = pg_command(&config, "pg_dump", &database_url)?
+            .arg("--format=custom")
+            .arg("--no-owner")
+            .arg(format!("--file={}", partial.display()))
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .map_err(|e| anyhow!("Failed to run pg_dump: {}", e))?;
+
+        if !output.status.success() {
+            let _ = std::fs::remove_file(&partial);
+            return Err(anyhow!("pg_dump failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+
+        // Only a dump pg_restore can read counts as a backup
+        self.emit(BackupOperation::Backup, BackupStage::Verifying, Some(&file_name), None, None);
+        if let Err(e) = verify_dump(&config, &partial).await {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e);
+        }
+
+        std::fs::rename(&partial, &target)?;
+
+        self.emit(BackupOperation::Backup, BackupStage::Pruning, Some(&file_name), None, None);
+        self.prune(&config)?;
+
+        let entry = list_backups(&dir)?
+            .into_iter()
+            .find(|entry| entry.file_name == file_name)
+            .ok_or_else(|| anyhow!("Backup {} disappeared", file_name))?;
+
+        info!("Backup {} completed ({} bytes)", entry.file_name, entry.size_bytes);
+        self.emit(BackupOperation::Backup, BackupStage::Completed, Some(&file_name), Some(100), None);
+        Ok(entry)
+    }
+
+    pub async fn restore(&self, file_name: &str) -> Result<()> {
+        let result = self.try_restore(file_name).await;
+
+        if let Err(e) = &result {
+            error!("Restore of {} failed: {}", file_name, e);
+            self.emit(BackupOperation::Restore, BackupStage::Failed, Some(file_name), None, Some(e.to_string()));
+        }
+        result
+    }
+
+    async fn try_restore(&self, file_name: &str) -> Result<()> {
+        if backup_timestamp(file_name).is_none() {
+            return Err(anyhow!("Not a backup file: {}", file_name));
+        }
+
+        let _guard = self
+            .busy
+            .try_lock()
+            .map_err(|_| anyhow!("Another backup or restore is running"))?;
+
+        let config = self.config.read().await.clone();
+        let database_url = self.database_url().await?;
+        let path = config.dir().join(file_name);
+
+        self.emit(BackupOperation::Restore, BackupStage::Verifying, Some(file_name), Some(0), None);
+        let total = verify_dump(&config, &path).await?;
+
+        warn!("Restoring database from {}", file_name);
+        self.emit(BackupOperation::Restore, BackupStage::Restoring, Some(file_name), Some(0), None);
+
+        let mut child = pg_command(&config, "pg_restore", &database_url)?
+            .arg("--clean")
+            .arg("--if-exists")
+            .arg("--no-owner")
+            .arg("--exit-on-error")
+            .arg("--verbose")
+            .arg(&path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow!("Failed to run pg_restore: {}", e))?;
+
+        // --verbose reports each object on stderr
+        let mut lines = BufReader::new(child.stderr.take().expect("piped stderr")).lines();
+        let mut done = 0usize;
+        let mut last_line = String::new();
+
+        while let Some(line) = lines.next_line().await? {
+            if line.contains("creating") || line.contains("processing data") {
+                done += 1;
+                let percent = (done * 100 / total.max(1)).min(99) as u8;
+                self.emit(BackupOperation::Restore, BackupStage::Restoring, Some(file_name), Some(percent), Some(line.clone()));
+            }
+            last_line = line;
+        }
+
+        let status = child.wait().await?;
+        if !status.success() {
+            return Err(anyhow!("pg_restore failed: {}", last_line));
+        }
+
+        info!("Database restored from {}", file_name);
+        self.emit(BackupOperation::Restore, BackupStage::Completed, Some(file_name), Some(100), None);
+        Ok(())
+    }
+
+    /// Encrypt a dump into `destination`, e.g. a USB stick
+    pub async fn export(
+        &self,
+        file_name: &str,
+        destination: &Path,
+        passphrase: &str,
+        origin: BackupOrigin,
+    ) -> Result<ExportedBackup> {
+        let result = self.try_export(file_name, destination, passphrase, origin).await;
+
+        if let Err(e) = &result {
+            error!("Export of {} failed: {}", file_name, e);
+            self.emit(BackupOperation::Export, BackupStage::Failed, Some(file_name), None, Some(e.to_string()));
+        }
+        result
+    }
+
+    async fn try_export(
+        &self,
+        file_name: &str,
+        destination: &Path,
+        passphrase: &str,
+        origin: BackupOrigin,
+    ) -> Result<ExportedBackup> {
+        let Some(created_at) = backup_timestamp(file_name) else {
+            return Err(anyhow!("Not a backup file: {}", file_name));
+        };
+        archive::check_passphrase(passphrase)?;
+        if !destination.is_dir() {
+            return Err(anyhow!("Export folder {:?} does not exist", destination));
+        }
+
+        let _guard = self
+            .busy
+            .try_lock()
+            .map_err(|_| anyhow!("Another backup or restore is running"))?;
+
+        let source = self.config.read().await.dir().join(file_name);
+        if !source.is_file() {
+            return Err(anyhow!("Backup {} not found", file_name));
+        }
+
+        let stem = file_name.trim_end_matches(BACKUP_EXTENSION);
+        let archive_name = match &origin.branch_code {
+            Some(branch_code) => format!("{}-{}{}", branch_code, stem, ARCHIVE_EXTENSION),
+            None => format!("{}{}", stem, ARCHIVE_EXTENSION),
+        };
+        let target = destination.join(&archive_name);
+        if target.exists() {
+            return Err(anyhow!("{:?} already exists", target));
+        }
+        let partial = destination.join(format!("{}{}", archive_name, PARTIAL_EXTENSION));
+
+        let manifest = ArchiveManifest {
+            branch_id: origin.branch_id,
+            branch_code: origin.branch_code,
+            terminal_code: origin.terminal_code,
+            backup_file: file_name.to_string(),
+            backup_created_at: created_at.to_rfc3339(),
+            exported_at: Utc::now().to_rfc3339(),
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            schema_version: schema_version(),
+            dump_size: 0,
+            dump_sha256: String::new(),
+        };
+
+        info!("Exporting backup {} to {:?}", file_name, target);
+        self.emit(BackupOperation::Export, BackupStage::Encrypting, Some(file_name), None, None);
+
+        let passphrase = passphrase.to_string();
+        let archive_path = partial.clone();
+        let written = tokio::task::spawn_blocking(move || -> Result<ArchiveManifest> {
+            let manifest = archive::write_archive(&source, &archive_path, manifest, &passphrase, KdfParams::default())?;
+
+            // Read back what reached the removable drive before calling it done
+            archive::read_archive(&archive_path, &passphrase, &mut std::io::sink())?;
+            Ok(manifest)
+        });
+
+        let manifest = match written.await? {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                let _ = std::fs::remove_file(&partial);
+                return Err(e);
+            }
+        };
+        std::fs::rename(&partial, &target)?;
+
+        self.emit(BackupOperation::Export, BackupStage::Completed, Some(file_name), Some(100), None);
+        Ok(ExportedBackup { path: target, manifest })
+    }
+
+    /// Decrypt an exported archive back into the backup folder
+    pub async fn import(&self, archive_path: &Path, passphrase: &str) -> Result<ImportedBackup> {
+        let result = self.try_import(archive_path, passphrase).await;
+
+        if let Err(e) = &result {
+            error!("Import of {:?} failed: {}", archive_path, e);
+            self.emit(BackupOperation::Import, BackupStage::Failed, None, None, Some(e.to_string()));
+        }
+        result
+    }
+
+    async fn try_import(&self, archive_path: &Path, passphrase: &str) -> Result<ImportedBackup> {
+        let manifest = archive::read_manifest(archive_path)?;
+        let file_name = manifest.backup_file.clone();
+        if backup_timestamp(&file_name).is_none() {
+            return Err(anyhow!("Archive names an invalid backup file: {}", file_name));
+        }
+
+        let _guard = self
+            .busy
+            .try_lock()
+            .map_err(|_| anyhow!("Another backup or restore is running"))?;
+
+        let config = self.config.read().await.clone();
+        let dir = config.dir();
+        utils::ensure_directory_exists(&dir).map_err(|e| anyhow!(e))?;
+
+        let target = dir.join(&file_name);
+        let partial = dir.join(format!("{}{}", file_name, PARTIAL_EXTENSION));
+
+        if target.exists() {
+            let (_, existing) = archive::hash_file(&target)?;
+            if existing != manifest.dump_sha256 {
+                return Err(anyhow!("A different backup named {} already exists", file_name));
+            }
+            info!("Backup {} is already present", file_name);
+        } else {
+            info!("Importing backup {} from {:?}", file_name, archive_path);
+            self.emit(BackupOperation::Import, BackupStage::Decrypting, Some(&file_name), None, None);
+
+            let passphrase = passphrase.to_string();
+            let source = archive_path.to_path_buf();
+            let out_path = partial.clone();
+            let decrypted = tokio::task::spawn_blocking(move || -> Result<ArchiveManifest> {
+                let mut out = std::io::BufWriter::new(std::fs::File::create(&out_path)?);
+                archive::read_archive(&source, &passphrase, &mut out)
+            });
+
+            if let Err(e) = decrypted.await? {
+                let _ = std::fs::remove_file(&partial);
+                return Err(e);
+            }
+
+            self.emit(BackupOperation::Import, BackupStage::Verifying, Some(&file_name), None, None);
+            if let Err(e) = verify_dump(&config, &partial).await {
+                let _ = std::fs::remove_file(&partial);
+                return Err(e);
+            }
+            std::fs::rename(&partial, &target)?;
+        }
+
+        let entry = list_backups(&dir)?
+            .into_iter()
+            .find(|entry| entry.file_name == file_name)
+            .ok_or_else(|| anyhow!("Backup {} disappeared", file_name))?;
+
+        let schema_matches = manifest.schema_version == schema_version();
+        if !schema_matches {
+            warn!(
+                "Backup {} was taken with schema {} (current {})",
+                file_name,
+                manifest.schema_version,
+                schema_version()
+            );
+        }
+
+        self.emit(BackupOperation::Import, BackupStage::Completed, Some(&file_name), Some(100), None);
+        Ok(ImportedBackup {
+            entry,
+            manifest,
+            schema_matches,
+        })
+    }
+
+    fn prune(&self, config: &BackupConfig) -> Result<()> {
+        let dir = config.dir();
+        let entries = list_backups(&dir)?;
+
+        for entry in expired_backups(&entries, config.keep_last, config.max_age_days, Utc::now()) {
+            info!("Removing expired backup {}", entry.file_name);
+            if let Err(e) = std::fs::remove_file(dir.join(&entry.file_name)) {
+                warn!("Failed to remove {}: {}", entry.file_name, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn database_url(&self) -> Result<String> {
+        self.database_url
+            .read()
+            .await
+            .clone()
+            .ok_or_else(|| anyhow!("Database URL is not configured"))
+    }
+
+    fn emit(
+        &self,
+        operation: BackupOperation,
+        stage: BackupStage,
+        file_name: Option<&str>,
+        percent: Option<u8>,
+        message: Option<String>,
+    ) {
+        if let Some(callback) = &self.callback {
+            callback(BackupProgress {
+                operation,
+                stage,
+                file_name: file_name.map(str::to_string),
+                percent,
+                message,
+                timestamp: Utc::now().to_rfc3339(),
+            });
+        }
+    }
+}
+
+/// Latest migration this build ships, recorded in exported archives
+pub fn schema_version() -> String {
+    MIGRATIONS.last().map(|migration| migration.version).unwrap_or(0).to_string()
+}
+
+/// The password goes through `PGPASSWORD` so it never shows up in the process list
+fn pg_command(config: &BackupConfig, tool: &str, database_url: &str) -> Result<Command> {
+    let (url, password) = secrets::split_database_password(database_url)?;
+
+    let mut command = Command::new(config.tool(tool));
+    command.arg(format!("--dbname={}", url)).env("PGCONNECT_TIMEOUT", "10");
+    if let Some(password) = password {
+        command.env("PGPASSWORD", password.expose());
+    }
+    Ok(command)
+}
+
+/// `pg_restore --list` must read the archive; returns its entry count
+async fn verify_dump(config: &BackupConfig, path: &Path) -> Result<usize> {
+    let output = Command::new(config.tool("pg_restore"))
+        .arg("--list")
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| anyhow!("Failed to run pg_restore: {}", e))?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Backup verification failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let entries = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with(';'))
+        .count();
+
+    if entries == 0 {
+        return Err(anyhow!("Backup verification failed: archive is empty"));
+    }
+    Ok(entries)
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cron_next_after_in_wib() {
+        let nightly = CronSchedule::parse("0 2 * * *").unwrap();
+        // 20:00 UTC on 1 May is 03:00 WIB on 2 May, so next is 02:00 WIB on 3 May
+        let after = Utc.with_ymd_and_hms(2024, 5, 1, 20, 0, 0).unwrap();
+        assert_eq!(nightly.next_after(after), Some(Utc.with_ymd_and_hms(2024, 5, 2, 19, 0, 0).unwrap()));
+
+        let every_quarter = CronSchedule::parse("*/15 8-17 * * 1-5").unwrap();
+        // Saturday 4 May 10:00 WIB rolls over to Monday 08:00 WIB
+        let saturday = Utc.with_ymd_and_hms(2024, 5, 4, 3, 0, 0).unwrap();
+        assert_eq!(every_quarter.next_after(saturday), Some(Utc.with_ymd_and_hms(2024, 5, 6, 1, 0, 0).unwrap()));
+
+        assert!(CronSchedule::parse("0 2 * *").is_err());
+        assert!(CronSchedule::parse("61 2 * * *").is_err());
+        assert!(CronSchedule::parse("0 0 31 2 *").unwrap().next_after(after).is_none());
+    }
+
+    #[test]
+    fn test_retention_keeps_newest_and_recent() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 31, 0, 0, 0).unwrap();
+        let entries: Vec<BackupEntry> = [30, 29, 20, 10, 1]
+            .iter()
+            .map(|day| {
+                let at = Utc.with_ymd_and_hms(2024, 5, *day, 0, 0, 0).unwrap();
+                let file_name = backup_file_name(at);
+                BackupEntry {
+                    created_at: backup_timestamp(&file_name).unwrap().to_rfc3339(),
+                    file_name,
+                    size_bytes: 1,
+                }
+            })
+            .collect();
+
+        let expired: Vec<&str> = expired_backups(&entries, 2, Some(15), now)
+            .into_iter()
+            .map(|entry| entry.file_name.as_str())
+            .collect();
+        assert_eq!(expired, vec![entries[3].file_name.as_str(), entries[4].file_name.as_str()]);
+
+        assert_eq!(expired_backups(&entries, 3, None, now).len(), 2);
+        assert!(expired_backups(&entries[..1], 0, Some(0), now).is_empty());
+    }
+
+    #[test]
+    fn test_backup_names_are_unique_below_a_second() {
+        let at = Utc.with_ymd_and_hms(2024, 5, 1, 19, 0, 0).unwrap();
+        let first = backup_file_name(at);
+        let second = backup_file_name(at + ChronoDuration::milliseconds(1));
+        assert_eq!(first, "pos-backup-20240502-020000-000.dump");
+        assert_ne!(first, second);
+
+        // Names from before milliseconds still list, ordered by time
+        let legacy = "pos-backup-20240502-015959.dump";
+        let dir = std::env::temp_dir().join(format!("backup-names-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in [legacy, first.as_str(), second.as_str()] {
+            std::fs::write(dir.join(name), b"x").unwrap();
+        }
+        let names: Vec<String> = list_backups(&dir).unwrap().into_iter().map(|entry| entry.file_name).collect();
+        assert_eq!(names, vec![second.clone(), first.clone(), legacy.to_string()]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Needs a scratch database, e.g. `POS_TEST_DATABASE_URL=postgresql://localhost/pos_backup_test`
+    #[tokio::test]
+    async fn test_backup_and_restore_against_local_postgres() {
+        let Ok(database_url) = std::env::var("POS_TEST_DATABASE_URL") else {
+            eprintln!("POS_TEST_DATABASE_URL not set; skipping");
+            return;
+        };
+
+        let dir = std::env::temp_dir().join(format!("backup-{}", uuid::Uuid::new_v4()));
+        let config = BackupConfig {
+            backup_dir: Some(dir.clone()),
+            ..BackupConfig::default()
+        };
+
+        let psql = |sql: &str| {
+            let status = std::process::Command::new("psql")
+                .arg(&database_url)
+                .args(["-q", "-v", "ON_ERROR_STOP=1", "-c", sql])
+                .status()
+                .unwrap();
+            assert!(status.success(), "psql failed: {}", sql);
+        };
+        psql("DROP TABLE IF EXISTS backup_probe; CREATE TABLE backup_probe (id INT); INSERT INTO backup_probe VALUES (1), (2);");
+
+        let mut manager = BackupManager::new();
+        let events = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        manager.set_progress_callback(move |progress| sink.lock().push(progress.stage));
+        manager.start(config, Some(database_url.clone())).await.unwrap();
+
+        let entry = manager.worker().backup().await.unwrap();
+        assert!(entry.size_bytes > 0);
+        assert_eq!(manager.list_backups().await.unwrap(), vec![entry.clone()]);
+
+        // Through a USB stick and back
+        let usb = dir.join("usb");
+        std::fs::create_dir_all(&usb).unwrap();
+        let origin = BackupOrigin {
+            branch_code: Some("JKT01".to_string()),
+            ..BackupOrigin::default()
+        };
+        let exported = manager.worker().export(&entry.file_name, &usb, "kasir rahasia", origin).await.unwrap();
+        assert_eq!(exported.manifest.dump_size, entry.size_bytes);
+        assert!(exported.path.file_name().unwrap().to_string_lossy().starts_with("JKT01-pos-backup-"));
+
+        std::fs::remove_file(dir.join(&entry.file_name)).unwrap();
+        assert!(manager.worker().import(&exported.path, "wrong passphrase").await.is_err());
+        let imported = manager.worker().import(&exported.path, "kasir rahasia").await.unwrap();
+        assert_eq!(imported.entry, entry);
+        assert!(imported.schema_matches);
+
+        psql("DROP TABLE backup_probe;");
+        manager.worker().restore(&entry.file_name).await.unwrap();
+        psql("DO $$ BEGIN IF (SELECT COUNT(*) FROM backup_probe) <> 2 THEN RAISE EXCEPTION 'not restored'; END IF; END $$;");
+        psql("DROP TABLE backup_probe;");
+
+        assert!(manager.worker().restore("../etc/passwd").await.is_err());
+        assert_eq!(events.lock().last(), Some(&BackupStage::Failed));
+        assert!(events.lock().contains(&BackupStage::Restoring));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,994 @@
+// ======================================================================
+// CATALOG CACHE
+// Salinan lokal katalog produk cabang untuk scan dan cek harga saat offline
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use log::{debug, error, info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::Mutex;
+
+use crate::{
+    scale::{self, EmbeddedBarcode, EmbeddedBarcodeLayout},
+    utils,
+};
+
+/// File name of the catalog snapshot inside the app data directory
+pub const CATALOG_FILE: &str = "catalog.db";
+
+/// Endpoint serving catalog changes since a timestamp
+const DELTA_PATH: &str = "/api/catalog/delta";
+
+/// Minimum trigram similarity for a fuzzy match
+const FUZZY_THRESHOLD: f32 = 0.35;
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS catalog_meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS catalog_products (
+    id TEXT PRIMARY KEY,
+    sku TEXT NOT NULL,
+    barcode TEXT,
+    name TEXT NOT NULL,
+    category_id TEXT,
+    brand_id TEXT,
+    unit_id TEXT NOT NULL,
+    tax_group_id TEXT,
+    cost INTEGER NOT NULL DEFAULT 0,
+    is_active INTEGER NOT NULL DEFAULT 1,
+    updated_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS catalog_prices (
+    id TEXT PRIMARY KEY,
+    product_id TEXT NOT NULL,
+    price_level TEXT NOT NULL DEFAULT 'default',
+    branch_id TEXT,
+    price INTEGER NOT NULL,
+    effective_from TEXT NOT NULL,
+    effective_to TEXT,
+    is_active INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE TABLE IF NOT EXISTS catalog_tax_groups (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    rate REAL NOT NULL DEFAULT 0,
+    is_active INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE TABLE IF NOT EXISTS catalog_units (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    symbol TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS catalog_stocks (
+    product_id TEXT PRIMARY KEY,
+    quantity INTEGER NOT NULL DEFAULT 0,
+    reserved_quantity INTEGER NOT NULL DEFAULT 0,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_catalog_prices_product ON catalog_prices(product_id, price_level);
+"#;
+
+// ======================================================================
+// DELTA TYPES
+// ======================================================================
+
+/// Rows as served by `GET /api/catalog/delta`, mirroring the Postgres tables
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogDelta {
+    pub server_time: String,
+    #[serde(default)]
+    pub products: Vec<ProductRow>,
+    #[serde(default)]
+    pub prices: Vec<PriceRow>,
+    #[serde(default)]
+    pub tax_groups: Vec<TaxGroupRow>,
+    #[serde(default)]
+    pub units: Vec<UnitRow>,
+    #[serde(default)]
+    pub stocks: Vec<StockRow>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductRow {
+    pub id: String,
+    pub sku: String,
+    pub barcode: Option<String>,
+    pub name: String,
+    pub category_id: Option<String>,
+    pub brand_id: Option<String>,
+    pub unit_id: String,
+    pub tax_group_id: Option<String>,
+    #[serde(default)]
+    pub cost: i64,
+    pub is_active: bool,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceRow {
+    pub id: String,
+    pub product_id: String,
+    pub price_level: String,
+    pub branch_id: Option<String>,
+    pub price: i64,
+    pub effective_from: String,
+    pub effective_to: Option<String>,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaxGroupRow {
+    pub id: String,
+    pub name: String,
+    /// `DECIMAL(5,4)` arrives as a string such as `"0.1100"`
+    #[serde(deserialize_with = "deserialize_decimal")]
+    pub rate: f64,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnitRow {
+    pub id: String,
+    pub name: String,
+    pub symbol: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StockRow {
+    pub product_id: String,
+    pub quantity: i64,
+    pub reserved_quantity: i64,
+    pub updated_at: String,
+}
+
+fn deserialize_decimal<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Decimal {
+        Number(f64),
+        Text(String),
+    }
+
+    match Decimal::deserialize(deserializer)? {
+        Decimal::Number(value) => Ok(value),
+        Decimal::Text(text) => text.trim().parse().map_err(serde::de::Error::custom),
+    }
+}
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+/// A product with its effective branch price, tax and stock
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogProduct {
+    pub id: String,
+    pub sku: String,
+    pub barcode: Option<String>,
+    pub name: String,
+    pub unit: Option<String>,
+    pub tax_group: Option<String>,
+    pub tax_rate: f64,
+    pub price: Option<i64>,
+    /// On hand minus reserved, when the branch tracks stock for it
+    pub stock: Option<i64>,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BarcodeMatch {
+    pub product: CatalogProduct,
+    /// Set for in-store weight/price barcodes (prefix 20–29)
+    pub embedded: Option<EmbeddedBarcode>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeltaSummary {
+    pub products: usize,
+    pub prices: usize,
+    pub stocks: usize,
+    pub server_time: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CatalogStats {
+    pub branch_id: Option<String>,
+    pub products: i64,
+    pub indexed_products: usize,
+    pub last_synced_at: Option<String>,
+}
+
+// ======================================================================
+// SEARCH INDEX
+// ======================================================================
+
+struct IndexEntry {
+    product_id: String,
+    name_length: usize,
+    tokens: Vec<String>,
+    trigram_count: usize,
+}
+
+/// In-memory prefix and trigram index over active products
+#[derive(Default)]
+pub struct CatalogIndex {
+    entries: Vec<IndexEntry>,
+    by_code: HashMap<String, u32>,
+    /// Sorted `(token, entry)` pairs for prefix range scans
+    prefixes: Vec<(String, u32)>,
+    trigrams: HashMap<String, Vec<u32>>,
+}
+
+/// Lowercase, strip punctuation and split into words
+fn fold(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn trigrams(words: &[String]) -> HashSet<String> {
+    let mut grams = HashSet::new();
+    for word in words {
+        let padded: Vec<char> = format!("  {} ", word).chars().collect();
+        for window in padded.windows(3) {
+            grams.insert(window.iter().collect());
+        }
+    }
+    grams
+}
+
+impl CatalogIndex {
+    pub fn build<'a>(products: impl Iterator<Item = (&'a str, &'a str, Option<&'a str>, &'a str)>) -> Self {
+        let mut index = Self::default();
+
+        for (id, sku, barcode, name) in products {
+            let slot = index.entries.len() as u32;
+            let words = fold(name);
+            let grams = trigrams(&words);
+
+            let mut tokens = words;
+            tokens.extend(fold(sku));
+            if let Some(barcode) = barcode {
+                tokens.push(barcode.to_lowercase());
+                index.by_code.insert(barcode.trim().to_uppercase(), slot);
+            }
+            index.by_code.entry(sku.trim().to_uppercase()).or_insert(slot);
+
+            for token in &tokens {
+                index.prefixes.push((token.clone(), slot));
+            }
+            for gram in &grams {
+                index.trigrams.entry(gram.clone()).or_default().push(slot);
+            }
+
+            index.entries.push(IndexEntry {
+                product_id: id.to_string(),
+                name_length: name.chars().count(),
+                tokens,
+                trigram_count: grams.len(),
+            });
+        }
+
+        index.prefixes.sort_unstable();
+        index.prefixes.dedup();
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Exact barcode or SKU match
+    pub fn lookup_code(&self, code: &str) -> Option<&str> {
+        self.by_code
+            .get(&code.trim().to_uppercase())
+            .map(|slot| self.entries[*slot as usize].product_id.as_str())
+    }
+
+    /// Product ids ranked by prefix matches first, then fuzzy similarity
+    pub fn search(&self, query: &str, limit: usize) -> Vec<String> {
+        let words = fold(query);
+        if words.is_empty() || limit == 0 {
+            return Vec::new();
+        }
+
+        let mut ranked = self.prefix_matches(&words);
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked.truncate(limit);
+
+        if ranked.len() < limit {
+            let seen: HashSet<u32> = ranked.iter().map(|(slot, _)| *slot).collect();
+            let mut fuzzy = self.fuzzy_matches(&words, &seen);
+            fuzzy.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            ranked.extend(fuzzy.into_iter().take(limit - ranked.len()));
+        }
+
+        ranked
+            .into_iter()
+            .map(|(slot, _)| self.entries[slot as usize].product_id.clone())
+            .collect()
+    }
+
+    fn prefix_range(&self, prefix: &str) -> &[(String, u32)] {
+        let start = self.prefixes.partition_point(|(token, _)| token.as_str() < prefix);
+        let end = start
+            + self.prefixes[start..].partition_point(|(token, _)| token.starts_with(prefix));
+        &self.prefixes[start..end]
+    }
+
+    /// Every query word must prefix some token of the product
+    fn prefix_matches(&self, words: &[String]) -> Vec<(u32, f32)> {
+        // Drive the scan from the most selective word
+        let ranges: Vec<&[(String, u32)]> = words.iter().map(|word| self.prefix_range(word)).collect();
+        let Some(driver) = ranges.iter().min_by_key(|range| range.len()) else {
+            return Vec::new();
+        };
+
+        let candidates: HashSet<u32> = driver.iter().map(|(_, slot)| *slot).collect();
+
+        candidates
+            .into_iter()
+            .filter_map(|slot| {
+                let entry = &self.entries[slot as usize];
+                let all_match = words
+                    .iter()
+                    .all(|word| entry.tokens.iter().any(|token| token.starts_with(word.as_str())));
+                if !all_match {
+                    return None;
+                }
+
+                let exact = words
+                    .iter()
+                    .filter(|word| entry.tokens.iter().any(|token| token == *word))
+                    .count();
+                let leading = entry.tokens.first().is_some_and(|token| token.starts_with(words[0].as_str()));
+
+                // Exact words and a matching first word rank higher; shorter names break ties
+                let score = 2.0 + exact as f32 + if leading { 1.0 } else { 0.0 }
+                    - entry.name_length as f32 / 1000.0;
+                Some((slot, score))
+            })
+            .collect()
+    }
+
+    /// Dice coefficient over word trigrams, tolerant of typos
+    fn fuzzy_matches(&self, words: &[String], exclude: &HashSet<u32>) -> Vec<(u32, f32)> {
+        let grams = trigrams(words);
+        if grams.is_empty() {
+            return Vec::new();
+        }
+
+        let mut shared: HashMap<u32, u16> = HashMap::new();
+        for gram in &grams {
+            if let Some(postings) = self.trigrams.get(gram) {
+                for slot in postings {
+                    *shared.entry(*slot).or_default() += 1;
+                }
+            }
+        }
+
+        shared
+            .into_iter()
+            .filter(|(slot, _)| !exclude.contains(slot))
+            .filter_map(|(slot, count)| {
+                let entry = &self.entries[slot as usize];
+                let score = 2.0 * count as f32 / (grams.len() + entry.trigram_count) as f32;
+                (score >= FUZZY_THRESHOLD).then_some((slot, score))
+            })
+            .collect()
+    }
+}
+
+// ======================================================================
+// CATALOG CACHE
+// ======================================================================
+
+pub struct CatalogCache {
+    conn: Connection,
+    index: CatalogIndex,
+    path: Option<PathBuf>,
+    embedded_layout: EmbeddedBarcodeLayout,
+    upstream: Option<String>,
+}
+
+impl CatalogCache {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            utils::ensure_directory_exists(parent).map_err(|e| anyhow!(e))?;
+        }
+
+        let conn = Connection::open(path)
+            .map_err(|e| anyhow!("Failed to open catalog {:?}: {}", path, e))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+
+        let cache = Self::init(conn, Some(path.to_path_buf()))?;
+        info!("Opened catalog cache at {:?} ({} products indexed)", path, cache.index.len());
+        Ok(cache)
+    }
+
+    /// Open the snapshot in the app data directory, falling back to memory
+    pub fn open_default() -> Self {
+        let path = utils::get_app_data_dir().join(CATALOG_FILE);
+
+        Self::open(&path).unwrap_or_else(|e| {
+            error!("{}; caching the catalog in memory only", e);
+            Self::open_in_memory().expect("in-memory catalog")
+        })
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?, None)
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn set_embedded_layout(&mut self, layout: EmbeddedBarcodeLayout) {
+        self.embedded_layout = layout;
+    }
+
+    /// Base URL serving the delta endpoint, set once the sidecar is up
+    pub fn set_upstream(&mut self, base_url: &str) {
+        self.upstream = Some(base_url.to_string());
+    }
+
+    pub fn upstream(&self) -> Option<&str> {
+        self.upstream.as_deref()
+    }
+
+    pub fn branch_id(&self) -> Result<Option<String>> {
+        self.meta("branch_id")
+    }
+
+    pub fn last_synced_at(&self) -> Result<Option<String>> {
+        self.meta("last_synced_at")
+    }
+
+    /// Switch branches; a different branch means prices and stock start over
+    pub fn set_branch(&mut self, branch_id: &str) -> Result<()> {
+        if self.branch_id()?.as_deref() == Some(branch_id) {
+            return Ok(());
+        }
+
+        info!("Catalog cache switching to branch {}", branch_id);
+        self.conn.execute_batch(
+            "DELETE FROM catalog_products; DELETE FROM catalog_prices; DELETE FROM catalog_stocks; \
+             DELETE FROM catalog_meta WHERE key = 'last_synced_at';",
+        )?;
+        self.set_meta("branch_id", branch_id)?;
+        self.index = CatalogIndex::default();
+        Ok(())
+    }
+
+    /// Upsert a delta in one transaction and refresh the search index
+    pub fn apply_delta(&mut self, delta: &CatalogDelta) -> Result<DeltaSummary> {
+        let server_time = normalize_timestamp(&delta.server_time)?;
+        let tx = self.conn.transaction()?;
+
+        for unit in &delta.units {
+            tx.execute(
+                "INSERT INTO catalog_units (id, name, symbol) VALUES (?1, ?2, ?3) \
+                 ON CONFLICT(id) DO UPDATE SET name = excluded.name, symbol = excluded.symbol",
+                params![unit.id, unit.name, unit.symbol],
+            )?;
+        }
+
+        for group in &delta.tax_groups {
+            tx.execute(
+                "INSERT INTO catalog_tax_groups (id, name, rate, is_active) VALUES (?1, ?2, ?3, ?4) \
+                 ON CONFLICT(id) DO UPDATE SET name = excluded.name, rate = excluded.rate, \
+                 is_active = excluded.is_active",
+                params![group.id, group.name, group.rate, group.is_active],
+            )?;
+        }
+
+        for product in &delta.products {
+            tx.execute(
+                "INSERT INTO catalog_products (id, sku, barcode, name, category_id, brand_id, unit_id, \
+                 tax_group_id, cost, is_active, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11) \
+                 ON CONFLICT(id) DO UPDATE SET sku = excluded.sku, barcode = excluded.barcode, \
+                 name = excluded.name, category_id = excluded.category_id, brand_id = excluded.brand_id, \
+                 unit_id = excluded.unit_id, tax_group_id = excluded.tax_group_id, cost = excluded.cost, \
+                 is_active = excluded.is_active, updated_at = excluded.updated_at",
+                params![
+                    product.id,
+                    product.sku,
+                    product.barcode,
+                    product.name,
+                    product.category_id,
+                    product.brand_id,
+                    product.unit_id,
+                    product.tax_group_id,
+                    product.cost,
+                    product.is_active,
+                    normalize_timestamp(&product.updated_at)?,
+                ],
+            )?;
+        }
+
+        for price in &delta.prices {
+            tx.execute(
+                "INSERT INTO catalog_prices (id, product_id, price_level, branch_id, price, effective_from, \
+                 effective_to, is_active) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8) \
+                 ON CONFLICT(id) DO UPDATE SET product_id = excluded.product_id, \
+                 price_level = excluded.price_level, branch_id = excluded.branch_id, price = excluded.price, \
+                 effective_from = excluded.effective_from, effective_to = excluded.effective_to, \
+                 is_active = excluded.is_active",
+                params![
+                    price.id,
+                    price.product_id,
+                    price.price_level,
+                    price.branch_id,
+                    price.price,
+                    normalize_timestamp(&price.effective_from)?,
+                    price.effective_to.as_deref().map(normalize_timestamp).transpose()?,
+                    price.is_active,
+                ],
+            )?;
+        }
+
+        for stock in &delta.stocks {
+            tx.execute(
+                "INSERT INTO catalog_stocks (product_id, quantity, reserved_quantity, updated_at) \
+                 VALUES (?1, ?2, ?3, ?4) ON CONFLICT(product_id) DO UPDATE SET \
+                 quantity = excluded.quantity, reserved_quantity = excluded.reserved_quantity, \
+                 updated_at = excluded.updated_at",
+                params![
+                    stock.product_id,
+                    stock.quantity,
+                    stock.reserved_quantity,
+                    normalize_timestamp(&stock.updated_at)?,
+                ],
+            )?;
+        }
+
+        tx.execute(
+            "INSERT INTO catalog_meta (key, value) VALUES ('last_synced_at', ?1) \
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![server_time],
+        )?;
+        tx.commit()?;
+
+        if !delta.products.is_empty() {
+            self.rebuild_index()?;
+        }
+
+        let summary = DeltaSummary {
+            products: delta.products.len(),
+            prices: delta.prices.len(),
+            stocks: delta.stocks.len(),
+            server_time,
+        };
+        debug!("Applied catalog delta: {:?}", summary);
+        Ok(summary)
+    }
+
+    /// Resolve a scanned code, including weight/price-embedded EAN-13
+    pub fn lookup_barcode(&self, code: &str) -> Result<Option<BarcodeMatch>> {
+        if let Some(id) = self.index.lookup_code(code) {
+            return Ok(self.get_product(id)?.map(|product| BarcodeMatch {
+                product,
+                embedded: None,
+            }));
+        }
+
+        let Some(embedded) = scale::parse_embedded_barcode(code, &self.embedded_layout) else {
+            return Ok(None);
+        };
+
+        // Scales print the PLU, usually the SKU with or without leading zeros
+        let plu = embedded.item_code.trim_start_matches('0');
+        let id = self
+            .index
+            .lookup_code(&embedded.item_code)
+            .or_else(|| self.index.lookup_code(plu));
+
+        match id {
+            Some(id) => Ok(self.get_product(id)?.map(|product| BarcodeMatch {
+                product,
+                embedded: Some(embedded),
+            })),
+            None => Ok(None),
+        }
+    }
+
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<CatalogProduct>> {
+        let mut products = Vec::new();
+        for id in self.index.search(query, limit) {
+            if let Some(product) = self.get_product(&id)? {
+                products.push(product);
+            }
+        }
+        Ok(products)
+    }
+
+    pub fn get_product(&self, id: &str) -> Result<Option<CatalogProduct>> {
+        let branch_id = self.branch_id()?;
+        let now = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+
+        let product = self
+            .conn
+            .query_row(
+                "SELECT p.id, p.sku, p.barcode, p.name, u.symbol, t.name, COALESCE(t.rate, 0), \
+                 (SELECT pr.price FROM catalog_prices pr \
+                  WHERE pr.product_id = p.id AND pr.is_active = 1 AND pr.price_level = 'default' \
+                  AND (pr.branch_id IS NULL OR pr.branch_id = ?2) \
+                  AND pr.effective_from <= ?3 AND (pr.effective_to IS NULL OR pr.effective_to > ?3) \
+                  ORDER BY pr.branch_id IS NULL, pr.effective_from DESC LIMIT 1), \
+                 s.quantity - s.reserved_quantity, p.is_active \
+                 FROM catalog_products p \
+                 LEFT JOIN catalog_units u ON u.id = p.unit_id \
+                 LEFT JOIN catalog_tax_groups t ON t.id = p.tax_group_id AND t.is_active = 1 \
+                 LEFT JOIN catalog_stocks s ON s.product_id = p.id \
+                 WHERE p.id = ?1",
+                params![id, branch_id, now],
+                |row| {
+                    Ok(CatalogProduct {
+                        id: row.get(0)?,
+                        sku: row.get(1)?,
+                        barcode: row.get(2)?,
+                        name: row.get(3)?,
+                        unit: row.get(4)?,
+                        tax_group: row.get(5)?,
+                        tax_rate: row.get(6)?,
+                        price: row.get(7)?,
+                        stock: row.get(8)?,
+                        is_active: row.get(9)?,
+                    })
+                },
+            )
+            .optional()?;
+
+        Ok(product)
+    }
+
+    pub fn stats(&self) -> Result<CatalogStats> {
+        Ok(CatalogStats {
+            branch_id: self.branch_id()?,
+            products: self
+                .conn
+                .query_row("SELECT COUNT(*) FROM catalog_products", [], |row| row.get(0))?,
+            indexed_products: self.index.len(),
+            last_synced_at: self.last_synced_at()?,
+        })
+    }
+
+    // ======================================================================
+    // PRIVATE METHODS
+    // ======================================================================
+
+    fn init(conn: Connection, path: Option<PathBuf>) -> Result<Self> {
+        conn.execute_batch(SCHEMA)
+            .map_err(|e| anyhow!("Failed to initialize catalog schema: {}", e))?;
+
+        let mut cache = Self {
+            conn,
+            index: CatalogIndex::default(),
+            path,
+            embedded_layout: EmbeddedBarcodeLayout::default(),
+            upstream: None,
+        };
+        cache.rebuild_index()?;
+        Ok(cache)
+    }
+
+    fn rebuild_index(&mut self) -> Result<()> {
+        let started = std::time::Instant::now();
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, sku, barcode, name FROM catalog_products WHERE is_active = 1")?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let index = CatalogIndex::build(
+            rows.iter()
+                .map(|(id, sku, barcode, name)| (id.as_str(), sku.as_str(), barcode.as_deref(), name.as_str())),
+        );
+        drop(stmt);
+
+        debug!("Indexed {} products in {:?}", index.len(), started.elapsed());
+        self.index = index;
+        Ok(())
+    }
+
+    fn meta(&self, key: &str) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row("SELECT value FROM catalog_meta WHERE key = ?1", params![key], |row| row.get(0))
+            .optional()?)
+    }
+
+    fn set_meta(&self, key: &str, value: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO catalog_meta (key, value) VALUES (?1, ?2) \
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+}
+
+/// Store timestamps as UTC with fixed precision so they compare as text
+fn normalize_timestamp(value: &str) -> Result<String> {
+    let parsed = chrono::DateTime::parse_from_rfc3339(value)
+        .map_err(|e| anyhow!("Invalid timestamp {:?}: {}", value, e))?;
+    Ok(parsed
+        .with_timezone(&chrono::Utc)
+        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        .to_string())
+}
+
+// ======================================================================
+// DELTA SYNC
+// ======================================================================
+
+#[derive(Deserialize)]
+struct DeltaResponse {
+    success: bool,
+    data: Option<CatalogDelta>,
+    error: Option<String>,
+}
+
+/// Fetch catalog changes for a branch since the last sync
+pub async fn fetch_delta(
+    client: &reqwest::Client,
+    base_url: &str,
+    branch_id: &str,
+    since: Option<&str>,
+) -> Result<CatalogDelta> {
+    let url = format!("{}{}", base_url.trim_end_matches('/'), DELTA_PATH);
+    let mut query = vec![("branchId", branch_id)];
+    if let Some(since) = since {
+        query.push(("since", since));
+    }
+
+    let response: DeltaResponse = client
+        .get(&url)
+        .query(&query)
+        .timeout(Duration::from_secs(60))
+        .send()
+        .await?
+        .json()
+        .await
+        .map_err(|e| anyhow!("Invalid catalog delta response: {}", e))?;
+
+    match (response.success, response.data) {
+        (true, Some(delta)) => Ok(delta),
+        _ => Err(anyhow!(
+            "Catalog delta failed: {}",
+            response.error.unwrap_or_else(|| "unknown error".to_string())
+        )),
+    }
+}
+
+/// Pull and apply one delta; the network round trip happens outside the lock
+pub async fn refresh(
+    cache: &Mutex<CatalogCache>,
+    client: &reqwest::Client,
+    branch_id: &str,
+) -> Result<DeltaSummary> {
+    let (base_url, since) = {
+        let mut cache = cache.lock().await;
+        let base_url = cache
+            .upstream()
+            .ok_or_else(|| anyhow!("Catalog upstream is not configured"))?
+            .to_string();
+        cache.set_branch(branch_id)?;
+        (base_url, cache.last_synced_at()?)
+    };
+
+    let delta = fetch_delta(client, &base_url, branch_id, since.as_deref()).await?;
+    let summary = cache.lock().await.apply_delta(&delta)?;
+
+    if summary.products > 0 || summary.prices > 0 || summary.stocks > 0 {
+        info!(
+            "Catalog updated: {} products, {} prices, {} stocks",
+            summary.products, summary.prices, summary.stocks
+        );
+    }
+    Ok(summary)
+}
+
+/// Keep the snapshot fresh while the app runs
+pub async fn refresh_loop(cache: Arc<Mutex<CatalogCache>>, branch_id: String, every: Duration) {
+    let client = reqwest::Client::new();
+    let mut interval = tokio::time::interval(every);
+
+    loop {
+        interval.tick().await;
+        if let Err(e) = refresh(&cache, &client, &branch_id).await {
+            warn!("Catalog refresh failed, serving cached data: {}", e);
+        }
+    }
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn product(id: &str, sku: &str, barcode: Option<&str>, name: &str) -> ProductRow {
+        ProductRow {
+            id: id.to_string(),
+            sku: sku.to_string(),
+            barcode: barcode.map(str::to_string),
+            name: name.to_string(),
+            category_id: None,
+            brand_id: None,
+            unit_id: "unit-pcs".to_string(),
+            tax_group_id: Some("ppn".to_string()),
+            cost: 0,
+            is_active: true,
+            updated_at: "2024-05-01T08:00:00+07:00".to_string(),
+        }
+    }
+
+    fn price(id: &str, product_id: &str, branch_id: Option<&str>, price: i64, from: &str) -> PriceRow {
+        PriceRow {
+            id: id.to_string(),
+            product_id: product_id.to_string(),
+            price_level: "default".to_string(),
+            branch_id: branch_id.map(str::to_string),
+            price,
+            effective_from: from.to_string(),
+            effective_to: None,
+            is_active: true,
+        }
+    }
+
+    fn seeded_cache() -> CatalogCache {
+        let mut cache = CatalogCache::open_in_memory().unwrap();
+        cache.set_branch("branch-1").unwrap();
+
+        let delta: CatalogDelta = serde_json::from_value(serde_json::json!({
+            "serverTime": "2024-05-01T10:00:00Z",
+            "units": [{ "id": "unit-pcs", "name": "Pieces", "symbol": "PCS" }],
+            "taxGroups": [{ "id": "ppn", "name": "PPN 11%", "rate": "0.1100", "isActive": true }],
+        }))
+        .unwrap();
+        let mut delta = CatalogDelta {
+            products: vec![
+                product("p1", "BRG001", Some("8992388123456"), "Indomie Goreng Ayam Bawang"),
+                product("p2", "BRG002", Some("8992771234567"), "Aqua 600ml"),
+                product("p3", "01234", None, "Beras Premium Curah"),
+            ],
+            prices: vec![
+                price("pr1", "p1", None, 3_500, "2024-01-01T00:00:00Z"),
+                price("pr2", "p1", Some("branch-1"), 3_300, "2024-01-01T00:00:00Z"),
+                price("pr3", "p2", None, 3_000, "2024-01-01T00:00:00Z"),
+                price("pr4", "p2", None, 3_500, "2999-01-01T00:00:00Z"),
+                price("pr5", "p3", None, 14_000, "2024-01-01T00:00:00Z"),
+            ],
+            stocks: vec![StockRow {
+                product_id: "p1".to_string(),
+                quantity: 100,
+                reserved_quantity: 4,
+                updated_at: "2024-05-01T09:00:00Z".to_string(),
+            }],
+            ..delta
+        };
+        delta.server_time = "2024-05-01T10:00:00+07:00".to_string();
+
+        cache.apply_delta(&delta).unwrap();
+        cache
+    }
+
+    #[test]
+    fn test_lookup_barcode_resolves_branch_price_tax_and_stock() {
+        let cache = seeded_cache();
+
+        let found = cache.lookup_barcode("8992388123456").unwrap().unwrap();
+        assert_eq!(found.product.price, Some(3_300));
+        assert_eq!(found.product.stock, Some(96));
+        assert_eq!(found.product.unit.as_deref(), Some("PCS"));
+        assert!((found.product.tax_rate - 0.11).abs() < 1e-9);
+
+        // Future price is not effective yet
+        let aqua = cache.lookup_barcode("brg002").unwrap().unwrap();
+        assert_eq!(aqua.product.price, Some(3_000));
+
+        assert!(cache.lookup_barcode("0000000000000").unwrap().is_none());
+        assert_eq!(cache.last_synced_at().unwrap().as_deref(), Some("2024-05-01T03:00:00.000Z"));
+    }
+
+    #[test]
+    fn test_lookup_embedded_weight_barcode() {
+        let cache = seeded_cache();
+
+        let found = cache.lookup_barcode("2001234012508").unwrap().unwrap();
+        assert_eq!(found.product.id, "p3");
+        assert_eq!(found.embedded.unwrap().weight_grams, Some(1_250));
+    }
+
+    #[test]
+    fn test_search_prefix_and_fuzzy() {
+        let cache = seeded_cache();
+
+        let ids = |query: &str| -> Vec<String> {
+            cache.search(query, 10).unwrap().into_iter().map(|p| p.id).collect()
+        };
+
+        assert_eq!(ids("indo gor"), vec!["p1"]);
+        assert_eq!(ids("AQUA"), vec!["p2"]);
+        assert_eq!(ids("brg00").len(), 2);
+        assert_eq!(ids("indomei goreng").first().map(String::as_str), Some("p1"));
+        assert!(ids("zzzz").is_empty());
+    }
+
+    #[test]
+    fn test_delta_deactivates_and_branch_switch_clears() {
+        let mut cache = seeded_cache();
+
+        let mut inactive = product("p2", "BRG002", Some("8992771234567"), "Aqua 600ml");
+        inactive.is_active = false;
+        cache
+            .apply_delta(&CatalogDelta {
+                server_time: "2024-05-02T10:00:00Z".to_string(),
+                products: vec![inactive],
+                ..CatalogDelta::default()
+            })
+            .unwrap();
+        assert!(cache.lookup_barcode("8992771234567").unwrap().is_none());
+
+        cache.set_branch("branch-2").unwrap();
+        assert_eq!(cache.stats().unwrap().products, 0);
+        assert!(cache.last_synced_at().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_index_scales_to_100k_products() {
+        let products: Vec<(String, String, String)> = (0..100_000)
+            .map(|i| (format!("p{}", i), format!("SKU{:06}", i), format!("Produk {} Varian {}", i % 977, i)))
+            .collect();
+
+        let index = CatalogIndex::build(
+            products.iter().map(|(id, sku, name)| (id.as_str(), sku.as_str(), None, name.as_str())),
+        );
+        assert_eq!(index.len(), 100_000);
+
+        let started = std::time::Instant::now();
+        assert_eq!(index.lookup_code("sku012345"), Some("p12345"));
+        assert_eq!(index.search("varian 99999", 5).first().map(String::as_str), Some("p99999"));
+        assert_eq!(index.search("produk 976", 20).len(), 20);
+        debug!("100k search took {:?}", started.elapsed());
+    }
+}
@@ -3,17 +3,56 @@
 // Implementasi command handlers untuk komunikasi frontend-backend
 // ======================================================================
 
-use crate::{AppState, sidecar::SidecarStatus, system::SystemInfo, watchdog::WatchdogStatus};
-use log::{error, info};
+use crate::{
+    AppState,
+    approval::{Approval, ApprovalAction, ApprovalCredential, SupervisorInfo, TotpEnrollment},
+    archive::{self, ArchiveManifest},
+    audit::{self, AuditEvent, AuditOutcome, AuditQuery, AuditVerification},
+    backup::{BackupConfig, BackupEntry, BackupOrigin, BackupStatus, CronSchedule, ExportedBackup, ImportedBackup},
+    catalog::{self, BarcodeMatch, CatalogProduct, CatalogStats, DeltaSummary},
+    customer_display::{CustomerDisplay, DisplayStatus, VfdCommandSet},
+    devices::{DeviceConfig, DeviceKind},
+    files::{self, FileService, ImportedCsv, SavedFile},
+    idle_lock::{self, IdleLockConfig, LockStatus},
+    ipc::SessionInfo,
+    kiosk::{self, KioskConfig, KioskStatus},
+    journal::{JournalOrder, JournalStats, NewOrder, SyncStatus},
+    migrations::MigrationProgress,
+    order_no::{self, OrderNumberAllocator, OrderNumberStatus},
+    postgres::{self, LocalDatabaseConfig, PostgresStatus},
+    printer,
+    scale::{self, EmbeddedBarcode, EmbeddedBarcodeLayout, ScaleProtocol, ScaleService, ScaleStatus, WeightReading},
+    scanner::{ScannerService, ScannerStatus, SerialPortEntry},
+    secrets::{self, SecretInfo, SecretKind},
+    sidecar::SidecarStatus,
+    sync::{SyncConfig, SyncProgress},
+    system::SystemInfo,
+    terminal::{self, RegistrationRequest, RequestSigner, TerminalIdentity},
+    tunnel::{TunnelConfig, TunnelStatus},
+    updater::{self, AvailableUpdate, UpdateConfig, UpdateStatus},
+    url_policy::{UrlPolicy, UrlPolicyConfig},
+    utils,
+    watchdog::WatchdogStatus,
+    window_manager::{self, CustomerView, DisplayInfo},
+};
+use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use tauri::{command, AppHandle, Manager, State};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use tauri::{command, AppHandle, Manager, State, Window};
 
 // ======================================================================
 // TYPES
 // ======================================================================
 
-#[derive(Debug, Serialize, Deserialize)]
+/// File name of the app config inside the app data directory
+pub const CONFIG_FILE: &str = "config.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppConfig {
     pub auto_start: bool,
     pub minimize_to_tray: bool,
@@ -23,6 +62,30 @@ pub struct AppConfig {
     pub language: String,
     pub notifications_enabled: bool,
     pub auto_update: bool,
+    /// Upstream Postgres, probed by the watchdog; falls back to `DATABASE_URL`
+    pub database_url: Option<String>,
+    /// Branch whose prices and stock the local catalog caches
+    pub branch_id: Option<String>,
+    /// Printed on terminal order numbers, e.g. `JKT01`
+    pub branch_code: Option<String>,
+    /// This till within the branch, e.g. `T02`
+    pub terminal_code: Option<String>,
+    /// Set once by `provision_terminal`; the private key lives next to the config
+    pub terminal: Option<TerminalIdentity>,
+    /// Scheduled `pg_dump` of `database_url`
+    pub backup: BackupConfig,
+    /// Bundled PostgreSQL for stores without a server; overrides `database_url` when enabled
+    pub local_database: LocalDatabaseConfig,
+    /// SSH port-forward to a `database_url` that is only reachable from the VPS
+    pub tunnel: TunnelConfig,
+    /// What `open_external_url` may hand to the OS
+    pub url_policy: UrlPolicyConfig,
+    /// Lock the main window down on dedicated tills
+    pub kiosk: KioskConfig,
+    /// Lock screen after a period without input
+    pub idle_lock: IdleLockConfig,
+    /// Channel feed and signing key; `auto_update` turns background checks on
+    pub updates: UpdateConfig,
 }
 
 impl Default for AppConfig {
@@ -36,7 +99,121 @@ impl Default for AppConfig {
             language: "id".to_string(),
             notifications_enabled: true,
             auto_update: true,
+            database_url: None,
+            branch_id: None,
+            branch_code: None,
+            terminal_code: None,
+            terminal: None,
+            backup: BackupConfig::default(),
+            local_database: LocalDatabaseConfig::default(),
+            tunnel: TunnelConfig::default(),
+            url_policy: UrlPolicyConfig::default(),
+            kiosk: KioskConfig::default(),
+            idle_lock: IdleLockConfig::default(),
+            updates: UpdateConfig::default(),
+        }
+    }
+}
+
+impl AppConfig {
+    /// Load `config.json`, falling back to defaults when it is missing or unreadable
+    pub fn load() -> Self {
+        let path = utils::get_app_data_dir().join(CONFIG_FILE);
+        
+        match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                error!("Invalid app config {:?}: {}", path, e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+    
+    /// Connection URL with the password from the credential store filled in
+    pub fn database_url(&self) -> Option<String> {
+        let url = self.database_url
+            .clone()
+            .or_else(|| std::env::var("DATABASE_URL").ok())?;
+        
+        match secrets::store().get(SecretKind::DatabasePassword) {
+            Ok(Some(password)) => Some(secrets::with_database_password(&url, &password).unwrap_or(url)),
+            Ok(None) => Some(url),
+            Err(e) => {
+                error!("{}", e);
+                Some(url)
+            }
+        }
+    }
+    
+    /// Move a password typed into `database_url` to the credential store; true when one was moved
+    pub fn move_database_password(&mut self) -> Result<bool, String> {
+        let Some(url) = &self.database_url else {
+            return Ok(false);
+        };
+        let (url, password) = secrets::split_database_password(url).map_err(|e| e.to_string())?;
+        let Some(password) = password else {
+            return Ok(false);
+        };
+        
+        secrets::store()
+            .set(SecretKind::DatabasePassword, password.expose())
+            .map_err(|e| e.to_string())?;
+        self.database_url = Some(url);
+        Ok(true)
+    }
+    
+    /// Terminal order numbering, when both branch and terminal codes are set
+    pub fn order_numbering(&self) -> Option<OrderNumberAllocator> {
+        let (Some(branch_code), Some(terminal_code)) = (&self.branch_code, &self.terminal_code) else {
+            return None;
+        };
+        
+        match OrderNumberAllocator::open_default(branch_code, terminal_code) {
+            Ok(numbering) => Some(numbering),
+            Err(e) => {
+                // The journal floor still prevents reusing a number already recorded
+                error!("{}; keeping the order counter in memory", e);
+                OrderNumberAllocator::in_memory(branch_code, terminal_code).ok()
+            }
+        }
+    }
+    
+    /// Signer for sync requests, once the till is provisioned
+    pub fn request_signer(&self) -> Option<RequestSigner> {
+        self.terminal.as_ref().and_then(terminal::load_signer)
+    }
+    
+    /// Sync settings towards the sidecar on `port`, signed and with the stored sync token
+    pub fn sync_config(&self, port: u16) -> SyncConfig {
+        SyncConfig {
+            base_url: format!("http://127.0.0.1:{}", port),
+            signer: self.request_signer(),
+            token: secrets::store().get(SecretKind::SyncToken).unwrap_or_else(|e| {
+                warn!("{}", e);
+                None
+            }),
+            ..SyncConfig::default()
+        }
+    }
+    
+    pub fn save(&self) -> Result<(), String> {
+        if let Some(code) = &self.branch_code {
+            order_no::validate_branch_code(code).map_err(|e| e.to_string())?;
+        }
+        if let Some(code) = &self.terminal_code {
+            order_no::validate_terminal_code(code).map_err(|e| e.to_string())?;
         }
+        CronSchedule::parse(&self.backup.schedule).map_err(|e| e.to_string())?;
+        if self.tunnel.enabled {
+            self.tunnel.validate().map_err(|e| e.to_string())?;
+        }
+        if let Some(feed_url) = &self.updates.feed_url {
+            updater::validate_update_url(feed_url).map_err(|e| e.to_string())?;
+        }
+        
+        let path = utils::get_app_data_dir().join(CONFIG_FILE);
+        let json = serde_json::to_vec_pretty(self).map_err(|e| e.to_string())?;
+        utils::write_file_atomic(&path, &json)
     }
 }
 
@@ -70,12 +247,17 @@ impl<T> CommandResult<T> {
 // ======================================================================
 
 #[command]
-pub async fn start_sidecar(state: State<'_, AppState>) -> Result<CommandResult<String>, String> {
+pub async fn start_sidecar(window: Window, state: State<'_, AppState>) -> Result<CommandResult<String>, String> {
     info!("Command: start_sidecar");
     
     let mut sidecar = state.sidecar_manager.lock().await;
+    let result = sidecar.start().await;
+    record_caller_event(&window, &state, "start_sidecar", match &result {
+        Ok(port) => json!({ "port": port }),
+        Err(e) => json!({ "error": e.to_string() }),
+    });
     
-    match sidecar.start().await {
+    match result {
         Ok(port) => {
             let url = format!("http://localhost:{}", port);
             info!("Sidecar started on port {}", port);
@@ -89,12 +271,17 @@ pub async fn start_sidecar(state: State<'_, AppState>) -> Result<CommandResult<S
 }
 
 #[command]
-pub async fn stop_sidecar(state: State<'_, AppState>) -> Result<CommandResult<()>, String> {
+pub async fn stop_sidecar(window: Window, state: State<'_, AppState>) -> Result<CommandResult<()>, String> {
     info!("Command: stop_sidecar");
     
     let mut sidecar = state.sidecar_manager.lock().await;
+    let result = sidecar.stop().await;
+    record_caller_event(&window, &state, "stop_sidecar", match &result {
+        Ok(_) => json!({}),
+        Err(e) => json!({ "error": e.to_string() }),
+    });
     
-    match sidecar.stop().await {
+    match result {
         Ok(_) => {
             info!("Sidecar stopped successfully");
             Ok(CommandResult::success(()))
@@ -107,12 +294,17 @@ pub async fn stop_sidecar(state: State<'_, AppState>) -> Result<CommandResult<()
 }
 
 #[command]
-pub async fn restart_sidecar(state: State<'_, AppState>) -> Result<CommandResult<String>, String> {
+pub async fn restart_sidecar(window: Window, state: State<'_, AppState>) -> Result<CommandResult<String>, String> {
     info!("Command: restart_sidecar");
     
     let mut sidecar = state.sidecar_manager.lock().await;
+    let result = sidecar.restart().await;
+    record_caller_event(&window, &state, "restart_sidecar", match &result {
+        Ok(port) => json!({ "port": port }),
+        Err(e) => json!({ "error": e.to_string() }),
+    });
     
-    match sidecar.restart().await {
+    match result {
         Ok(port) => {
             let url = format!("http://localhost:{}", port);
             info!("Sidecar restarted on port {}", port);
@@ -205,107 +397,1930 @@ pub async fn get_watchdog_status(state: State<'_, AppState>) -> Result<CommandRe
 }
 
 // ======================================================================
-// UTILITY COMMANDS
+// SCANNER COMMANDS
 // ======================================================================
 
 #[command]
-pub async fn show_notification(title: String, body: String, app_handle: AppHandle) -> Result<CommandResult<()>, String> {
-    use tauri::api::notification::Notification;
+pub async fn list_serial_ports() -> Result<CommandResult<Vec<SerialPortEntry>>, String> {
+    match ScannerService::list_ports() {
+        Ok(ports) => Ok(CommandResult::success(ports)),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+#[command]
+pub async fn start_scanner(
+    state: State<'_, AppState>,
+    device_id: Option<String>,
+    port: Option<String>,
+    baud_rate: Option<u32>,
+) -> Result<CommandResult<()>, String> {
+    info!("Command: start_scanner {:?} {:?}", device_id, port);
     
-    match Notification::new(&app_handle.config().tauri.bundle.identifier)
-        .title(&title)
-        .body(&body)
-        .show()
-    {
+    let device = match resolve_device(&state, DeviceKind::Scanner, device_id, port, baud_rate).await {
+        Ok(device) => device,
+        Err(e) => return Ok(CommandResult::error(e.to_string())),
+    };
+    let config = device.scanner_config();
+    
+    let mut scanner = state.scanner.lock().await;
+    
+    match scanner.start(config) {
         Ok(_) => Ok(CommandResult::success(())),
-        Err(e) => Ok(CommandResult::error(e.to_string())),
+        Err(e) => {
+            error!("Failed to start scanner: {}", e);
+            Ok(CommandResult::error(e.to_string()))
+        }
     }
 }
 
 #[command]
-pub async fn open_external_url(url: String) -> Result<CommandResult<()>, String> {
-    use tauri::api::shell;
+pub async fn stop_scanner(state: State<'_, AppState>) -> Result<CommandResult<()>, String> {
+    info!("Command: stop_scanner");
     
-    match shell::open(&url, None) {
+    let mut scanner = state.scanner.lock().await;
+    
+    match scanner.stop() {
         Ok(_) => Ok(CommandResult::success(())),
         Err(e) => Ok(CommandResult::error(e.to_string())),
     }
 }
 
 #[command]
-pub async fn get_app_version() -> Result<CommandResult<String>, String> {
-    let version = env!("CARGO_PKG_VERSION").to_string();
-    Ok(CommandResult::success(version))
+pub async fn get_scanner_status(state: State<'_, AppState>) -> Result<CommandResult<ScannerStatus>, String> {
+    let scanner = state.scanner.lock().await;
+    Ok(CommandResult::success(scanner.get_status()))
 }
 
+// ======================================================================
+// CUSTOMER DISPLAY COMMANDS
+// ======================================================================
+
 #[command]
-pub async fn get_app_config() -> Result<CommandResult<AppConfig>, String> {
-    // In a real app, this would load from a config file
-    let config = AppConfig::default();
-    Ok(CommandResult::success(config))
+pub async fn open_customer_display(
+    state: State<'_, AppState>,
+    device_id: Option<String>,
+    port: Option<String>,
+    baud_rate: Option<u32>,
+    command_set: Option<VfdCommandSet>,
+) -> Result<CommandResult<()>, String> {
+    info!("Command: open_customer_display {:?} {:?}", device_id, port);
+    
+    let device = match resolve_device(&state, DeviceKind::CustomerDisplay, device_id, port, baud_rate).await {
+        Ok(device) => device,
+        Err(e) => return Ok(CommandResult::error(e.to_string())),
+    };
+    let mut config = device.display_config();
+    if let Some(command_set) = command_set {
+        config.command_set = command_set;
+    }
+    
+    let mut display = state.customer_display.lock().await;
+    
+    match display.open(config) {
+        Ok(_) => Ok(CommandResult::success(())),
+        Err(e) => {
+            error!("Failed to open customer display: {}", e);
+            Ok(CommandResult::error(e.to_string()))
+        }
+    }
 }
 
 #[command]
-pub async fn save_app_config(config: AppConfig) -> Result<CommandResult<()>, String> {
-    // In a real app, this would save to a config file
-    info!("Saving app config: {:?}", config);
+pub async fn close_customer_display(state: State<'_, AppState>) -> Result<CommandResult<()>, String> {
+    state.customer_display.lock().await.close();
     Ok(CommandResult::success(()))
 }
 
+#[command]
+pub async fn display_line_item(
+    state: State<'_, AppState>,
+    name: String,
+    quantity: f64,
+    price: i64,
+) -> Result<CommandResult<()>, String> {
+    let mut display = state.customer_display.lock().await;
+    Ok(display_result(display.show_line_item(&name, quantity, price)))
+}
+
+#[command]
+pub async fn display_subtotal(state: State<'_, AppState>, amount: i64) -> Result<CommandResult<()>, String> {
+    let mut display = state.customer_display.lock().await;
+    Ok(display_result(display.show_subtotal(amount)))
+}
+
+#[command]
+pub async fn display_total(state: State<'_, AppState>, amount: i64) -> Result<CommandResult<()>, String> {
+    let mut display = state.customer_display.lock().await;
+    Ok(display_result(display.show_total(amount)))
+}
+
+#[command]
+pub async fn display_change(
+    state: State<'_, AppState>,
+    paid: i64,
+    change: i64,
+) -> Result<CommandResult<()>, String> {
+    let mut display = state.customer_display.lock().await;
+    Ok(display_result(display.show_change(paid, change)))
+}
+
+#[command]
+pub async fn display_idle(
+    state: State<'_, AppState>,
+    upper: Option<String>,
+    lower: Option<String>,
+) -> Result<CommandResult<()>, String> {
+    let mut display = state.customer_display.lock().await;
+    
+    if let (Some(upper), Some(lower)) = (upper, lower) {
+        display.set_idle_message(upper, lower);
+    }
+    
+    Ok(display_result(display.show_idle()))
+}
+
+#[command]
+pub async fn get_customer_display_status(state: State<'_, AppState>) -> Result<CommandResult<DisplayStatus>, String> {
+    let display = state.customer_display.lock().await;
+    Ok(CommandResult::success(display.get_status()))
+}
+
+fn display_result(result: anyhow::Result<()>) -> CommandResult<()> {
+    match result {
+        Ok(_) => CommandResult::success(()),
+        Err(e) => {
+            error!("Customer display error: {}", e);
+            CommandResult::error(e.to_string())
+        }
+    }
+}
+
 // ======================================================================
-// WINDOW COMMANDS
+// SCALE COMMANDS
 // ======================================================================
 
 #[command]
-pub async fn minimize_to_tray(app_handle: AppHandle) -> Result<CommandResult<()>, String> {
-    if let Some(window) = app_handle.get_window("main") {
-        match window.hide() {
-            Ok(_) => Ok(CommandResult::success(())),
-            Err(e) => Ok(CommandResult::error(e.to_string())),
+pub async fn open_scale(
+    state: State<'_, AppState>,
+    device_id: Option<String>,
+    port: Option<String>,
+    baud_rate: Option<u32>,
+    protocol: Option<ScaleProtocol>,
+) -> Result<CommandResult<()>, String> {
+    info!("Command: open_scale {:?} {:?}", device_id, port);
+    
+    let device = match resolve_device(&state, DeviceKind::Scale, device_id, port, baud_rate).await {
+        Ok(device) => device,
+        Err(e) => return Ok(CommandResult::error(e.to_string())),
+    };
+    let mut config = device.scale_config();
+    if let Some(protocol) = protocol {
+        config.protocol = protocol;
+    }
+    
+    let mut scale = state.scale.lock().await;
+    
+    match scale.open(config) {
+        Ok(_) => Ok(CommandResult::success(())),
+        Err(e) => {
+            error!("Failed to open scale: {}", e);
+            Ok(CommandResult::error(e.to_string()))
         }
-    } else {
-        Ok(CommandResult::error("Main window not found".to_string()))
     }
 }
 
 #[command]
-pub async fn show_from_tray(app_handle: AppHandle) -> Result<CommandResult<()>, String> {
-    if let Some(window) = app_handle.get_window("main") {
-        match window.show() {
-            Ok(_) => {
-                let _ = window.set_focus();
-                Ok(CommandResult::success(()))
+pub async fn close_scale(state: State<'_, AppState>) -> Result<CommandResult<()>, String> {
+    info!("Command: close_scale");
+    
+    let mut scale = state.scale.lock().await;
+    scale.close();
+    
+    Ok(CommandResult::success(()))
+}
+
+#[command]
+pub async fn read_weight(
+    state: State<'_, AppState>,
+    timeout_ms: Option<u64>,
+) -> Result<CommandResult<WeightReading>, String> {
+    // Wait outside the lock so tare/zero stay responsive
+    let handle = state.scale.lock().await.weight_handle();
+    let wait = Duration::from_millis(timeout_ms.unwrap_or(3000));
+    
+    match handle.read_stable(wait).await {
+        Ok(reading) => Ok(CommandResult::success(reading)),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+#[command]
+pub async fn tare_scale(state: State<'_, AppState>) -> Result<CommandResult<()>, String> {
+    info!("Command: tare_scale");
+    
+    let scale = state.scale.lock().await;
+    
+    match scale.tare() {
+        Ok(_) => Ok(CommandResult::success(())),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+#[command]
+pub async fn zero_scale(state: State<'_, AppState>) -> Result<CommandResult<()>, String> {
+    info!("Command: zero_scale");
+    
+    let scale = state.scale.lock().await;
+    
+    match scale.zero() {
+        Ok(_) => Ok(CommandResult::success(())),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+#[command]
+pub async fn get_scale_status(state: State<'_, AppState>) -> Result<CommandResult<ScaleStatus>, String> {
+    let scale = state.scale.lock().await;
+    Ok(CommandResult::success(scale.get_status()))
+}
+
+#[command]
+pub async fn parse_weight_barcode(
+    code: String,
+    layout: Option<EmbeddedBarcodeLayout>,
+) -> Result<CommandResult<Option<EmbeddedBarcode>>, String> {
+    let layout = layout.unwrap_or_default();
+    Ok(CommandResult::success(scale::parse_embedded_barcode(&code, &layout)))
+}
+
+// ======================================================================
+// DEVICE COMMANDS
+// ======================================================================
+
+#[command]
+pub async fn list_devices(state: State<'_, AppState>) -> Result<CommandResult<Vec<DeviceConfig>>, String> {
+    let devices = state.devices.lock().await;
+    Ok(CommandResult::success(devices.list()))
+}
+
+#[command]
+pub async fn add_device(
+    state: State<'_, AppState>,
+    device: DeviceConfig,
+) -> Result<CommandResult<DeviceConfig>, String> {
+    info!("Command: add_device {} ({:?}) on {}", device.name, device.kind, device.port);
+    
+    let mut devices = state.devices.lock().await;
+    
+    match devices.upsert(device) {
+        Ok(device) => Ok(CommandResult::success(device)),
+        Err(e) => {
+            error!("Failed to save device: {}", e);
+            Ok(CommandResult::error(e.to_string()))
+        }
+    }
+}
+
+#[command]
+pub async fn remove_device(state: State<'_, AppState>, device_id: String) -> Result<CommandResult<()>, String> {
+    info!("Command: remove_device {}", device_id);
+    
+    let mut devices = state.devices.lock().await;
+    let device = devices.get(&device_id).cloned();
+    
+    if let Err(e) = devices.remove(&device_id) {
+        return Ok(CommandResult::error(e.to_string()));
+    }
+    drop(devices);
+    
+    // Release the port if the removed device is the one in use
+    if let Some(device) = device {
+        match device.kind {
+            DeviceKind::Scanner => {
+                let mut scanner = state.scanner.lock().await;
+                if scanner_port(&scanner.get_status()) == Some(device.port.as_str()) {
+                    let _ = scanner.stop();
+                }
             }
-            Err(e) => Ok(CommandResult::error(e.to_string())),
+            DeviceKind::Scale => {
+                let mut scale = state.scale.lock().await;
+                if matches!(scale.get_status(), ScaleStatus::Open { port, .. } if port == device.port) {
+                    scale.close();
+                }
+            }
+            DeviceKind::CustomerDisplay => {
+                let mut display = state.customer_display.lock().await;
+                if matches!(display.get_status(), DisplayStatus::Open { port, .. } if port == device.port) {
+                    display.close();
+                }
+            }
+            DeviceKind::Printer | DeviceKind::CashDrawer => {}
         }
-    } else {
-        Ok(CommandResult::error("Main window not found".to_string()))
     }
+    
+    Ok(CommandResult::success(()))
 }
 
+/// Run the per-device check: test page, drawer kick, scan echo, weight read or display text
 #[command]
-pub async fn toggle_window_visibility(app_handle: AppHandle) -> Result<CommandResult<bool>, String> {
-    if let Some(window) = app_handle.get_window("main") {
-        match window.is_visible() {
-            Ok(is_visible) => {
-                if is_visible {
-                    match window.hide() {
-                        Ok(_) => Ok(CommandResult::success(false)),
-                        Err(e) => Ok(CommandResult::error(e.to_string())),
-                    }
-                } else {
-                    match window.show() {
-                        Ok(_) => {
-                            let _ = window.set_focus();
-                            Ok(CommandResult::success(true))
-                        }
-                        Err(e) => Ok(CommandResult::error(e.to_string())),
+pub async fn test_device(state: State<'_, AppState>, device_id: String) -> Result<CommandResult<String>, String> {
+    info!("Command: test_device {}", device_id);
+    
+    let device = match state.devices.lock().await.get(&device_id) {
+        Some(device) => device.clone(),
+        None => return Ok(CommandResult::error(format!("Device {} not found", device_id))),
+    };
+    
+    match run_device_test(&state, &device).await {
+        Ok(message) => Ok(CommandResult::success(message)),
+        Err(e) => {
+            error!("Device test failed for {}: {}", device.name, e);
+            Ok(CommandResult::error(e.to_string()))
+        }
+    }
+}
+
+/// Kick the cash drawer outside a sale (change, float check); always audited
+#[command]
+pub async fn open_cash_drawer(
+    window: Window,
+    state: State<'_, AppState>,
+    device_id: Option<String>,
+    reason: String,
+) -> Result<CommandResult<()>, String> {
+    info!("Command: open_cash_drawer ({})", reason);
+    
+    let result = async {
+        let device = resolve_device(&state, DeviceKind::CashDrawer, device_id, None, None).await?;
+        let name = device.name.clone();
+        tokio::task::spawn_blocking(move || printer::kick_drawer(&device.port, device.baud_rate)).await??;
+        Ok::<_, anyhow::Error>(name)
+    }
+    .await;
+    
+    record_caller_event(&window, &state, "open_cash_drawer", match &result {
+        Ok(name) => json!({ "device": name, "reason": reason }),
+        Err(e) => json!({ "reason": reason, "error": e.to_string() }),
+    });
+    
+    match result {
+        Ok(_) => Ok(CommandResult::success(())),
+        Err(e) => {
+            error!("Failed to open cash drawer: {}", e);
+            Ok(CommandResult::error(e.to_string()))
+        }
+    }
+}
+
+/// Use an explicit port when given, otherwise the registry entry
+async fn resolve_device(
+    state: &State<'_, AppState>,
+    kind: DeviceKind,
+    device_id: Option<String>,
+    port: Option<String>,
+    baud_rate: Option<u32>,
+) -> anyhow::Result<DeviceConfig> {
+    let mut device = match port {
+        Some(port) => DeviceConfig {
+            id: String::new(),
+            name: port.clone(),
+            kind,
+            port,
+            baud_rate: 9600,
+            options: HashMap::new(),
+            enabled: true,
+        },
+        None => state.devices.lock().await.resolve(kind, device_id.as_deref())?,
+    };
+    
+    if let Some(baud_rate) = baud_rate {
+        device.baud_rate = baud_rate;
+    }
+    
+    Ok(device)
+}
+
+async fn run_device_test(state: &State<'_, AppState>, device: &DeviceConfig) -> anyhow::Result<String> {
+    const TEST_WAIT: Duration = Duration::from_secs(10);
+    
+    match device.kind {
+        DeviceKind::Printer => {
+            let device = device.clone();
+            tokio::task::spawn_blocking(move || {
+                printer::print_test_page(&device.port, device.baud_rate, &device.name, device.printer_columns())
+            })
+            .await??;
+            Ok("Halaman tes dicetak".to_string())
+        }
+        DeviceKind::CashDrawer => {
+            let device = device.clone();
+            tokio::task::spawn_blocking(move || printer::kick_drawer(&device.port, device.baud_rate)).await??;
+            Ok("Laci kas dibuka".to_string())
+        }
+        DeviceKind::Scanner => {
+            // The running service may already hold this port; otherwise test on a handle of our own
+            let own = if scanner_port(&state.scanner.lock().await.get_status()) == Some(device.port.as_str()) {
+                None
+            } else {
+                let mut scanner = ScannerService::new();
+                scanner.start(device.scanner_config())?;
+                Some(scanner)
+            };
+            let baseline = scanner_scan_count(&scanner_status(state, own.as_ref()).await);
+            
+            // Echo the next code scanned on this device
+            let deadline = Instant::now() + TEST_WAIT;
+            let result = loop {
+                if let ScannerStatus::Running { scans, last_scan, .. } = scanner_status(state, own.as_ref()).await {
+                    if scans > baseline {
+                        break Ok(format!("Scan diterima: {}", last_scan.unwrap_or_default()));
                     }
                 }
+                if Instant::now() >= deadline {
+                    break Err(anyhow::anyhow!("Tidak ada scan dalam {} detik", TEST_WAIT.as_secs()));
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            };
+            
+            if let Some(mut scanner) = own {
+                let _ = scanner.stop();
             }
-            Err(e) => Ok(CommandResult::error(e.to_string())),
+            result
         }
-    } else {
-        Ok(CommandResult::error("Main window not found".to_string()))
+        DeviceKind::Scale => {
+            let live = {
+                let scale = state.scale.lock().await;
+                matches!(scale.get_status(), ScaleStatus::Open { port, .. } if port == device.port)
+                    .then(|| scale.weight_handle())
+            };
+            
+            match live {
+                Some(handle) => {
+                    let reading = handle.read_stable(TEST_WAIT).await?;
+                    Ok(format!("Berat stabil: {:.3} kg", reading.kilograms))
+                }
+                None => {
+                    let mut scale = ScaleService::new();
+                    scale.open(device.scale_config())?;
+                    let result = scale.weight_handle().read_stable(TEST_WAIT).await;
+                    scale.close();
+                    Ok(format!("Berat stabil: {:.3} kg", result?.kilograms))
+                }
+            }
+        }
+        DeviceKind::CustomerDisplay => {
+            let mut display = state.customer_display.lock().await;
+            if matches!(display.get_status(), DisplayStatus::Open { port, .. } if port == device.port) {
+                display.show("TES DISPLAY", &device.name)?;
+            } else {
+                let mut own = CustomerDisplay::new();
+                own.open(device.display_config())?;
+                let result = own.show("TES DISPLAY", &device.name);
+                own.close();
+                result?;
+            }
+            Ok("Teks tes ditampilkan".to_string())
+        }
+    }
+}
+
+async fn scanner_status(state: &State<'_, AppState>, own: Option<&ScannerService>) -> ScannerStatus {
+    match own {
+        Some(scanner) => scanner.get_status(),
+        None => state.scanner.lock().await.get_status(),
+    }
+}
+
+fn scanner_port(status: &ScannerStatus) -> Option<&str> {
+    match status {
+        ScannerStatus::Running { port, .. } => Some(port),
+        _ => None,
+    }
+}
+
+fn scanner_scan_count(status: &ScannerStatus) -> u64 {
+    match status {
+        ScannerStatus::Running { scans, .. } => *scans,
+        _ => 0,
+    }
+}
+
+// ======================================================================
+// JOURNAL COMMANDS
+// ======================================================================
+
+/// Record a checkout in the local journal; safe to retry with the same idempotency key
+#[command]
+pub async fn journal_record_order(
+    state: State<'_, AppState>,
+    order: NewOrder,
+) -> Result<CommandResult<JournalOrder>, String> {
+    info!("Command: journal_record_order {:?}", order.idempotency_key);
+    
+    let mut journal = state.journal.lock().await;
+    
+    match journal.record_order(order) {
+        Ok(order) => {
+            // Push upstream right away when the server is reachable
+            state.sync.lock().await.trigger();
+            Ok(CommandResult::success(order))
+        }
+        Err(e) => {
+            error!("Failed to journal order: {}", e);
+            Ok(CommandResult::error(e.to_string()))
+        }
+    }
+}
+
+#[command]
+pub async fn journal_get_order(
+    state: State<'_, AppState>,
+    idempotency_key: String,
+) -> Result<CommandResult<Option<JournalOrder>>, String> {
+    let journal = state.journal.lock().await;
+    
+    match journal.get_order(&idempotency_key) {
+        Ok(order) => Ok(CommandResult::success(order)),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
     }
-}
\ No newline at end of file
+}
+
+#[command]
+pub async fn journal_list_orders(
+    state: State<'_, AppState>,
+    sync_status: Option<SyncStatus>,
+    limit: Option<usize>,
+) -> Result<CommandResult<Vec<JournalOrder>>, String> {
+    let journal = state.journal.lock().await;
+    
+    match journal.list_orders(sync_status, limit.unwrap_or(50)) {
+        Ok(orders) => Ok(CommandResult::success(orders)),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+#[command]
+pub async fn journal_requeue_order(
+    state: State<'_, AppState>,
+    idempotency_key: String,
+) -> Result<CommandResult<()>, String> {
+    info!("Command: journal_requeue_order {}", idempotency_key);
+    
+    let journal = state.journal.lock().await;
+    
+    match journal.requeue(&idempotency_key) {
+        Ok(_) => {
+            state.sync.lock().await.trigger();
+            Ok(CommandResult::success(()))
+        }
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+/// Branch/terminal counter used for offline receipt numbers
+#[command]
+pub async fn get_order_numbering(
+    state: State<'_, AppState>,
+) -> Result<CommandResult<Option<OrderNumberStatus>>, String> {
+    let journal = state.journal.lock().await;
+    Ok(CommandResult::success(journal.numbering_status()))
+}
+
+#[command]
+pub async fn get_journal_stats(state: State<'_, AppState>) -> Result<CommandResult<JournalStats>, String> {
+    let journal = state.journal.lock().await;
+    
+    match journal.stats() {
+        Ok(stats) => Ok(CommandResult::success(stats)),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+// ======================================================================
+// SYNC COMMANDS
+// ======================================================================
+
+#[command]
+pub async fn get_sync_status(state: State<'_, AppState>) -> Result<CommandResult<SyncProgress>, String> {
+    let sync = state.sync.lock().await;
+    Ok(CommandResult::success(sync.get_progress().await))
+}
+
+/// Replay pending orders now instead of waiting for the next retry
+#[command]
+pub async fn trigger_sync(state: State<'_, AppState>) -> Result<CommandResult<()>, String> {
+    info!("Command: trigger_sync");
+    
+    let sync = state.sync.lock().await;
+    
+    if sync.is_running() {
+        sync.trigger();
+        Ok(CommandResult::success(()))
+    } else {
+        Ok(CommandResult::error("Sync engine is not running".to_string()))
+    }
+}
+
+// ======================================================================
+// CATALOG COMMANDS
+// ======================================================================
+
+/// Resolve a scanned barcode or SKU from the local catalog
+#[command]
+pub async fn lookup_barcode(
+    state: State<'_, AppState>,
+    code: String,
+) -> Result<CommandResult<Option<BarcodeMatch>>, String> {
+    let catalog = state.catalog.lock().await;
+    
+    match catalog.lookup_barcode(&code) {
+        Ok(found) => Ok(CommandResult::success(found)),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+#[command]
+pub async fn search_products(
+    state: State<'_, AppState>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<CommandResult<Vec<CatalogProduct>>, String> {
+    let catalog = state.catalog.lock().await;
+    
+    match catalog.search(&query, limit.unwrap_or(20)) {
+        Ok(products) => Ok(CommandResult::success(products)),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+/// Pull catalog changes now instead of waiting for the next refresh
+#[command]
+pub async fn sync_catalog(state: State<'_, AppState>) -> Result<CommandResult<DeltaSummary>, String> {
+    info!("Command: sync_catalog");
+    
+    let Some(branch_id) = AppConfig::load().branch_id else {
+        return Ok(CommandResult::error("Branch is not configured".to_string()));
+    };
+    
+    match catalog::refresh(&state.catalog, &reqwest::Client::new(), &branch_id).await {
+        Ok(summary) => Ok(CommandResult::success(summary)),
+        Err(e) => {
+            error!("Catalog sync failed: {}", e);
+            Ok(CommandResult::error(e.to_string()))
+        }
+    }
+}
+
+#[command]
+pub async fn get_catalog_stats(state: State<'_, AppState>) -> Result<CommandResult<CatalogStats>, String> {
+    let catalog = state.catalog.lock().await;
+    
+    match catalog.stats() {
+        Ok(stats) => Ok(CommandResult::success(stats)),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+// ======================================================================
+// TERMINAL COMMANDS
+// ======================================================================
+
+/// One-time registration of this PC as a till of `branch_code`
+#[command]
+pub async fn provision_terminal(
+    state: State<'_, AppState>,
+    branch_code: String,
+    terminal_code: Option<String>,
+    name: Option<String>,
+    enrollment_token: String,
+) -> Result<CommandResult<TerminalIdentity>, String> {
+    info!("Command: provision_terminal {}", branch_code);
+    
+    let mut config = AppConfig::load();
+    if let Some(identity) = &config.terminal {
+        return Ok(CommandResult::error(format!(
+            "Terminal is already registered as {}-{}",
+            identity.branch_code, identity.terminal_code
+        )));
+    }
+    
+    if let Err(e) = order_no::validate_branch_code(&branch_code) {
+        return Ok(CommandResult::error(e.to_string()));
+    }
+    if enrollment_token.trim().is_empty() {
+        return Ok(CommandResult::error("Enrollment token is required".to_string()));
+    }
+    if let Some(code) = &terminal_code {
+        if let Err(e) = order_no::validate_terminal_code(code) {
+            return Ok(CommandResult::error(e.to_string()));
+        }
+    }
+    
+    let SidecarStatus::Running { port, .. } = state.sidecar_manager.lock().await.get_status().await else {
+        return Ok(CommandResult::error("Server is not running".to_string()));
+    };
+    let base_url = format!("http://127.0.0.1:{}", port);
+    
+    let request = RegistrationRequest {
+        branch_code,
+        terminal_code,
+        name,
+        enrollment_token: enrollment_token.trim().to_string(),
+    };
+    
+    let identity = match terminal::register(&reqwest::Client::new(), &base_url, &request, &terminal::default_key_path()).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            error!("Terminal provisioning failed: {}", e);
+            return Ok(CommandResult::error(e.to_string()));
+        }
+    };
+    
+    config.branch_id = Some(identity.branch_id.clone());
+    config.branch_code = Some(identity.branch_code.clone());
+    config.terminal_code = Some(identity.terminal_code.clone());
+    config.terminal = Some(identity.clone());
+    
+    if let Err(e) = config.save() {
+        return Ok(CommandResult::error(e));
+    }
+    
+    // Number, sign and attribute everything from here on as this till
+    state.journal.lock().await.set_numbering(config.order_numbering());
+    
+    // The sidecar only reads its env at spawn, so restart it as this till
+    let port = {
+        let mut sidecar = state.sidecar_manager.lock().await;
+        sidecar.set_env(identity.sidecar_env());
+        match sidecar.restart().await {
+            Ok(port) => port,
+            Err(e) => {
+                error!("Failed to restart sidecar after provisioning: {}", e);
+                return Ok(CommandResult::error(format!(
+                    "Terminal registered, but the server failed to restart: {}",
+                    e
+                )));
+            }
+        }
+    };
+    
+    let mut sync = state.sync.lock().await;
+    sync.stop().await;
+    if let Err(e) = sync.start(config.sync_config(port)).await {
+        error!("Failed to restart sync engine: {}", e);
+    }
+    
+    Ok(CommandResult::success(identity))
+}
+
+#[command]
+pub async fn get_terminal_identity() -> Result<CommandResult<Option<TerminalIdentity>>, String> {
+    Ok(CommandResult::success(AppConfig::load().terminal))
+}
+
+// ======================================================================
+// BACKUP COMMANDS
+// ======================================================================
+
+/// Verified dumps in the backup folder, newest first
+#[command]
+pub async fn list_backups(state: State<'_, AppState>) -> Result<CommandResult<Vec<BackupEntry>>, String> {
+    let backup = state.backup.lock().await;
+    
+    match backup.list_backups().await {
+        Ok(entries) => Ok(CommandResult::success(entries)),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+/// Dump the database now; progress arrives on `backup://progress`
+#[command]
+pub async fn create_backup(state: State<'_, AppState>) -> Result<CommandResult<BackupEntry>, String> {
+    info!("Command: create_backup");
+    
+    let worker = state.backup.lock().await.worker();
+    
+    match worker.backup().await {
+        Ok(entry) => Ok(CommandResult::success(entry)),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+/// Replace the database contents with `file_name`; sync and the sidecar pause meanwhile
+#[command]
+pub async fn restore_backup(state: State<'_, AppState>, file_name: String) -> Result<CommandResult<()>, String> {
+    info!("Command: restore_backup {}", file_name);
+    
+    let worker = state.backup.lock().await.worker();
+    
+    // Nothing may write to the database while pg_restore drops and recreates it
+    let sync_was_running = state.sync.lock().await.is_running();
+    state.sync.lock().await.stop().await;
+    let _ = state.watchdog.lock().await.stop().await;
+    let _ = state.sidecar_manager.lock().await.stop().await;
+    
+    let result = worker.restore(&file_name).await;
+    
+    match state.sidecar_manager.lock().await.start().await {
+        Ok(port) => {
+            if let Err(e) = state.watchdog.lock().await.start(port).await {
+                warn!("Failed to restart watchdog after restore: {}", e);
+            }
+            if sync_was_running {
+                if let Err(e) = state.sync.lock().await.start(AppConfig::load().sync_config(port)).await {
+                    error!("Failed to restart sync engine after restore: {}", e);
+                }
+            }
+        }
+        Err(e) => error!("Failed to restart sidecar after restore: {}", e),
+    }
+    
+    match result {
+        Ok(_) => Ok(CommandResult::success(())),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+/// Encrypt a backup into a user-picked folder such as a USB stick
+#[command]
+pub async fn export_backup(
+    state: State<'_, AppState>,
+    file_name: String,
+    destination: String,
+    passphrase: String,
+) -> Result<CommandResult<ExportedBackup>, String> {
+    info!("Command: export_backup {} -> {}", file_name, destination);
+    
+    let config = AppConfig::load();
+    let origin = BackupOrigin {
+        branch_id: config.branch_id,
+        branch_code: config.branch_code,
+        terminal_code: config.terminal_code,
+    };
+    
+    let worker = state.backup.lock().await.worker();
+    
+    match worker.export(&file_name, std::path::Path::new(&destination), &passphrase, origin).await {
+        Ok(exported) => Ok(CommandResult::success(exported)),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+/// Manifest of an exported archive, shown before asking for the passphrase
+#[command]
+pub async fn inspect_backup_archive(archive_path: String) -> Result<CommandResult<ArchiveManifest>, String> {
+    match archive::read_manifest(std::path::Path::new(&archive_path)) {
+        Ok(manifest) => Ok(CommandResult::success(manifest)),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+/// Decrypt and verify an exported archive into the backup folder
+#[command]
+pub async fn import_backup(
+    state: State<'_, AppState>,
+    archive_path: String,
+    passphrase: String,
+) -> Result<CommandResult<ImportedBackup>, String> {
+    info!("Command: import_backup {}", archive_path);
+    
+    let worker = state.backup.lock().await.worker();
+    
+    match worker.import(std::path::Path::new(&archive_path), &passphrase).await {
+        Ok(imported) => Ok(CommandResult::success(imported)),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+#[command]
+pub async fn get_backup_status(state: State<'_, AppState>) -> Result<CommandResult<BackupStatus>, String> {
+    let backup = state.backup.lock().await;
+    Ok(CommandResult::success(backup.get_status().await))
+}
+
+// ======================================================================
+// LOCAL DATABASE COMMANDS
+// ======================================================================
+
+#[command]
+pub async fn get_local_database_status(state: State<'_, AppState>) -> Result<CommandResult<PostgresStatus>, String> {
+    let postgres = state.postgres.lock().await;
+    Ok(CommandResult::success(postgres.get_status().await))
+}
+
+/// Restart the bundled PostgreSQL; the sidecar keeps its URL since the port is reused
+#[command]
+pub async fn restart_local_database(state: State<'_, AppState>) -> Result<CommandResult<()>, String> {
+    info!("Command: restart_local_database");
+    
+    let mut postgres = state.postgres.lock().await;
+    postgres.configure(AppConfig::load().local_database).await;
+    
+    match postgres.restart().await {
+        Ok(_) => Ok(CommandResult::success(())),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+// ======================================================================
+// MIGRATION COMMANDS
+// ======================================================================
+
+/// Latest migration step, for a startup screen opened after the run began
+#[command]
+pub async fn get_migration_status(state: State<'_, AppState>) -> Result<CommandResult<Option<MigrationProgress>>, String> {
+    let migrations = state.migrations.lock().await;
+    Ok(CommandResult::success(migrations.last_progress().await))
+}
+
+// ======================================================================
+// TUNNEL COMMANDS
+// ======================================================================
+
+#[command]
+pub async fn get_tunnel_status(state: State<'_, AppState>) -> Result<CommandResult<TunnelStatus>, String> {
+    let tunnel = state.tunnel.lock().await;
+    Ok(CommandResult::success(tunnel.get_status().await))
+}
+
+/// Reopen the tunnel with the saved settings; the local port is kept so the sidecar URL stays valid
+#[command]
+pub async fn restart_tunnel(state: State<'_, AppState>) -> Result<CommandResult<()>, String> {
+    info!("Command: restart_tunnel");
+    
+    let config = AppConfig::load();
+    let Some(database_url) = config.database_url() else {
+        return Ok(CommandResult::error("No database URL configured".to_string()));
+    };
+    
+    let mut tunnel = state.tunnel.lock().await;
+    match tunnel.start(config.tunnel, &database_url).await {
+        Ok(_) => Ok(CommandResult::success(())),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+// ======================================================================
+// SECRET COMMANDS
+// ======================================================================
+
+/// Which secrets are set; values never leave the shell
+#[command]
+pub async fn list_secrets() -> Result<CommandResult<Vec<SecretInfo>>, String> {
+    Ok(CommandResult::success(secrets::store().list()))
+}
+
+#[command]
+pub async fn set_secret(kind: SecretKind, value: String) -> Result<CommandResult<SecretInfo>, String> {
+    info!("Command: set_secret {:?}", kind);
+    
+    let store = secrets::store();
+    match store.set(kind, &value) {
+        Ok(_) => Ok(CommandResult::success(SecretInfo {
+            kind,
+            is_set: !value.is_empty(),
+            backend: store.backend(),
+        })),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+#[command]
+pub async fn delete_secret(kind: SecretKind) -> Result<CommandResult<()>, String> {
+    info!("Command: delete_secret {:?}", kind);
+    
+    match secrets::store().delete(kind) {
+        Ok(_) => Ok(CommandResult::success(())),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+/// Check a stored secret without revealing it: the database password by connecting,
+/// the others by reading them back
+#[command]
+pub async fn test_secret(state: State<'_, AppState>, kind: SecretKind) -> Result<CommandResult<String>, String> {
+    info!("Command: test_secret {:?}", kind);
+    
+    match secrets::store().get(kind) {
+        Ok(Some(_)) => {}
+        Ok(None) => return Ok(CommandResult::error(format!("{} is not set", kind.account()))),
+        Err(e) => return Ok(CommandResult::error(e.to_string())),
+    }
+    
+    if kind != SecretKind::DatabasePassword {
+        return Ok(CommandResult::success(format!("{} is stored and readable", kind.account())));
+    }
+    
+    let config = AppConfig::load();
+    if config.local_database.enabled {
+        return Ok(CommandResult::error("The bundled database does not use this password".to_string()));
+    }
+    let Some(mut url) = config.database_url() else {
+        return Ok(CommandResult::error("No database URL configured".to_string()));
+    };
+    if let Some(forwarded) = state.tunnel.lock().await.forward_url(&url) {
+        url = forwarded;
+    }
+    
+    let connect = postgres::connect(&url);
+    match tokio::time::timeout(Duration::from_secs(10), connect).await {
+        Ok(Ok(_)) => Ok(CommandResult::success("Connected to the database".to_string())),
+        // The driver error names the cause (e.g. authentication failed) but not the password
+        Ok(Err(e)) => Ok(CommandResult::error(format!("Database connection failed: {}", e))),
+        Err(_) => Ok(CommandResult::error("Timeout connecting to the database".to_string())),
+    }
+}
+
+// ======================================================================
+// IPC SESSION COMMANDS
+// ======================================================================
+
+/// Attach the role token the web app issued at login to the calling window
+#[command]
+pub async fn begin_shell_session(
+    window: Window,
+    state: State<'_, AppState>,
+    token: String,
+) -> Result<CommandResult<SessionInfo>, String> {
+    match state.ipc.begin_session(window.label(), &token) {
+        Ok(info) => {
+            // Logging in again proves who is at the till as well as the lock PIN does;
+            // a reload re-sends the earlier login and leaves the lock in place
+            if window.label() == window_manager::MAIN_WINDOW_LABEL {
+                if let Some(event) = state.idle_lock.unlock_after_login(info.auth_time) {
+                    idle_lock::announce(&window.app_handle(), false, &event);
+                }
+                state.idle_lock.heartbeat();
+            }
+            Ok(CommandResult::success(info))
+        }
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+#[command]
+pub async fn end_shell_session(window: Window, state: State<'_, AppState>) -> Result<CommandResult<()>, String> {
+    state.ipc.end_session(window.label());
+    Ok(CommandResult::success(()))
+}
+
+#[command]
+pub async fn get_shell_session(
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<CommandResult<Option<SessionInfo>>, String> {
+    Ok(CommandResult::success(state.ipc.session(window.label())))
+}
+
+// ======================================================================
+// LOCK SCREEN COMMANDS
+// ======================================================================
+
+#[command]
+pub async fn get_lock_status(window: Window, state: State<'_, AppState>) -> Result<CommandResult<LockStatus>, String> {
+    let user_id = state.ipc.session(window.label()).map(|session| session.user_id);
+    Ok(CommandResult::success(state.idle_lock.status(user_id.as_deref())))
+}
+
+/// Lock the screen now, e.g. when the cashier steps away
+#[command]
+pub async fn lock_session(window: Window, state: State<'_, AppState>) -> Result<CommandResult<()>, String> {
+    info!("Command: lock_session");
+    
+    let user_id = state.ipc.session(window.label()).map(|session| session.user_id);
+    if let Some(event) = state.idle_lock.lock(user_id, "manual") {
+        idle_lock::announce(&window.app_handle(), true, &event);
+    }
+    Ok(CommandResult::success(()))
+}
+
+/// Unlock with the locked cashier's PIN, or with a supervisor's PIN or authenticator code
+#[command]
+pub async fn unlock_session(
+    window: Window,
+    state: State<'_, AppState>,
+    pin: Option<String>,
+    supervisor: Option<ApprovalCredential>,
+) -> Result<CommandResult<()>, String> {
+    info!("Command: unlock_session");
+    
+    if !state.idle_lock.is_locked() {
+        return Ok(CommandResult::success(()));
+    }
+    
+    let result = match (pin, supervisor) {
+        (_, Some(credential)) => approve_and_record(&window, &state, ApprovalAction::Unlock, json!({}), credential)
+            .await
+            .and_then(|_| state.idle_lock.unlock("supervisor").ok_or_else(|| "Screen is not locked".to_string())),
+        (Some(pin), None) => {
+            let idle_lock = state.idle_lock.clone();
+            tokio::task::spawn_blocking(move || idle_lock.unlock_with_pin(&pin))
+                .await
+                .map_err(|e| e.to_string())?
+                .map_err(|e| e.to_string())
+        }
+        (None, None) => Err("PIN is required".to_string()),
+    };
+    
+    match result {
+        Ok(event) => {
+            idle_lock::announce(&window.app_handle(), false, &event);
+            Ok(CommandResult::success(()))
+        }
+        Err(e) => {
+            let user_id = state.ipc.session(window.label()).map(|session| session.user_id);
+            state.audit.record(
+                AuditEvent::new("unlock_session", AuditOutcome::Denied, json!({ "reason": e }))
+                    .caller(window.label(), user_id),
+            );
+            Ok(CommandResult::error(e))
+        }
+    }
+}
+
+/// Set or change the session user's own lock PIN
+#[command]
+pub async fn set_lock_pin(
+    window: Window,
+    state: State<'_, AppState>,
+    current_pin: Option<String>,
+    new_pin: String,
+) -> Result<CommandResult<()>, String> {
+    info!("Command: set_lock_pin");
+    
+    let Some(user_id) = state.ipc.session(window.label()).map(|session| session.user_id) else {
+        return Ok(CommandResult::error("Log in before setting a lock PIN".to_string()));
+    };
+    
+    let idle_lock = state.idle_lock.clone();
+    let pin_user = user_id.clone();
+    let result = tokio::task::spawn_blocking(move || idle_lock.set_pin(&pin_user, current_pin.as_deref(), &new_pin))
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    match result {
+        Ok(()) => {
+            record_caller_event(&window, &state, "set_lock_pin", json!({ "user_id": user_id }));
+            Ok(CommandResult::success(()))
+        }
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+/// Forget a cashier's lock PIN so they can set a new one
+#[command]
+pub async fn remove_lock_pin(
+    window: Window,
+    state: State<'_, AppState>,
+    user_id: String,
+) -> Result<CommandResult<bool>, String> {
+    info!("Command: remove_lock_pin {}", user_id);
+    
+    match state.idle_lock.remove_pin(&user_id) {
+        Ok(removed) => {
+            record_caller_event(&window, &state, "remove_lock_pin", json!({ "user_id": user_id }));
+            Ok(CommandResult::success(removed))
+        }
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+// ======================================================================
+// AUDIT COMMANDS
+// ======================================================================
+
+/// Audit events matching `query`, newest first; the latest 100 when no limit is given
+#[command]
+pub async fn get_audit_events(
+    state: State<'_, AppState>,
+    query: Option<AuditQuery>,
+) -> Result<CommandResult<Vec<AuditEvent>>, String> {
+    let mut query = query.unwrap_or_default();
+    query.limit = Some(query.limit.unwrap_or(100));
+    
+    match state.audit.query(&query) {
+        Ok(events) => Ok(CommandResult::success(events)),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+/// Check the hash chain of the audit log for edited, removed or reordered entries
+#[command]
+pub async fn verify_audit_log(state: State<'_, AppState>) -> Result<CommandResult<AuditVerification>, String> {
+    info!("Command: verify_audit_log");
+    
+    match state.audit.verify() {
+        Ok(verification) => {
+            if !verification.valid {
+                warn!("Audit log verification failed: {:?}", verification.error);
+            }
+            Ok(CommandResult::success(verification))
+        }
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+/// Write matching events as CSV (WIB times) to `Documents/POS/Exports`
+#[command]
+pub async fn export_audit_events(
+    window: Window,
+    state: State<'_, AppState>,
+    query: Option<AuditQuery>,
+) -> Result<CommandResult<SavedFile>, String> {
+    info!("Command: export_audit_events");
+    
+    let mut query = query.unwrap_or_default();
+    query.limit = None;
+    
+    let events = match state.audit.query(&query) {
+        Ok(events) => events,
+        Err(e) => return Ok(CommandResult::error(e.to_string())),
+    };
+    
+    let wib = chrono::FixedOffset::east_opt(7 * 3600).expect("valid offset");
+    let file_name = format!("audit-{}.csv", chrono::Utc::now().with_timezone(&wib).format("%Y%m%d-%H%M%S"));
+    
+    match FileService::open_default().export(&file_name, audit::to_csv(&events).as_bytes()) {
+        Ok(saved) => {
+            record_caller_event(&window, &state, "export_audit_events", json!({
+                "query": query,
+                "events": events.len(),
+                "path": saved.path,
+            }));
+            Ok(CommandResult::success(saved))
+        }
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+/// Record a void or refund the web API completed; needs the approval token that allowed it,
+/// and the entry carries the approved context from the token rather than the caller's word
+#[command]
+pub async fn record_audit_event(
+    window: Window,
+    state: State<'_, AppState>,
+    action: ApprovalAction,
+    approval_token: String,
+    detail: serde_json::Value,
+) -> Result<CommandResult<()>, String> {
+    let name = match action {
+        ApprovalAction::Void => "void",
+        ApprovalAction::Refund => "refund",
+        _ => return Ok(CommandResult::error(format!("{:?} is recorded by the shell itself", action))),
+    };
+    if !detail.is_object() || detail.to_string().len() > approval::MAX_CONTEXT_BYTES {
+        return Ok(CommandResult::error(format!(
+            "Detail must be an object of at most {} bytes",
+            approval::MAX_CONTEXT_BYTES
+        )));
+    }
+    
+    let user_id = state.ipc.session(window.label()).map(|session| session.user_id);
+    let claims = match state.approvals.redeem_for_record(&approval_token, action, user_id.as_deref()) {
+        Ok(claims) => claims,
+        Err(e) => {
+            state.audit.record(
+                AuditEvent::new(name, AuditOutcome::Denied, json!({ "reason": e.to_string() }))
+                    .caller(window.label(), user_id),
+            );
+            return Ok(CommandResult::error(e.to_string()));
+        }
+    };
+    
+    state.audit.record(
+        AuditEvent::new(name, AuditOutcome::Allowed, json!({
+            "jti": claims.jti,
+            "supervisor_id": claims.supervisor_id,
+            "context": claims.context,
+            "result": detail,
+        }))
+        .caller(window.label(), user_id),
+    );
+    Ok(CommandResult::success(()))
+}
+
+fn record_caller_event(window: &Window, state: &State<'_, AppState>, action: &str, detail: serde_json::Value) {
+    let user_id = state.ipc.session(window.label()).map(|session| session.user_id);
+    state.audit.record(AuditEvent::new(action, AuditOutcome::Allowed, detail).caller(window.label(), user_id));
+}
+
+// ======================================================================
+// FILE COMMANDS
+// ======================================================================
+
+/// Save an export (base64 contents) to `Documents/POS/Exports`
+#[command]
+pub async fn export_file(
+    window: Window,
+    state: State<'_, AppState>,
+    file_name: String,
+    contents: String,
+) -> Result<CommandResult<SavedFile>, String> {
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+    
+    info!("Command: export_file {:?}", file_name);
+    
+    // Refuse oversized payloads before decoding them
+    if contents.len() / 4 * 3 > files::MAX_EXPORT_BYTES + 3 {
+        return Ok(CommandResult::error(format!("Export is larger than {} MB", files::MAX_EXPORT_BYTES / 1024 / 1024)));
+    }
+    let bytes = match BASE64.decode(contents.as_bytes()) {
+        Ok(bytes) => bytes,
+        Err(e) => return Ok(CommandResult::error(format!("Invalid export contents: {}", e))),
+    };
+    
+    match FileService::open_default().export(&file_name, &bytes) {
+        Ok(saved) => {
+            let user_id = state.ipc.session(window.label()).map(|session| session.user_id);
+            state.audit.record(
+                AuditEvent::new("export_file", AuditOutcome::Allowed, json!({ "path": saved.path, "size": saved.size }))
+                    .caller(window.label(), user_id),
+            );
+            Ok(CommandResult::success(saved))
+        }
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+/// Read a CSV by name from `Documents/POS/Imports`, or from the native picker when no
+/// name is given; `None` when the picker is cancelled
+#[command]
+pub async fn import_csv(
+    window: Window,
+    state: State<'_, AppState>,
+    file_name: Option<String>,
+) -> Result<CommandResult<Option<ImportedCsv>>, String> {
+    use tauri::api::dialog::blocking::FileDialogBuilder;
+    
+    info!("Command: import_csv {:?}", file_name);
+    
+    let service = FileService::open_default();
+    let result = match file_name {
+        Some(file_name) => service.import_named(&file_name),
+        None => {
+            let start_dir = service.imports_dir().to_path_buf();
+            let picked = tokio::task::spawn_blocking(move || {
+                let mut dialog = FileDialogBuilder::new().add_filter("CSV", &["csv"]);
+                if start_dir.is_dir() {
+                    dialog = dialog.set_directory(start_dir);
+                }
+                dialog.pick_file()
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+    
+            match picked {
+                Some(path) => service.import_picked(&path),
+                None => return Ok(CommandResult::success(None)),
+            }
+        }
+    };
+    
+    match result {
+        Ok(imported) => {
+            let user_id = state.ipc.session(window.label()).map(|session| session.user_id);
+            state.audit.record(
+                AuditEvent::new("import_csv", AuditOutcome::Allowed, json!({ "file": imported.file_name, "size": imported.size }))
+                    .caller(window.label(), user_id),
+            );
+            Ok(CommandResult::success(Some(imported)))
+        }
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+/// Append web-side log lines to a file in the shell's `logs` directory
+#[command]
+pub async fn save_log(file_name: String, contents: String) -> Result<CommandResult<SavedFile>, String> {
+    match FileService::open_default().append_log(&file_name, &contents) {
+        Ok(saved) => Ok(CommandResult::success(saved)),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+// ======================================================================
+// SUPERVISOR APPROVAL COMMANDS
+// ======================================================================
+
+/// Supervisors the approval dialog can offer; no hashes or secrets
+#[command]
+pub async fn list_supervisors(state: State<'_, AppState>) -> Result<CommandResult<Vec<SupervisorInfo>>, String> {
+    match state.approvals.list() {
+        Ok(supervisors) => Ok(CommandResult::success(supervisors)),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+#[command]
+pub async fn set_supervisor_pin(
+    window: Window,
+    state: State<'_, AppState>,
+    supervisor_id: String,
+    name: String,
+    pin: String,
+) -> Result<CommandResult<SupervisorInfo>, String> {
+    info!("Command: set_supervisor_pin {}", supervisor_id);
+    
+    let approvals = state.approvals.clone();
+    let result = tokio::task::spawn_blocking(move || approvals.set_pin(&supervisor_id, &name, &pin))
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    match result {
+        Ok(info) => {
+            record_caller_event(&window, &state, "set_supervisor_pin", json!({ "supervisor_id": info.id }));
+            Ok(CommandResult::success(info))
+        }
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+/// New authenticator key; the secret is shown once for the supervisor to scan
+#[command]
+pub async fn enroll_supervisor_totp(
+    window: Window,
+    state: State<'_, AppState>,
+    supervisor_id: String,
+) -> Result<CommandResult<TotpEnrollment>, String> {
+    info!("Command: enroll_supervisor_totp {}", supervisor_id);
+    
+    match state.approvals.enroll_totp(&supervisor_id) {
+        Ok(enrollment) => {
+            record_caller_event(&window, &state, "enroll_supervisor_totp", json!({ "supervisor_id": supervisor_id }));
+            Ok(CommandResult::success(enrollment))
+        }
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+#[command]
+pub async fn remove_supervisor(
+    window: Window,
+    state: State<'_, AppState>,
+    supervisor_id: String,
+) -> Result<CommandResult<()>, String> {
+    info!("Command: remove_supervisor {}", supervisor_id);
+    
+    match state.approvals.remove(&supervisor_id) {
+        Ok(_) => {
+            record_caller_event(&window, &state, "remove_supervisor", json!({ "supervisor_id": supervisor_id }));
+            Ok(CommandResult::success(()))
+        }
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+/// Check a supervisor PIN or authenticator code and return a short-lived token the web API
+/// accepts for `action` on `context`; every attempt is audited
+#[command]
+pub async fn request_supervisor_approval(
+    window: Window,
+    state: State<'_, AppState>,
+    action: ApprovalAction,
+    context: serde_json::Value,
+    credential: ApprovalCredential,
+) -> Result<CommandResult<Approval>, String> {
+    info!("Command: request_supervisor_approval {:?}", action);
+    
+    match approve_and_record(&window, &state, action, context, credential).await {
+        Ok(approval) => Ok(CommandResult::success(approval)),
+        Err(e) => Ok(CommandResult::error(e)),
+    }
+}
+
+/// Check a supervisor credential and record the approval or refusal
+async fn approve_and_record(
+    window: &Window,
+    state: &State<'_, AppState>,
+    action: ApprovalAction,
+    context: serde_json::Value,
+    credential: ApprovalCredential,
+) -> Result<Approval, String> {
+    let user_id = state.ipc.session(window.label()).map(|session| session.user_id);
+    let supervisor_id = credential.supervisor_id().to_string();
+    let method = credential.method();
+    
+    let approvals = state.approvals.clone();
+    let (requested_by, approved_context) = (user_id.clone(), context.clone());
+    let result = tokio::task::spawn_blocking(move || {
+        approvals.approve(action, approved_context, &credential, requested_by)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    
+    match result {
+        Ok(approval) => {
+            state.audit.record(
+                AuditEvent::new("request_supervisor_approval", AuditOutcome::Allowed, json!({
+                    "action": action,
+                    "context": context,
+                    "supervisor_id": supervisor_id,
+                    "method": method,
+                    "jti": approval.claims.jti,
+                }))
+                .caller(window.label(), user_id),
+            );
+            Ok(approval)
+        }
+        Err(e) => {
+            warn!("Supervisor approval for {:?} refused: {}", action, e);
+            state.audit.record(
+                AuditEvent::new("request_supervisor_approval", AuditOutcome::Denied, json!({
+                    "action": action,
+                    "context": context,
+                    "supervisor_id": supervisor_id,
+                    "method": method,
+                    "reason": e.to_string(),
+                }))
+                .caller(window.label(), user_id),
+            );
+            Err(e.to_string())
+        }
+    }
+}
+
+// ======================================================================
+// KIOSK COMMANDS
+// ======================================================================
+
+#[command]
+pub async fn get_kiosk_status() -> Result<CommandResult<KioskStatus>, String> {
+    Ok(CommandResult::success(KioskStatus {
+        enabled: AppConfig::load().kiosk.enabled,
+    }))
+}
+
+/// Release the main window; needs a supervisor PIN or authenticator code
+#[command]
+pub async fn leave_kiosk_mode(
+    window: Window,
+    state: State<'_, AppState>,
+    credential: ApprovalCredential,
+) -> Result<CommandResult<()>, String> {
+    info!("Command: leave_kiosk_mode");
+    
+    let mut config = AppConfig::load();
+    if !config.kiosk.enabled {
+        return Ok(CommandResult::error("Kiosk mode is not enabled".to_string()));
+    }
+    
+    let approval = match approve_and_record(&window, &state, ApprovalAction::LeaveKiosk, json!({}), credential).await {
+        Ok(approval) => approval,
+        Err(e) => return Ok(CommandResult::error(e)),
+    };
+    
+    config.kiosk.enabled = false;
+    if let Err(e) = config.save() {
+        return Ok(CommandResult::error(e));
+    }
+    record_caller_event(&window, &state, "leave_kiosk_mode", json!({ "supervisor_id": approval.claims.supervisor_id }));
+    
+    if let Some(main) = window_manager::main_window(&window.app_handle()) {
+        if let Err(e) = kiosk::apply(&main, false) {
+            error!("Failed to release kiosk window: {}", e);
+        }
+    }
+    
+    Ok(CommandResult::success(()))
+}
+
+/// Shut the app down; in kiosk mode only with a supervisor PIN or authenticator code
+#[command]
+pub async fn quit_app(
+    window: Window,
+    state: State<'_, AppState>,
+    credential: Option<ApprovalCredential>,
+) -> Result<CommandResult<()>, String> {
+    info!("Command: quit_app");
+    
+    let supervisor_id = if AppConfig::load().kiosk.enabled {
+        let Some(credential) = credential else {
+            return Ok(CommandResult::error("Quitting in kiosk mode needs supervisor approval".to_string()));
+        };
+        match approve_and_record(&window, &state, ApprovalAction::Quit, json!({}), credential).await {
+            Ok(approval) => Some(approval.claims.supervisor_id),
+            Err(e) => return Ok(CommandResult::error(e)),
+        }
+    } else {
+        None
+    };
+    
+    record_caller_event(&window, &state, "quit_app", json!({ "supervisor_id": supervisor_id }));
+    crate::quit(&window.app_handle());
+    Ok(CommandResult::success(()))
+}
+
+// ======================================================================
+// UPDATE COMMANDS
+// ======================================================================
+
+#[command]
+pub async fn get_update_status(state: State<'_, AppState>) -> Result<CommandResult<UpdateStatus>, String> {
+    Ok(CommandResult::success(state.updater.lock().await.get_status().await))
+}
+
+/// Check the configured feed now and download a newer release; progress arrives on `updater://status`
+#[command]
+pub async fn check_for_update(
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<CommandResult<Option<AvailableUpdate>>, String> {
+    info!("Command: check_for_update");
+    
+    let config = AppConfig::load();
+    let key = updater::rollout_key(config.terminal.as_ref());
+    // The download can take minutes; status and install only need the manager briefly
+    let worker = state.updater.lock().await.worker();
+    
+    match worker.check_and_download(&config.updates, &key).await {
+        Ok(update) => {
+            let version = update.as_ref().map(|update| update.version.clone());
+            record_caller_event(&window, &state, "check_for_update", json!({ "version": version }));
+            Ok(CommandResult::success(update))
+        }
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+/// Install the downloaded update now; refused while a shift is open or a sale is on screen
+#[command]
+pub async fn install_update(
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<CommandResult<()>, String> {
+    info!("Command: install_update");
+    
+    if let Some(reason) = updater::install_blocker(&state).await {
+        return Ok(CommandResult::error(format!("Update not installed: {}", reason)));
+    }
+    
+    let version = state.updater.lock().await.ready().await.map(|(version, _)| version);
+    record_caller_event(&window, &state, "install_update", json!({ "version": version }));
+    match updater::install_and_restart(&window.app_handle(), &state).await {
+        Ok(()) => Ok(CommandResult::success(())),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+// ======================================================================
+// UTILITY COMMANDS
+// ======================================================================
+
+#[command]
+pub async fn show_notification(title: String, body: String, app_handle: AppHandle) -> Result<CommandResult<()>, String> {
+    use tauri::api::notification::Notification;
+    
+    match Notification::new(&app_handle.config().tauri.bundle.identifier)
+        .title(&title)
+        .body(&body)
+        .show()
+    {
+        Ok(_) => Ok(CommandResult::success(())),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+/// Open a link in the system browser, if the URL policy allows it
+#[command]
+pub async fn open_external_url(
+    window: Window,
+    state: State<'_, AppState>,
+    url: String,
+) -> Result<CommandResult<()>, String> {
+    use tauri::api::shell;
+    
+    let user_id = state.ipc.session(window.label()).map(|session| session.user_id);
+    let policy = UrlPolicy::new(&AppConfig::load().url_policy);
+    
+    let url = match policy.check(&url) {
+        Ok(url) => url,
+        Err(e) => {
+            warn!("Refused to open {:?}: {}", url, e);
+            state.audit.record(
+                AuditEvent::new("open_external_url", AuditOutcome::Denied, json!({ "url": url, "reason": e.to_string() }))
+                    .caller(window.label(), user_id),
+            );
+            return Ok(CommandResult::error(e.to_string()));
+        }
+    };
+    
+    state.audit.record(
+        AuditEvent::new("open_external_url", AuditOutcome::Allowed, json!({ "url": url.as_str() }))
+            .caller(window.label(), user_id),
+    );
+    
+    match shell::open(&window.shell_scope(), url.as_str(), None) {
+        Ok(_) => Ok(CommandResult::success(())),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+#[command]
+pub async fn get_app_version() -> Result<CommandResult<String>, String> {
+    let version = env!("CARGO_PKG_VERSION").to_string();
+    Ok(CommandResult::success(version))
+}
+
+#[command]
+pub async fn get_app_config() -> Result<CommandResult<AppConfig>, String> {
+    Ok(CommandResult::success(AppConfig::load()))
+}
+
+#[command]
+pub async fn save_app_config(
+    window: Window,
+    state: State<'_, AppState>,
+    mut config: AppConfig,
+) -> Result<CommandResult<()>, String> {
+    // Never write the database password to config.json (or the log)
+    if let Err(e) = config.move_database_password() {
+        return Ok(CommandResult::error(e));
+    }
+    info!("Saving app config: {:?}", config);
+    
+    // The terminal identity only changes through provisioning, and leaving kiosk mode needs a supervisor
+    let previous = AppConfig::load();
+    config.terminal = previous.terminal.clone();
+    config.kiosk.enabled |= previous.kiosk.enabled;
+    
+    match config.save() {
+        Ok(_) => {
+            let changed = changed_config_keys(&previous, &config);
+            record_caller_event(&window, &state, "save_app_config", json!({ "changed": changed }));
+            
+            state.idle_lock.configure(&config.idle_lock);
+            
+            if config.kiosk.enabled && !previous.kiosk.enabled {
+                if let Some(main) = window_manager::main_window(&window.app_handle()) {
+                    if let Err(e) = kiosk::apply(&main, true) {
+                        error!("Failed to lock kiosk window: {}", e);
+                    }
+                }
+            }
+            
+            state.journal.lock().await.set_numbering(config.order_numbering());
+            
+            let mut backup = state.backup.lock().await;
+            if let Err(e) = backup.start(config.backup.clone(), state.database_url().await).await {
+                error!("Failed to restart backup scheduler: {}", e);
+            }
+            
+            let key = updater::rollout_key(config.terminal.as_ref());
+            state.updater.lock().await.start(config.updates.clone(), config.auto_update, key).await;
+            
+            Ok(CommandResult::success(()))
+        }
+        Err(e) => Ok(CommandResult::error(e)),
+    }
+}
+
+/// Top-level config sections that differ, for the audit log; values are left out
+fn changed_config_keys(previous: &AppConfig, config: &AppConfig) -> Vec<String> {
+    let (Ok(serde_json::Value::Object(previous)), Ok(serde_json::Value::Object(config))) =
+        (serde_json::to_value(previous), serde_json::to_value(config))
+    else {
+        return Vec::new();
+    };
+    
+    config
+        .iter()
+        .filter(|(key, value)| previous.get(*key) != Some(*value))
+        .map(|(key, _)| key.clone())
+        .collect()
+}
+
+// ======================================================================
+// WINDOW COMMANDS
+// ======================================================================
+
+#[command]
+pub async fn minimize_to_tray(app_handle: AppHandle) -> Result<CommandResult<()>, String> {
+    if AppConfig::load().kiosk.enabled {
+        return Ok(CommandResult::error("The window stays open in kiosk mode".to_string()));
+    }
+    
+    if let Some(window) = window_manager::main_window(&app_handle) {
+        match window.hide() {
+            Ok(_) => Ok(CommandResult::success(())),
+            Err(e) => Ok(CommandResult::error(e.to_string())),
+        }
+    } else {
+        Ok(CommandResult::error("Main window not found".to_string()))
+    }
+}
+
+#[command]
+pub async fn show_from_tray(app_handle: AppHandle) -> Result<CommandResult<()>, String> {
+    if let Some(window) = window_manager::main_window(&app_handle) {
+        match window.show() {
+            Ok(_) => {
+                let _ = window.set_focus();
+                Ok(CommandResult::success(()))
+            }
+            Err(e) => Ok(CommandResult::error(e.to_string())),
+        }
+    } else {
+        Ok(CommandResult::error("Main window not found".to_string()))
+    }
+}
+
+#[command]
+pub async fn toggle_window_visibility(app_handle: AppHandle) -> Result<CommandResult<bool>, String> {
+    if AppConfig::load().kiosk.enabled {
+        return Ok(CommandResult::error("The window stays open in kiosk mode".to_string()));
+    }
+    
+    if let Some(window) = window_manager::main_window(&app_handle) {
+        match window.is_visible() {
+            Ok(is_visible) => {
+                if is_visible {
+                    match window.hide() {
+                        Ok(_) => Ok(CommandResult::success(false)),
+                        Err(e) => Ok(CommandResult::error(e.to_string())),
+                    }
+                } else {
+                    match window.show() {
+                        Ok(_) => {
+                            let _ = window.set_focus();
+                            Ok(CommandResult::success(true))
+                        }
+                        Err(e) => Ok(CommandResult::error(e.to_string())),
+                    }
+                }
+            }
+            Err(e) => Ok(CommandResult::error(e.to_string())),
+        }
+    } else {
+        Ok(CommandResult::error("Main window not found".to_string()))
+    }
+}
+
+// ======================================================================
+// CUSTOMER WINDOW COMMANDS
+// ======================================================================
+
+#[command]
+pub async fn list_displays(app_handle: AppHandle) -> Result<CommandResult<Vec<DisplayInfo>>, String> {
+    match window_manager::list_displays(&app_handle) {
+        Ok(displays) => Ok(CommandResult::success(displays)),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+#[command]
+pub async fn open_customer_window(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    display_index: usize,
+) -> Result<CommandResult<()>, String> {
+    info!("Command: open_customer_window on display {}", display_index);
+    
+    match window_manager::open_customer_window(&app_handle, display_index) {
+        Ok(_) => {
+            // Replay the current cart so the new window is not blank
+            let view = state.customer_view.lock().await.clone();
+            let _ = window_manager::push_customer_view(&app_handle, &view);
+            Ok(CommandResult::success(()))
+        }
+        Err(e) => {
+            error!("Failed to open customer window: {}", e);
+            Ok(CommandResult::error(e.to_string()))
+        }
+    }
+}
+
+#[command]
+pub async fn close_customer_window(app_handle: AppHandle) -> Result<CommandResult<()>, String> {
+    match window_manager::close_customer_window(&app_handle) {
+        Ok(_) => Ok(CommandResult::success(())),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+#[command]
+pub async fn update_customer_view(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    mut view: CustomerView,
+) -> Result<CommandResult<()>, String> {
+    if let Some(payload) = view.qris_payload.as_deref() {
+        match window_manager::render_qris_svg(payload) {
+            Ok(svg) => view.qris_svg = Some(svg),
+            Err(e) => error!("Failed to render QRIS code: {}", e),
+        }
+    }
+    
+    *state.customer_view.lock().await = view.clone();
+    
+    match window_manager::push_customer_view(&app_handle, &view) {
+        Ok(_) => Ok(CommandResult::success(())),
+        Err(e) => Ok(CommandResult::error(e.to_string())),
+    }
+}
+
+#[command]
+pub async fn get_customer_view(state: State<'_, AppState>) -> Result<CommandResult<CustomerView>, String> {
+    let view = state.customer_view.lock().await.clone();
+    Ok(CommandResult::success(view))
+}
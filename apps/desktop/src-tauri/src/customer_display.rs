@@ -0,0 +1,383 @@
+// ======================================================================
+// CUSTOMER DISPLAY
+// Driver pole display VFD 2x20 untuk menampilkan item dan total ke pelanggan
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use serialport::SerialPort;
+use std::{io::Write, time::Duration};
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+/// Command sets found on common VFD pole displays
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VfdCommandSet {
+    /// Epson DM-D compatible (ESC @, US $ cursor addressing)
+    #[default]
+    EscPos,
+    /// CD5220 (ESC Q A / ESC Q B line commands)
+    Cd5220,
+    /// AEDEX (!#1 / !#2 line commands)
+    Aedex,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DisplayStatus {
+    Closed,
+    Open {
+        port: String,
+        command_set: VfdCommandSet,
+        lines: [String; 2],
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct DisplayConfig {
+    pub port: String,
+    pub baud_rate: u32,
+    pub command_set: VfdCommandSet,
+    pub columns: usize,
+    pub idle_message: [String; 2],
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            port: String::new(),
+            baud_rate: 9600,
+            command_set: VfdCommandSet::EscPos,
+            columns: 20,
+            idle_message: ["SELAMAT DATANG".to_string(), "TERIMA KASIH".to_string()],
+        }
+    }
+}
+
+// ======================================================================
+// ENCODER
+// ======================================================================
+
+/// Translates two text lines into the byte sequence for a command set
+#[derive(Debug, Clone)]
+pub struct VfdEncoder {
+    command_set: VfdCommandSet,
+    columns: usize,
+}
+
+impl VfdEncoder {
+    pub fn new(command_set: VfdCommandSet, columns: usize) -> Self {
+        Self {
+            command_set,
+            columns,
+        }
+    }
+
+    /// Reset the display to its power-on state
+    pub fn init(&self) -> Vec<u8> {
+        match self.command_set {
+            VfdCommandSet::EscPos | VfdCommandSet::Cd5220 => vec![0x1B, 0x40, 0x0C],
+            VfdCommandSet::Aedex => {
+                let mut bytes = b"!#4".to_vec();
+                bytes.extend(std::iter::repeat(b' ').take(self.columns * 2));
+                bytes.push(b'\r');
+                bytes
+            }
+        }
+    }
+
+    /// Overwrite both lines
+    pub fn lines(&self, upper: &str, lower: &str) -> Vec<u8> {
+        let upper = self.fit(upper);
+        let lower = self.fit(lower);
+        let mut bytes = Vec::with_capacity(self.columns * 2 + 16);
+
+        match self.command_set {
+            VfdCommandSet::EscPos => {
+                // Home, write upper line, then address column 1 of row 2
+                bytes.push(0x0B);
+                bytes.extend_from_slice(&upper);
+                bytes.extend_from_slice(&[0x1F, 0x24, 0x01, 0x02]);
+                bytes.extend_from_slice(&lower);
+            }
+            VfdCommandSet::Cd5220 => {
+                bytes.extend_from_slice(&[0x1B, 0x51, 0x41]);
+                bytes.extend_from_slice(&upper);
+                bytes.push(0x0D);
+                bytes.extend_from_slice(&[0x1B, 0x51, 0x42]);
+                bytes.extend_from_slice(&lower);
+                bytes.push(0x0D);
+            }
+            VfdCommandSet::Aedex => {
+                bytes.extend_from_slice(b"!#1");
+                bytes.extend_from_slice(&upper);
+                bytes.push(b'\r');
+                bytes.extend_from_slice(b"!#2");
+                bytes.extend_from_slice(&lower);
+                bytes.push(b'\r');
+            }
+        }
+
+        bytes
+    }
+
+    /// Pad/truncate to the display width; VFDs only render printable ASCII
+    fn fit(&self, text: &str) -> Vec<u8> {
+        let mut bytes: Vec<u8> = text
+            .chars()
+            .filter(|c| !c.is_control())
+            .map(|c| if c.is_ascii() { c as u8 } else { b'?' })
+            .take(self.columns)
+            .collect();
+        bytes.resize(self.columns, b' ');
+        bytes
+    }
+}
+
+// ======================================================================
+// LAYOUTS
+// ======================================================================
+
+/// Put `left` and `right` on one line, right-aligning `right`
+pub fn justify(left: &str, right: &str, columns: usize) -> String {
+    let right_len = right.chars().count();
+    if right_len >= columns {
+        return right.chars().take(columns).collect();
+    }
+
+    let left: String = left.chars().take(columns - right_len - 1).collect();
+    let padding = columns - left.chars().count() - right_len;
+    format!("{}{}{}", left, " ".repeat(padding), right)
+}
+
+pub fn layout_line_item(name: &str, quantity: f64, price: i64, columns: usize) -> [String; 2] {
+    let line_total = (quantity * price as f64).round() as i64;
+    let quantity = if quantity.fract() == 0.0 {
+        format!("{}", quantity as i64)
+    } else {
+        format!("{:.3}", quantity).trim_end_matches('0').to_string()
+    };
+
+    let total = crate::utils::format_rupiah(line_total);
+    let unit = format!("{}x{}", quantity, crate::utils::format_rupiah(price).trim_start_matches("Rp "));
+
+    // Drop the unit price rather than truncate it when the line is too narrow
+    let left = if unit.chars().count() + total.chars().count() < columns {
+        unit
+    } else {
+        format!("{}x", quantity)
+    };
+
+    [name.chars().take(columns).collect(), justify(&left, &total, columns)]
+}
+
+pub fn layout_amount(label: &str, amount: i64, columns: usize) -> [String; 2] {
+    [label.to_string(), justify("", &crate::utils::format_rupiah(amount), columns)]
+}
+
+pub fn layout_change(paid: i64, change: i64, columns: usize) -> [String; 2] {
+    [
+        justify("BAYAR", &crate::utils::format_rupiah(paid), columns),
+        justify("KEMBALI", &crate::utils::format_rupiah(change), columns),
+    ]
+}
+
+// ======================================================================
+// CUSTOMER DISPLAY
+// ======================================================================
+
+pub struct CustomerDisplay {
+    port: Option<Box<dyn SerialPort>>,
+    config: DisplayConfig,
+    encoder: VfdEncoder,
+    lines: [String; 2],
+}
+
+impl CustomerDisplay {
+    pub fn new() -> Self {
+        let config = DisplayConfig::default();
+        Self {
+            port: None,
+            encoder: VfdEncoder::new(config.command_set, config.columns),
+            config,
+            lines: [String::new(), String::new()],
+        }
+    }
+
+    /// Open the serial port and show the idle message
+    pub fn open(&mut self, config: DisplayConfig) -> Result<()> {
+        info!("Opening customer display on {} ({:?})", config.port, config.command_set);
+
+        let port = serialport::new(&config.port, config.baud_rate)
+            .timeout(Duration::from_millis(500))
+            .open()
+            .map_err(|e| anyhow!("Failed to open display port {}: {}", config.port, e))?;
+
+        self.open_with_port(config, port)
+    }
+
+    /// Use an already opened port
+    pub fn open_with_port(&mut self, config: DisplayConfig, port: Box<dyn SerialPort>) -> Result<()> {
+        self.encoder = VfdEncoder::new(config.command_set, config.columns);
+        self.config = config;
+        self.port = Some(port);
+
+        let init = self.encoder.init();
+        self.write(&init)?;
+        self.show_idle()
+    }
+
+    pub fn close(&mut self) {
+        if self.port.take().is_some() {
+            info!("Customer display closed");
+        }
+        self.lines = [String::new(), String::new()];
+    }
+
+    pub fn show_line_item(&mut self, name: &str, quantity: f64, price: i64) -> Result<()> {
+        let [upper, lower] = layout_line_item(name, quantity, price, self.config.columns);
+        self.show(&upper, &lower)
+    }
+
+    pub fn show_subtotal(&mut self, amount: i64) -> Result<()> {
+        let [upper, lower] = layout_amount("SUBTOTAL", amount, self.config.columns);
+        self.show(&upper, &lower)
+    }
+
+    pub fn show_total(&mut self, amount: i64) -> Result<()> {
+        let [upper, lower] = layout_amount("TOTAL", amount, self.config.columns);
+        self.show(&upper, &lower)
+    }
+
+    pub fn show_change(&mut self, paid: i64, change: i64) -> Result<()> {
+        let [upper, lower] = layout_change(paid, change, self.config.columns);
+        self.show(&upper, &lower)
+    }
+
+    pub fn show_idle(&mut self) -> Result<()> {
+        let [upper, lower] = self.config.idle_message.clone();
+        self.show(&upper, &lower)
+    }
+
+    pub fn set_idle_message(&mut self, upper: String, lower: String) {
+        self.config.idle_message = [upper, lower];
+    }
+
+    /// Write two raw lines
+    pub fn show(&mut self, upper: &str, lower: &str) -> Result<()> {
+        debug!("Customer display: {:?} / {:?}", upper, lower);
+
+        let bytes = self.encoder.lines(upper, lower);
+        self.write(&bytes)?;
+        self.lines = [upper.to_string(), lower.to_string()];
+        Ok(())
+    }
+
+    pub fn get_status(&self) -> DisplayStatus {
+        match self.port {
+            Some(_) => DisplayStatus::Open {
+                port: self.config.port.clone(),
+                command_set: self.config.command_set,
+                lines: self.lines.clone(),
+            },
+            None => DisplayStatus::Closed,
+        }
+    }
+
+    // ======================================================================
+    // PRIVATE METHODS
+    // ======================================================================
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        let port = self
+            .port
+            .as_mut()
+            .ok_or_else(|| anyhow!("Customer display is not open"))?;
+
+        port.write_all(bytes)
+            .and_then(|_| port.flush())
+            .map_err(|e| anyhow!("Failed to write to customer display: {}", e))
+    }
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_justify() {
+        assert_eq!(justify("TOTAL", "Rp 12.500", 20), "TOTAL      Rp 12.500");
+        assert_eq!(justify("KEMBALIAN PANJANG", "Rp 1.250.000", 20), "KEMBALI Rp 1.250.000");
+        assert_eq!(justify("", "Rp 5.000", 10), "  Rp 5.000");
+    }
+
+    #[test]
+    fn test_layout_line_item() {
+        let [upper, lower] = layout_line_item("Indomie Goreng Rasa Rendang", 2.0, 3500, 20);
+        assert_eq!(upper, "Indomie Goreng Rasa ");
+        assert_eq!(lower, "2x3.500     Rp 7.000");
+
+        let [_, lower] = layout_line_item("Beras", 1.25, 14000, 20);
+        assert_eq!(lower, "1.25x      Rp 17.500");
+    }
+
+    #[test]
+    fn test_encoder_cd5220() {
+        let encoder = VfdEncoder::new(VfdCommandSet::Cd5220, 4);
+        assert_eq!(
+            encoder.lines("AB", "Rp 1234"),
+            b"\x1bQAAB  \r\x1bQBRp 1\r".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_encoder_escpos_and_aedex() {
+        let escpos = VfdEncoder::new(VfdCommandSet::EscPos, 3);
+        assert_eq!(escpos.lines("é", "x"), b"\x0b?  \x1f\x24\x01\x02x  ".to_vec());
+
+        let aedex = VfdEncoder::new(VfdCommandSet::Aedex, 3);
+        assert_eq!(aedex.lines("A", "B"), b"!#1A  \r!#2B  \r".to_vec());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_display_over_pseudo_terminal() {
+        use serialport::TTYPort;
+        use std::io::Read;
+
+        let (mut master, slave) = TTYPort::pair().expect("failed to create pty pair");
+        let config = DisplayConfig {
+            port: slave.name().unwrap_or_default(),
+            command_set: VfdCommandSet::Cd5220,
+            ..DisplayConfig::default()
+        };
+
+        let mut display = CustomerDisplay::new();
+        display.open_with_port(config, Box::new(slave)).unwrap();
+        display.show_total(25000).unwrap();
+
+        let mut received = Vec::new();
+        let mut buf = [0u8; 256];
+        while let Ok(n) = master.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            received.extend_from_slice(&buf[..n]);
+        }
+
+        let text = String::from_utf8_lossy(&received);
+        assert!(received.starts_with(&[0x1B, 0x40, 0x0C]));
+        assert!(text.contains("SELAMAT DATANG"));
+        assert!(text.ends_with("\x1bQB           Rp 25.000\r"));
+        assert!(matches!(display.get_status(), DisplayStatus::Open { .. }));
+
+        display.close();
+        assert!(matches!(display.get_status(), DisplayStatus::Closed));
+    }
+}
@@ -0,0 +1,310 @@
+// ======================================================================
+// DEVICE REGISTRY
+// Daftar perangkat keras kasir (printer, laci, scanner, timbangan, display)
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    customer_display::DisplayConfig,
+    scale::ScaleConfig,
+    scanner::ScannerConfig,
+    utils,
+};
+
+/// File name of the registry inside the app data directory
+pub const DEVICES_FILE: &str = "devices.json";
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeviceKind {
+    Printer,
+    CashDrawer,
+    Scanner,
+    Scale,
+    CustomerDisplay,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceConfig {
+    /// Generated when the device is first added
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub kind: DeviceKind,
+    /// Serial port name, or `tcp://host:port` for network printers
+    pub port: String,
+    #[serde(default = "default_baud_rate")]
+    pub baud_rate: u32,
+    /// Driver specific settings, e.g. `protocol`, `command_set`, `columns`
+    #[serde(default)]
+    pub options: HashMap<String, serde_json::Value>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_baud_rate() -> u32 {
+    9600
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RegistryFile {
+    devices: Vec<DeviceConfig>,
+}
+
+// ======================================================================
+// DEVICE CONFIG
+// ======================================================================
+
+impl DeviceConfig {
+    /// Read a typed option, ignoring values of the wrong shape
+    pub fn option<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let value = self.options.get(key)?;
+        match serde_json::from_value(value.clone()) {
+            Ok(parsed) => Some(parsed),
+            Err(e) => {
+                warn!("Ignoring option {} on device {}: {}", key, self.id, e);
+                None
+            }
+        }
+    }
+
+    pub fn scanner_config(&self) -> ScannerConfig {
+        let defaults = ScannerConfig::default();
+        ScannerConfig {
+            port: self.port.clone(),
+            baud_rate: self.baud_rate,
+            min_length: self.option("min_length").unwrap_or(defaults.min_length),
+            max_length: self.option("max_length").unwrap_or(defaults.max_length),
+            ..defaults
+        }
+    }
+
+    pub fn scale_config(&self) -> ScaleConfig {
+        let defaults = ScaleConfig::default();
+        ScaleConfig {
+            port: self.port.clone(),
+            baud_rate: self.baud_rate,
+            protocol: self.option("protocol").unwrap_or(defaults.protocol),
+            stable_samples: self.option("stable_samples").unwrap_or(defaults.stable_samples),
+            stable_tolerance_grams: self
+                .option("stable_tolerance_grams")
+                .unwrap_or(defaults.stable_tolerance_grams),
+            ..defaults
+        }
+    }
+
+    pub fn display_config(&self) -> DisplayConfig {
+        let defaults = DisplayConfig::default();
+        DisplayConfig {
+            port: self.port.clone(),
+            baud_rate: self.baud_rate,
+            command_set: self.option("command_set").unwrap_or(defaults.command_set),
+            columns: self.option("columns").unwrap_or(defaults.columns),
+            ..defaults
+        }
+    }
+
+    /// Receipt width in characters
+    pub fn printer_columns(&self) -> usize {
+        self.option("columns").unwrap_or(32)
+    }
+}
+
+// ======================================================================
+// DEVICE REGISTRY
+// ======================================================================
+
+#[derive(Debug)]
+pub struct DeviceRegistry {
+    path: PathBuf,
+    devices: Vec<DeviceConfig>,
+}
+
+impl DeviceRegistry {
+    /// Load the registry from disk; a missing file is an empty registry
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let devices = match std::fs::read(&path) {
+            Ok(bytes) => {
+                let file: RegistryFile = serde_json::from_slice(&bytes)
+                    .map_err(|e| anyhow!("Invalid device registry {:?}: {}", path, e))?;
+                file.devices
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(anyhow!("Failed to read device registry {:?}: {}", path, e)),
+        };
+
+        info!("Loaded {} devices from {:?}", devices.len(), path);
+        Ok(Self { path, devices })
+    }
+
+    /// Registry at the default location beside `config.json`
+    pub fn load_default() -> Self {
+        let path = utils::get_app_data_dir().join(DEVICES_FILE);
+        Self::load(path.clone()).unwrap_or_else(|e| {
+            warn!("{}; starting with an empty device registry", e);
+            Self {
+                path,
+                devices: Vec::new(),
+            }
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn list(&self) -> Vec<DeviceConfig> {
+        self.devices.clone()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&DeviceConfig> {
+        self.devices.iter().find(|device| device.id == id)
+    }
+
+    /// Pick a device by id, or the first enabled device of that kind
+    pub fn resolve(&self, kind: DeviceKind, device_id: Option<&str>) -> Result<DeviceConfig> {
+        let device = match device_id {
+            Some(id) => self
+                .get(id)
+                .ok_or_else(|| anyhow!("Device {} not found", id))?,
+            None => self
+                .devices
+                .iter()
+                .find(|device| device.kind == kind && device.enabled)
+                .ok_or_else(|| anyhow!("No {:?} configured", kind))?,
+        };
+
+        if device.kind != kind {
+            return Err(anyhow!("Device {} is a {:?}, not a {:?}", device.id, device.kind, kind));
+        }
+
+        Ok(device.clone())
+    }
+
+    /// Add a device, or replace the one with the same id
+    pub fn upsert(&mut self, mut device: DeviceConfig) -> Result<DeviceConfig> {
+        if device.name.trim().is_empty() {
+            return Err(anyhow!("Device name is required"));
+        }
+        if device.port.trim().is_empty() {
+            return Err(anyhow!("Device port is required"));
+        }
+        if device.id.is_empty() {
+            device.id = uuid::Uuid::new_v4().to_string();
+        }
+
+        match self.devices.iter_mut().find(|existing| existing.id == device.id) {
+            Some(existing) => *existing = device.clone(),
+            None => self.devices.push(device.clone()),
+        }
+
+        self.save()?;
+        Ok(device)
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<()> {
+        let before = self.devices.len();
+        self.devices.retain(|device| device.id != id);
+
+        if self.devices.len() == before {
+            return Err(anyhow!("Device {} not found", id));
+        }
+
+        self.save()
+    }
+
+    // ======================================================================
+    // PRIVATE METHODS
+    // ======================================================================
+
+    fn save(&self) -> Result<()> {
+        debug!("Saving {} devices to {:?}", self.devices.len(), self.path);
+
+        let file = RegistryFile {
+            devices: self.devices.clone(),
+        };
+        let json = serde_json::to_vec_pretty(&file)?;
+        utils::write_file_atomic(&self.path, &json).map_err(|e| anyhow!(e))
+    }
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{customer_display::VfdCommandSet, scale::ScaleProtocol};
+
+    fn device(kind: DeviceKind, port: &str) -> DeviceConfig {
+        DeviceConfig {
+            id: String::new(),
+            name: format!("{:?}", kind),
+            kind,
+            port: port.to_string(),
+            baud_rate: 9600,
+            options: HashMap::new(),
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_registry_roundtrip() {
+        let path = std::env::temp_dir()
+            .join(format!("pos-kasir-devices-{}", std::process::id()))
+            .join(DEVICES_FILE);
+
+        let mut registry = DeviceRegistry::load(path.clone()).unwrap();
+        let scanner = registry.upsert(device(DeviceKind::Scanner, "COM3")).unwrap();
+        let mut scale = registry.upsert(device(DeviceKind::Scale, "COM4")).unwrap();
+        assert!(!scanner.id.is_empty());
+
+        scale.enabled = false;
+        registry.upsert(scale.clone()).unwrap();
+
+        let reloaded = DeviceRegistry::load(path.clone()).unwrap();
+        assert_eq!(reloaded.list().len(), 2);
+        assert_eq!(reloaded.resolve(DeviceKind::Scanner, None).unwrap().port, "COM3");
+        assert!(reloaded.resolve(DeviceKind::Scale, None).is_err());
+        assert_eq!(reloaded.resolve(DeviceKind::Scale, Some(&scale.id)).unwrap().port, "COM4");
+        assert!(reloaded.resolve(DeviceKind::Printer, Some(&scanner.id)).is_err());
+
+        registry.remove(&scanner.id).unwrap();
+        assert!(registry.remove(&scanner.id).is_err());
+        assert_eq!(DeviceRegistry::load(path.clone()).unwrap().list().len(), 1);
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_driver_configs_from_options() {
+        let mut display = device(DeviceKind::CustomerDisplay, "/dev/ttyUSB0");
+        display.options.insert("command_set".into(), serde_json::json!("Cd5220"));
+        display.options.insert("columns".into(), serde_json::json!(16));
+        let config = display.display_config();
+        assert_eq!(config.command_set, VfdCommandSet::Cd5220);
+        assert_eq!(config.columns, 16);
+
+        let mut scale = device(DeviceKind::Scale, "/dev/ttyUSB1");
+        scale.options.insert("protocol".into(), serde_json::json!("RequestResponse"));
+        scale.options.insert("stable_samples".into(), serde_json::json!("many"));
+        let config = scale.scale_config();
+        assert_eq!(config.protocol, ScaleProtocol::RequestResponse);
+        assert_eq!(config.stable_samples, ScaleConfig::default().stable_samples);
+    }
+}
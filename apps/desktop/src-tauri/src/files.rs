@@ -0,0 +1,335 @@
+// ======================================================================
+// FILE SERVICE
+// Ekspor, impor CSV, dan log untuk webview dalam folder yang dibatasi
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::{
+    fs::{File, OpenOptions},
+    io::{ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::utils;
+
+/// Reports and product lists; Excel itself caps a sheet well below this
+pub const MAX_EXPORT_BYTES: usize = 50 * 1024 * 1024;
+
+pub const MAX_IMPORT_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Per `save_log` call
+pub const MAX_LOG_WRITE_BYTES: usize = 1024 * 1024;
+
+/// A log file past this is rotated to `<name>.1.log`
+pub const MAX_LOG_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+const MAX_NAME_LENGTH: usize = 128;
+
+const EXPORT_EXTENSIONS: &[&str] = &["csv", "xlsx", "json", "pdf", "txt"];
+
+/// Names Windows maps to devices whatever the extension
+const RESERVED_NAMES: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8", "com9", "lpt1",
+    "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedFile {
+    /// Final name, after sanitising and de-duplication
+    pub file_name: String,
+    pub path: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedCsv {
+    pub file_name: String,
+    pub size: u64,
+    /// UTF-8 text without a byte-order mark
+    pub contents: String,
+}
+
+// ======================================================================
+// FILE SERVICE
+// ======================================================================
+
+/// The only file access the webview has: each operation is pinned to one directory
+pub struct FileService {
+    exports_dir: PathBuf,
+    imports_dir: PathBuf,
+    logs_dir: PathBuf,
+}
+
+impl FileService {
+    /// `Documents/POS/Exports`, `Documents/POS/Imports` and `logs` in app data
+    pub fn open_default() -> Self {
+        let pos_dir = utils::get_documents_dir().join("POS");
+        Self::with_dirs(pos_dir.join("Exports"), pos_dir.join("Imports"), utils::get_app_data_dir().join("logs"))
+    }
+
+    pub fn with_dirs(exports_dir: PathBuf, imports_dir: PathBuf, logs_dir: PathBuf) -> Self {
+        Self {
+            exports_dir,
+            imports_dir,
+            logs_dir,
+        }
+    }
+
+    pub fn imports_dir(&self) -> &Path {
+        &self.imports_dir
+    }
+
+    /// Write a new file to the exports directory; an existing name gets a ` (n)` suffix
+    /// instead of being overwritten
+    pub fn export(&self, file_name: &str, contents: &[u8]) -> Result<SavedFile> {
+        if contents.len() > MAX_EXPORT_BYTES {
+            return Err(anyhow!("Export is larger than {} MB", MAX_EXPORT_BYTES / 1024 / 1024));
+        }
+
+        let name = confined_name(file_name, EXPORT_EXTENSIONS, None)?;
+        utils::ensure_directory_exists(&self.exports_dir).map_err(|e| anyhow!(e))?;
+
+        let (path, mut file) = create_unique(&self.exports_dir, &name)?;
+        let written = file.write_all(contents).and_then(|_| file.sync_all());
+        if let Err(e) = written {
+            let _ = std::fs::remove_file(&path);
+            return Err(anyhow!("Failed to write {:?}: {}", path, e));
+        }
+
+        Ok(saved(path, contents.len() as u64))
+    }
+
+    /// Read a CSV from the imports directory by name
+    pub fn import_named(&self, file_name: &str) -> Result<ImportedCsv> {
+        let name = confined_name(file_name, &["csv"], None)?;
+        let path = self.imports_dir.join(name);
+        reject_symlink(&path)?;
+        read_csv(&path)
+    }
+
+    /// Read a CSV the user picked in the native dialog; the webview never supplies this path
+    pub fn import_picked(&self, path: &Path) -> Result<ImportedCsv> {
+        let is_csv = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+        if !is_csv {
+            return Err(anyhow!("Only .csv files can be imported"));
+        }
+        read_csv(path)
+    }
+
+    /// Append to a log file in the logs directory, rotating it once it grows too large
+    pub fn append_log(&self, file_name: &str, contents: &str) -> Result<SavedFile> {
+        if contents.len() > MAX_LOG_WRITE_BYTES {
+            return Err(anyhow!("Log entry is larger than {} KB", MAX_LOG_WRITE_BYTES / 1024));
+        }
+
+        let name = confined_name(file_name, &["log"], Some("log"))?;
+        utils::ensure_directory_exists(&self.logs_dir).map_err(|e| anyhow!(e))?;
+
+        let path = self.logs_dir.join(&name);
+        reject_symlink(&path)?;
+
+        let current = std::fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+        if current > 0 && current + contents.len() as u64 > MAX_LOG_FILE_BYTES {
+            std::fs::rename(&path, path.with_extension("1.log"))?;
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        file.write_all(contents.as_bytes())?;
+        if !contents.ends_with('\n') {
+            file.write_all(b"\n")?;
+        }
+
+        let size = file.metadata()?.len();
+        Ok(saved(path, size))
+    }
+}
+
+// ======================================================================
+// HELPER FUNCTIONS
+// ======================================================================
+
+/// A single path component the directory cannot be escaped from, with an allowed
+/// extension; `default_extension` is appended when the name has none
+fn confined_name(file_name: &str, extensions: &[&str], default_extension: Option<&str>) -> Result<String> {
+    let mut name = utils::sanitize_filename(file_name.trim()).trim().to_string();
+    if name.is_empty() {
+        return Err(anyhow!("File name is empty"));
+    }
+
+    let extension = Path::new(&name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    match (extension, default_extension) {
+        (Some(ext), _) if extensions.contains(&ext.as_str()) => {}
+        (None, Some(default)) => name = format!("{}.{}", name, default),
+        _ => {
+            return Err(anyhow!("File type not allowed; expected one of: {}", extensions.join(", ")));
+        }
+    }
+
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(anyhow!("File name is longer than {} characters", MAX_NAME_LENGTH));
+    }
+
+    let stem = name.split('.').next().unwrap_or_default().trim().to_ascii_lowercase();
+    if stem.is_empty() || RESERVED_NAMES.contains(&stem.as_str()) {
+        return Err(anyhow!("{:?} is not a usable file name", name));
+    }
+
+    // sanitize_filename already replaces separators; this guards against it ever changing
+    let mut components = Path::new(&name).components();
+    if !matches!(
+        (components.next(), components.next()),
+        (Some(std::path::Component::Normal(_)), None)
+    ) {
+        return Err(anyhow!("{:?} is not a plain file name", name));
+    }
+
+    Ok(name)
+}
+
+/// `create_new` fails on an existing file or symlink, so nothing outside the
+/// directory can be written through a planted link
+fn create_unique(dir: &Path, name: &str) -> Result<(PathBuf, File)> {
+    let (stem, extension) = name.rsplit_once('.').unwrap_or((name, ""));
+
+    for n in 0..100 {
+        let candidate = if n == 0 {
+            name.to_string()
+        } else {
+            format!("{} ({}).{}", stem, n, extension)
+        };
+
+        let path = dir.join(candidate);
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(anyhow!("Failed to create {:?}: {}", path, e)),
+        }
+    }
+
+    Err(anyhow!("Too many exports named {:?}", name))
+}
+
+fn reject_symlink(path: &Path) -> Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_symlink() => Err(anyhow!("{:?} is a symbolic link", path)),
+        _ => Ok(()),
+    }
+}
+
+fn read_csv(path: &Path) -> Result<ImportedCsv> {
+    let file = File::open(path).map_err(|e| anyhow!("Failed to open {:?}: {}", path, e))?;
+    let meta = file.metadata()?;
+    if !meta.is_file() {
+        return Err(anyhow!("{:?} is not a regular file", path));
+    }
+    if meta.len() > MAX_IMPORT_BYTES {
+        return Err(anyhow!("File is larger than {} MB", MAX_IMPORT_BYTES / 1024 / 1024));
+    }
+
+    // The file may grow between the check and the read
+    let mut bytes = Vec::with_capacity(meta.len() as usize);
+    file.take(MAX_IMPORT_BYTES + 1).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > MAX_IMPORT_BYTES {
+        return Err(anyhow!("File is larger than {} MB", MAX_IMPORT_BYTES / 1024 / 1024));
+    }
+
+    let contents = String::from_utf8(bytes).map_err(|_| anyhow!("File is not UTF-8 text; save it as CSV UTF-8"))?;
+
+    Ok(ImportedCsv {
+        file_name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        size: meta.len(),
+        // Excel writes a BOM in front of "CSV UTF-8"
+        contents: contents.trim_start_matches('\u{feff}').to_string(),
+    })
+}
+
+fn saved(path: PathBuf, size: u64) -> SavedFile {
+    SavedFile {
+        file_name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        path: path.to_string_lossy().into_owned(),
+        size,
+    }
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service() -> (FileService, PathBuf) {
+        let root = std::env::temp_dir().join(format!("pos-files-{}", uuid::Uuid::new_v4()));
+        let service = FileService::with_dirs(root.join("Exports"), root.join("Imports"), root.join("logs"));
+        (service, root)
+    }
+
+    #[test]
+    fn test_names_are_confined() {
+        assert_eq!(confined_name("Laporan: Mei/2024.xlsx", EXPORT_EXTENSIONS, None).unwrap(), "Laporan_ Mei_2024.xlsx");
+        assert_eq!(confined_name("../../.bashrc.CSV", EXPORT_EXTENSIONS, None).unwrap(), "_.._.bashrc.CSV");
+        assert_eq!(confined_name("kasir", &["log"], Some("log")).unwrap(), "kasir.log");
+
+        for name in ["..", "", "  ", "report.exe", "report", "C:\\Windows\\x.bat", "nul.csv", "COM1.txt", ".csv"] {
+            assert!(confined_name(name, EXPORT_EXTENSIONS, None).is_err(), "{:?}", name);
+        }
+        assert!(confined_name(&format!("{}.csv", "a".repeat(MAX_NAME_LENGTH)), EXPORT_EXTENSIONS, None).is_err());
+    }
+
+    #[test]
+    fn test_export_import_and_log() {
+        let (service, root) = service();
+
+        let first = service.export("../penjualan.csv", b"a,b\n1,2\n").unwrap();
+        let second = service.export("penjualan.csv", b"a,b\n").unwrap();
+        assert_eq!(first.file_name, "_penjualan.csv");
+        assert_eq!(second.file_name, "penjualan.csv");
+        assert_eq!(service.export("penjualan.csv", b"").unwrap().file_name, "penjualan (1).csv");
+        assert!(Path::new(&first.path).starts_with(root.join("Exports")));
+        assert!(service.export("big.csv", &vec![0; MAX_EXPORT_BYTES + 1]).is_err());
+
+        std::fs::create_dir_all(service.imports_dir()).unwrap();
+        std::fs::write(service.imports_dir().join("produk.csv"), "\u{feff}sku,nama\nA1,Kopi\n").unwrap();
+        let imported = service.import_named("produk.csv").unwrap();
+        assert_eq!(imported.contents, "sku,nama\nA1,Kopi\n");
+        assert!(service.import_named("../Exports/penjualan.csv").is_err());
+        assert!(service.import_picked(&root.join("Exports").join("_penjualan.csv")).is_ok());
+        assert!(service.import_picked(&root.join("secret.json")).is_err());
+
+        service.append_log("web", "first").unwrap();
+        let log = service.append_log("web.log", "second\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&log.path).unwrap(), "first\nsecond\n");
+        assert!(service.append_log("web", &"x".repeat(MAX_LOG_WRITE_BYTES + 1)).is_err());
+
+        #[cfg(unix)]
+        {
+            let outside = root.join("outside.csv");
+            std::fs::write(&outside, "x").unwrap();
+            std::os::unix::fs::symlink(&outside, service.imports_dir().join("link.csv")).unwrap();
+            assert!(service.import_named("link.csv").is_err());
+            std::os::unix::fs::symlink(&outside, root.join("Exports").join("planted.csv")).unwrap();
+            assert_eq!(service.export("planted.csv", b"y").unwrap().file_name, "planted (1).csv");
+            assert_eq!(std::fs::read_to_string(&outside).unwrap(), "x");
+        }
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
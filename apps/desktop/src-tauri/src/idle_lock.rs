@@ -0,0 +1,544 @@
+// ======================================================================
+// IDLE LOCK
+// Kunci layar kasir otomatis setelah tidak ada aktivitas
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use log::{info, warn};
+use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tauri::{AppHandle, Manager, Window};
+
+use crate::{
+    approval::{pin_hasher, validate_pin},
+    audit::{AuditEvent, AuditOutcome},
+    utils, window_manager, AppState,
+};
+
+/// Argon2 hashes of the cashiers' lock PINs, keyed by `users.id`
+pub const CASHIER_PINS_FILE: &str = "cashier_pins.json";
+
+/// Wrong lock PINs and the lockout, so restarting the app does not reset them
+pub const LOCK_ATTEMPTS_FILE: &str = "lock_attempts.json";
+
+/// Emitted by the main window on input, at most every `HEARTBEAT_THROTTLE_MS`
+pub const HEARTBEAT_EVENT: &str = "session://heartbeat";
+pub const LOCKED_EVENT: &str = "session://locked";
+pub const UNLOCKED_EVENT: &str = "session://unlocked";
+
+/// Commands the lock overlay itself needs; `System` commands are always allowed
+pub const ALLOWED_WHILE_LOCKED: &[&str] = &["unlock_session"];
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Wrong PINs before unlocking with a PIN is refused for `LOCKOUT`
+const MAX_FAILURES: u32 = 5;
+
+const LOCKOUT_SECONDS: i64 = 5 * 60;
+
+/// Heartbeat listeners and the lock overlay, injected on every page load of the main window
+const LOCK_SCRIPT: &str = r#"(function () {
+  if (window.__POS_LOCK__) return;
+
+  var last = 0;
+  function heartbeat() {
+    var now = Date.now();
+    if (overlay || now - last < 15000 || !window.__TAURI__) return;
+    last = now;
+    window.__TAURI__.event.emit('session://heartbeat');
+  }
+  ['keydown', 'pointerdown', 'touchstart', 'wheel'].forEach(function (type) {
+    window.addEventListener(type, heartbeat, { capture: true, passive: true });
+  });
+
+  var overlay = null;
+  function show() {
+    if (overlay) return;
+    overlay = document.createElement('div');
+    overlay.setAttribute('style', 'position:fixed;inset:0;z-index:2147483647;display:flex;align-items:center;' +
+      'justify-content:center;background:rgba(15,23,42,0.97);color:#fff;font-family:system-ui,sans-serif');
+    overlay.innerHTML =
+      '<form style="display:flex;flex-direction:column;gap:12px;width:280px;text-align:center">' +
+      '<h2 style="margin:0;font-size:22px">Layar terkunci</h2>' +
+      '<p style="margin:0;color:#cbd5e1">Masukkan PIN kasir untuk melanjutkan</p>' +
+      '<input name="pin" type="password" inputmode="numeric" autocomplete="off" placeholder="PIN" ' +
+      'style="padding:10px;font-size:20px;text-align:center;border-radius:6px;border:0;color:#0f172a">' +
+      '<input name="supervisor" autocomplete="off" placeholder="ID supervisor (bila memakai PIN supervisor)" ' +
+      'style="padding:8px;font-size:13px;border-radius:6px;border:0;color:#0f172a">' +
+      '<button type="submit" style="padding:10px;font-size:16px;border-radius:6px;border:0;' +
+      'background:#2563eb;color:#fff">Buka</button>' +
+      '<p data-error style="margin:0;min-height:18px;color:#f87171"></p>' +
+      '</form>';
+    var form = overlay.querySelector('form');
+    var error = overlay.querySelector('[data-error]');
+    form.addEventListener('submit', function (e) {
+      e.preventDefault();
+      var pin = form.elements.pin.value;
+      var supervisorId = form.elements.supervisor.value.trim();
+      var args = supervisorId
+        ? { supervisor: { method: 'pin', supervisor_id: supervisorId, pin: pin } }
+        : { pin: pin };
+      form.elements.pin.value = '';
+      window.__TAURI__.invoke('unlock_session', args).then(function (result) {
+        if (!result.success) error.textContent = result.error;
+      }, function (e) {
+        error.textContent = String(e);
+      });
+    });
+    // Keys typed into the overlay must not reach the page underneath
+    overlay.addEventListener('keydown', function (e) { e.stopPropagation(); });
+    document.body.appendChild(overlay);
+    form.elements.pin.focus();
+  }
+  function hide() {
+    if (overlay) overlay.parentNode.removeChild(overlay);
+    overlay = null;
+  }
+  window.__POS_LOCK__ = { show: show, hide: hide };
+})();"#;
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdleLockConfig {
+    pub enabled: bool,
+    /// Minutes without input before the screen locks
+    pub timeout_minutes: u32,
+}
+
+impl Default for IdleLockConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout_minutes: 5,
+        }
+    }
+}
+
+/// Payload of `session://locked` and `session://unlocked`
+#[derive(Debug, Clone, Serialize)]
+pub struct LockEvent {
+    /// Cashier whose session was locked
+    pub user_id: Option<String>,
+    /// `idle` or `manual` when locking; `pin`, `supervisor` or `login` when unlocking
+    pub reason: String,
+    pub at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LockStatus {
+    pub locked: bool,
+    pub locked_by: Option<LockEvent>,
+    /// Whether the session user has a lock PIN
+    pub has_pin: bool,
+    /// `None` when idle locking is off
+    pub timeout_minutes: Option<u32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Attempts {
+    failures: u32,
+    /// Unix seconds
+    locked_out_until: Option<i64>,
+}
+
+#[derive(Debug)]
+struct LockState {
+    timeout: Option<Duration>,
+    last_activity: Instant,
+    locked: Option<LockEvent>,
+    /// Unix seconds the current lock began
+    locked_at: i64,
+    /// Mirrors the attempts file
+    attempts: Attempts,
+}
+
+// ======================================================================
+// IDLE LOCK
+// ======================================================================
+
+pub struct IdleLock {
+    path: PathBuf,
+    attempts_path: PathBuf,
+    /// Serialises read-modify-write of the PIN file
+    file_lock: parking_lot::Mutex<()>,
+    state: parking_lot::Mutex<LockState>,
+}
+
+impl IdleLock {
+    pub fn open_default() -> Self {
+        Self::open(utils::get_app_data_dir().join(CASHIER_PINS_FILE))
+    }
+
+    pub fn open(path: PathBuf) -> Self {
+        let attempts_path = path.with_file_name(LOCK_ATTEMPTS_FILE);
+        let attempts = match std::fs::read(&attempts_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                // Fail closed; a supervisor can still unlock
+                warn!("Invalid {:?} ({}); refusing lock PINs for now", attempts_path, e);
+                Attempts {
+                    failures: MAX_FAILURES,
+                    locked_out_until: Some(chrono::Utc::now().timestamp() + LOCKOUT_SECONDS),
+                }
+            }),
+            Err(_) => Attempts::default(),
+        };
+
+        Self {
+            path,
+            attempts_path,
+            file_lock: parking_lot::Mutex::new(()),
+            state: parking_lot::Mutex::new(LockState {
+                timeout: None,
+                last_activity: Instant::now(),
+                locked: None,
+                locked_at: 0,
+                attempts,
+            }),
+        }
+    }
+
+    pub fn configure(&self, config: &IdleLockConfig) {
+        let mut state = self.state.lock();
+        state.timeout = config
+            .enabled
+            .then(|| Duration::from_secs(u64::from(config.timeout_minutes.max(1)) * 60));
+        state.last_activity = Instant::now();
+    }
+
+    /// Input in the main window; ignored while locked
+    pub fn heartbeat(&self) {
+        let mut state = self.state.lock();
+        if state.locked.is_none() {
+            state.last_activity = Instant::now();
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.state.lock().locked.is_some()
+    }
+
+    pub fn status(&self, user_id: Option<&str>) -> LockStatus {
+        let has_pin = user_id.is_some_and(|user_id| self.has_pin(user_id));
+        let state = self.state.lock();
+        LockStatus {
+            locked: state.locked.is_some(),
+            locked_by: state.locked.clone(),
+            has_pin,
+            timeout_minutes: state.timeout.map(|timeout| (timeout.as_secs() / 60) as u32),
+        }
+    }
+
+    /// Lock when a cashier is logged in and the timeout has passed
+    pub fn check_idle(&self, user_id: Option<String>) -> Option<LockEvent> {
+        {
+            let state = self.state.lock();
+            let timeout = state.timeout?;
+            if state.locked.is_some() || user_id.is_none() || state.last_activity.elapsed() < timeout {
+                return None;
+            }
+        }
+        self.lock(user_id, "idle")
+    }
+
+    /// `None` when already locked
+    pub fn lock(&self, user_id: Option<String>, reason: &str) -> Option<LockEvent> {
+        let mut state = self.state.lock();
+        if state.locked.is_some() {
+            return None;
+        }
+
+        info!("Screen locked ({}) for {:?}", reason, user_id);
+        let event = LockEvent::new(user_id, reason);
+        state.locked = Some(event.clone());
+        state.locked_at = chrono::Utc::now().timestamp();
+        Some(event)
+    }
+
+    /// Unlock with the locked cashier's PIN. The attempt is counted and saved before the PIN
+    /// is checked, so parallel guesses cannot get past the limit. Runs Argon2, so call it off
+    /// the async runtime.
+    pub fn unlock_with_pin(&self, pin: &str) -> Result<LockEvent> {
+        let user_id = {
+            let mut state = self.state.lock();
+            let now = chrono::Utc::now().timestamp();
+            let user_id = {
+                let locked = state.locked.as_ref().ok_or_else(|| anyhow!("Screen is not locked"))?;
+                locked.user_id.clone().ok_or_else(|| anyhow!("No cashier to unlock for; ask a supervisor"))?
+            };
+            match state.attempts.locked_out_until {
+                Some(until) if until > now => {
+                    let minutes = (until - now) / 60 + 1;
+                    return Err(anyhow!("Too many wrong PINs; try again in {} minute(s) or ask a supervisor", minutes));
+                }
+                Some(_) => state.attempts = Attempts::default(),
+                None => {}
+            }
+
+            state.attempts.failures += 1;
+            if state.attempts.failures >= MAX_FAILURES {
+                warn!("Lock PIN tried {} times for {}", state.attempts.failures, user_id);
+                state.attempts.locked_out_until = Some(now + LOCKOUT_SECONDS);
+            }
+            self.save_attempts(&state.attempts)?;
+            user_id
+        };
+
+        self.verify_pin(&user_id, pin)?;
+
+        // Only the cashier who was locked out is let back in with their PIN
+        let mut state = self.state.lock();
+        if state.locked.as_ref().and_then(|locked| locked.user_id.as_deref()) != Some(user_id.as_str()) {
+            return Err(anyhow!("Screen is not locked"));
+        }
+        self.unlock_state(&mut state, "pin").ok_or_else(|| anyhow!("Screen is not locked"))
+    }
+
+    /// Unlock for a password login made after the screen locked. Reloading the page re-sends
+    /// the session of the earlier login, which leaves the screen locked.
+    pub fn unlock_after_login(&self, auth_time: i64) -> Option<LockEvent> {
+        let mut state = self.state.lock();
+        if auth_time <= state.locked_at {
+            return None;
+        }
+        self.unlock_state(&mut state, "login")
+    }
+
+    /// Unlock after some other check (supervisor approval); `None` when not locked
+    pub fn unlock(&self, reason: &str) -> Option<LockEvent> {
+        self.unlock_state(&mut self.state.lock(), reason)
+    }
+
+    pub fn has_pin(&self, user_id: &str) -> bool {
+        let _guard = self.file_lock.lock();
+        self.load().map(|pins| pins.contains_key(user_id)).unwrap_or(false)
+    }
+
+    /// Set or change a cashier's lock PIN; changing needs the current one
+    pub fn set_pin(&self, user_id: &str, current_pin: Option<&str>, new_pin: &str) -> Result<()> {
+        validate_pin(new_pin)?;
+        if self.has_pin(user_id) {
+            self.verify_pin(user_id, current_pin.unwrap_or_default())
+                .map_err(|_| anyhow!("Current PIN is wrong"))?;
+        }
+
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = pin_hasher()
+            .hash_password(new_pin.as_bytes(), &salt)
+            .map_err(|e| anyhow!("Failed to hash PIN: {}", e))?
+            .to_string();
+
+        let _guard = self.file_lock.lock();
+        let mut pins = self.load()?;
+        pins.insert(user_id.to_string(), hash);
+        self.save(&pins)?;
+        info!("Lock PIN set for {}", user_id);
+        Ok(())
+    }
+
+    /// Forget a cashier's lock PIN, e.g. when they forgot it; true when one was set
+    pub fn remove_pin(&self, user_id: &str) -> Result<bool> {
+        let _guard = self.file_lock.lock();
+        let mut pins = self.load()?;
+        let removed = pins.remove(user_id).is_some();
+        if removed {
+            self.save(&pins)?;
+        }
+        Ok(removed)
+    }
+
+    // ======================================================================
+    // PRIVATE METHODS
+    // ======================================================================
+
+    fn unlock_state(&self, state: &mut LockState, reason: &str) -> Option<LockEvent> {
+        let locked = state.locked.take()?;
+        if state.attempts.failures > 0 {
+            state.attempts = Attempts::default();
+            if let Err(e) = self.save_attempts(&state.attempts) {
+                warn!("Failed to save lock PIN attempts: {}", e);
+            }
+        }
+        state.last_activity = Instant::now();
+
+        info!("Screen unlocked ({}) for {:?}", reason, locked.user_id);
+        Some(LockEvent::new(locked.user_id, reason))
+    }
+
+    fn load(&self) -> Result<HashMap<String, String>> {
+        match std::fs::read(&self.path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| anyhow!("Invalid {:?}: {}", self.path, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(e) => Err(anyhow!("Failed to read {:?}: {}", self.path, e)),
+        }
+    }
+
+    fn save(&self, pins: &HashMap<String, String>) -> Result<()> {
+        utils::write_private(&self.path, &serde_json::to_vec_pretty(pins)?).map_err(|e| anyhow!(e))
+    }
+
+    fn save_attempts(&self, attempts: &Attempts) -> Result<()> {
+        utils::write_private(&self.attempts_path, &serde_json::to_vec(attempts)?).map_err(|e| anyhow!(e))
+    }
+
+    fn verify_pin(&self, user_id: &str, pin: &str) -> Result<()> {
+        let hash = {
+            let _guard = self.file_lock.lock();
+            self.load()?
+                .remove(user_id)
+                .ok_or_else(|| anyhow!("No lock PIN set; ask a supervisor"))?
+        };
+        let hash = PasswordHash::new(&hash).map_err(|e| anyhow!("Corrupt PIN hash: {}", e))?;
+        pin_hasher()
+            .verify_password(pin.as_bytes(), &hash)
+            .map_err(|_| anyhow!("Wrong PIN"))
+    }
+}
+
+impl LockEvent {
+    fn new(user_id: Option<String>, reason: &str) -> Self {
+        Self {
+            user_id,
+            reason: reason.to_string(),
+            at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+// ======================================================================
+// WINDOW
+// ======================================================================
+
+/// Check for inactivity every few seconds and lock when the timeout has passed
+pub async fn watch(app: AppHandle, idle_lock: Arc<IdleLock>) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let user_id = app
+            .try_state::<AppState>()
+            .and_then(|state| state.ipc.session(window_manager::MAIN_WINDOW_LABEL))
+            .map(|session| session.user_id);
+        if let Some(event) = idle_lock.check_idle(user_id) {
+            announce(&app, true, &event);
+        }
+    }
+}
+
+/// Show or remove the overlay, tell every window and record it in the audit log
+pub fn announce(app: &AppHandle, locked: bool, event: &LockEvent) {
+    let (name, action) = if locked {
+        (LOCKED_EVENT, "session_locked")
+    } else {
+        (UNLOCKED_EVENT, "session_unlocked")
+    };
+
+    let _ = app.emit_all(name, event);
+    if let Some(window) = window_manager::main_window(app) {
+        if let Err(e) = install_script(&window, locked) {
+            warn!("Failed to update lock overlay: {}", e);
+        }
+    }
+    if let Some(state) = app.try_state::<AppState>() {
+        state.audit.record(
+            AuditEvent::new(action, AuditOutcome::Allowed, serde_json::json!({ "reason": event.reason }))
+                .caller(window_manager::MAIN_WINDOW_LABEL, event.user_id.clone()),
+        );
+    }
+}
+
+/// Re-arm the webview after navigation; the overlay must survive a reload
+pub fn install_script(window: &Window, locked: bool) -> Result<()> {
+    window.eval(LOCK_SCRIPT)?;
+    window.eval(if locked {
+        "window.__POS_LOCK__.show();"
+    } else {
+        "window.__POS_LOCK__.hide();"
+    })?;
+    Ok(())
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn idle_lock() -> IdleLock {
+        let path = std::env::temp_dir().join(format!("pos-idle-{}", uuid::Uuid::new_v4())).join(CASHIER_PINS_FILE);
+        IdleLock::open(path)
+    }
+
+    #[test]
+    fn test_idle_timeout_locks_logged_in_session() {
+        let idle_lock = idle_lock();
+        assert!(idle_lock.check_idle(Some("usr_001".to_string())).is_none());
+
+        idle_lock.configure(&IdleLockConfig { enabled: true, timeout_minutes: 5 });
+        idle_lock.state.lock().last_activity = Instant::now() - Duration::from_secs(301);
+        assert!(idle_lock.check_idle(None).is_none());
+
+        let event = idle_lock.check_idle(Some("usr_001".to_string())).unwrap();
+        assert_eq!(event.reason, "idle");
+        assert!(idle_lock.is_locked());
+        assert!(idle_lock.check_idle(Some("usr_001".to_string())).is_none());
+
+        // Input while locked does not count as activity, and neither does a reload that
+        // re-sends the login from before the lock
+        idle_lock.heartbeat();
+        let locked_at = idle_lock.state.lock().locked_at;
+        assert!(idle_lock.unlock_after_login(locked_at - 60).is_none());
+        assert!(idle_lock.unlock_after_login(locked_at).is_none());
+        assert!(idle_lock.is_locked());
+        let unlocked = idle_lock.unlock_after_login(locked_at + 1).unwrap();
+        assert_eq!(unlocked.reason, "login");
+        assert_eq!(unlocked.user_id.as_deref(), Some("usr_001"));
+        assert!(idle_lock.check_idle(Some("usr_001".to_string())).is_none());
+    }
+
+    #[test]
+    fn test_unlock_with_pin_and_lockout() {
+        let idle_lock = idle_lock();
+        idle_lock.set_pin("usr_001", None, "4821").unwrap();
+        assert!(idle_lock.set_pin("usr_001", Some("0000"), "5937").is_err());
+        assert!(idle_lock.set_pin("usr_001", None, "1234").is_err());
+        idle_lock.set_pin("usr_001", Some("4821"), "5937").unwrap();
+
+        assert!(idle_lock.unlock_with_pin("5937").is_err());
+        idle_lock.lock(Some("usr_001".to_string()), "manual").unwrap();
+        assert_eq!(idle_lock.unlock_with_pin("5937").unwrap().reason, "pin");
+
+        idle_lock.lock(Some("usr_001".to_string()), "manual").unwrap();
+        for _ in 0..MAX_FAILURES {
+            assert!(idle_lock.unlock_with_pin("4821").is_err());
+        }
+        let locked_out = idle_lock.unlock_with_pin("5937").unwrap_err();
+        assert!(locked_out.to_string().contains("Too many"));
+        assert!(idle_lock.is_locked());
+
+        // Restarting the app keeps the lockout
+        let reopened = IdleLock::open(idle_lock.path.clone());
+        reopened.lock(Some("usr_001".to_string()), "manual").unwrap();
+        assert!(reopened.unlock_with_pin("5937").unwrap_err().to_string().contains("Too many"));
+
+        // A supervisor unlock clears the count
+        idle_lock.unlock("supervisor").unwrap();
+        idle_lock.lock(Some("usr_001".to_string()), "manual").unwrap();
+        assert_eq!(idle_lock.unlock_with_pin("5937").unwrap().reason, "pin");
+
+        assert!(idle_lock.remove_pin("usr_001").unwrap());
+        assert!(!idle_lock.has_pin("usr_001"));
+    }
+}
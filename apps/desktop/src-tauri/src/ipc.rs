@@ -0,0 +1,383 @@
+// ======================================================================
+// IPC AUTHORIZATION
+// Izin per command berdasarkan origin window dan peran sesi dari web app
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use base64::{
+    engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD as BASE64_URL},
+    Engine,
+};
+use hmac::{Hmac, Mac};
+use log::{info, warn};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU16, Ordering},
+        Arc,
+    },
+};
+use tauri::{Invoke, Manager, Runtime};
+
+use crate::{
+    audit::{AuditEvent, AuditOutcome},
+    idle_lock, AppState,
+};
+
+/// Sidecar environment variable carrying the key the web app signs session tokens with
+pub const SESSION_SECRET_ENV: &str = "POS_SHELL_SESSION_SECRET";
+
+/// `devPath` in tauri.conf.json, served by `next dev` rather than the sidecar
+const DEV_SERVER_PORT: u16 = 3000;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+/// Role granted by the web app after login, lowest first
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Cashier,
+    Supervisor,
+    Admin,
+}
+
+/// What a command needs beyond coming from the sidecar origin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    /// Status and window plumbing; no session needed
+    System,
+    Role(Role),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionClaims {
+    /// `users.id` of the logged-in user
+    pub sub: String,
+    pub role: Role,
+    /// Unix seconds
+    pub exp: i64,
+    /// Unix seconds the user last logged in with their password; tokens re-issued on reload keep it
+    #[serde(default)]
+    pub auth_time: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub user_id: String,
+    pub role: Role,
+    pub expires_at: String,
+    /// Unix seconds of the password login behind the session
+    pub auth_time: i64,
+}
+
+impl From<&SessionClaims> for SessionInfo {
+    fn from(claims: &SessionClaims) -> Self {
+        Self {
+            user_id: claims.sub.clone(),
+            role: claims.role,
+            expires_at: chrono::DateTime::from_timestamp(claims.exp, 0)
+                .map(|at| at.to_rfc3339())
+                .unwrap_or_default(),
+            auth_time: claims.auth_time,
+        }
+    }
+}
+
+// ======================================================================
+// IPC GUARD
+// ======================================================================
+
+pub struct IpcGuard {
+    secret: [u8; 32],
+    sidecar_port: Arc<AtomicU16>,
+    /// Session per window label, so the customer window never inherits the cashier's
+    sessions: parking_lot::Mutex<HashMap<String, SessionClaims>>,
+}
+
+impl IpcGuard {
+    pub fn new(sidecar_port: Arc<AtomicU16>) -> Self {
+        let mut secret = [0u8; 32];
+        OsRng.fill_bytes(&mut secret);
+
+        Self {
+            secret,
+            sidecar_port,
+            sessions: parking_lot::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Handed to the sidecar so the web app can issue session tokens for this run only
+    pub fn sidecar_env(&self) -> (String, String) {
+        (SESSION_SECRET_ENV.to_string(), BASE64.encode(self.secret))
+    }
+
+    /// `hmac_sha256(secret, purpose)`, so other tokens the web app checks cannot pass as sessions
+    pub fn derive_key(&self, purpose: &str) -> [u8; 32] {
+        let mut mac = HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts any key length");
+        mac.update(purpose.as_bytes());
+        mac.finalize().into_bytes().into()
+    }
+
+    /// Decide whether `window` may invoke `command`
+    pub fn authorize(&self, command: &str, window: &str, url: &reqwest::Url) -> Result<(), String> {
+        if !self.is_trusted_origin(url) {
+            return Err(format!("Command {} is not allowed from {}", command, url.origin().ascii_serialization()));
+        }
+
+        let required = match capability(command) {
+            Some(Capability::System) => return Ok(()),
+            Some(Capability::Role(role)) => role,
+            // New commands must be tagged before the webview can reach them
+            None => return Err(format!("Command {} has no capability tag", command)),
+        };
+
+        let mut sessions = self.sessions.lock();
+        let role = match sessions.get(window) {
+            Some(claims) if claims.exp > chrono::Utc::now().timestamp() => claims.role,
+            Some(_) => {
+                sessions.remove(window);
+                return Err("Session expired; log in again".to_string());
+            }
+            None => return Err(format!("Command {} requires a {:?} session", command, required)),
+        };
+
+        if role < required {
+            return Err(format!("Command {} requires {:?}, session is {:?}", command, required, role));
+        }
+        Ok(())
+    }
+
+    /// Verify a token issued by the web app and attach it to `window`
+    pub fn begin_session(&self, window: &str, token: &str) -> Result<SessionInfo> {
+        let claims = self.verify(token)?;
+        info!("IPC session for window {}: {:?} until {}", window, claims.role, claims.exp);
+
+        let info = SessionInfo::from(&claims);
+        self.sessions.lock().insert(window.to_string(), claims);
+        Ok(info)
+    }
+
+    pub fn end_session(&self, window: &str) {
+        self.sessions.lock().remove(window);
+    }
+
+    pub fn session(&self, window: &str) -> Option<SessionInfo> {
+        let sessions = self.sessions.lock();
+        sessions
+            .get(window)
+            .filter(|claims| claims.exp > chrono::Utc::now().timestamp())
+            .map(SessionInfo::from)
+    }
+
+    // ======================================================================
+    // PRIVATE METHODS
+    // ======================================================================
+
+    /// Pages served by our own sidecar (or `next dev` in debug builds) on loopback
+    fn is_trusted_origin(&self, url: &reqwest::Url) -> bool {
+        if url.scheme() != "http" || !matches!(url.host_str(), Some("127.0.0.1" | "localhost")) {
+            return false;
+        }
+
+        let port = url.port_or_known_default();
+        let sidecar_port = self.sidecar_port.load(Ordering::SeqCst);
+        (sidecar_port != 0 && port == Some(sidecar_port)) || (cfg!(debug_assertions) && port == Some(DEV_SERVER_PORT))
+    }
+
+    /// `base64url(claims).base64url(hmac_sha256(secret, base64url(claims)))`
+    fn verify(&self, token: &str) -> Result<SessionClaims> {
+        let (payload, signature) = token.split_once('.').ok_or_else(|| anyhow!("Malformed session token"))?;
+        let signature = BASE64_URL
+            .decode(signature)
+            .map_err(|_| anyhow!("Malformed session token"))?;
+
+        let mut mac = HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts any key length");
+        mac.update(payload.as_bytes());
+        mac.verify_slice(&signature)
+            .map_err(|_| anyhow!("Invalid session token signature"))?;
+
+        let claims: SessionClaims = BASE64_URL
+            .decode(payload)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| anyhow!("Malformed session token"))?;
+
+        if claims.exp <= chrono::Utc::now().timestamp() {
+            warn!("Rejected expired session token for {}", claims.sub);
+            return Err(anyhow!("Session token expired"));
+        }
+        Ok(claims)
+    }
+}
+
+/// Wrap the command handler so every invoke passes `IpcGuard::authorize` first
+pub fn guarded<R, F>(handler: F) -> impl Fn(Invoke<R>) + Send + Sync + 'static
+where
+    R: Runtime,
+    F: Fn(Invoke<R>) + Send + Sync + 'static,
+{
+    move |invoke: Invoke<R>| {
+        let window = invoke.message.window();
+        let Some(state) = window.try_state::<AppState>() else {
+            invoke.resolver.reject("Application state is not ready");
+            return;
+        };
+
+        let command = invoke.message.command().to_string();
+        let url = window.url();
+        let result = state.ipc.authorize(&command, window.label(), &url).and_then(|()| {
+            let exempt = capability(&command) == Some(Capability::System)
+                || idle_lock::ALLOWED_WHILE_LOCKED.contains(&command.as_str());
+            if !exempt && state.idle_lock.is_locked() {
+                return Err("Screen is locked; unlock it with your PIN first".to_string());
+            }
+            Ok(())
+        });
+        match result {
+            Ok(()) => handler(invoke),
+            Err(e) => {
+                warn!("Rejected IPC call from window {}: {}", window.label(), e);
+                state.audit.record(
+                    AuditEvent::new(&command, AuditOutcome::Denied, serde_json::json!({
+                        "origin": url.origin().ascii_serialization(),
+                        "reason": e,
+                    }))
+                    .caller(window.label(), state.ipc.session(window.label()).map(|session| session.user_id)),
+                );
+                invoke.resolver.reject(e);
+            }
+        }
+    }
+}
+
+// ======================================================================
+// CAPABILITY TABLE
+// ======================================================================
+
+/// Capability of every command in `generate_handler!`; `None` means untagged and denied
+pub fn capability(command: &str) -> Option<Capability> {
+    use Capability::{Role as R, System};
+    use Role::{Admin, Cashier, Supervisor};
+
+    Some(match command {
+        // Status, sessions and window plumbing
+        "get_sidecar_status" | "get_system_info" | "get_watchdog_status" | "get_scanner_status"
+        | "get_customer_display_status" | "get_scale_status" | "get_sync_status" | "get_catalog_stats"
+        | "get_terminal_identity" | "get_backup_status" | "get_local_database_status" | "get_migration_status"
+        | "get_tunnel_status" | "get_app_version" | "get_customer_view" | "minimize_to_tray" | "show_from_tray"
+        | "toggle_window_visibility" | "begin_shell_session" | "end_shell_session" | "get_shell_session"
+        | "save_log" | "get_kiosk_status" | "get_lock_status" | "get_update_status" => System,
+
+        // The kiosk prompt also runs on the login page; in kiosk mode both need a supervisor credential
+        "quit_app" | "leave_kiosk_mode" => System,
+
+        // Selling
+        "list_serial_ports" | "start_scanner" | "stop_scanner" | "open_customer_display" | "close_customer_display"
+        | "display_line_item" | "display_subtotal" | "display_total" | "display_change" | "display_idle"
+        | "open_scale" | "close_scale" | "read_weight" | "tare_scale" | "zero_scale" | "parse_weight_barcode"
+        | "list_devices" | "journal_record_order" | "journal_get_order" | "journal_list_orders" | "get_journal_stats"
+        | "get_order_numbering" | "trigger_sync" | "lookup_barcode" | "search_products" | "sync_catalog"
+        | "list_displays" | "open_customer_window" | "close_customer_window" | "update_customer_view"
+        | "show_notification" | "open_external_url" | "get_app_config" | "export_file" | "list_supervisors"
+        | "request_supervisor_approval" | "record_audit_event" | "open_cash_drawer" | "lock_session"
+        | "unlock_session" | "set_lock_pin" => R(Cashier),
+
+        // Store operations
+        "journal_requeue_order" | "test_device" | "list_backups" | "create_backup" | "export_backup"
+        | "inspect_backup_archive" | "restart_tunnel" | "list_secrets" | "get_audit_events"
+        | "import_csv" | "verify_audit_log" | "export_audit_events" => R(Supervisor),
+
+        // Shell and till configuration
+        "start_sidecar" | "stop_sidecar" | "restart_sidecar" | "check_port_availability" | "get_available_port"
+        | "start_watchdog" | "stop_watchdog" | "add_device" | "remove_device" | "provision_terminal"
+        | "restore_backup" | "import_backup" | "restart_local_database" | "set_secret" | "delete_secret"
+        | "test_secret" | "save_app_config" | "set_supervisor_pin" | "enroll_supervisor_totp"
+        | "remove_supervisor" | "remove_lock_pin" | "check_for_update" | "install_update" => R(Admin),
+
+        _ => return None,
+    })
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(guard: &IpcGuard, claims: &SessionClaims) -> String {
+        let payload = BASE64_URL.encode(serde_json::to_vec(claims).unwrap());
+        let mut mac = HmacSha256::new_from_slice(&guard.secret).unwrap();
+        mac.update(payload.as_bytes());
+        format!("{}.{}", payload, BASE64_URL.encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn test_every_registered_command_is_tagged() {
+        let main = include_str!("main.rs");
+        let start = main.find("generate_handler![").unwrap();
+        let end = start + main[start..].find(']').unwrap();
+
+        let commands: Vec<&str> = main[start + "generate_handler![".len()..end]
+            .lines()
+            .map(|line| line.trim().trim_end_matches(','))
+            .filter(|line| !line.is_empty() && !line.starts_with("//"))
+            .collect();
+
+        assert!(commands.len() > 50);
+        for command in commands {
+            assert!(capability(command).is_some(), "{} has no capability tag", command);
+        }
+    }
+
+    #[test]
+    fn test_origin_session_and_roles() {
+        let port = Arc::new(AtomicU16::new(0));
+        let guard = IpcGuard::new(Arc::clone(&port));
+        let sidecar = reqwest::Url::parse("http://127.0.0.1:3417/pos").unwrap();
+
+        // Nothing is trusted until the sidecar is up, and never a foreign origin
+        assert!(guard.authorize("get_app_version", "main", &sidecar).is_err());
+        port.store(3417, Ordering::SeqCst);
+        assert!(guard.authorize("get_app_version", "main", &sidecar).is_ok());
+        for foreign in ["https://evil.example/pos", "http://127.0.0.1:8080/", "tauri://localhost/"] {
+            let url = reqwest::Url::parse(foreign).unwrap();
+            assert!(guard.authorize("get_app_version", "main", &url).is_err(), "{}", foreign);
+        }
+
+        assert!(guard.authorize("lookup_barcode", "main", &sidecar).is_err());
+        assert!(guard.authorize("made_up_command", "main", &sidecar).is_err());
+        // The kiosk prompt on the login page; the supervisor credential is checked by the command
+        assert!(guard.authorize("quit_app", "main", &sidecar).is_ok());
+
+        let exp = chrono::Utc::now().timestamp() + 3600;
+        let cashier = SessionClaims { sub: "u-1".to_string(), role: Role::Cashier, exp, auth_time: exp - 3600 };
+        guard.begin_session("main", &token(&guard, &cashier)).unwrap();
+        assert!(guard.authorize("lookup_barcode", "main", &sidecar).is_ok());
+        assert!(guard.authorize("save_app_config", "main", &sidecar).is_err());
+        // Sessions do not leak to other windows
+        assert!(guard.authorize("lookup_barcode", "customer", &sidecar).is_err());
+
+        let admin = SessionClaims { role: Role::Admin, ..cashier.clone() };
+        guard.begin_session("main", &token(&guard, &admin)).unwrap();
+        assert!(guard.authorize("save_app_config", "main", &sidecar).is_ok());
+        guard.end_session("main");
+        assert!(guard.authorize("save_app_config", "main", &sidecar).is_err());
+
+        // Tampered, foreign-key and expired tokens are refused
+        let forged = token(&guard, &cashier).replacen('.', "x.", 1);
+        assert!(guard.begin_session("main", &forged).is_err());
+        let other = IpcGuard::new(Arc::new(AtomicU16::new(0)));
+        assert!(guard.begin_session("main", &token(&other, &admin)).is_err());
+        let expired = SessionClaims { exp: exp - 7200, ..admin };
+        assert!(guard.begin_session("main", &token(&guard, &expired)).is_err());
+    }
+}
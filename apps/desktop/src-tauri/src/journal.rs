@@ -0,0 +1,874 @@
+// ======================================================================
+// OFFLINE JOURNAL
+// Jurnal transaksi lokal (SQLite) agar kasir tetap jalan saat server offline
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use log::{debug, info};
+use rusqlite::{params, Connection, OptionalExtension, Row, Transaction};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    order_no::{self, OrderNo, OrderNumberAllocator, OrderNumberStatus},
+    utils,
+};
+
+/// File name of the journal inside the app data directory
+pub const JOURNAL_FILE: &str = "journal.db";
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS journal_orders (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    idempotency_key TEXT NOT NULL UNIQUE,
+    order_no TEXT NOT NULL UNIQUE,
+    branch_id TEXT NOT NULL,
+    session_id TEXT,
+    customer_id TEXT,
+    customer_name TEXT,
+    cashier_id TEXT NOT NULL,
+    cashier_name TEXT NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('paid', 'void', 'refund')),
+    subtotal INTEGER NOT NULL,
+    discount_amount INTEGER NOT NULL DEFAULT 0,
+    tax_amount INTEGER NOT NULL DEFAULT 0,
+    rounding_amount INTEGER NOT NULL DEFAULT 0,
+    total INTEGER NOT NULL,
+    paid_total INTEGER NOT NULL,
+    change_amount INTEGER NOT NULL DEFAULT 0,
+    notes TEXT,
+    paid_at TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    sync_status TEXT NOT NULL DEFAULT 'pending'
+        CHECK (sync_status IN ('pending', 'synced', 'conflict', 'failed')),
+    sync_attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    server_id TEXT,
+    synced_at TEXT
+);
+
+CREATE TABLE IF NOT EXISTS journal_order_lines (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    order_seq INTEGER NOT NULL REFERENCES journal_orders(seq),
+    line_no INTEGER NOT NULL,
+    product_id TEXT NOT NULL,
+    product_code TEXT NOT NULL,
+    product_name TEXT NOT NULL,
+    quantity INTEGER NOT NULL,
+    price INTEGER NOT NULL,
+    discount_amount INTEGER NOT NULL DEFAULT 0,
+    tax_amount INTEGER NOT NULL DEFAULT 0,
+    line_total INTEGER GENERATED ALWAYS AS ((quantity * price) - discount_amount + tax_amount) STORED,
+    UNIQUE (order_seq, line_no)
+);
+
+CREATE TABLE IF NOT EXISTS journal_payments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    order_seq INTEGER NOT NULL REFERENCES journal_orders(seq),
+    payment_method TEXT NOT NULL
+        CHECK (payment_method IN ('cash', 'card', 'ewallet', 'transfer', 'voucher')),
+    amount INTEGER NOT NULL,
+    reference TEXT,
+    captured_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_journal_orders_sync ON journal_orders(sync_status, seq);
+
+-- Append-only: sales are never rewritten, only their sync bookkeeping
+CREATE TRIGGER IF NOT EXISTS journal_orders_no_delete
+BEFORE DELETE ON journal_orders
+BEGIN
+    SELECT RAISE(ABORT, 'journal is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS journal_orders_no_rewrite
+BEFORE UPDATE OF idempotency_key, order_no, branch_id, session_id, customer_id, customer_name,
+    cashier_id, cashier_name, status, subtotal, discount_amount, tax_amount, rounding_amount,
+    total, paid_total, change_amount, notes, paid_at, created_at ON journal_orders
+BEGIN
+    SELECT RAISE(ABORT, 'journal is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS journal_order_lines_no_update
+BEFORE UPDATE ON journal_order_lines
+BEGIN
+    SELECT RAISE(ABORT, 'journal is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS journal_order_lines_no_delete
+BEFORE DELETE ON journal_order_lines
+BEGIN
+    SELECT RAISE(ABORT, 'journal is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS journal_payments_no_update
+BEFORE UPDATE ON journal_payments
+BEGIN
+    SELECT RAISE(ABORT, 'journal is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS journal_payments_no_delete
+BEFORE DELETE ON journal_payments
+BEGIN
+    SELECT RAISE(ABORT, 'journal is append-only');
+END;
+"#;
+
+const ORDER_COLUMNS: &str = "seq, idempotency_key, order_no, branch_id, session_id, customer_id, \
+    customer_name, cashier_id, cashier_name, status, subtotal, discount_amount, tax_amount, \
+    rounding_amount, total, paid_total, change_amount, notes, paid_at, created_at, sync_status, \
+    sync_attempts, last_error, server_id, synced_at";
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderStatus {
+    Paid,
+    Void,
+    Refund,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaymentMethod {
+    Cash,
+    Card,
+    Ewallet,
+    Transfer,
+    Voucher,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncStatus {
+    Pending,
+    Synced,
+    Conflict,
+    Failed,
+}
+
+/// A checkout as submitted by the webview, shaped like `pos_orders`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewOrder {
+    /// Generated by the webview per checkout so retries never double-book
+    pub idempotency_key: Option<String>,
+    pub branch_id: String,
+    pub session_id: Option<String>,
+    pub customer_id: Option<String>,
+    pub customer_name: Option<String>,
+    pub cashier_id: String,
+    pub cashier_name: String,
+    #[serde(default)]
+    pub discount_amount: i64,
+    #[serde(default)]
+    pub rounding_amount: i64,
+    pub notes: Option<String>,
+    pub lines: Vec<NewOrderLine>,
+    pub payments: Vec<NewPayment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewOrderLine {
+    pub product_id: String,
+    pub product_code: String,
+    pub product_name: String,
+    pub quantity: i64,
+    pub price: i64,
+    #[serde(default)]
+    pub discount_amount: i64,
+    #[serde(default)]
+    pub tax_amount: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewPayment {
+    pub method: PaymentMethod,
+    pub amount: i64,
+    pub reference: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalOrder {
+    pub seq: i64,
+    pub idempotency_key: String,
+    pub order_no: String,
+    pub branch_id: String,
+    pub session_id: Option<String>,
+    pub customer_id: Option<String>,
+    pub customer_name: Option<String>,
+    pub cashier_id: String,
+    pub cashier_name: String,
+    pub status: OrderStatus,
+    pub subtotal: i64,
+    pub discount_amount: i64,
+    pub tax_amount: i64,
+    pub rounding_amount: i64,
+    pub total: i64,
+    pub paid_total: i64,
+    pub change_amount: i64,
+    pub notes: Option<String>,
+    pub paid_at: String,
+    pub created_at: String,
+    pub sync_status: SyncStatus,
+    pub sync_attempts: i64,
+    pub last_error: Option<String>,
+    pub server_id: Option<String>,
+    pub synced_at: Option<String>,
+    pub lines: Vec<JournalOrderLine>,
+    pub payments: Vec<JournalPayment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalOrderLine {
+    pub line_no: i64,
+    pub product_id: String,
+    pub product_code: String,
+    pub product_name: String,
+    pub quantity: i64,
+    pub price: i64,
+    pub discount_amount: i64,
+    pub tax_amount: i64,
+    pub line_total: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalPayment {
+    pub method: PaymentMethod,
+    pub amount: i64,
+    pub reference: Option<String>,
+    pub captured_at: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JournalStats {
+    pub pending: i64,
+    pub synced: i64,
+    pub conflict: i64,
+    pub failed: i64,
+    pub oldest_pending_at: Option<String>,
+    /// False when the on-disk journal could not be opened
+    pub persistent: bool,
+}
+
+// ======================================================================
+// ENUM MAPPING
+// ======================================================================
+
+impl OrderStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            OrderStatus::Paid => "paid",
+            OrderStatus::Void => "void",
+            OrderStatus::Refund => "refund",
+        }
+    }
+
+    fn parse(value: &str) -> Result<Self> {
+        match value {
+            "paid" => Ok(OrderStatus::Paid),
+            "void" => Ok(OrderStatus::Void),
+            "refund" => Ok(OrderStatus::Refund),
+            other => Err(anyhow!("Unknown order status {}", other)),
+        }
+    }
+}
+
+impl PaymentMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentMethod::Cash => "cash",
+            PaymentMethod::Card => "card",
+            PaymentMethod::Ewallet => "ewallet",
+            PaymentMethod::Transfer => "transfer",
+            PaymentMethod::Voucher => "voucher",
+        }
+    }
+
+    fn parse(value: &str) -> Result<Self> {
+        match value {
+            "cash" => Ok(PaymentMethod::Cash),
+            "card" => Ok(PaymentMethod::Card),
+            "ewallet" => Ok(PaymentMethod::Ewallet),
+            "transfer" => Ok(PaymentMethod::Transfer),
+            "voucher" => Ok(PaymentMethod::Voucher),
+            other => Err(anyhow!("Unknown payment method {}", other)),
+        }
+    }
+}
+
+impl SyncStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncStatus::Pending => "pending",
+            SyncStatus::Synced => "synced",
+            SyncStatus::Conflict => "conflict",
+            SyncStatus::Failed => "failed",
+        }
+    }
+
+    fn parse(value: &str) -> Result<Self> {
+        match value {
+            "pending" => Ok(SyncStatus::Pending),
+            "synced" => Ok(SyncStatus::Synced),
+            "conflict" => Ok(SyncStatus::Conflict),
+            "failed" => Ok(SyncStatus::Failed),
+            other => Err(anyhow!("Unknown sync status {}", other)),
+        }
+    }
+}
+
+// ======================================================================
+// JOURNAL
+// ======================================================================
+
+pub struct Journal {
+    conn: Connection,
+    path: Option<PathBuf>,
+    numbering: Option<OrderNumberAllocator>,
+}
+
+impl Journal {
+    /// Open (or create) the journal database at `path`
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            utils::ensure_directory_exists(parent).map_err(|e| anyhow!(e))?;
+        }
+
+        let conn = Connection::open(path)
+            .map_err(|e| anyhow!("Failed to open journal {:?}: {}", path, e))?;
+
+        // Every committed sale must survive a power cut
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "FULL")?;
+        conn.pragma_update(None, "foreign_keys", "ON")?;
+
+        let journal = Self::init(conn, Some(path.to_path_buf()))?;
+        info!("Opened transaction journal at {:?}", path);
+        Ok(journal)
+    }
+
+    /// Open the journal in the app data directory.
+    /// No in-memory fallback: sales rung up there would be lost on the next crash.
+    pub fn open_default() -> Result<Self> {
+        let path = utils::get_app_data_dir().join(JOURNAL_FILE);
+        Self::open(&path)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        let conn = Connection::open_in_memory()?;
+        conn.pragma_update(None, "foreign_keys", "ON")?;
+        Self::init(conn, None)
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Number new orders from this terminal's own counter
+    pub fn set_numbering(&mut self, numbering: Option<OrderNumberAllocator>) {
+        self.numbering = numbering;
+    }
+
+    pub fn numbering_status(&self) -> Option<OrderNumberStatus> {
+        self.numbering.as_ref().map(OrderNumberAllocator::status)
+    }
+
+    /// Append a checkout; replaying the same idempotency key returns the stored order
+    pub fn record_order(&mut self, order: NewOrder) -> Result<JournalOrder> {
+        Self::validate(&order)?;
+
+        let key = order
+            .idempotency_key
+            .clone()
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+        if let Some(existing) = self.get_order(&key)? {
+            debug!("Journal replay for idempotency key {}", key);
+            return Ok(existing);
+        }
+
+        let subtotal: i64 = order
+            .lines
+            .iter()
+            .map(|line| line.quantity * line.price - line.discount_amount)
+            .sum();
+        let tax_amount: i64 = order.lines.iter().map(|line| line.tax_amount).sum();
+        let total = subtotal - order.discount_amount + tax_amount + order.rounding_amount;
+        let paid_total: i64 = order.payments.iter().map(|payment| payment.amount).sum();
+
+        if paid_total < total {
+            return Err(anyhow!(
+                "Payment {} is less than total {}",
+                utils::format_rupiah(paid_total),
+                utils::format_rupiah(total)
+            ));
+        }
+
+        let mut paid_at = chrono::Utc::now();
+        let tx = self.conn.transaction()?;
+
+        // Numbers only come from the terminal counter: the server would renumber anything else,
+        // and the printed receipt would no longer match
+        let Some(numbering) = self.numbering.as_mut() else {
+            return Err(anyhow!("No terminal code configured; provision this till before taking orders"));
+        };
+        let order_no = Self::terminal_order_no(&tx, numbering, paid_at)?;
+        // After a small clock step back the number keeps the later date; so does the order
+        paid_at = paid_at.max(order_no::business_day_start(&order_no.business_date)?);
+        let order_no = order_no.to_string();
+        let now = paid_at.to_rfc3339();
+
+        tx.execute(
+            "INSERT INTO journal_orders (idempotency_key, order_no, branch_id, session_id, customer_id, \
+             customer_name, cashier_id, cashier_name, status, subtotal, discount_amount, tax_amount, \
+             rounding_amount, total, paid_total, change_amount, notes, paid_at, created_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?18)",
+            params![
+                key,
+                order_no,
+                order.branch_id,
+                order.session_id,
+                order.customer_id,
+                order.customer_name,
+                order.cashier_id,
+                order.cashier_name,
+                OrderStatus::Paid.as_str(),
+                subtotal,
+                order.discount_amount,
+                tax_amount,
+                order.rounding_amount,
+                total,
+                paid_total,
+                paid_total - total,
+                order.notes,
+                now,
+            ],
+        )
+        .map_err(|e| anyhow!("Failed to journal order {}: {}", order_no, e))?;
+
+        let seq = tx.last_insert_rowid();
+
+        for (index, line) in order.lines.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO journal_order_lines (order_seq, line_no, product_id, product_code, \
+                 product_name, quantity, price, discount_amount, tax_amount) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    seq,
+                    index as i64 + 1,
+                    line.product_id,
+                    line.product_code,
+                    line.product_name,
+                    line.quantity,
+                    line.price,
+                    line.discount_amount,
+                    line.tax_amount,
+                ],
+            )?;
+        }
+
+        for payment in &order.payments {
+            tx.execute(
+                "INSERT INTO journal_payments (order_seq, payment_method, amount, reference, captured_at) \
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![seq, payment.method.as_str(), payment.amount, payment.reference, now],
+            )?;
+        }
+
+        tx.commit()?;
+        info!("Journaled order {} ({})", order_no, utils::format_rupiah(total));
+
+        self.get_order(&key)?
+            .ok_or_else(|| anyhow!("Journaled order {} not found", key))
+    }
+
+    pub fn get_order(&self, idempotency_key: &str) -> Result<Option<JournalOrder>> {
+        let sql = format!("SELECT {} FROM journal_orders WHERE idempotency_key = ?1", ORDER_COLUMNS);
+        let order = self
+            .conn
+            .query_row(&sql, params![idempotency_key], Self::map_order)
+            .optional()?;
+
+        match order {
+            Some(order) => Ok(Some(self.load_children(order?)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Most recent orders first, optionally filtered by sync status
+    pub fn list_orders(&self, sync_status: Option<SyncStatus>, limit: usize) -> Result<Vec<JournalOrder>> {
+        let sql = format!(
+            "SELECT {} FROM journal_orders WHERE (?1 IS NULL OR sync_status = ?1) ORDER BY seq DESC LIMIT ?2",
+            ORDER_COLUMNS
+        );
+        self.query_orders(&sql, params![sync_status.map(|s| s.as_str()), limit as i64])
+    }
+
+    /// Orders still to be replayed upstream, oldest first
+    pub fn pending_orders(&self, limit: usize) -> Result<Vec<JournalOrder>> {
+        let sql = format!(
+            "SELECT {} FROM journal_orders WHERE sync_status = 'pending' ORDER BY seq ASC LIMIT ?1",
+            ORDER_COLUMNS
+        );
+        self.query_orders(&sql, params![limit as i64])
+    }
+
+    pub fn mark_synced(&self, idempotency_key: &str, server_id: Option<&str>) -> Result<()> {
+        self.update_sync(
+            idempotency_key,
+            "UPDATE journal_orders SET sync_status = 'synced', sync_attempts = sync_attempts + 1, \
+             last_error = NULL, server_id = ?2, synced_at = ?3 WHERE idempotency_key = ?1",
+            params![idempotency_key, server_id, chrono::Utc::now().to_rfc3339()],
+        )
+    }
+
+    /// Record a failed attempt; the order stays pending for the next retry
+    pub fn record_attempt_error(&self, idempotency_key: &str, error: &str) -> Result<()> {
+        self.update_sync(
+            idempotency_key,
+            "UPDATE journal_orders SET sync_attempts = sync_attempts + 1, last_error = ?2 \
+             WHERE idempotency_key = ?1",
+            params![idempotency_key, error],
+        )
+    }
+
+    /// Park an order that needs a human (conflict) or can never be accepted (failed)
+    pub fn mark_unsynced(&self, idempotency_key: &str, status: SyncStatus, error: &str) -> Result<()> {
+        self.update_sync(
+            idempotency_key,
+            "UPDATE journal_orders SET sync_status = ?2, sync_attempts = sync_attempts + 1, \
+             last_error = ?3 WHERE idempotency_key = ?1",
+            params![idempotency_key, status.as_str(), error],
+        )
+    }
+
+    /// Put conflicting or failed orders back in the queue after they were resolved
+    pub fn requeue(&self, idempotency_key: &str) -> Result<()> {
+        self.update_sync(
+            idempotency_key,
+            "UPDATE journal_orders SET sync_status = 'pending', last_error = NULL \
+             WHERE idempotency_key = ?1 AND sync_status IN ('conflict', 'failed')",
+            params![idempotency_key],
+        )
+    }
+
+    pub fn stats(&self) -> Result<JournalStats> {
+        let mut stats = JournalStats {
+            persistent: self.path.is_some(),
+            ..JournalStats::default()
+        };
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT sync_status, COUNT(*) FROM journal_orders GROUP BY sync_status")?;
+        let counts = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?;
+
+        for count in counts {
+            let (status, count) = count?;
+            match SyncStatus::parse(&status)? {
+                SyncStatus::Pending => stats.pending = count,
+                SyncStatus::Synced => stats.synced = count,
+                SyncStatus::Conflict => stats.conflict = count,
+                SyncStatus::Failed => stats.failed = count,
+            }
+        }
+
+        stats.oldest_pending_at = self
+            .conn
+            .query_row(
+                "SELECT created_at FROM journal_orders WHERE sync_status = 'pending' ORDER BY seq LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(stats)
+    }
+
+    // ======================================================================
+    // PRIVATE METHODS
+    // ======================================================================
+
+    fn init(conn: Connection, path: Option<PathBuf>) -> Result<Self> {
+        conn.execute_batch(SCHEMA)
+            .map_err(|e| anyhow!("Failed to initialize journal schema: {}", e))?;
+        Ok(Self {
+            conn,
+            path,
+            numbering: None,
+        })
+    }
+
+    fn validate(order: &NewOrder) -> Result<()> {
+        if order.lines.is_empty() {
+            return Err(anyhow!("Order has no lines"));
+        }
+        if order.payments.is_empty() {
+            return Err(anyhow!("Order has no payments"));
+        }
+        if order.lines.iter().any(|line| line.quantity <= 0 || line.price < 0) {
+            return Err(anyhow!("Order lines need a positive quantity and price"));
+        }
+        if order.payments.iter().any(|payment| payment.amount <= 0) {
+            return Err(anyhow!("Payment amounts must be positive"));
+        }
+        Ok(())
+    }
+
+    /// Allocate from the terminal counter, never reusing a number already journaled
+    fn terminal_order_no(
+        tx: &Transaction,
+        numbering: &mut OrderNumberAllocator,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<OrderNo> {
+        let last: Option<String> = tx.query_row(
+            "SELECT MAX(order_no) FROM journal_orders WHERE order_no LIKE ?1 || '%'",
+            params![numbering.prefix_at(now)],
+            |row| row.get(0),
+        )?;
+
+        if let Some(used) = last.as_deref().and_then(order_no::parse_order_no) {
+            numbering.ensure_at_least(&used)?;
+        }
+        numbering.next_at(now)
+    }
+
+    fn update_sync(&self, idempotency_key: &str, sql: &str, params: impl rusqlite::Params) -> Result<()> {
+        let changed = self.conn.execute(sql, params)?;
+        if changed == 0 {
+            return Err(anyhow!("Journal order {} not found", idempotency_key));
+        }
+        Ok(())
+    }
+
+    fn query_orders(&self, sql: &str, params: impl rusqlite::Params) -> Result<Vec<JournalOrder>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let rows = stmt.query_map(params, Self::map_order)?;
+
+        let mut orders = Vec::new();
+        for row in rows {
+            orders.push(self.load_children(row??)?);
+        }
+        Ok(orders)
+    }
+
+    fn map_order(row: &Row) -> rusqlite::Result<Result<JournalOrder>> {
+        let status: String = row.get(9)?;
+        let sync_status: String = row.get(20)?;
+
+        let (status, sync_status) = match (OrderStatus::parse(&status), SyncStatus::parse(&sync_status)) {
+            (Ok(status), Ok(sync_status)) => (status, sync_status),
+            (Err(e), _) | (_, Err(e)) => return Ok(Err(e)),
+        };
+
+        Ok(Ok(JournalOrder {
+            seq: row.get(0)?,
+            idempotency_key: row.get(1)?,
+            order_no: row.get(2)?,
+            branch_id: row.get(3)?,
+            session_id: row.get(4)?,
+            customer_id: row.get(5)?,
+            customer_name: row.get(6)?,
+            cashier_id: row.get(7)?,
+            cashier_name: row.get(8)?,
+            status,
+            subtotal: row.get(10)?,
+            discount_amount: row.get(11)?,
+            tax_amount: row.get(12)?,
+            rounding_amount: row.get(13)?,
+            total: row.get(14)?,
+            paid_total: row.get(15)?,
+            change_amount: row.get(16)?,
+            notes: row.get(17)?,
+            paid_at: row.get(18)?,
+            created_at: row.get(19)?,
+            sync_status,
+            sync_attempts: row.get(21)?,
+            last_error: row.get(22)?,
+            server_id: row.get(23)?,
+            synced_at: row.get(24)?,
+            lines: Vec::new(),
+            payments: Vec::new(),
+        }))
+    }
+
+    fn load_children(&self, mut order: JournalOrder) -> Result<JournalOrder> {
+        let mut stmt = self.conn.prepare(
+            "SELECT line_no, product_id, product_code, product_name, quantity, price, \
+             discount_amount, tax_amount, line_total FROM journal_order_lines \
+             WHERE order_seq = ?1 ORDER BY line_no",
+        )?;
+        order.lines = stmt
+            .query_map(params![order.seq], |row| {
+                Ok(JournalOrderLine {
+                    line_no: row.get(0)?,
+                    product_id: row.get(1)?,
+                    product_code: row.get(2)?,
+                    product_name: row.get(3)?,
+                    quantity: row.get(4)?,
+                    price: row.get(5)?,
+                    discount_amount: row.get(6)?,
+                    tax_amount: row.get(7)?,
+                    line_total: row.get(8)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let mut stmt = self.conn.prepare(
+            "SELECT payment_method, amount, reference, captured_at FROM journal_payments \
+             WHERE order_seq = ?1 ORDER BY id",
+        )?;
+        let payments = stmt
+            .query_map(params![order.seq], |row| {
+                Ok((row.get::<_, String>(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?
+            .collect::<rusqlite::Result<Vec<(String, i64, Option<String>, String)>>>()?;
+
+        order.payments = payments
+            .into_iter()
+            .map(|(method, amount, reference, captured_at)| {
+                Ok(JournalPayment {
+                    method: PaymentMethod::parse(&method)?,
+                    amount,
+                    reference,
+                    captured_at,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(order)
+    }
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbered_journal() -> Journal {
+        let mut journal = Journal::open_in_memory().unwrap();
+        journal.set_numbering(Some(OrderNumberAllocator::in_memory("JKT01", "T02").unwrap()));
+        journal
+    }
+
+    fn sample_order(key: &str) -> NewOrder {
+        NewOrder {
+            idempotency_key: Some(key.to_string()),
+            branch_id: "branch-1".to_string(),
+            session_id: None,
+            customer_id: None,
+            customer_name: None,
+            cashier_id: "kasir-1".to_string(),
+            cashier_name: "Sari".to_string(),
+            discount_amount: 1_000,
+            rounding_amount: 0,
+            notes: None,
+            lines: vec![
+                NewOrderLine {
+                    product_id: "p-1".to_string(),
+                    product_code: "8992761111113".to_string(),
+                    product_name: "Indomie Goreng".to_string(),
+                    quantity: 3,
+                    price: 3_500,
+                    discount_amount: 0,
+                    tax_amount: 0,
+                },
+                NewOrderLine {
+                    product_id: "p-2".to_string(),
+                    product_code: "8991002101234".to_string(),
+                    product_name: "Teh Botol".to_string(),
+                    quantity: 1,
+                    price: 5_000,
+                    discount_amount: 500,
+                    tax_amount: 495,
+                },
+            ],
+            payments: vec![NewPayment {
+                method: PaymentMethod::Cash,
+                amount: 20_000,
+                reference: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_record_order_totals_and_idempotency() {
+        let mut journal = numbered_journal();
+
+        let order = journal.record_order(sample_order("key-1")).unwrap();
+        assert_eq!(order.subtotal, 15_000);
+        assert_eq!(order.tax_amount, 495);
+        assert_eq!(order.total, 14_495);
+        assert_eq!(order.change_amount, 5_505);
+        assert_eq!(order.lines[1].line_total, 4_995);
+        assert_eq!(order.payments[0].method, PaymentMethod::Cash);
+        assert!(order_no::parse_order_no(&order.order_no).is_some());
+
+        let replay = journal.record_order(sample_order("key-1")).unwrap();
+        assert_eq!(replay.seq, order.seq);
+        assert_eq!(journal.stats().unwrap().pending, 1);
+    }
+
+    #[test]
+    fn test_rejects_underpayment() {
+        let mut journal = numbered_journal();
+        let mut order = sample_order("key-2");
+        order.payments[0].amount = 10_000;
+
+        assert!(journal.record_order(order).is_err());
+        assert_eq!(journal.stats().unwrap().pending, 0);
+
+        // Without a terminal code there is no number the server would keep
+        let mut unprovisioned = Journal::open_in_memory().unwrap();
+        assert!(unprovisioned.record_order(sample_order("key-3")).is_err());
+    }
+
+    #[test]
+    fn test_append_only_and_sync_bookkeeping() {
+        let mut journal = numbered_journal();
+        journal.record_order(sample_order("a")).unwrap();
+        journal.record_order(sample_order("b")).unwrap();
+
+        assert!(journal.conn.execute("UPDATE journal_orders SET total = 0", []).is_err());
+        assert!(journal.conn.execute("DELETE FROM journal_payments", []).is_err());
+
+        journal.record_attempt_error("a", "connection refused").unwrap();
+        journal.mark_unsynced("b", SyncStatus::Conflict, "duplicate order_no").unwrap();
+
+        let pending = journal.pending_orders(10).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].sync_attempts, 1);
+
+        journal.mark_synced("a", Some("srv-1")).unwrap();
+        journal.requeue("b").unwrap();
+
+        let stats = journal.stats().unwrap();
+        assert_eq!((stats.pending, stats.synced, stats.conflict), (1, 1, 0));
+        assert_eq!(journal.get_order("a").unwrap().unwrap().server_id.as_deref(), Some("srv-1"));
+    }
+
+    #[test]
+    fn test_terminal_numbering_skips_numbers_already_journaled() {
+        let mut journal = Journal::open_in_memory().unwrap();
+
+        // A counter that lost its state must not reuse a journaled number
+        let mut numbering = OrderNumberAllocator::in_memory("JKT01", "T02").unwrap();
+        let prefix = numbering.prefix_at(chrono::Utc::now());
+        numbering.ensure_at_least(&order_no::parse_order_no(&format!("{}00006", prefix)).unwrap()).unwrap();
+        journal.set_numbering(Some(numbering));
+        let order = journal.record_order(sample_order("existing")).unwrap();
+        assert_eq!(order.order_no, format!("{}00007", prefix));
+
+        journal.set_numbering(Some(OrderNumberAllocator::in_memory("JKT01", "T02").unwrap()));
+        let order = journal.record_order(sample_order("next")).unwrap();
+
+        assert_eq!(order.order_no, format!("{}00008", prefix));
+        assert_eq!(journal.numbering_status().unwrap().last_counter, 8);
+    }
+}
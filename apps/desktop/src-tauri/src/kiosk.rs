@@ -0,0 +1,158 @@
+// ======================================================================
+// KIOSK MODE
+// Mengunci jendela utama untuk terminal kasir khusus
+// ======================================================================
+
+use anyhow::Result;
+use log::info;
+use serde::{Deserialize, Serialize};
+use tauri::Window;
+
+/// Injected on every page load of the main window. The listeners stay installed
+/// and check `__POS_KIOSK__`, so leaving kiosk mode needs no reload. The supervisor
+/// prompt is the shell's own, so quitting works whatever page is loaded.
+const KIOSK_SCRIPT: &str = r#"(function () {
+  if (window.__POS_KIOSK_INSTALLED__) return;
+  window.__POS_KIOSK_INSTALLED__ = true;
+
+  function blocked(e) {
+    var key = (e.key || '').toLowerCase();
+    var ctrl = e.ctrlKey || e.metaKey;
+    if (key === 'f12' || key === 'browserback' || key === 'browserforward') return true;
+    if (ctrl && e.shiftKey && ['i', 'j', 'c', 'k'].indexOf(key) >= 0) return true;
+    if (ctrl && ['u', 'o', 'n', 't', 'l', 'w', 's'].indexOf(key) >= 0) return true;
+    if (e.altKey && (key === 'arrowleft' || key === 'arrowright' || key === 'home')) return true;
+    return false;
+  }
+
+  window.addEventListener('contextmenu', function (e) {
+    if (window.__POS_KIOSK__) e.preventDefault();
+  }, true);
+  window.addEventListener('keydown', function (e) {
+    if (window.__POS_KIOSK__ && (e.ctrlKey || e.metaKey) && e.shiftKey && (e.key || '').toLowerCase() === 'q') {
+      e.preventDefault();
+      showPrompt();
+      return;
+    }
+    if (window.__POS_KIOSK__ && blocked(e)) {
+      e.preventDefault();
+      e.stopPropagation();
+    }
+  }, true);
+  // Back/forward mouse buttons
+  window.addEventListener('mouseup', function (e) {
+    if (window.__POS_KIOSK__ && (e.button === 3 || e.button === 4)) e.preventDefault();
+  }, true);
+
+  var prompt = null;
+  function showPrompt() {
+    if (prompt) return;
+    prompt = document.createElement('div');
+    prompt.setAttribute('style', 'position:fixed;inset:0;z-index:2147483647;display:flex;align-items:center;' +
+      'justify-content:center;background:rgba(15,23,42,0.9);color:#fff;font-family:system-ui,sans-serif');
+    prompt.innerHTML =
+      '<form style="display:flex;flex-direction:column;gap:12px;width:300px;text-align:center">' +
+      '<h2 style="margin:0;font-size:22px">Persetujuan supervisor</h2>' +
+      '<input name="supervisor" autocomplete="off" placeholder="ID supervisor" ' +
+      'style="padding:10px;font-size:16px;border-radius:6px;border:0;color:#0f172a">' +
+      '<select name="method" style="padding:8px;font-size:14px;border-radius:6px;border:0;color:#0f172a">' +
+      '<option value="pin">PIN</option><option value="otp">Kode authenticator</option></select>' +
+      '<input name="code" type="password" inputmode="numeric" autocomplete="off" placeholder="PIN / kode" ' +
+      'style="padding:10px;font-size:20px;text-align:center;border-radius:6px;border:0;color:#0f172a">' +
+      '<button type="button" data-command="quit_app" style="padding:10px;font-size:16px;border-radius:6px;' +
+      'border:0;background:#dc2626;color:#fff">Tutup aplikasi</button>' +
+      '<button type="button" data-command="leave_kiosk_mode" style="padding:10px;font-size:16px;' +
+      'border-radius:6px;border:0;background:#2563eb;color:#fff">Keluar dari mode kiosk</button>' +
+      '<button type="button" data-cancel style="padding:8px;font-size:14px;border-radius:6px;border:0">Batal</button>' +
+      '<p data-error style="margin:0;min-height:18px;color:#f87171"></p>' +
+      '</form>';
+    var form = prompt.querySelector('form');
+    var error = prompt.querySelector('[data-error]');
+    form.addEventListener('submit', function (e) { e.preventDefault(); });
+    prompt.querySelector('[data-cancel]').addEventListener('click', hidePrompt);
+    Array.prototype.forEach.call(prompt.querySelectorAll('[data-command]'), function (button) {
+      button.addEventListener('click', function () {
+        var supervisorId = form.elements.supervisor.value.trim();
+        var code = form.elements.code.value;
+        var credential = form.elements.method.value === 'otp'
+          ? { method: 'otp', supervisor_id: supervisorId, code: code }
+          : { method: 'pin', supervisor_id: supervisorId, pin: code };
+        form.elements.code.value = '';
+        window.__TAURI__.invoke(button.getAttribute('data-command'), { credential: credential }).then(function (result) {
+          if (result.success) hidePrompt();
+          else error.textContent = result.error;
+        }, function (e) {
+          error.textContent = String(e);
+        });
+      });
+    });
+    // Keys typed into the prompt must not reach the page underneath
+    prompt.addEventListener('keydown', function (e) { e.stopPropagation(); });
+    document.body.appendChild(prompt);
+    form.elements.supervisor.focus();
+  }
+  function hidePrompt() {
+    if (prompt) prompt.parentNode.removeChild(prompt);
+    prompt = null;
+  }
+  window.__POS_KIOSK_PROMPT__ = { show: showPrompt, hide: hidePrompt };
+})();"#;
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KioskConfig {
+    /// Fullscreen, always on top, no devtools or context menu; leaving needs a supervisor
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KioskStatus {
+    pub enabled: bool,
+}
+
+// ======================================================================
+// WINDOW
+// ======================================================================
+
+/// Lock the main window down, or release it
+pub fn apply(window: &Window, enabled: bool) -> Result<()> {
+    info!("Kiosk mode {}", if enabled { "on" } else { "off" });
+
+    if enabled {
+        window.show()?;
+        window.set_resizable(false)?;
+        window.set_fullscreen(true)?;
+        window.set_always_on_top(true)?;
+        let _ = window.set_focus();
+    } else {
+        window.set_always_on_top(false)?;
+        window.set_fullscreen(false)?;
+        window.set_resizable(true)?;
+    }
+
+    install_script(window, enabled)
+}
+
+/// Re-arm the webview after navigation; the page lost the injected listeners
+pub fn install_script(window: &Window, enabled: bool) -> Result<()> {
+    // Release builds ship without devtools; debug builds close them while locked
+    #[cfg(debug_assertions)]
+    if enabled && window.is_devtools_open() {
+        window.close_devtools();
+    }
+
+    window.eval(KIOSK_SCRIPT)?;
+    window.eval(&format!("window.__POS_KIOSK__ = {};", enabled))?;
+    Ok(())
+}
+
+/// Ask for a supervisor PIN or authenticator code to quit or leave kiosk mode
+pub fn prompt_supervisor(window: &Window) -> Result<()> {
+    window.eval(KIOSK_SCRIPT)?;
+    window.eval("window.__POS_KIOSK_PROMPT__.show();")?;
+    Ok(())
+}
@@ -11,16 +11,60 @@ use std::sync::Arc;
 use tauri::{Manager, SystemTray, SystemTrayEvent, SystemTrayMenu, CustomMenuItem};
 use tokio::sync::Mutex;
 
+mod approval;
+mod archive;
+mod audit;
+mod backup;
+mod catalog;
 mod commands;
+mod customer_display;
+mod devices;
+mod files;
+mod idle_lock;
+mod ipc;
+mod journal;
+mod kiosk;
+mod migrations;
+mod order_no;
+mod postgres;
+mod printer;
+mod rollback;
+mod scale;
+mod scanner;
+mod secrets;
 mod sidecar;
+mod sync;
 mod system;
+mod terminal;
+mod tunnel;
+mod updater;
+mod url_policy;
 mod utils;
 mod watchdog;
+mod window_manager;
 
+use approval::{SupervisorAuthorizer, APPROVAL_KEY_PURPOSE};
+use audit::{AuditEvent, AuditLog, AuditOutcome};
+use backup::BackupManager;
+use catalog::CatalogCache;
 use commands::*;
+use customer_display::CustomerDisplay;
+use devices::{DeviceKind, DeviceRegistry};
+use idle_lock::IdleLock;
+use ipc::IpcGuard;
+use journal::Journal;
+use migrations::MigrationRunner;
+use postgres::EmbeddedPostgres;
+use rollback::{Launch, RollbackStore};
+use scale::ScaleService;
+use scanner::ScannerService;
 use sidecar::SidecarManager;
+use sync::SyncEngine;
 use system::SystemInfo;
+use tunnel::TunnelManager;
+use updater::UpdateManager;
 use watchdog::Watchdog;
+use window_manager::CustomerView;
 
 // ======================================================================
 // STATE MANAGEMENT
@@ -31,14 +75,79 @@ pub struct AppState {
     pub sidecar_manager: Arc<Mutex<SidecarManager>>,
     pub watchdog: Arc<Mutex<Watchdog>>,
     pub system_info: Arc<Mutex<SystemInfo>>,
+    pub scanner: Arc<Mutex<ScannerService>>,
+    pub customer_display: Arc<Mutex<CustomerDisplay>>,
+    pub scale: Arc<Mutex<ScaleService>>,
+    pub customer_view: Arc<Mutex<CustomerView>>,
+    pub devices: Arc<Mutex<DeviceRegistry>>,
+    pub journal: Arc<Mutex<Journal>>,
+    pub sync: Arc<Mutex<SyncEngine>>,
+    pub catalog: Arc<Mutex<CatalogCache>>,
+    pub backup: Arc<Mutex<BackupManager>>,
+    pub postgres: Arc<Mutex<EmbeddedPostgres>>,
+    pub migrations: Arc<Mutex<MigrationRunner>>,
+    pub tunnel: Arc<Mutex<TunnelManager>>,
+    pub ipc: Arc<IpcGuard>,
+    pub audit: Arc<AuditLog>,
+    pub approvals: Arc<SupervisorAuthorizer>,
+    pub idle_lock: Arc<IdleLock>,
+    pub updater: Arc<Mutex<UpdateManager>>,
+    pub rollback: Arc<RollbackStore>,
 }
 
 impl AppState {
-    pub fn new() -> Self {
-        Self {
-            sidecar_manager: Arc::new(Mutex::new(SidecarManager::new())),
+    /// Fails only when the order journal can't be opened
+    pub fn new() -> anyhow::Result<Self> {
+        let config = AppConfig::load();
+        let mut journal = Journal::open_default()?;
+        journal.set_numbering(config.order_numbering());
+        let journal = Arc::new(Mutex::new(journal));
+        let sidecar = SidecarManager::new();
+        let ipc = Arc::new(IpcGuard::new(sidecar.port_handle()));
+        let approvals = Arc::new(SupervisorAuthorizer::open_default(
+            ipc.derive_key(APPROVAL_KEY_PURPOSE),
+            secrets::store(),
+        ));
+        let idle_lock = IdleLock::open_default();
+        idle_lock.configure(&config.idle_lock);
+        let updater = UpdateManager::new();
+        let rollback = updater.rollback();
+        let mut sync = SyncEngine::new(Arc::clone(&journal));
+        sync.set_rollback(Arc::clone(&rollback));
+        
+        Ok(Self {
+            sidecar_manager: Arc::new(Mutex::new(sidecar)),
             watchdog: Arc::new(Mutex::new(Watchdog::new())),
             system_info: Arc::new(Mutex::new(SystemInfo::new())),
+            scanner: Arc::new(Mutex::new(ScannerService::new())),
+            customer_display: Arc::new(Mutex::new(CustomerDisplay::new())),
+            scale: Arc::new(Mutex::new(ScaleService::new())),
+            customer_view: Arc::new(Mutex::new(CustomerView::default())),
+            devices: Arc::new(Mutex::new(DeviceRegistry::load_default())),
+            sync: Arc::new(Mutex::new(sync)),
+            journal,
+            catalog: Arc::new(Mutex::new(CatalogCache::open_default())),
+            backup: Arc::new(Mutex::new(BackupManager::new())),
+            postgres: Arc::new(Mutex::new(EmbeddedPostgres::new())),
+            migrations: Arc::new(Mutex::new(MigrationRunner::new())),
+            tunnel: Arc::new(Mutex::new(TunnelManager::new())),
+            ipc,
+            audit: Arc::new(AuditLog::open_default()),
+            approvals,
+            idle_lock: Arc::new(idle_lock),
+            updater: Arc::new(Mutex::new(updater)),
+            rollback,
+        })
+    }
+    
+    /// The bundled database when it is running, then the tunnelled server, otherwise the configured one
+    pub async fn database_url(&self) -> Option<String> {
+        if let Some(url) = self.postgres.lock().await.database_url().await {
+            return Some(url);
+        }
+        match self.tunnel.lock().await.database_url() {
+            Some(url) => Some(url),
+            None => AppConfig::load().database_url(),
         }
     }
 }
@@ -72,22 +181,18 @@ fn handle_system_tray_event(app: &tauri::AppHandle, event: SystemTrayEvent) {
             ..
         } => {
             // Show main window on left click
-            if let Some(window) = app.get_window("main") {
-                let _ = window.show();
-                let _ = window.set_focus();
-            }
+            let _ = window_manager::show_main_window(app);
         }
         SystemTrayEvent::MenuItemClick { id, .. } => {
             match id.as_str() {
                 "show" => {
-                    if let Some(window) = app.get_window("main") {
-                        let _ = window.show();
-                        let _ = window.set_focus();
-                    }
+                    let _ = window_manager::show_main_window(app);
                 }
                 "hide" => {
-                    if let Some(window) = app.get_window("main") {
-                        let _ = window.hide();
+                    if let Some(window) = window_manager::main_window(app) {
+                        if !AppConfig::load().kiosk.enabled {
+                            let _ = window.hide();
+                        }
                     }
                 }
                 "restart" => {
@@ -96,22 +201,29 @@ fn handle_system_tray_event(app: &tauri::AppHandle, event: SystemTrayEvent) {
                     tauri::async_runtime::spawn(async move {
                         if let Some(state) = app_handle.try_state::<AppState>() {
                             let mut sidecar = state.sidecar_manager.lock().await;
-                            if let Err(e) = sidecar.restart().await {
+                            let result = sidecar.restart().await;
+                            state.audit.record(AuditEvent::new("restart_sidecar", AuditOutcome::Allowed, match &result {
+                                Ok(port) => serde_json::json!({ "source": "tray", "port": port }),
+                                Err(e) => serde_json::json!({ "source": "tray", "error": e.to_string() }),
+                            }));
+                            if let Err(e) = result {
                                 error!("Failed to restart sidecar: {}", e);
                             }
                         }
                     });
                 }
                 "quit" => {
-                    // Graceful shutdown
-                    let app_handle = app.clone();
-                    tauri::async_runtime::spawn(async move {
-                        if let Some(state) = app_handle.try_state::<AppState>() {
-                            let mut sidecar = state.sidecar_manager.lock().await;
-                            let _ = sidecar.stop().await;
+                    if AppConfig::load().kiosk.enabled {
+                        // The shell asks for a supervisor PIN over whatever page is open, then calls `quit_app`
+                        let _ = window_manager::show_main_window(app);
+                        if let Some(window) = window_manager::main_window(app) {
+                            if let Err(e) = kiosk::prompt_supervisor(&window) {
+                                error!("Failed to show the supervisor prompt: {}", e);
+                            }
                         }
-                        app_handle.exit(0);
-                    });
+                    } else {
+                        quit(app);
+                    }
                 }
                 _ => {}
             }
@@ -120,6 +232,46 @@ fn handle_system_tray_event(app: &tauri::AppHandle, event: SystemTrayEvent) {
     }
 }
 
+/// Stop the sidecar, then exit
+pub fn quit(app: &tauri::AppHandle) {
+    // Graceful shutdown
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Some(state) = app_handle.try_state::<AppState>() {
+            let mut sidecar = state.sidecar_manager.lock().await;
+            let _ = sidecar.stop().await;
+        }
+        app_handle.exit(0);
+    });
+}
+
+// ======================================================================
+// DEVICES
+// ======================================================================
+
+/// Connect the enabled serial devices from the registry
+async fn open_registered_devices(state: &AppState) {
+    let devices = state.devices.lock().await;
+    
+    if let Ok(device) = devices.resolve(DeviceKind::Scanner, None) {
+        if let Err(e) = state.scanner.lock().await.start(device.scanner_config()) {
+            warn!("Failed to start scanner {}: {}", device.name, e);
+        }
+    }
+    
+    if let Ok(device) = devices.resolve(DeviceKind::Scale, None) {
+        if let Err(e) = state.scale.lock().await.open(device.scale_config()) {
+            warn!("Failed to open scale {}: {}", device.name, e);
+        }
+    }
+    
+    if let Ok(device) = devices.resolve(DeviceKind::CustomerDisplay, None) {
+        if let Err(e) = state.customer_display.lock().await.open(device.display_config()) {
+            warn!("Failed to open customer display {}: {}", device.name, e);
+        }
+    }
+}
+
 // ======================================================================
 // MAIN FUNCTION
 // ======================================================================
@@ -136,15 +288,33 @@ fn main() {
     
     info!("Starting POS Kasir Suite Desktop Application");
     
+    // Without a journal on disk a crash would lose sales, so the till does not open at all
+    let state = match AppState::new() {
+        Ok(state) => state,
+        Err(e) => {
+            error!("Cannot start without the order journal: {}", e);
+            tauri::api::dialog::blocking::message::<tauri::Wry>(
+                None,
+                "POS Kasir tidak bisa dibuka",
+                format!(
+                    "Jurnal transaksi tidak bisa dibuka:\n{}\n\nPeriksa ruang disk dan izin folder data aplikasi, lalu buka ulang aplikasi.",
+                    e
+                ),
+            );
+            std::process::exit(1);
+        }
+    };
+    
     // Create system tray
     let system_tray = create_system_tray();
     
     // Build Tauri application
     tauri::Builder::default()
-        .manage(AppState::new())
+        .manage(state)
         .system_tray(system_tray)
         .on_system_tray_event(handle_system_tray_event)
-        .invoke_handler(tauri::generate_handler![
+        // Every command is checked against the caller's origin and session role first
+        .invoke_handler(ipc::guarded(tauri::generate_handler![
             // Sidecar commands
             start_sidecar,
             stop_sidecar,
@@ -161,6 +331,86 @@ fn main() {
             stop_watchdog,
             get_watchdog_status,
             
+            // Scanner commands
+            list_serial_ports,
+            start_scanner,
+            stop_scanner,
+            get_scanner_status,
+            
+            // Customer display commands
+            open_customer_display,
+            close_customer_display,
+            display_line_item,
+            display_subtotal,
+            display_total,
+            display_change,
+            display_idle,
+            get_customer_display_status,
+            
+            // Scale commands
+            open_scale,
+            close_scale,
+            read_weight,
+            tare_scale,
+            zero_scale,
+            get_scale_status,
+            parse_weight_barcode,
+            
+            // Device registry commands
+            list_devices,
+            add_device,
+            remove_device,
+            test_device,
+            open_cash_drawer,
+            
+            // Journal commands
+            journal_record_order,
+            journal_get_order,
+            journal_list_orders,
+            journal_requeue_order,
+            get_journal_stats,
+            get_order_numbering,
+            
+            // Sync commands
+            get_sync_status,
+            trigger_sync,
+            
+            // Catalog commands
+            lookup_barcode,
+            search_products,
+            sync_catalog,
+            get_catalog_stats,
+            
+            // Terminal commands
+            provision_terminal,
+            get_terminal_identity,
+            
+            // Backup commands
+            list_backups,
+            create_backup,
+            restore_backup,
+            export_backup,
+            inspect_backup_archive,
+            import_backup,
+            get_backup_status,
+            
+            // Local database commands
+            get_local_database_status,
+            restart_local_database,
+            
+            // Migration commands
+            get_migration_status,
+            
+            // Tunnel commands
+            get_tunnel_status,
+            restart_tunnel,
+            
+            // Secret commands
+            list_secrets,
+            set_secret,
+            delete_secret,
+            test_secret,
+            
             // Utility commands
             show_notification,
             open_external_url,
@@ -171,31 +421,334 @@ fn main() {
             // Window commands
             minimize_to_tray,
             show_from_tray,
-            toggle_window_visibility
-        ])
+            toggle_window_visibility,
+            
+            // Customer window commands
+            list_displays,
+            open_customer_window,
+            close_customer_window,
+            update_customer_view,
+            get_customer_view,
+            
+            // IPC session commands
+            begin_shell_session,
+            end_shell_session,
+            get_shell_session,
+            
+            // Audit commands
+            get_audit_events,
+            verify_audit_log,
+            export_audit_events,
+            record_audit_event,
+            
+            // File commands
+            export_file,
+            import_csv,
+            save_log,
+            
+            // Lock screen commands
+            get_lock_status,
+            lock_session,
+            unlock_session,
+            set_lock_pin,
+            remove_lock_pin,
+            
+            // Kiosk commands
+            get_kiosk_status,
+            leave_kiosk_mode,
+            quit_app,
+            
+            // Update commands
+            get_update_status,
+            check_for_update,
+            install_update,
+            
+            // Supervisor approval commands
+            list_supervisors,
+            set_supervisor_pin,
+            enroll_supervisor_totp,
+            remove_supervisor,
+            request_supervisor_approval
+        ]))
+        // Kiosk listeners are lost on every navigation
+        .on_page_load(|window, _| {
+            if window.label() != window_manager::MAIN_WINDOW_LABEL {
+                return;
+            }
+            if AppConfig::load().kiosk.enabled {
+                if let Err(e) = kiosk::install_script(&window, true) {
+                    warn!("Failed to install kiosk script: {}", e);
+                }
+            }
+            // Heartbeats, and the lock overlay again if the page reloaded while locked
+            let locked = window.state::<AppState>().idle_lock.is_locked();
+            if let Err(e) = idle_lock::install_script(&window, locked) {
+                warn!("Failed to install lock script: {}", e);
+            }
+        })
         .setup(|app| {
             let app_handle = app.handle();
             
+            // Input in the main window keeps the session unlocked
+            let idle_lock = Arc::clone(&app.state::<AppState>().idle_lock);
+            let heartbeat_lock = Arc::clone(&idle_lock);
+            app.listen_global(idle_lock::HEARTBEAT_EVENT, move |_| heartbeat_lock.heartbeat());
+            tauri::async_runtime::spawn(idle_lock::watch(app_handle.clone(), idle_lock));
+            
+            // Dedicated tills come up locked
+            if AppConfig::load().kiosk.enabled {
+                if let Some(window) = window_manager::main_window(&app_handle) {
+                    if let Err(e) = kiosk::apply(&window, true) {
+                        error!("Failed to enter kiosk mode: {}", e);
+                    }
+                }
+            }
+            
+            // Forward hardware scans and weights to the webview, then connect registered devices
+            let scan_handle = app_handle.clone();
+            tauri::async_runtime::block_on(async {
+                let state = app.state::<AppState>();
+                
+                // Scans must not reach the cart under the lock screen
+                let scan_lock = Arc::clone(&state.idle_lock);
+                state.scanner.lock().await.set_scan_callback(move |event| {
+                    if !scan_lock.is_locked() {
+                        let _ = scan_handle.emit_all("scanner://scan", event);
+                    }
+                });
+                
+                let weight_handle = app.handle();
+                state.scale.lock().await.set_weight_callback(move |reading| {
+                    let _ = weight_handle.emit_all("scale://weight", reading);
+                });
+                
+                let sync_handle = app.handle();
+                state.sync.lock().await.set_progress_callback(move |progress| {
+                    let _ = sync_handle.emit_all("sync://progress", progress);
+                });
+                
+                let migration_handle = app.handle();
+                state.migrations.lock().await.set_progress_callback(move |progress| {
+                    let _ = migration_handle.emit_all("migration://progress", progress);
+                });
+                
+                let tunnel_handle = app.handle();
+                state.tunnel.lock().await.set_status_callback(move |status| {
+                    let _ = tunnel_handle.emit_all("tunnel://status", status);
+                });
+                
+                let backup_handle = app.handle();
+                state.backup.lock().await.set_progress_callback(move |progress| {
+                    let _ = backup_handle.emit_all("backup://progress", progress);
+                });
+                
+                let update_handle = app.handle();
+                state.updater.lock().await.set_status_callback(move |status| {
+                    let _ = update_handle.emit_all("updater://status", status);
+                });
+                
+                open_registered_devices(&state).await;
+            });
+            
             // Start sidecar server on app startup
             tauri::async_runtime::spawn(async move {
                 if let Some(state) = app_handle.try_state::<AppState>() {
+                    // A new version that keeps dying before probation ends goes back to the old one
+                    if let Launch::Failed(reason) = state.rollback.begin_launch(env!("CARGO_PKG_VERSION")) {
+                        rollback::roll_back(&app_handle, &state, &reason).await;
+                        return;
+                    }
+                    
+                    let mut config = AppConfig::load();
+                    
+                    // Older configs kept the database password in config.json
+                    match config.move_database_password() {
+                        Ok(true) => {
+                            if let Err(e) = config.save() {
+                                error!("Failed to save config after moving the database password: {}", e);
+                            }
+                        }
+                        Ok(false) => {}
+                        Err(e) => warn!("Database password stays in config.json: {}", e),
+                    }
+                    
+                    // The bundled database has to be up before anything connects to it
+                    if config.local_database.enabled {
+                        let mut postgres = state.postgres.lock().await;
+                        postgres.configure(config.local_database.clone()).await;
+                        if let Err(e) = postgres.start().await {
+                            error!("Failed to start local database: {}", e);
+                        }
+                    } else if config.tunnel.enabled {
+                        // Remote Postgres behind SSH; the sidecar only ever sees the local end
+                        if let Some(url) = config.database_url() {
+                            if let Err(e) = state.tunnel.lock().await.start(config.tunnel.clone(), &url).await {
+                                error!("Failed to open database tunnel: {}", e);
+                            }
+                        }
+                    }
+                    let database_url = state.database_url().await;
+                    
+                    // Bring the schema up to date; a drifted or failed migration keeps the server down
+                    if let Some(url) = &database_url {
+                        match state.migrations.lock().await.run(url).await {
+                            Ok(Some(_)) => {}
+                            Ok(None) => {
+                                // Offline at startup: migrate as soon as the database answers
+                                let retry_handle = app_handle.clone();
+                                let url = url.clone();
+                                tauri::async_runtime::spawn(async move {
+                                    let state = retry_handle.state::<AppState>();
+                                    loop {
+                                        tokio::time::sleep(migrations::RETRY_INTERVAL).await;
+                                        let result = state.migrations.lock().await.run(&url).await;
+                                        match result {
+                                            Ok(None) => continue,
+                                            Ok(Some(_)) => break,
+                                            Err(e) => {
+                                                let _ = state.watchdog.lock().await.stop().await;
+                                                let _ = state.sidecar_manager.lock().await.stop().await;
+                                                if let Some(window) = window_manager::main_window(&retry_handle) {
+                                                    let _ = window.emit("sidecar-error", format!("Database migration failed: {}", e));
+                                                }
+                                                break;
+                                            }
+                                        }
+                                    }
+                                });
+                            }
+                            Err(e) => {
+                                if let Some(window) = window_manager::main_window(&app_handle) {
+                                    let _ = window.emit("sidecar-error", format!("Database migration failed: {}", e));
+                                }
+                                return;
+                            }
+                        }
+                    }
+                    
+                    // Scheduled dumps talk to Postgres directly, not through the sidecar
+                    if let Err(e) = state.backup.lock().await.start(config.backup.clone(), database_url.clone()).await {
+                        warn!("Failed to start backup scheduler: {}", e);
+                    }
+                    
                     let mut sidecar = state.sidecar_manager.lock().await;
                     
+                    // Let the sidecar attribute its own requests to this till
+                    if let Some(identity) = &config.terminal {
+                        sidecar.set_env(identity.sidecar_env());
+                    }
+                    if let Some(url) = &database_url {
+                        sidecar.set_env([("DATABASE_URL".to_string(), url.clone())]);
+                    }
+                    sidecar.set_env(secrets::store().sidecar_env());
+                    sidecar.set_env([state.ipc.sidecar_env()]);
+                    
                     match sidecar.start().await {
-                        Ok(_) => {
+                        Ok(port) => {
                             info!("Sidecar server started successfully");
                             
-                            // Start watchdog
+                            // Start watchdog, probing the upstream database as well
                             let mut watchdog = state.watchdog.lock().await;
-                            if let Err(e) = watchdog.start(sidecar.get_port()).await {
+                            watchdog.set_database_probe(database_url.as_deref());
+                            if config.tunnel.enabled && !config.local_database.enabled {
+                                watchdog.set_tunnel_status(Some(state.tunnel.lock().await.status_handle()));
+                            }
+                            
+                            let restart_handle = app_handle.clone();
+                            watchdog.set_restart_callback(move || {
+                                let handle = restart_handle.clone();
+                                tauri::async_runtime::spawn(async move {
+                                    if let Some(state) = handle.try_state::<AppState>() {
+                                        let restarted = state.sidecar_manager.lock().await.restart().await;
+                                        match restarted {
+                                            Ok(port) => state.watchdog.lock().await.set_target_port(port).await,
+                                            Err(e) => error!("Watchdog failed to restart sidecar: {}", e),
+                                        }
+                                    }
+                                });
+                            });
+                            
+                            // A sidecar that keeps dying right after an update rolls the update back
+                            let crash_handle = app_handle.clone();
+                            watchdog.set_crash_loop_callback(move |restarts| {
+                                let handle = crash_handle.clone();
+                                tauri::async_runtime::spawn(async move {
+                                    if let Some(state) = handle.try_state::<AppState>() {
+                                        if state.rollback.on_probation() {
+                                            let reason = format!("sidecar crash loop ({} restarts)", restarts);
+                                            rollback::roll_back(&handle, &state, &reason).await;
+                                            return;
+                                        }
+                                        let restarted = state.sidecar_manager.lock().await.restart().await;
+                                        match restarted {
+                                            Ok(port) => state.watchdog.lock().await.set_target_port(port).await,
+                                            Err(e) => error!("Watchdog failed to restart sidecar: {}", e),
+                                        }
+                                    }
+                                });
+                            });
+                            
+                            let database_handle = app_handle.clone();
+                            watchdog.set_database_callback(move |online| {
+                                let _ = database_handle.emit_all("database://status", online);
+                                
+                                // Replay the offline journal as soon as the database is back,
+                                // and bring the bundled database back when it went away
+                                let handle = database_handle.clone();
+                                tauri::async_runtime::spawn(async move {
+                                    if let Some(state) = handle.try_state::<AppState>() {
+                                        if online {
+                                            state.sync.lock().await.trigger();
+                                        } else if let Err(e) = state.postgres.lock().await.recover().await {
+                                            error!("Failed to restart local database: {}", e);
+                                        }
+                                    }
+                                });
+                            });
+                            
+                            if let Err(e) = watchdog.start(port).await {
                                 warn!("Failed to start watchdog: {}", e);
                             }
+                            
+                            // Replay journaled orders through the sidecar API
+                            if let Err(e) = state.sync.lock().await.start(config.sync_config(port)).await {
+                                warn!("Failed to start sync engine: {}", e);
+                            }
+                            
+                            // Keep the offline catalog fresh for the configured branch
+                            state.catalog.lock().await.set_upstream(&format!("http://127.0.0.1:{}", port));
+                            match config.branch_id.clone() {
+                                Some(branch_id) => {
+                                    let catalog = Arc::clone(&state.catalog);
+                                    tauri::async_runtime::spawn(catalog::refresh_loop(
+                                        catalog,
+                                        branch_id,
+                                        std::time::Duration::from_secs(300),
+                                    ));
+                                }
+                                None => warn!("No branch configured; catalog cache will not refresh"),
+                            }
+                            
+                            if state.rollback.on_probation() {
+                                tauri::async_runtime::spawn(rollback::confirm_after_probation(app_handle.clone()));
+                            }
+                            
+                            // Download updates in the background; install only between shifts
+                            let key = updater::rollout_key(config.terminal.as_ref());
+                            state.updater.lock().await.start(config.updates.clone(), config.auto_update, key).await;
+                            tauri::async_runtime::spawn(updater::install_when_idle(app_handle.clone()));
                         }
                         Err(e) => {
                             error!("Failed to start sidecar server: {}", e);
                             
+                            if state.rollback.on_probation() {
+                                drop(sidecar);
+                                rollback::roll_back(&app_handle, &state, &format!("sidecar failed to start: {}", e)).await;
+                                return;
+                            }
+                            
                             // Show error notification
-                            if let Some(window) = app_handle.get_window("main") {
+                            if let Some(window) = window_manager::main_window(&app_handle) {
                                 let _ = window.emit("sidecar-error", format!("Failed to start server: {}", e));
                             }
                         }
@@ -207,9 +760,13 @@ fn main() {
         })
         .on_window_event(|event| {
             match event.event() {
-                tauri::WindowEvent::CloseRequested { api, .. } => {
-                    // Prevent window from closing, hide to tray instead
-                    event.window().hide().unwrap();
+                tauri::WindowEvent::CloseRequested { api, .. }
+                    if event.window().label() == window_manager::MAIN_WINDOW_LABEL =>
+                {
+                    // Prevent window from closing, hide to tray instead (kiosk: stay put)
+                    if !AppConfig::load().kiosk.enabled {
+                        event.window().hide().unwrap();
+                    }
                     api.prevent_close();
                 }
                 _ => {}
@@ -226,6 +783,19 @@ fn main() {
                         if let Some(state) = app_handle_clone.try_state::<AppState>() {
                             info!("Shutting down application...");
                             
+                            // Release serial devices
+                            let _ = state.scanner.lock().await.stop();
+                            state.customer_display.lock().await.close();
+                            state.scale.lock().await.close();
+                            
+                            // A clean quit is not a failed launch of an update on probation
+                            state.rollback.end_launch(env!("CARGO_PKG_VERSION"));
+                            
+                            // Stop sync before the sidecar goes away
+                            state.sync.lock().await.stop().await;
+                            state.backup.lock().await.stop().await;
+                            state.updater.lock().await.stop().await;
+                            
                             // Stop watchdog
                             let mut watchdog = state.watchdog.lock().await;
                             let _ = watchdog.stop().await;
@@ -233,6 +803,10 @@ fn main() {
                             // Stop sidecar
                             let mut sidecar = state.sidecar_manager.lock().await;
                             let _ = sidecar.stop().await;
+                            state.tunnel.lock().await.stop().await;
+                            
+                            // Stop the bundled database last
+                            let _ = state.postgres.lock().await.stop().await;
                             
                             info!("Application shutdown complete");
                         }
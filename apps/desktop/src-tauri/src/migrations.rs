@@ -0,0 +1,523 @@
+// ======================================================================
+// DATABASE MIGRATIONS
+// Migrasi skema berurutan dengan checksum, dijalankan sebelum sidecar
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::RwLock;
+use tokio_postgres::Client;
+
+use crate::{archive, postgres};
+
+/// Serialises runners from several tills sharing one database
+const MIGRATION_LOCK_KEY: i64 = 0x0050_4f53_5f4d_4947; // "POS_MIG"
+
+/// Wait between attempts while the database was unreachable at startup
+pub const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Original single-file schema, adopted as migration 1.
+/// Frozen: later schema changes live in `database/migrations`, so adopted databases still get them.
+pub const BOOTSTRAP_SQL: &str = include_str!("../../../../database/pos_database.sql");
+
+/// Schema changes shipped with the app, in order.
+/// Append new ones here; never edit a migration that has been released.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "bootstrap",
+        sql: BOOTSTRAP_SQL,
+    },
+    Migration {
+        version: 2,
+        name: "offline_order_numbers",
+        sql: include_str!("../../../../database/migrations/0002_offline_order_numbers.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "pos_terminals",
+        sql: include_str!("../../../../database/migrations/0003_pos_terminals.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "terminal_update_failures",
+        sql: include_str!("../../../../database/migrations/0004_terminal_update_failures.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "idempotency_keys",
+        sql: include_str!("../../../../database/migrations/0005_idempotency_keys.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "product_prices_updated_at",
+        sql: include_str!("../../../../database/migrations/0006_product_prices_updated_at.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "terminal_request_nonces",
+        sql: include_str!("../../../../database/migrations/0007_terminal_request_nonces.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "terminal_enrollment_tokens",
+        sql: include_str!("../../../../database/migrations/0008_terminal_enrollment_tokens.sql"),
+    },
+];
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+impl Migration {
+    /// SHA-256 of the script with line endings normalised, so Windows checkouts match
+    pub fn checksum(&self) -> String {
+        archive::sha256_hex(self.sql.replace("\r\n", "\n").as_bytes())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MigrationStage {
+    Checking,
+    Applying,
+    Completed,
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationProgress {
+    pub stage: MigrationStage,
+    pub version: Option<i32>,
+    pub name: Option<String>,
+    /// Migrations applied so far in this run
+    pub applied: usize,
+    /// Migrations pending at the start of this run
+    pub pending: usize,
+    pub message: Option<String>,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub current_version: i32,
+    pub applied: Vec<i32>,
+    /// True when an existing bootstrap-only database was adopted as version 1
+    pub baselined: bool,
+}
+
+// ======================================================================
+// MIGRATION RUNNER
+// ======================================================================
+
+pub struct MigrationRunner {
+    migrations: &'static [Migration],
+    last_progress: Arc<RwLock<Option<MigrationProgress>>>,
+    progress_callback: Option<Arc<dyn Fn(MigrationProgress) + Send + Sync>>,
+}
+
+impl MigrationRunner {
+    pub fn new() -> Self {
+        Self::with_migrations(MIGRATIONS)
+    }
+
+    pub fn with_migrations(migrations: &'static [Migration]) -> Self {
+        Self {
+            migrations,
+            last_progress: Arc::new(RwLock::new(None)),
+            progress_callback: None,
+        }
+    }
+
+    /// Register the handler invoked for each migration step
+    pub fn set_progress_callback<F>(&mut self, callback: F)
+    where
+        F: Fn(MigrationProgress) + Send + Sync + 'static,
+    {
+        self.progress_callback = Some(Arc::new(callback));
+    }
+
+    /// Latest step, for a startup screen that subscribed late
+    pub async fn last_progress(&self) -> Option<MigrationProgress> {
+        self.last_progress.read().await.clone()
+    }
+
+    /// Bring the schema up to date.
+    /// `Ok(None)` when nothing answers at the database address; orders keep journaling offline.
+    /// A refused connection, checksum drift, unknown versions and failed scripts are errors.
+    pub async fn run(&self, database_url: &str) -> Result<Option<MigrationReport>> {
+        self.emit(MigrationStage::Checking, None, 0, 0, None).await;
+
+        let mut client = match postgres::connect(database_url).await {
+            Ok(client) => client,
+            Err(e) if postgres::is_unreachable(&e) => {
+                warn!("Skipping migrations, database unreachable: {}", e);
+                self.emit(MigrationStage::Skipped, None, 0, 0, Some(e.to_string())).await;
+                return Ok(None);
+            }
+            Err(e) => {
+                // Reachable but refused (authentication, SSL, missing database): not an offline till
+                error!("Cannot connect to run migrations: {}", e);
+                self.emit(MigrationStage::Failed, None, 0, 0, Some(e.to_string())).await;
+                return Err(e);
+            }
+        };
+
+        client.execute("SELECT pg_advisory_lock($1)", &[&MIGRATION_LOCK_KEY]).await?;
+        let result = self.migrate(&mut client).await;
+        let _ = client.execute("SELECT pg_advisory_unlock($1)", &[&MIGRATION_LOCK_KEY]).await;
+
+        match result {
+            Ok(report) => Ok(Some(report)),
+            Err(e) => {
+                error!("Database migration failed: {}", e);
+                self.emit(MigrationStage::Failed, None, 0, 0, Some(e.to_string())).await;
+                Err(e)
+            }
+        }
+    }
+
+    // ======================================================================
+    // PRIVATE METHODS
+    // ======================================================================
+
+    async fn migrate(&self, client: &mut Client) -> Result<MigrationReport> {
+        let baselined = self.ensure_table(client).await?;
+
+        let rows = client
+            .query("SELECT version, name, checksum FROM schema_migrations ORDER BY version", &[])
+            .await?;
+        let applied: Vec<(i32, String, String)> = rows.iter().map(|row| (row.get(0), row.get(1), row.get(2))).collect();
+
+        let pending = pending_migrations(self.migrations, &applied)?;
+        let mut report = MigrationReport {
+            current_version: applied.last().map(|(version, _, _)| *version).unwrap_or(0),
+            applied: Vec::new(),
+            baselined,
+        };
+
+        if pending.is_empty() {
+            debug!("Database schema is at version {}", report.current_version);
+        }
+
+        for migration in &pending {
+            info!("Applying migration {} ({})", migration.version, migration.name);
+            self.emit(MigrationStage::Applying, Some(migration), report.applied.len(), pending.len(), None)
+                .await;
+
+            let started = Instant::now();
+            let transaction = client.transaction().await?;
+            transaction
+                .batch_execute(migration.sql)
+                .await
+                .map_err(|e| anyhow!("Migration {} ({}) failed: {}", migration.version, migration.name, describe(&e)))?;
+            transaction
+                .execute(
+                    "INSERT INTO schema_migrations (version, name, checksum, execution_ms) VALUES ($1, $2, $3, $4)",
+                    &[
+                        &migration.version,
+                        &migration.name,
+                        &migration.checksum(),
+                        &(started.elapsed().as_millis() as i32),
+                    ],
+                )
+                .await?;
+            transaction.commit().await?;
+
+            report.current_version = migration.version;
+            report.applied.push(migration.version);
+        }
+
+        self.emit(
+            MigrationStage::Completed,
+            None,
+            report.applied.len(),
+            pending.len(),
+            Some(format!("Schema version {}", report.current_version)),
+        )
+        .await;
+
+        Ok(report)
+    }
+
+    /// Create the tracking table; a database bootstrapped before it existed becomes version 1
+    async fn ensure_table(&self, client: &Client) -> Result<bool> {
+        let row = client
+            .query_one(
+                "SELECT to_regclass('public.schema_migrations') IS NOT NULL, to_regclass('public.branches') IS NOT NULL",
+                &[],
+            )
+            .await?;
+        let (tracked, bootstrapped): (bool, bool) = (row.get(0), row.get(1));
+
+        if tracked {
+            return Ok(false);
+        }
+
+        client
+            .batch_execute(
+                "CREATE TABLE IF NOT EXISTS schema_migrations (
+                    version INTEGER PRIMARY KEY,
+                    name VARCHAR(100) NOT NULL,
+                    checksum CHAR(64) NOT NULL,
+                    applied_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                    execution_ms INTEGER
+                );
+                COMMENT ON TABLE schema_migrations IS 'Migrasi skema yang sudah diterapkan oleh aplikasi desktop';",
+            )
+            .await?;
+
+        let baseline = self.migrations.first().filter(|migration| migration.version == 1);
+        match (bootstrapped, baseline) {
+            (true, Some(bootstrap)) => {
+                info!("Adopting existing database as schema version 1");
+                client
+                    .execute(
+                        "INSERT INTO schema_migrations (version, name, checksum) VALUES ($1, $2, $3)",
+                        &[&bootstrap.version, &bootstrap.name, &bootstrap.checksum()],
+                    )
+                    .await?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn emit(
+        &self,
+        stage: MigrationStage,
+        migration: Option<&Migration>,
+        applied: usize,
+        pending: usize,
+        message: Option<String>,
+    ) {
+        let progress = MigrationProgress {
+            stage,
+            version: migration.map(|migration| migration.version),
+            name: migration.map(|migration| migration.name.to_string()),
+            applied,
+            pending,
+            message,
+            timestamp: Utc::now().to_rfc3339(),
+        };
+
+        *self.last_progress.write().await = Some(progress.clone());
+        if let Some(callback) = &self.progress_callback {
+            callback(progress);
+        }
+    }
+}
+
+/// Migrations still to apply, after checking the recorded ones against this build
+fn pending_migrations<'a>(migrations: &'a [Migration], applied: &[(i32, String, String)]) -> Result<Vec<&'a Migration>> {
+    for (version, name, checksum) in applied {
+        let Some(migration) = migrations.iter().find(|migration| migration.version == *version) else {
+            return Err(anyhow!(
+                "Database has migration {} ({}) that this app does not know; update the app",
+                version,
+                name
+            ));
+        };
+
+        if migration.checksum() != *checksum {
+            return Err(anyhow!(
+                "Migration {} ({}) was changed after it was applied (checksum drift)",
+                version,
+                name
+            ));
+        }
+    }
+
+    let latest = applied.last().map(|(version, _, _)| *version).unwrap_or(0);
+    let pending: Vec<&Migration> = migrations
+        .iter()
+        .filter(|migration| !applied.iter().any(|(version, _, _)| *version == migration.version))
+        .collect();
+
+    if let Some(late) = pending.iter().find(|migration| migration.version < latest) {
+        return Err(anyhow!(
+            "Migration {} ({}) is older than applied version {}; migrations must be appended in order",
+            late.version,
+            late.name,
+            latest
+        ));
+    }
+
+    Ok(pending)
+}
+
+fn describe(error: &tokio_postgres::Error) -> String {
+    match error.as_db_error() {
+        Some(db) => format!("{} ({})", db.message(), db.code().code()),
+        None => error.to_string(),
+    }
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V1: Migration = Migration {
+        version: 1,
+        name: "create_probe",
+        sql: "CREATE TABLE migration_probe (id INT PRIMARY KEY);",
+    };
+    const V2: Migration = Migration {
+        version: 2,
+        name: "seed_probe",
+        sql: "INSERT INTO migration_probe VALUES (1), (2);",
+    };
+    const V2_EDITED: Migration = Migration {
+        version: 2,
+        name: "seed_probe",
+        sql: "INSERT INTO migration_probe VALUES (1), (2), (3);",
+    };
+    const BROKEN: Migration = Migration {
+        version: 3,
+        name: "broken",
+        sql: "INSERT INTO migration_probe VALUES (3); SELECT * FROM missing_table;",
+    };
+
+    #[test]
+    fn test_checksum_ignores_line_endings() {
+        let unix = Migration { sql: "SELECT 1;\nSELECT 2;\n", ..V1 };
+        let windows = Migration { sql: "SELECT 1;\r\nSELECT 2;\r\n", ..V1 };
+        assert_eq!(unix.checksum(), windows.checksum());
+        assert_ne!(V2.checksum(), V2_EDITED.checksum());
+    }
+
+    #[test]
+    fn test_bootstrap_is_frozen() {
+        // Adopted databases are recorded with this checksum; schema changes belong in a new migration
+        assert_eq!(
+            MIGRATIONS[0].checksum(),
+            "4e5ff1d4bff67c52a53ddd695a037039d5d501fee9daed50e172b9bce211c5d7"
+        );
+        assert!(MIGRATIONS.iter().enumerate().all(|(i, migration)| migration.version == i as i32 + 1));
+    }
+
+    #[test]
+    fn test_referenced_tables_exist_earlier() {
+        let created = |sql: &str, table: &str| {
+            sql.contains(&format!("CREATE TABLE {} (", table))
+                || sql.contains(&format!("CREATE TABLE IF NOT EXISTS {} (", table))
+        };
+
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            for reference in migration.sql.split("REFERENCES ").skip(1) {
+                let table = reference.split('(').next().unwrap().trim();
+                assert!(
+                    MIGRATIONS[..=i].iter().any(|earlier| created(earlier.sql, table)),
+                    "migration {} references {} before it is created",
+                    migration.version,
+                    table
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_pending_migrations_checks_recorded_history() {
+        let row = |migration: &Migration| (migration.version, migration.name.to_string(), migration.checksum());
+
+        let pending = pending_migrations(&[V1, V2], &[row(&V1)]).unwrap();
+        assert_eq!(pending.iter().map(|migration| migration.version).collect::<Vec<_>>(), vec![2]);
+
+        let drift = pending_migrations(&[V1, V2_EDITED], &[row(&V1), row(&V2)]).unwrap_err();
+        assert!(drift.to_string().contains("checksum drift"));
+
+        let newer = pending_migrations(&[V1], &[row(&V1), row(&V2)]).unwrap_err();
+        assert!(newer.to_string().contains("does not know"));
+
+        let late = pending_migrations(&[V1, V2, BROKEN], &[row(&V1), row(&BROKEN)]).unwrap_err();
+        assert!(late.to_string().contains("appended in order"));
+    }
+
+    /// Needs a scratch database, e.g. `POS_TEST_DATABASE_URL=postgresql://localhost/pos_migration_test`
+    #[tokio::test]
+    async fn test_migrations_against_local_postgres() {
+        let Ok(database_url) = std::env::var("POS_TEST_DATABASE_URL") else {
+            eprintln!("POS_TEST_DATABASE_URL not set; skipping");
+            return;
+        };
+
+        let (client, connection) = tokio_postgres::connect(&database_url, tokio_postgres::NoTls).await.unwrap();
+        tokio::spawn(connection);
+        client
+            .batch_execute("DROP TABLE IF EXISTS migration_probe, schema_migrations")
+            .await
+            .unwrap();
+
+        static FIRST: &[Migration] = &[V1];
+        static SECOND: &[Migration] = &[V1, V2];
+        static EDITED: &[Migration] = &[V1, V2_EDITED];
+        static FAILING: &[Migration] = &[V1, V2, BROKEN];
+
+        let report = MigrationRunner::with_migrations(FIRST).run(&database_url).await.unwrap().unwrap();
+        assert_eq!(report.applied, vec![1]);
+
+        let mut runner = MigrationRunner::with_migrations(SECOND);
+        let stages = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&stages);
+        runner.set_progress_callback(move |progress| sink.lock().push(progress.stage));
+
+        let report = runner.run(&database_url).await.unwrap().unwrap();
+        assert_eq!((report.current_version, report.applied.clone()), (2, vec![2]));
+        assert_eq!(
+            *stages.lock(),
+            vec![MigrationStage::Checking, MigrationStage::Applying, MigrationStage::Completed]
+        );
+        assert!(runner.run(&database_url).await.unwrap().unwrap().applied.is_empty());
+
+        assert!(MigrationRunner::with_migrations(EDITED).run(&database_url).await.is_err());
+
+        // A failing script leaves neither its changes nor a record behind
+        assert!(MigrationRunner::with_migrations(FAILING).run(&database_url).await.is_err());
+        let count: i64 = client
+            .query_one("SELECT COUNT(*) FROM migration_probe", &[])
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(count, 2);
+        let version: i32 = client
+            .query_one("SELECT MAX(version) FROM schema_migrations", &[])
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(version, 2);
+
+        // A server that answers but refuses us is an error, not an offline till
+        let (base, _) = database_url.rsplit_once('/').unwrap();
+        let missing = format!("{}/pos_missing_database", base);
+        assert!(MigrationRunner::with_migrations(FIRST).run(&missing).await.is_err());
+
+        let unreachable = MigrationRunner::with_migrations(FIRST)
+            .run("postgresql://postgres@127.0.0.1:1/none?connect_timeout=1")
+            .await
+            .unwrap();
+        assert!(unreachable.is_none());
+
+        client
+            .batch_execute("DROP TABLE migration_probe, schema_migrations")
+            .await
+            .unwrap();
+    }
+}
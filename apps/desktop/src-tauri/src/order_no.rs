@@ -0,0 +1,371 @@
+// ======================================================================
+// ORDER NUMBERING
+// Penomoran struk per terminal agar kasir bisa mencetak nomor saat offline
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, FixedOffset, Utc};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use crate::utils;
+
+/// File name of the counter inside the app data directory
+pub const ORDER_COUNTER_FILE: &str = "order_counter.json";
+
+/// Five digits per terminal per day
+const MAX_COUNTER: u32 = 99_999;
+
+/// How far the clock may step back behind the counter's date (e.g. an NTP correction at midnight)
+const CLOCK_STEP_BACK_TOLERANCE_MINUTES: i64 = 10;
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderNo {
+    pub branch_code: String,
+    pub terminal_code: String,
+    /// `YYYYMMDD` in WIB
+    pub business_date: String,
+    pub counter: u32,
+}
+
+impl fmt::Display for OrderNo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}-{}-{:05}",
+            self.branch_code, self.terminal_code, self.business_date, self.counter
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderNumberStatus {
+    pub branch_code: String,
+    pub terminal_code: String,
+    pub business_date: String,
+    pub last_counter: u32,
+    pub persistent: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CounterState {
+    branch_code: String,
+    terminal_code: String,
+    business_date: String,
+    last: u32,
+}
+
+// ======================================================================
+// FORMAT
+// ======================================================================
+
+/// Branch codes are 2–10 uppercase letters or digits
+pub fn validate_branch_code(code: &str) -> Result<()> {
+    let valid = (2..=10).contains(&code.len())
+        && code.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow!("Invalid branch code {:?}", code))
+    }
+}
+
+/// Terminal codes are `T` followed by 2–4 digits, e.g. `T01`
+pub fn validate_terminal_code(code: &str) -> Result<()> {
+    let digits = code.strip_prefix('T').unwrap_or("");
+    let valid = (2..=4).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_digit());
+
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow!("Invalid terminal code {:?} (expected T01..T9999)", code))
+    }
+}
+
+/// Parse a terminal-issued number; server-issued numbers return `None`
+pub fn parse_order_no(value: &str) -> Option<OrderNo> {
+    let mut parts = value.split('-');
+    let (branch_code, terminal_code, business_date, counter) =
+        (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+
+    validate_branch_code(branch_code).ok()?;
+    validate_terminal_code(terminal_code).ok()?;
+    chrono::NaiveDate::parse_from_str(business_date, "%Y%m%d").ok()?;
+    if counter.len() != 5 || !counter.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    Some(OrderNo {
+        branch_code: branch_code.to_string(),
+        terminal_code: terminal_code.to_string(),
+        business_date: business_date.to_string(),
+        counter: counter.parse().ok()?,
+    })
+}
+
+/// Business date in WIB, so numbering rolls over at local midnight
+pub fn business_date(now: DateTime<Utc>) -> String {
+    let wib = FixedOffset::east_opt(7 * 3600).expect("valid offset");
+    now.with_timezone(&wib).format("%Y%m%d").to_string()
+}
+
+/// Midnight WIB at the start of a `YYYYMMDD` business date
+pub fn business_day_start(date: &str) -> Result<DateTime<Utc>> {
+    let wib = FixedOffset::east_opt(7 * 3600).expect("valid offset");
+    let midnight = chrono::NaiveDate::parse_from_str(date, "%Y%m%d")
+        .map_err(|e| anyhow!("Invalid business date {}: {}", date, e))?
+        .and_hms_opt(0, 0, 0)
+        .expect("valid time");
+    Ok(midnight
+        .and_local_timezone(wib)
+        .single()
+        .expect("fixed offset")
+        .with_timezone(&Utc))
+}
+
+// ======================================================================
+// ALLOCATOR
+// ======================================================================
+
+/// Hands out terminal order numbers, persisting each before it is used
+///
+/// Numbers look like `JKT01-T02-20240501-00042`. Server numbers from
+/// `generate_order_no` use an alphabetic prefix (`JKT01-POS-...`) and the
+/// server rejects `T<digits>` prefixes, so the two never collide.
+pub struct OrderNumberAllocator {
+    path: Option<PathBuf>,
+    state: CounterState,
+}
+
+impl OrderNumberAllocator {
+    pub fn open(path: &Path, branch_code: &str, terminal_code: &str) -> Result<Self> {
+        validate_branch_code(branch_code)?;
+        validate_terminal_code(terminal_code)?;
+
+        let stored = match std::fs::read(path) {
+            Ok(bytes) => Some(
+                serde_json::from_slice::<CounterState>(&bytes)
+                    .map_err(|e| anyhow!("Corrupt order counter {:?}: {}", path, e))?,
+            ),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(anyhow!("Failed to read order counter {:?}: {}", path, e)),
+        };
+
+        let state = match stored {
+            Some(state) if state.branch_code == branch_code && state.terminal_code == terminal_code => state,
+            Some(state) => {
+                warn!(
+                    "Terminal identity changed from {}-{} to {}-{}; numbering restarts",
+                    state.branch_code, state.terminal_code, branch_code, terminal_code
+                );
+                Self::fresh_state(branch_code, terminal_code)
+            }
+            None => Self::fresh_state(branch_code, terminal_code),
+        };
+
+        info!(
+            "Order numbering {}-{} resumed at {} #{}",
+            branch_code, terminal_code, state.business_date, state.last
+        );
+
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            state,
+        })
+    }
+
+    /// Open the counter in the app data directory
+    pub fn open_default(branch_code: &str, terminal_code: &str) -> Result<Self> {
+        Self::open(
+            &utils::get_app_data_dir().join(ORDER_COUNTER_FILE),
+            branch_code,
+            terminal_code,
+        )
+    }
+
+    pub fn in_memory(branch_code: &str, terminal_code: &str) -> Result<Self> {
+        validate_branch_code(branch_code)?;
+        validate_terminal_code(terminal_code)?;
+
+        Ok(Self {
+            path: None,
+            state: Self::fresh_state(branch_code, terminal_code),
+        })
+    }
+
+    /// Number prefix for the business day of `now`, e.g. `JKT01-T02-20240501-`
+    pub fn prefix_at(&self, now: DateTime<Utc>) -> String {
+        format!(
+            "{}-{}-{}-",
+            self.state.branch_code,
+            self.state.terminal_code,
+            business_date(now)
+        )
+    }
+
+    /// Never hand out a number at or below one already used
+    pub fn ensure_at_least(&mut self, used: &OrderNo) -> Result<()> {
+        if used.branch_code != self.state.branch_code || used.terminal_code != self.state.terminal_code {
+            return Ok(());
+        }
+
+        let ahead = used.business_date > self.state.business_date
+            || (used.business_date == self.state.business_date && used.counter > self.state.last);
+
+        if ahead {
+            warn!("Order counter behind journal; advancing to {}", used);
+            self.state.business_date = used.business_date.clone();
+            self.state.last = used.counter;
+            self.persist()?;
+        }
+        Ok(())
+    }
+
+    /// Allocate the next number; it is on disk before the caller sees it
+    pub fn next_at(&mut self, now: DateTime<Utc>) -> Result<OrderNo> {
+        let date = business_date(now);
+        let previous = self.state.clone();
+
+        if date > self.state.business_date {
+            self.state.business_date = date;
+            self.state.last = 0;
+        } else if date < self.state.business_date {
+            // Clock went backwards. A small step keeps numbering under the later date and the
+            // caller stamps the order at that day's start; anything more has to be fixed first,
+            // or the receipt date would not match the transaction date
+            let start = business_day_start(&self.state.business_date)?;
+            if now + chrono::Duration::minutes(CLOCK_STEP_BACK_TOLERANCE_MINUTES) < start {
+                return Err(anyhow!(
+                    "System date {} is before the last receipt date {}; correct the date and time",
+                    date,
+                    self.state.business_date
+                ));
+            }
+            warn!("System date {} is before counter date {}", date, self.state.business_date);
+        }
+
+        if self.state.last >= MAX_COUNTER {
+            self.state = previous;
+            return Err(anyhow!("Order counter exhausted for {}", self.state.business_date));
+        }
+        self.state.last += 1;
+
+        if let Err(e) = self.persist() {
+            self.state = previous;
+            return Err(e);
+        }
+
+        let order_no = OrderNo {
+            branch_code: self.state.branch_code.clone(),
+            terminal_code: self.state.terminal_code.clone(),
+            business_date: self.state.business_date.clone(),
+            counter: self.state.last,
+        };
+        debug!("Allocated order number {}", order_no);
+        Ok(order_no)
+    }
+
+    pub fn status(&self) -> OrderNumberStatus {
+        OrderNumberStatus {
+            branch_code: self.state.branch_code.clone(),
+            terminal_code: self.state.terminal_code.clone(),
+            business_date: self.state.business_date.clone(),
+            last_counter: self.state.last,
+            persistent: self.path.is_some(),
+        }
+    }
+
+    // ======================================================================
+    // PRIVATE METHODS
+    // ======================================================================
+
+    fn fresh_state(branch_code: &str, terminal_code: &str) -> CounterState {
+        CounterState {
+            branch_code: branch_code.to_string(),
+            terminal_code: terminal_code.to_string(),
+            business_date: String::new(),
+            last: 0,
+        }
+    }
+
+    fn persist(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let json = serde_json::to_vec_pretty(&self.state)?;
+        utils::write_file_atomic(path, &json).map_err(|e| anyhow!("Failed to persist order counter: {}", e))
+    }
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_order_no_rejects_server_numbers() {
+        let parsed = parse_order_no("JKT01-T02-20240501-00042").unwrap();
+        assert_eq!(parsed.terminal_code, "T02");
+        assert_eq!(parsed.counter, 42);
+        assert_eq!(parsed.to_string(), "JKT01-T02-20240501-00042");
+
+        assert!(parse_order_no("JKT01-POS-20240501-000042").is_none());
+        assert!(parse_order_no("TRX-20240101-001").is_none());
+        assert!(parse_order_no("jkt01-T02-20240501-00042").is_none());
+        assert!(validate_terminal_code("T1").is_err());
+    }
+
+    #[test]
+    fn test_allocator_persists_and_rolls_over_at_wib_midnight() {
+        let dir = std::env::temp_dir().join(format!("order-no-{}", uuid::Uuid::new_v4()));
+        let path = dir.join(ORDER_COUNTER_FILE);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // 16:59 UTC is 23:59 WIB on 1 May; 17:00 UTC is already 2 May
+        let before_midnight = Utc.with_ymd_and_hms(2024, 5, 1, 16, 59, 0).unwrap();
+        let after_midnight = Utc.with_ymd_and_hms(2024, 5, 1, 17, 0, 0).unwrap();
+
+        let mut allocator = OrderNumberAllocator::open(&path, "JKT01", "T02").unwrap();
+        assert_eq!(allocator.next_at(before_midnight).unwrap().to_string(), "JKT01-T02-20240501-00001");
+        assert_eq!(allocator.next_at(before_midnight).unwrap().counter, 2);
+        drop(allocator);
+
+        // Simulated restart resumes after the last persisted number
+        let mut allocator = OrderNumberAllocator::open(&path, "JKT01", "T02").unwrap();
+        assert_eq!(allocator.next_at(before_midnight).unwrap().counter, 3);
+        assert_eq!(allocator.next_at(after_midnight).unwrap().to_string(), "JKT01-T02-20240502-00001");
+
+        // A journal number beyond the counter pushes it forward
+        allocator
+            .ensure_at_least(&parse_order_no("JKT01-T02-20240502-00010").unwrap())
+            .unwrap();
+        assert_eq!(allocator.next_at(after_midnight).unwrap().counter, 11);
+
+        // A clock stepping back over midnight keeps the later date; a real rollback is refused
+        let stepped_back = Utc.with_ymd_and_hms(2024, 5, 1, 16, 55, 0).unwrap();
+        assert_eq!(allocator.next_at(stepped_back).unwrap().to_string(), "JKT01-T02-20240502-00012");
+        let rolled_back = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        assert!(allocator.next_at(rolled_back).is_err());
+        assert_eq!(allocator.status().last_counter, 12);
+        assert_eq!(business_day_start("20240502").unwrap(), after_midnight);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,630 @@
+// ======================================================================
+// EMBEDDED POSTGRESQL
+// Mengelola PostgreSQL lokal (bundled) untuk toko tanpa VPS
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use log::{debug, error, info, warn};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use native_tls::TlsConnector;
+use postgres_native_tls::MakeTlsConnector;
+use tokio::{process::Child, sync::Mutex, time::sleep};
+use tokio_postgres::Client;
+
+use crate::utils;
+
+/// Cluster folder inside the app data directory
+pub const LOCAL_DB_DIR: &str = "pgdata";
+
+/// Generated password of the local superuser, next to the cluster folder
+pub const LOCAL_DB_PASSWORD_FILE: &str = "local_db.secret";
+
+/// Server log next to the cluster folder, appended across runs
+pub const LOCAL_DB_LOG_FILE: &str = "postgres.log";
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocalDatabaseConfig {
+    pub enabled: bool,
+    /// Folder holding `initdb`, `postgres`, `pg_ctl`, `pg_isready` and `psql`
+    pub bin_dir: Option<PathBuf>,
+    /// Defaults to `pgdata` in the app data directory
+    pub data_dir: Option<PathBuf>,
+    pub port_range: (u16, u16),
+    pub database: String,
+    pub user: String,
+}
+
+impl Default for LocalDatabaseConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bin_dir: None,
+            data_dir: None,
+            port_range: (5433, 5532),
+            database: "pos_kasir".to_string(),
+            user: "pos".to_string(),
+        }
+    }
+}
+
+impl LocalDatabaseConfig {
+    pub fn data_dir(&self) -> PathBuf {
+        self.data_dir
+            .clone()
+            .unwrap_or_else(|| utils::get_app_data_dir().join(LOCAL_DB_DIR))
+    }
+
+    /// Sibling of the cluster folder, e.g. the password file or server log
+    fn sibling(&self, file_name: &str) -> PathBuf {
+        self.data_dir().with_file_name(file_name)
+    }
+
+    /// Explicit folder, then the bundled `postgres/bin` next to the executable, then `PATH`
+    fn tool(&self, name: &str) -> PathBuf {
+        let file = if cfg!(windows) { format!("{}.exe", name) } else { name.to_string() };
+
+        if let Some(dir) = &self.bin_dir {
+            return dir.join(file);
+        }
+
+        let bundled = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(|dir| dir.join("postgres").join("bin").join(&file)));
+
+        match bundled {
+            Some(path) if path.is_file() => path,
+            _ => PathBuf::from(file),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        for (label, name) in [("database", &self.database), ("user", &self.user)] {
+            let valid = name.chars().next().is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+                && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+            if !valid {
+                return Err(anyhow!("Invalid local {} name {:?}", label, name));
+            }
+        }
+
+        if self.port_range.0 == 0 || self.port_range.0 > self.port_range.1 {
+            return Err(anyhow!("Invalid port range {:?}", self.port_range));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PostgresStatus {
+    Disabled,
+    Stopped,
+    Initializing,
+    Starting,
+    Running { port: u16, pid: u32, uptime: u64 },
+    Stopping,
+    Error { message: String },
+}
+
+#[derive(Debug)]
+struct PostgresProcess {
+    child: Child,
+    port: u16,
+    started_at: Instant,
+}
+
+// ======================================================================
+// EMBEDDED POSTGRES
+// ======================================================================
+
+pub struct EmbeddedPostgres {
+    process: Arc<Mutex<Option<PostgresProcess>>>,
+    status: Arc<Mutex<PostgresStatus>>,
+    config: LocalDatabaseConfig,
+    startup_timeout: Duration,
+    /// Reused on restart so the URL handed to the sidecar stays valid
+    last_port: Option<u16>,
+}
+
+impl EmbeddedPostgres {
+    pub fn new() -> Self {
+        Self {
+            process: Arc::new(Mutex::new(None)),
+            status: Arc::new(Mutex::new(PostgresStatus::Disabled)),
+            config: LocalDatabaseConfig::default(),
+            startup_timeout: Duration::from_secs(30),
+            last_port: None,
+        }
+    }
+
+    /// Settings for the next start
+    pub async fn configure(&mut self, config: LocalDatabaseConfig) {
+        let mut status = self.status.lock().await;
+        if matches!(*status, PostgresStatus::Disabled | PostgresStatus::Stopped) {
+            *status = if config.enabled { PostgresStatus::Stopped } else { PostgresStatus::Disabled };
+        }
+        self.config = config;
+    }
+
+    /// Initialise on first run, start the server and create the database; returns the database URL
+    pub async fn start(&mut self) -> Result<String> {
+        info!("Starting local PostgreSQL...");
+
+        if !self.config.enabled {
+            return Err(anyhow!("Local database is disabled"));
+        }
+        if self.process.lock().await.is_some() {
+            return Err(anyhow!("Local database is already running"));
+        }
+
+        match self.try_start().await {
+            Ok(url) => Ok(url),
+            Err(e) => {
+                error!("Local PostgreSQL failed to start: {}", e);
+                self.kill_process().await;
+                *self.status.lock().await = PostgresStatus::Error { message: e.to_string() };
+                Err(e)
+            }
+        }
+    }
+
+    /// Fast shutdown: roll back open transactions, then checkpoint
+    pub async fn stop(&mut self) -> Result<()> {
+        if self.process.lock().await.is_none() {
+            return Ok(());
+        }
+
+        info!("Stopping local PostgreSQL...");
+        *self.status.lock().await = PostgresStatus::Stopping;
+
+        let data_dir = self.config.data_dir();
+        let output = tokio::process::Command::new(self.config.tool("pg_ctl"))
+            .arg("stop")
+            .arg("-D")
+            .arg(&data_dir)
+            .args(["-m", "fast", "-w", "-t", "30"])
+            .stdin(Stdio::null())
+            .output()
+            .await;
+
+        match output {
+            Ok(output) if output.status.success() => {
+                if let Some(mut process) = self.process.lock().await.take() {
+                    let _ = process.child.wait().await;
+                }
+            }
+            Ok(output) => warn!("pg_ctl stop failed: {}", String::from_utf8_lossy(&output.stderr).trim()),
+            Err(e) => warn!("Failed to run pg_ctl: {}", e),
+        }
+
+        // No-op unless pg_ctl failed to stop it
+        self.kill_process().await;
+
+        *self.status.lock().await = PostgresStatus::Stopped;
+        info!("Local PostgreSQL stopped");
+        Ok(())
+    }
+
+    pub async fn restart(&mut self) -> Result<String> {
+        info!("Restarting local PostgreSQL...");
+        self.stop().await?;
+        self.start().await
+    }
+
+    /// Restart when the server died or stopped answering; true if it was restarted
+    pub async fn recover(&mut self) -> Result<bool> {
+        if !self.config.enabled || self.health_check().await {
+            return Ok(false);
+        }
+
+        warn!("Local PostgreSQL is not responding, restarting");
+        self.restart().await.map(|_| true)
+    }
+
+    pub async fn get_status(&self) -> PostgresStatus {
+        let status = self.status.lock().await;
+
+        if let PostgresStatus::Running { port, pid, .. } = *status {
+            if let Some(process) = self.process.lock().await.as_ref() {
+                let uptime = process.started_at.elapsed().as_secs();
+                return PostgresStatus::Running { port, pid, uptime };
+            }
+        }
+
+        status.clone()
+    }
+
+    /// URL of the running local database
+    pub async fn database_url(&self) -> Option<String> {
+        let port = self.process.lock().await.as_ref().map(|process| process.port)?;
+        let password = read_password(&self.config.sibling(LOCAL_DB_PASSWORD_FILE)).ok()?;
+        Some(self.url_for(port, &password))
+    }
+
+    pub async fn health_check(&self) -> bool {
+        let mut process = self.process.lock().await;
+
+        let port = match process.as_mut() {
+            Some(process) => match process.child.try_wait() {
+                Ok(None) => process.port,
+                Ok(Some(status)) => {
+                    error!("Local PostgreSQL exited: {}", status);
+                    return false;
+                }
+                Err(_) => return false,
+            },
+            None => return false,
+        };
+        drop(process);
+
+        self.is_ready(port).await
+    }
+
+    // ======================================================================
+    // PRIVATE METHODS
+    // ======================================================================
+
+    async fn try_start(&mut self) -> Result<String> {
+        self.config.validate()?;
+
+        let data_dir = self.config.data_dir();
+        let password = ensure_password(&self.config.sibling(LOCAL_DB_PASSWORD_FILE))?;
+
+        if !data_dir.join("PG_VERSION").exists() {
+            *self.status.lock().await = PostgresStatus::Initializing;
+            self.init_cluster(&data_dir, &password).await?;
+        }
+
+        // A server left behind by a crashed app still holds the cluster lock
+        self.stop_orphan(&data_dir).await;
+
+        *self.status.lock().await = PostgresStatus::Starting;
+
+        let port = self.pick_port().await?;
+        info!("Using port {} for local PostgreSQL", port);
+
+        let child = self.spawn_server(&data_dir, port)?;
+        let pid = child.id().unwrap_or_default();
+        *self.process.lock().await = Some(PostgresProcess {
+            child,
+            port,
+            started_at: Instant::now(),
+        });
+        self.last_port = Some(port);
+
+        self.wait_for_ready(port).await?;
+        self.ensure_database(port, &password).await?;
+
+        *self.status.lock().await = PostgresStatus::Running { port, pid, uptime: 0 };
+        info!("Local PostgreSQL is ready on port {}", port);
+
+        Ok(self.url_for(port, &password))
+    }
+
+    async fn init_cluster(&self, data_dir: &Path, password: &str) -> Result<()> {
+        info!("Initialising local PostgreSQL cluster at {:?}", data_dir);
+
+        let created = !data_dir.exists();
+        if let Some(parent) = data_dir.parent() {
+            utils::ensure_directory_exists(parent).map_err(|e| anyhow!(e))?;
+        }
+
+        // initdb reads the superuser password from a file; keep it only for the call
+        let pwfile = self.config.sibling("local_db.pwfile");
+        utils::write_private(&pwfile, password.as_bytes()).map_err(|e| anyhow!(e))?;
+
+        let output = tokio::process::Command::new(self.config.tool("initdb"))
+            .arg("-D")
+            .arg(data_dir)
+            .arg(format!("--username={}", self.config.user))
+            .arg(format!("--pwfile={}", pwfile.display()))
+            .args(["--auth=scram-sha-256", "--encoding=UTF8", "--locale=C"])
+            .stdin(Stdio::null())
+            .output()
+            .await;
+        let _ = std::fs::remove_file(&pwfile);
+
+        let output = output.map_err(|e| anyhow!("Failed to run initdb: {}", e))?;
+        if !output.status.success() {
+            // Leave no half-initialised cluster behind for the next attempt
+            if created {
+                let _ = std::fs::remove_dir_all(data_dir);
+            }
+            return Err(anyhow!("initdb failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+
+        Ok(())
+    }
+
+    async fn stop_orphan(&self, data_dir: &Path) {
+        let running = tokio::process::Command::new(self.config.tool("pg_ctl"))
+            .arg("status")
+            .arg("-D")
+            .arg(data_dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .await
+            .is_ok_and(|status| status.success());
+
+        if running {
+            warn!("Stopping a local PostgreSQL left over from a previous run");
+            let _ = tokio::process::Command::new(self.config.tool("pg_ctl"))
+                .arg("stop")
+                .arg("-D")
+                .arg(data_dir)
+                .args(["-m", "fast", "-w", "-t", "30"])
+                .stdin(Stdio::null())
+                .output()
+                .await;
+        }
+    }
+
+    async fn pick_port(&self) -> Result<u16> {
+        if let Some(port) = self.last_port {
+            if utils::is_port_available(port).await {
+                return Ok(port);
+            }
+        }
+
+        let (start, end) = self.config.port_range;
+        utils::find_available_port(start, end)
+            .await
+            .ok_or_else(|| anyhow!("No available port found in range {:?}", self.config.port_range))
+    }
+
+    fn spawn_server(&self, data_dir: &Path, port: u16) -> Result<Child> {
+        debug!("Spawning postgres on port {}", port);
+
+        // Unread pipes would stall the server once full, so log to a file
+        let log = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.config.sibling(LOCAL_DB_LOG_FILE))?;
+
+        let mut cmd = tokio::process::Command::new(self.config.tool("postgres"));
+        cmd.arg("-D")
+            .arg(data_dir)
+            .arg("-p")
+            .arg(port.to_string())
+            .args(["-c", "listen_addresses=127.0.0.1", "-c", "timezone=Asia/Jakarta"])
+            .stdin(Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log);
+
+        // Keep the socket next to the cluster instead of a shared /tmp
+        if cfg!(unix) {
+            cmd.arg("-k").arg(data_dir);
+        }
+
+        cmd.spawn().map_err(|e| anyhow!("Failed to spawn postgres: {}", e))
+    }
+
+    async fn wait_for_ready(&self, port: u16) -> Result<()> {
+        let start_time = Instant::now();
+
+        while start_time.elapsed() < self.startup_timeout {
+            if let Some(process) = self.process.lock().await.as_mut() {
+                if let Ok(Some(status)) = process.child.try_wait() {
+                    return Err(anyhow!("postgres exited during startup ({}), see {}", status, LOCAL_DB_LOG_FILE));
+                }
+            }
+
+            if self.is_ready(port).await {
+                return Ok(());
+            }
+            sleep(Duration::from_millis(500)).await;
+        }
+
+        Err(anyhow!("Timeout waiting for local PostgreSQL to be ready"))
+    }
+
+    async fn is_ready(&self, port: u16) -> bool {
+        tokio::process::Command::new(self.config.tool("pg_isready"))
+            .args(["-h", "127.0.0.1", "-p", &port.to_string(), "-t", "2"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .await
+            .is_ok_and(|status| status.success())
+    }
+
+    /// Create the POS database; the migration runner fills in the schema
+    async fn ensure_database(&self, port: u16, password: &str) -> Result<()> {
+        let database = &self.config.database;
+
+        let exists = self
+            .psql_query(port, password, "postgres", &format!("SELECT 1 FROM pg_database WHERE datname = '{}'", database))
+            .await?;
+        if exists != "1" {
+            info!("Creating local database {}", database);
+            self.psql_query(port, password, "postgres", &format!("CREATE DATABASE {}", database))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn psql_query(&self, port: u16, password: &str, database: &str, sql: &str) -> Result<String> {
+        let output = self
+            .psql(port, password, database)
+            .args(["-tAq", "-v", "ON_ERROR_STOP=1", "-c", sql])
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .map_err(|e| anyhow!("Failed to run psql: {}", e))?;
+
+        if !output.status.success() {
+            return Err(anyhow!("psql failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn psql(&self, port: u16, password: &str, database: &str) -> tokio::process::Command {
+        let mut cmd = tokio::process::Command::new(self.config.tool("psql"));
+        cmd.args(["-h", "127.0.0.1", "-p", &port.to_string(), "-U", &self.config.user, "-d", database])
+            .env("PGPASSWORD", password)
+            .env("PGCONNECT_TIMEOUT", "10");
+        cmd
+    }
+
+    fn url_for(&self, port: u16, password: &str) -> String {
+        format!(
+            "postgresql://{}:{}@127.0.0.1:{}/{}",
+            self.config.user, password, port, self.config.database
+        )
+    }
+
+    async fn kill_process(&mut self) {
+        if let Some(mut process) = self.process.lock().await.take() {
+            warn!("Killing local PostgreSQL with PID: {:?}", process.child.id());
+            let _ = process.child.kill().await;
+        }
+    }
+}
+
+/// Password of the local superuser, generated on first use
+fn ensure_password(path: &Path) -> Result<String> {
+    if let Ok(password) = read_password(path) {
+        return Ok(password);
+    }
+
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    let password: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    utils::write_private(path, password.as_bytes()).map_err(|e| anyhow!(e))?;
+    Ok(password)
+}
+
+fn read_password(path: &Path) -> Result<String> {
+    let password = std::fs::read_to_string(path)?.trim().to_string();
+    if password.is_empty() {
+        return Err(anyhow!("Empty local database password in {:?}", path));
+    }
+    Ok(password)
+}
+
+// ======================================================================
+// CLIENT CONNECTIONS
+// ======================================================================
+
+/// Connect to the configured database, negotiating SSL as the URL's `sslmode` asks
+/// (prefer when unset), so servers that require SSL are reachable.
+/// As with libpq's prefer/require, the server certificate is not verified.
+pub async fn connect(database_url: &str) -> Result<Client> {
+    let connector = TlsConnector::builder()
+        .danger_accept_invalid_certs(true)
+        .danger_accept_invalid_hostnames(true)
+        .build()?;
+    let (client, connection) = tokio_postgres::connect(database_url, MakeTlsConnector::new(connector)).await?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            debug!("Database connection closed: {}", e);
+        }
+    });
+    Ok(client)
+}
+
+/// Nothing answered at the address, as opposed to a server that refused us
+pub fn is_unreachable(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<tokio_postgres::Error>().is_some_and(|e| {
+        e.as_db_error().is_none() && std::error::Error::source(e).is_some_and(|source| source.is::<std::io::Error>())
+    })
+}
+
+// ======================================================================
+// DROP IMPLEMENTATION
+// ======================================================================
+
+impl Drop for EmbeddedPostgres {
+    fn drop(&mut self) {
+        // Synchronous fallback; a normal exit goes through stop(). The tokio runtime reaps the child.
+        if let Ok(mut process_guard) = self.process.try_lock() {
+            if let Some(mut process) = process_guard.take() {
+                let _ = std::process::Command::new(self.config.tool("pg_ctl"))
+                    .arg("stop")
+                    .arg("-D")
+                    .arg(self.config.data_dir())
+                    .args(["-m", "fast", "-w"])
+                    .stdin(Stdio::null())
+                    .output();
+                if !matches!(process.child.try_wait(), Ok(Some(_))) {
+                    let _ = process.child.start_kill();
+                }
+            }
+        }
+    }
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Needs PostgreSQL binaries and a non-root user, e.g. `POS_TEST_PG_BIN_DIR=/usr/lib/postgresql/15/bin`
+    #[tokio::test]
+    async fn test_embedded_postgres_lifecycle() {
+        let Ok(bin_dir) = std::env::var("POS_TEST_PG_BIN_DIR") else {
+            eprintln!("POS_TEST_PG_BIN_DIR not set; skipping");
+            return;
+        };
+
+        let root = std::env::temp_dir().join(format!("postgres-{}", uuid::Uuid::new_v4()));
+        let data_dir = root.join(LOCAL_DB_DIR);
+        let config = LocalDatabaseConfig {
+            enabled: true,
+            bin_dir: Some(PathBuf::from(bin_dir)),
+            data_dir: Some(data_dir.clone()),
+            port_range: (55500, 55600),
+            ..LocalDatabaseConfig::default()
+        };
+
+        let mut postgres = EmbeddedPostgres::new();
+        postgres.configure(config).await;
+
+        let url = postgres.start().await.unwrap();
+        assert!(url.starts_with("postgresql://pos:"));
+        assert!(postgres.health_check().await);
+        assert_eq!(postgres.database_url().await, Some(url.clone()));
+        assert!(matches!(postgres.get_status().await, PostgresStatus::Running { .. }));
+
+        let password = read_password(&root.join(LOCAL_DB_PASSWORD_FILE)).unwrap();
+        let port = postgres.last_port.unwrap();
+        let connected = postgres
+            .psql_query(port, &password, "pos_kasir", "SELECT current_database()")
+            .await
+            .unwrap();
+        assert_eq!(connected, "pos_kasir");
+
+        // A killed server is brought back on the same port with its data
+        if let Some(process) = postgres.process.lock().await.as_mut() {
+            process.child.kill().await.unwrap();
+        }
+        assert!(!postgres.health_check().await);
+        assert!(postgres.recover().await.unwrap());
+        assert_eq!(postgres.database_url().await, Some(url));
+        assert!(!postgres.recover().await.unwrap());
+
+        postgres.stop().await.unwrap();
+        assert!(!postgres.health_check().await);
+        assert!(matches!(postgres.get_status().await, PostgresStatus::Stopped));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,139 @@
+// ======================================================================
+// RECEIPT PRINTER
+// Printer struk ESC/POS (serial atau jaringan) dan laci kas
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use log::{debug, info};
+use std::{
+    io::Write,
+    net::TcpStream,
+    time::Duration,
+};
+
+// ======================================================================
+// CONSTANTS
+// ======================================================================
+
+const ESC: u8 = 0x1B;
+const GS: u8 = 0x1D;
+
+/// Pulse drawer pin 2 for 50 ms on, 500 ms off (`ESC p 0 25 250`)
+pub const DRAWER_KICK: [u8; 5] = [ESC, b'p', 0, 25, 250];
+
+/// Ports of the form `tcp://host:port` address a network printer
+const TCP_PREFIX: &str = "tcp://";
+
+// ======================================================================
+// ESC/POS ENCODING
+// ======================================================================
+
+/// Build the self-test receipt printed from the device settings page
+pub fn test_page(device_name: &str, columns: usize) -> Vec<u8> {
+    let rule = "-".repeat(columns.max(16));
+    let timestamp = chrono::Local::now().format("%d/%m/%Y %H:%M:%S");
+
+    let mut bytes = vec![ESC, b'@'];
+
+    // Centered, double height title
+    bytes.extend_from_slice(&[ESC, b'a', 1, ESC, b'!', 0x10]);
+    bytes.extend_from_slice(b"TES PRINTER\n");
+    bytes.extend_from_slice(&[ESC, b'!', 0]);
+    bytes.extend_from_slice(format!("{}\n{}\n", ascii(device_name), rule).as_bytes());
+
+    bytes.extend_from_slice(&[ESC, b'a', 0]);
+    bytes.extend_from_slice(format!("Waktu : {}\n", timestamp).as_bytes());
+    bytes.extend_from_slice(format!("Lebar : {} kolom\n", columns).as_bytes());
+    bytes.extend_from_slice(format!("{}\n", crate::utils::format_rupiah(1_234_500)).as_bytes());
+    bytes.extend_from_slice(format!("{}\n", rule).as_bytes());
+
+    bytes.extend_from_slice(&[ESC, b'a', 1]);
+    bytes.extend_from_slice(b"Printer siap digunakan\n\n\n");
+
+    // Partial cut after feeding past the cutter
+    bytes.extend_from_slice(&[GS, b'V', 66, 0]);
+    bytes
+}
+
+fn ascii(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_ascii() && !c.is_ascii_control() { c } else { '?' })
+        .collect()
+}
+
+// ======================================================================
+// TRANSPORT
+// ======================================================================
+
+/// Send raw ESC/POS bytes to a serial port or `tcp://host:port`
+pub fn send_raw(port: &str, baud_rate: u32, bytes: &[u8]) -> Result<()> {
+    debug!("Sending {} bytes to printer {}", bytes.len(), port);
+
+    if let Some(address) = port.strip_prefix(TCP_PREFIX) {
+        let address = address
+            .parse()
+            .map_err(|e| anyhow!("Invalid printer address {}: {}", address, e))?;
+        let mut stream = TcpStream::connect_timeout(&address, Duration::from_secs(3))
+            .map_err(|e| anyhow!("Failed to connect to printer {}: {}", port, e))?;
+        stream.set_write_timeout(Some(Duration::from_secs(5)))?;
+        stream.write_all(bytes)?;
+        stream.flush()?;
+    } else {
+        let mut serial = serialport::new(port, baud_rate)
+            .timeout(Duration::from_secs(5))
+            .open()
+            .map_err(|e| anyhow!("Failed to open printer port {}: {}", port, e))?;
+        serial.write_all(bytes)?;
+        serial.flush()?;
+    }
+
+    Ok(())
+}
+
+pub fn print_test_page(port: &str, baud_rate: u32, device_name: &str, columns: usize) -> Result<()> {
+    info!("Printing test page on {}", port);
+    send_raw(port, baud_rate, &test_page(device_name, columns))
+}
+
+/// Open the cash drawer wired to the printer's DK port
+pub fn kick_drawer(port: &str, baud_rate: u32) -> Result<()> {
+    info!("Kicking cash drawer on {}", port);
+    send_raw(port, baud_rate, &DRAWER_KICK)
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Read, net::TcpListener};
+
+    #[test]
+    fn test_drawer_kick_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = format!("tcp://{}", listener.local_addr().unwrap());
+
+        let reader = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut received = Vec::new();
+            stream.read_to_end(&mut received).unwrap();
+            received
+        });
+
+        kick_drawer(&port, 9600).unwrap();
+        assert_eq!(reader.join().unwrap(), DRAWER_KICK);
+    }
+
+    #[test]
+    fn test_test_page_layout() {
+        let page = test_page("Kasir Depan é", 32);
+        let text = String::from_utf8_lossy(&page);
+
+        assert!(page.starts_with(&[ESC, b'@']));
+        assert!(page.ends_with(&[GS, b'V', 66, 0]));
+        assert!(text.contains("Kasir Depan ?"));
+        assert!(text.contains(&"-".repeat(32)));
+    }
+}
@@ -0,0 +1,542 @@
+// ======================================================================
+// UPDATE ROLLBACK
+// Kembali ke versi sebelumnya bila versi baru gagal berjalan
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    audit::{AuditEvent, AuditOutcome},
+    updater, utils, AppState,
+};
+
+/// Update history inside the updates directory
+pub const ROLLBACK_FILE: &str = "rollback.json";
+
+/// Installers of the running and the previous version
+const KEPT_DIR: &str = "installed";
+
+/// A new version is trusted once its sidecar has stayed healthy this long
+pub const PROBATION: Duration = Duration::from_secs(5 * 60);
+
+/// How often the sidecar is checked during probation
+const PROBATION_POLL: Duration = Duration::from_secs(30);
+
+/// Launches of a new version that never passed probation before rolling back
+const MAX_UNCONFIRMED_LAUNCHES: u32 = 3;
+
+/// Reported failures kept in the history
+const MAX_FAILURES: usize = 20;
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bundle {
+    pub version: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingUpdate {
+    pub version: String,
+    pub from_version: String,
+    pub bundle: PathBuf,
+    pub installed_at: String,
+    /// Launches of `version` so far, counting the current one
+    pub launches: u32,
+}
+
+/// Sent upstream by the sync engine until the server accepts it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateFailure {
+    /// Idempotency key for the report
+    pub id: String,
+    pub version: String,
+    /// `None` when no installer of the previous version was kept
+    pub rolled_back_to: Option<String>,
+    pub reason: String,
+    pub failed_at: String,
+    #[serde(default)]
+    pub reported: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct RollbackState {
+    /// Reinstalls the version that ran before the last update
+    previous: Option<Bundle>,
+    /// Handed to the installer and not yet through probation
+    pending: Option<PendingUpdate>,
+    /// Installer of the running version; becomes `previous` on the next update
+    current: Option<Bundle>,
+    failures: Vec<UpdateFailure>,
+}
+
+/// What the last update means for this launch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Launch {
+    Stable,
+    /// First launches of a new version; a crash loop rolls it back
+    Probation,
+    /// The new version keeps dying before passing probation
+    Failed(String),
+}
+
+// ======================================================================
+// ROLLBACK STORE
+// ======================================================================
+
+pub struct RollbackStore {
+    dir: PathBuf,
+    state: parking_lot::Mutex<RollbackState>,
+}
+
+impl RollbackStore {
+    /// History in `dir`; a missing or unreadable file starts empty
+    pub fn open(dir: PathBuf) -> Self {
+        let path = dir.join(ROLLBACK_FILE);
+        let state = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                warn!("Invalid rollback history {:?}: {}", path, e);
+                RollbackState::default()
+            }),
+            Err(_) => RollbackState::default(),
+        };
+
+        Self {
+            dir,
+            state: parking_lot::Mutex::new(state),
+        }
+    }
+
+    /// Whether an update installed now could be undone: an installer of `running` is kept,
+    /// or `running` is an AppImage that `prepare` can copy
+    pub fn has_target(&self, running: &str) -> bool {
+        let state = self.state.lock();
+        let kept = state
+            .current
+            .as_ref()
+            .is_some_and(|current| current.version == running && current.path.exists());
+        kept || std::env::var_os("APPIMAGE").is_some()
+    }
+
+    /// Keep `bundle` as the installer of the running version, e.g. one fetched from the feed
+    /// after a manual install left none behind
+    pub fn keep_installer(&self, version: &str, bundle: &Path) -> Result<()> {
+        let mut state = self.state.lock();
+        let kept = self
+            .keep(bundle, version)
+            .ok_or_else(|| anyhow!("Failed to keep the installer of {}", version))?;
+        if let Some(stale) = state.current.replace(kept) {
+            if state.current.as_ref() != Some(&stale) && state.previous.as_ref() != Some(&stale) {
+                let _ = std::fs::remove_file(&stale.path);
+            }
+        }
+        self.save(&state)
+    }
+
+    /// Keep an installer of `running` and put `version` on probation; call right before installing.
+    /// Refused when nothing could be reinstalled should `version` fail.
+    pub fn prepare(&self, version: &str, bundle: &Path, running: &str) -> Result<()> {
+        let mut state = self.state.lock();
+
+        let previous = match state.current.take() {
+            Some(current) if current.version == running && current.path.exists() => Some(current),
+            stale => {
+                if let Some(stale) = stale {
+                    let _ = std::fs::remove_file(&stale.path);
+                }
+                self.keep_running_appimage(running)
+            }
+        };
+        let Some(previous) = previous else {
+            self.save(&state)?;
+            return Err(anyhow!("No installer of {} is kept, so update {} could not be rolled back", running, version));
+        };
+        info!("Update {} can roll back to {:?}", version, previous.path);
+        if let Some(stale) = state.previous.take() {
+            if stale != previous {
+                let _ = std::fs::remove_file(&stale.path);
+            }
+        }
+
+        state.previous = Some(previous);
+        state.pending = Some(PendingUpdate {
+            version: version.to_string(),
+            from_version: running.to_string(),
+            bundle: bundle.to_path_buf(),
+            installed_at: chrono::Utc::now().to_rfc3339(),
+            launches: 0,
+        });
+        self.save(&state)
+    }
+
+    /// The installer never ran; the running version stays current
+    pub fn cancel(&self) {
+        let mut state = self.state.lock();
+        if state.pending.take().is_some() {
+            state.current = state.previous.take();
+            if let Err(e) = self.save(&state) {
+                error!("{}", e);
+            }
+        }
+    }
+
+    /// Count this launch against the update on probation
+    pub fn begin_launch(&self, running: &str) -> Launch {
+        let mut state = self.state.lock();
+        let Some(pending) = state.pending.as_mut() else {
+            return Launch::Stable;
+        };
+
+        if pending.version != running {
+            warn!("Update {} was not applied, still running {}", pending.version, running);
+            drop(state);
+            self.cancel();
+            return Launch::Stable;
+        }
+
+        pending.launches += 1;
+        let launches = pending.launches;
+        if let Err(e) = self.save(&state) {
+            error!("{}", e);
+        }
+
+        if launches > MAX_UNCONFIRMED_LAUNCHES {
+            Launch::Failed(format!("{} launches without a healthy sidecar", launches - 1))
+        } else {
+            info!("Update {} on probation (launch {})", running, launches);
+            Launch::Probation
+        }
+    }
+
+    /// The app is quitting normally; a launch that ends cleanly does not count against probation
+    pub fn end_launch(&self, running: &str) {
+        let mut state = self.state.lock();
+        let Some(pending) = state.pending.as_mut().filter(|pending| pending.version == running) else {
+            return;
+        };
+        pending.launches = pending.launches.saturating_sub(1);
+        if let Err(e) = self.save(&state) {
+            error!("{}", e);
+        }
+    }
+
+    pub fn on_probation(&self) -> bool {
+        self.state.lock().pending.is_some()
+    }
+
+    /// The new version passed probation; keep its installer for the next rollback
+    pub fn confirm(&self) -> Result<()> {
+        let mut state = self.state.lock();
+        let Some(pending) = state.pending.take() else {
+            return Ok(());
+        };
+
+        state.current = self.keep(&pending.bundle, &pending.version);
+        info!("Update {} confirmed", pending.version);
+        self.save(&state)
+    }
+
+    /// Record the update on probation as failed and hand back the installer to reinstall
+    pub fn fail(&self, reason: &str) -> Result<Bundle> {
+        let mut state = self.state.lock();
+        let pending = state
+            .pending
+            .take()
+            .ok_or_else(|| anyhow!("No update is on probation"))?;
+        let previous = state.previous.take().filter(|previous| previous.path.exists());
+
+        state.failures.push(UpdateFailure {
+            id: uuid::Uuid::new_v4().to_string(),
+            version: pending.version.clone(),
+            rolled_back_to: previous.as_ref().map(|previous| previous.version.clone()),
+            reason: reason.to_string(),
+            failed_at: chrono::Utc::now().to_rfc3339(),
+            reported: false,
+        });
+        while state.failures.len() > MAX_FAILURES {
+            match state.failures.iter().position(|failure| failure.reported) {
+                Some(index) => state.failures.remove(index),
+                None => break,
+            };
+        }
+
+        let _ = std::fs::remove_file(&pending.bundle);
+        state.current = previous.clone();
+        self.save(&state)?;
+
+        previous.ok_or_else(|| anyhow!("No installer of {} was kept to roll back to", pending.from_version))
+    }
+
+    /// A version that was rolled back here is never installed again
+    pub fn is_failed(&self, version: &str) -> bool {
+        self.state.lock().failures.iter().any(|failure| failure.version == version)
+    }
+
+    pub fn unreported(&self) -> Vec<UpdateFailure> {
+        self.state
+            .lock()
+            .failures
+            .iter()
+            .filter(|failure| !failure.reported)
+            .cloned()
+            .collect()
+    }
+
+    pub fn mark_reported(&self, id: &str) {
+        let mut state = self.state.lock();
+        if let Some(failure) = state.failures.iter_mut().find(|failure| failure.id == id) {
+            failure.reported = true;
+            if let Err(e) = self.save(&state) {
+                error!("{}", e);
+            }
+        }
+    }
+
+    // ======================================================================
+    // PRIVATE METHODS
+    // ======================================================================
+
+    /// Move an installer into the kept directory, as `<version>-<name>`
+    fn keep(&self, bundle: &Path, version: &str) -> Option<Bundle> {
+        let name = bundle.file_name()?.to_string_lossy();
+        let kept = self.dir.join(KEPT_DIR);
+        let target = kept.join(utils::sanitize_filename(&format!("{}-{}", version, name)));
+
+        let moved = utils::ensure_directory_exists(&kept)
+            .map_err(|e| anyhow!(e))
+            .and_then(|_| match std::fs::rename(bundle, &target) {
+                Ok(()) => Ok(()),
+                Err(_) => std::fs::copy(bundle, &target).map(|_| ()).map_err(|e| anyhow!(e)),
+            });
+        match moved {
+            Ok(()) => Some(Bundle {
+                version: version.to_string(),
+                path: target,
+            }),
+            Err(e) => {
+                warn!("Failed to keep installer {:?}: {}", bundle, e);
+                None
+            }
+        }
+    }
+
+    /// An AppImage is its own installer, so the running one can always be kept
+    fn keep_running_appimage(&self, running: &str) -> Option<Bundle> {
+        let appimage = PathBuf::from(std::env::var_os("APPIMAGE")?);
+        let name = appimage.file_name()?.to_string_lossy();
+        let kept = self.dir.join(KEPT_DIR);
+        let target = kept.join(utils::sanitize_filename(&format!("{}-{}", running, name)));
+
+        let copied = utils::ensure_directory_exists(&kept)
+            .map_err(|e| anyhow!(e))
+            .and_then(|_| std::fs::copy(&appimage, &target).map_err(|e| anyhow!(e)));
+        match copied {
+            Ok(_) => Some(Bundle {
+                version: running.to_string(),
+                path: target,
+            }),
+            Err(e) => {
+                warn!("Failed to keep {:?}: {}", appimage, e);
+                None
+            }
+        }
+    }
+
+    fn save(&self, state: &RollbackState) -> Result<()> {
+        utils::ensure_directory_exists(&self.dir).map_err(|e| anyhow!(e))?;
+        utils::write_file_atomic(&self.dir.join(ROLLBACK_FILE), &serde_json::to_vec_pretty(state)?)
+            .map_err(|e| anyhow!("Failed to save rollback history: {}", e))
+    }
+}
+
+// ======================================================================
+// ROLLBACK
+// ======================================================================
+
+/// Reinstall the previous version and relaunch; without a kept installer the sidecar is restarted
+pub async fn roll_back(app: &AppHandle, state: &AppState, reason: &str) {
+    let version = env!("CARGO_PKG_VERSION");
+    error!("Rolling back update {}: {}", version, reason);
+
+    let _ = state.watchdog.lock().await.stop().await;
+    let _ = state.sidecar_manager.lock().await.stop().await;
+
+    let result = match state.rollback.fail(reason) {
+        Ok(bundle) => {
+            let path = bundle.path.clone();
+            tokio::task::spawn_blocking(move || updater::install_bundle(&path))
+                .await
+                .map_err(|e| anyhow!(e))
+                .and_then(|result| result)
+                .map(|_| bundle)
+        }
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(bundle) => {
+            state.audit.record(AuditEvent::new(
+                "rollback_update",
+                AuditOutcome::Allowed,
+                serde_json::json!({ "version": version, "rolled_back_to": bundle.version, "reason": reason }),
+            ));
+            info!("Reinstalling {} from {:?}", bundle.version, bundle.path);
+            updater::relaunch(app);
+        }
+        Err(e) => {
+            error!("Rollback failed: {}", e);
+            state.audit.record(AuditEvent::new(
+                "rollback_update",
+                AuditOutcome::Allowed,
+                serde_json::json!({ "version": version, "reason": reason, "error": e.to_string() }),
+            ));
+            if let Err(e) = state.sidecar_manager.lock().await.start().await {
+                error!("Failed to restart sidecar after a failed rollback: {}", e);
+            }
+        }
+    }
+}
+
+/// Confirm the update on probation once the sidecar has passed every health check for
+/// `PROBATION`; a failed check starts the wait over
+pub async fn confirm_after_probation(app: AppHandle) {
+    let mut healthy_since = None;
+    let mut interval = tokio::time::interval(PROBATION_POLL);
+    loop {
+        interval.tick().await;
+
+        let Some(state) = app.try_state::<AppState>() else {
+            continue;
+        };
+        if !state.rollback.on_probation() {
+            return;
+        }
+        let healthy = state.sidecar_manager.lock().await.health_check().await;
+        if !probation_passed(&mut healthy_since, healthy, Instant::now()) {
+            continue;
+        }
+        match state.rollback.confirm() {
+            Ok(()) => return,
+            Err(e) => error!("{}", e),
+        }
+    }
+}
+
+/// Track the current healthy streak; true once it has lasted `PROBATION`
+fn probation_passed(healthy_since: &mut Option<Instant>, healthy: bool, now: Instant) -> bool {
+    if !healthy {
+        if healthy_since.take().is_some() {
+            warn!("Sidecar unhealthy during probation; waiting another {:?}", PROBATION);
+        }
+        return false;
+    }
+    now.duration_since(*healthy_since.get_or_insert(now)) >= PROBATION
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pos-rollback-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn bundle(dir: &Path, name: &str) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, name).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_confirmed_update_becomes_the_rollback_target() {
+        let dir = scratch_dir();
+        let store = RollbackStore::open(dir.clone());
+
+        // Nothing kept for a manually installed 1.0.0, so 1.1.0 waits for its installer
+        assert!(!store.has_target("1.0.0"));
+        assert!(store.prepare("1.1.0", &bundle(&dir, "pos-1.1.0.msi"), "1.0.0").is_err());
+        assert!(!store.on_probation());
+        store.keep_installer("1.0.0", &bundle(&dir, "pos-1.0.0.msi")).unwrap();
+        assert!(store.has_target("1.0.0"));
+
+        store.prepare("1.1.0", &bundle(&dir, "pos-1.1.0.msi"), "1.0.0").unwrap();
+        assert_eq!(store.begin_launch("1.1.0"), Launch::Probation);
+        store.confirm().unwrap();
+        assert!(!store.on_probation());
+
+        store.prepare("1.2.0", &bundle(&dir, "pos-1.2.0.msi"), "1.1.0").unwrap();
+        let store = RollbackStore::open(dir.clone());
+        assert_eq!(store.begin_launch("1.2.0"), Launch::Probation);
+
+        let previous = store.fail("crash loop").unwrap();
+        assert_eq!(previous.version, "1.1.0");
+        assert_eq!(std::fs::read(&previous.path).unwrap(), b"pos-1.1.0.msi");
+        assert!(!dir.join("pos-1.2.0.msi").exists());
+        assert!(store.is_failed("1.2.0") && !store.is_failed("1.1.0"));
+
+        let failures = store.unreported();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].rolled_back_to.as_deref(), Some("1.1.0"));
+        store.mark_reported(&failures[0].id);
+        assert!(RollbackStore::open(dir).unreported().is_empty());
+    }
+
+    #[test]
+    fn test_unconfirmed_launches_fail_the_update() {
+        let dir = scratch_dir();
+        let store = RollbackStore::open(dir.clone());
+        assert_eq!(store.begin_launch("1.0.0"), Launch::Stable);
+        store.keep_installer("1.0.0", &bundle(&dir, "pos-1.0.0.msi")).unwrap();
+
+        // The installer never ran: the old version keeps going
+        store.prepare("1.1.0", &bundle(&dir, "pos-1.1.0.msi"), "1.0.0").unwrap();
+        assert_eq!(store.begin_launch("1.0.0"), Launch::Stable);
+        assert!(!store.on_probation());
+
+        store.prepare("1.1.0", &bundle(&dir, "pos-1.1.0.msi"), "1.0.0").unwrap();
+        // Quitting normally during probation does not use up a launch
+        for _ in 0..5 {
+            assert_eq!(store.begin_launch("1.1.0"), Launch::Probation);
+            store.end_launch("1.1.0");
+        }
+        for _ in 0..MAX_UNCONFIRMED_LAUNCHES {
+            assert_eq!(store.begin_launch("1.1.0"), Launch::Probation);
+        }
+        assert!(matches!(store.begin_launch("1.1.0"), Launch::Failed(_)));
+
+        // Still reported and never offered again, even when the kept installer has gone missing
+        std::fs::remove_dir_all(dir.join(KEPT_DIR)).unwrap();
+        assert!(store.fail("crash loop").is_err());
+        assert!(store.is_failed("1.1.0"));
+        assert_eq!(store.unreported()[0].rolled_back_to, None);
+    }
+
+    #[test]
+    fn test_probation_needs_an_unbroken_healthy_streak() {
+        let start = Instant::now();
+        let mut healthy_since = None;
+        assert!(!probation_passed(&mut healthy_since, true, start));
+        assert!(!probation_passed(&mut healthy_since, true, start + PROBATION / 2));
+        assert!(!probation_passed(&mut healthy_since, false, start + PROBATION / 2));
+        assert!(!probation_passed(&mut healthy_since, true, start + PROBATION));
+        assert!(!probation_passed(&mut healthy_since, true, start + PROBATION * 3 / 2));
+        assert!(probation_passed(&mut healthy_since, true, start + PROBATION * 2));
+    }
+}
@@ -0,0 +1,653 @@
+// ======================================================================
+// WEIGHING SCALE
+// Integrasi timbangan serial untuk produk curah/sembako dan barcode berat
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use log::{debug, error, info, warn};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serialport::SerialPort;
+use std::{
+    collections::VecDeque,
+    io::{ErrorKind, Read, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScaleProtocol {
+    /// The scale streams a weight line continuously
+    #[default]
+    Continuous,
+    /// The scale answers one weight line per request command
+    RequestResponse,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScaleFlag {
+    Stable,
+    Unstable,
+    Overload,
+}
+
+/// One decoded weight line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawWeight {
+    pub grams: i64,
+    /// Status reported by the scale itself, if its format carries one
+    pub flag: Option<ScaleFlag>,
+}
+
+/// Payload of the `scale://weight` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeightReading {
+    pub grams: i64,
+    pub kilograms: f64,
+    pub stable: bool,
+    pub overload: bool,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ScaleStatus {
+    Closed,
+    Open {
+        port: String,
+        protocol: ScaleProtocol,
+        last_reading: Option<WeightReading>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct ScaleConfig {
+    pub port: String,
+    pub baud_rate: u32,
+    pub protocol: ScaleProtocol,
+    pub request_command: Vec<u8>,
+    pub tare_command: Vec<u8>,
+    pub zero_command: Vec<u8>,
+    pub poll_interval: Duration,
+    /// Readings that must agree before a weight counts as stable
+    pub stable_samples: usize,
+    pub stable_tolerance_grams: i64,
+}
+
+impl Default for ScaleConfig {
+    fn default() -> Self {
+        Self {
+            port: String::new(),
+            baud_rate: 9600,
+            protocol: ScaleProtocol::Continuous,
+            request_command: b"W\r\n".to_vec(),
+            tare_command: b"T\r\n".to_vec(),
+            zero_command: b"Z\r\n".to_vec(),
+            poll_interval: Duration::from_millis(200),
+            stable_samples: 4,
+            stable_tolerance_grams: 2,
+        }
+    }
+}
+
+// ======================================================================
+// WEIGHT PARSING
+// ======================================================================
+
+/// Parse the weight strings sent by common retail scales, e.g.
+/// `ST,GS,+  1.234kg`, `US,NT,-0.010 kg`, `OL,GS,+ -.--- kg` or a bare `0.450kg`.
+pub fn parse_weight_line(line: &str) -> Option<RawWeight> {
+    let line = line.trim_matches(|c: char| c.is_control() || c.is_whitespace());
+    if line.is_empty() {
+        return None;
+    }
+
+    let upper = line.to_ascii_uppercase();
+    let flag = if upper.starts_with("ST") {
+        Some(ScaleFlag::Stable)
+    } else if upper.starts_with("US") {
+        Some(ScaleFlag::Unstable)
+    } else if upper.starts_with("OL") {
+        Some(ScaleFlag::Overload)
+    } else {
+        None
+    };
+
+    if flag == Some(ScaleFlag::Overload) {
+        return Some(RawWeight { grams: 0, flag });
+    }
+
+    // The weight is the last field; status fields are comma separated
+    let field = upper.rsplit(',').next().unwrap_or(&upper).trim();
+    let unit_start = field
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(field.len());
+    let (number, unit) = field.split_at(unit_start);
+    let number: String = number.chars().filter(|c| !c.is_whitespace()).collect();
+    let value: f64 = number.parse().ok()?;
+
+    let grams = match unit.trim() {
+        "KG" | "" => value * 1000.0,
+        "G" => value,
+        "LB" => value * 453.59237,
+        "OZ" => value * 28.349523125,
+        other => {
+            debug!("Unknown scale unit {:?}", other);
+            return None;
+        }
+    };
+
+    Some(RawWeight {
+        grams: grams.round() as i64,
+        flag,
+    })
+}
+
+/// Software stability check on top of the scale's own ST/US flag
+#[derive(Debug)]
+pub struct StabilityFilter {
+    samples: VecDeque<RawWeight>,
+    required: usize,
+    tolerance: i64,
+}
+
+impl StabilityFilter {
+    pub fn new(required: usize, tolerance: i64) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(required),
+            required: required.max(1),
+            tolerance,
+        }
+    }
+
+    pub fn push(&mut self, sample: RawWeight) -> WeightReading {
+        if self.samples.len() == self.required {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+
+        let overload = sample.flag == Some(ScaleFlag::Overload);
+        let min = self.samples.iter().map(|s| s.grams).min().unwrap_or(0);
+        let max = self.samples.iter().map(|s| s.grams).max().unwrap_or(0);
+        let stable = !overload
+            && self.samples.len() == self.required
+            && max - min <= self.tolerance
+            && self
+                .samples
+                .iter()
+                .all(|s| s.flag != Some(ScaleFlag::Unstable));
+
+        WeightReading {
+            grams: sample.grams,
+            kilograms: sample.grams as f64 / 1000.0,
+            stable,
+            overload,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.samples.clear();
+    }
+}
+
+// ======================================================================
+// EMBEDDED BARCODES
+// ======================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EmbeddedValueKind {
+    Weight,
+    Price,
+}
+
+/// Store-defined layout of EAN-13 in-store barcodes (prefix 20–29):
+/// `PP IIIII VVVVV C` with a configurable item/value split.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddedBarcodeLayout {
+    pub item_digits: usize,
+    pub weight_prefixes: Vec<u8>,
+    pub price_prefixes: Vec<u8>,
+    /// Multiplier applied to the encoded price, e.g. 10 when the last zero is dropped
+    pub price_multiplier: i64,
+}
+
+impl Default for EmbeddedBarcodeLayout {
+    fn default() -> Self {
+        Self {
+            item_digits: 5,
+            weight_prefixes: vec![20, 21, 22, 23, 24],
+            price_prefixes: vec![25, 26, 27, 28, 29],
+            price_multiplier: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbeddedBarcode {
+    pub prefix: u8,
+    pub item_code: String,
+    pub kind: EmbeddedValueKind,
+    pub weight_grams: Option<i64>,
+    pub price: Option<i64>,
+}
+
+/// Split a price/weight-embedded EAN-13 into product code plus value
+pub fn parse_embedded_barcode(code: &str, layout: &EmbeddedBarcodeLayout) -> Option<EmbeddedBarcode> {
+    let code = code.trim();
+    if code.len() != 13 || !crate::scanner::is_valid_gtin(code) {
+        return None;
+    }
+
+    let prefix: u8 = code[..2].parse().ok()?;
+    let kind = if layout.weight_prefixes.contains(&prefix) {
+        EmbeddedValueKind::Weight
+    } else if layout.price_prefixes.contains(&prefix) {
+        EmbeddedValueKind::Price
+    } else {
+        return None;
+    };
+
+    let item_end = 2 + layout.item_digits;
+    if item_end >= 12 {
+        return None;
+    }
+
+    let item_code = code[2..item_end].to_string();
+    let value: i64 = code[item_end..12].parse().ok()?;
+
+    let (weight_grams, price) = match kind {
+        EmbeddedValueKind::Weight => (Some(value), None),
+        EmbeddedValueKind::Price => (None, Some(value * layout.price_multiplier)),
+    };
+
+    Some(EmbeddedBarcode {
+        prefix,
+        item_code,
+        kind,
+        weight_grams,
+        price,
+    })
+}
+
+// ======================================================================
+// SCALE SERVICE
+// ======================================================================
+
+#[derive(Debug, Default)]
+struct ScaleShared {
+    open: bool,
+    last_reading: Option<WeightReading>,
+    error: Option<String>,
+}
+
+pub struct ScaleService {
+    config: Option<ScaleConfig>,
+    shared: Arc<Mutex<ScaleShared>>,
+    writer: Option<Arc<Mutex<Box<dyn SerialPort>>>>,
+    stop_flag: Arc<AtomicBool>,
+    reader: Option<JoinHandle<()>>,
+    weight_callback: Option<Arc<dyn Fn(WeightReading) + Send + Sync>>,
+}
+
+impl ScaleService {
+    pub fn new() -> Self {
+        Self {
+            config: None,
+            shared: Arc::new(Mutex::new(ScaleShared::default())),
+            writer: None,
+            stop_flag: Arc::new(AtomicBool::new(false)),
+            reader: None,
+            weight_callback: None,
+        }
+    }
+
+    /// Register the handler invoked whenever the reported weight changes
+    pub fn set_weight_callback<F>(&mut self, callback: F)
+    where
+        F: Fn(WeightReading) + Send + Sync + 'static,
+    {
+        self.weight_callback = Some(Arc::new(callback));
+    }
+
+    /// Open the scale port and start reading weights
+    pub fn open(&mut self, config: ScaleConfig) -> Result<()> {
+        info!("Opening scale on {} ({:?})", config.port, config.protocol);
+
+        let port = serialport::new(&config.port, config.baud_rate)
+            .timeout(Duration::from_millis(50))
+            .open()
+            .map_err(|e| anyhow!("Failed to open scale port {}: {}", config.port, e))?;
+
+        self.open_with_port(config, port)
+    }
+
+    /// Start reading from an already opened port
+    pub fn open_with_port(&mut self, config: ScaleConfig, port: Box<dyn SerialPort>) -> Result<()> {
+        if self.reader.is_some() {
+            self.close();
+        }
+
+        let writer = Arc::new(Mutex::new(
+            port.try_clone()
+                .map_err(|e| anyhow!("Failed to clone scale port: {}", e))?,
+        ));
+
+        *self.shared.lock() = ScaleShared {
+            open: true,
+            ..ScaleShared::default()
+        };
+        self.stop_flag.store(false, Ordering::SeqCst);
+
+        let shared = Arc::clone(&self.shared);
+        let stop_flag = Arc::clone(&self.stop_flag);
+        let callback = self.weight_callback.clone();
+        let loop_writer = Arc::clone(&writer);
+        let loop_config = config.clone();
+
+        let handle = std::thread::Builder::new()
+            .name("scale-reader".to_string())
+            .spawn(move || Self::reader_loop(port, loop_writer, loop_config, shared, stop_flag, callback))
+            .map_err(|e| anyhow!("Failed to spawn scale thread: {}", e))?;
+
+        self.reader = Some(handle);
+        self.writer = Some(writer);
+        self.config = Some(config);
+        Ok(())
+    }
+
+    pub fn close(&mut self) {
+        self.stop_flag.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.reader.take() {
+            if handle.join().is_err() {
+                warn!("Scale reader thread panicked");
+            }
+        }
+        self.writer = None;
+        self.config = None;
+        // A weight from before the close must not be handed out as current
+        *self.shared.lock() = ScaleShared::default();
+    }
+
+    pub fn tare(&self) -> Result<()> {
+        let command = self.require_config()?.tare_command.clone();
+        self.send(&command)
+    }
+
+    pub fn zero(&self) -> Result<()> {
+        let command = self.require_config()?.zero_command.clone();
+        self.send(&command)
+    }
+
+    /// Handle for waiting on a stable weight without holding the service lock
+    pub fn weight_handle(&self) -> WeightHandle {
+        WeightHandle {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+
+    pub fn get_status(&self) -> ScaleStatus {
+        let shared = self.shared.lock();
+
+        if let Some(message) = &shared.error {
+            return ScaleStatus::Error {
+                message: message.clone(),
+            };
+        }
+
+        match &self.config {
+            Some(config) => ScaleStatus::Open {
+                port: config.port.clone(),
+                protocol: config.protocol,
+                last_reading: shared.last_reading.clone(),
+            },
+            None => ScaleStatus::Closed,
+        }
+    }
+
+    // ======================================================================
+    // PRIVATE METHODS
+    // ======================================================================
+
+    fn require_config(&self) -> Result<&ScaleConfig> {
+        self.config.as_ref().ok_or_else(|| anyhow!("Scale is not open"))
+    }
+
+    fn send(&self, command: &[u8]) -> Result<()> {
+        let writer = self.writer.as_ref().ok_or_else(|| anyhow!("Scale is not open"))?;
+        let mut port = writer.lock();
+        port.write_all(command)
+            .and_then(|_| port.flush())
+            .map_err(|e| anyhow!("Failed to write to scale: {}", e))
+    }
+
+    fn reader_loop(
+        mut port: Box<dyn SerialPort>,
+        writer: Arc<Mutex<Box<dyn SerialPort>>>,
+        config: ScaleConfig,
+        shared: Arc<Mutex<ScaleShared>>,
+        stop_flag: Arc<AtomicBool>,
+        callback: Option<Arc<dyn Fn(WeightReading) + Send + Sync>>,
+    ) {
+        let mut filter = StabilityFilter::new(config.stable_samples, config.stable_tolerance_grams);
+        let mut line = Vec::new();
+        let mut buf = [0u8; 128];
+        let mut last_poll: Option<Instant> = None;
+        let mut last_emitted: Option<(i64, bool)> = None;
+
+        while !stop_flag.load(Ordering::SeqCst) {
+            if config.protocol == ScaleProtocol::RequestResponse
+                && !matches!(last_poll, Some(at) if at.elapsed() < config.poll_interval)
+            {
+                let mut port = writer.lock();
+                if let Err(e) = port.write_all(&config.request_command) {
+                    warn!("Failed to poll scale: {}", e);
+                }
+                last_poll = Some(Instant::now());
+            }
+
+            let n = match port.read(&mut buf) {
+                Ok(n) => n,
+                Err(e) if e.kind() == ErrorKind::TimedOut || e.kind() == ErrorKind::WouldBlock => 0,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    error!("Scale port {} read failed: {}", config.port, e);
+                    shared.lock().error = Some(format!("Read failed: {}", e));
+                    break;
+                }
+            };
+
+            for byte in &buf[..n] {
+                if *byte != b'\r' && *byte != b'\n' {
+                    if line.len() < 64 {
+                        line.push(*byte);
+                    }
+                    continue;
+                }
+
+                let text = String::from_utf8_lossy(&line).to_string();
+                line.clear();
+
+                let Some(raw) = parse_weight_line(&text) else {
+                    continue;
+                };
+
+                let reading = filter.push(raw);
+                let key = (reading.grams, reading.stable);
+                shared.lock().last_reading = Some(reading.clone());
+
+                if last_emitted != Some(key) {
+                    last_emitted = Some(key);
+                    if let Some(callback) = &callback {
+                        callback(reading);
+                    }
+                }
+            }
+        }
+
+        debug!("Scale reader loop ended");
+    }
+}
+
+/// Cloneable view on the latest scale reading
+#[derive(Clone)]
+pub struct WeightHandle {
+    shared: Arc<Mutex<ScaleShared>>,
+}
+
+impl WeightHandle {
+    /// Wait until the scale reports a stable weight
+    pub async fn read_stable(&self, wait: Duration) -> Result<WeightReading> {
+        let deadline = Instant::now() + wait;
+
+        loop {
+            {
+                let shared = self.shared.lock();
+                if !shared.open {
+                    return Err(anyhow!("Scale is not open"));
+                }
+                if let Some(message) = &shared.error {
+                    return Err(anyhow!("Scale error: {}", message));
+                }
+                if let Some(reading) = shared.last_reading.as_ref() {
+                    if reading.overload {
+                        return Err(anyhow!("Scale overload"));
+                    }
+                    if reading.stable {
+                        return Ok(reading.clone());
+                    }
+                }
+            }
+
+            if Instant::now() >= deadline {
+                return Err(anyhow!("Timeout waiting for a stable weight"));
+            }
+
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+}
+
+// ======================================================================
+// DROP IMPLEMENTATION
+// ======================================================================
+
+impl Drop for ScaleService {
+    fn drop(&mut self) {
+        self.stop_flag.store(true, Ordering::SeqCst);
+    }
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_weight_line() {
+        assert_eq!(
+            parse_weight_line("ST,GS,+  1.234kg\r\n"),
+            Some(RawWeight { grams: 1234, flag: Some(ScaleFlag::Stable) })
+        );
+        assert_eq!(
+            parse_weight_line("US,NT,-0.010 kg"),
+            Some(RawWeight { grams: -10, flag: Some(ScaleFlag::Unstable) })
+        );
+        assert_eq!(
+            parse_weight_line("  450 g"),
+            Some(RawWeight { grams: 450, flag: None })
+        );
+        assert_eq!(
+            parse_weight_line("OL,GS,+ -.--- kg").map(|w| w.flag),
+            Some(Some(ScaleFlag::Overload))
+        );
+        assert_eq!(parse_weight_line("garbage"), None);
+        assert_eq!(parse_weight_line(""), None);
+    }
+
+    #[test]
+    fn test_stability_filter() {
+        let mut filter = StabilityFilter::new(3, 2);
+        let sample = |grams, flag| RawWeight { grams, flag };
+
+        assert!(!filter.push(sample(1000, None)).stable);
+        assert!(!filter.push(sample(1001, None)).stable);
+        assert!(filter.push(sample(1002, None)).stable);
+        assert!(!filter.push(sample(1010, None)).stable);
+
+        filter.reset();
+        filter.push(sample(500, Some(ScaleFlag::Stable)));
+        filter.push(sample(500, Some(ScaleFlag::Unstable)));
+        assert!(!filter.push(sample(500, Some(ScaleFlag::Stable))).stable);
+    }
+
+    #[test]
+    fn test_parse_embedded_barcode() {
+        let layout = EmbeddedBarcodeLayout::default();
+
+        let weight = parse_embedded_barcode("2012345012509", &layout).unwrap();
+        assert_eq!(weight.item_code, "12345");
+        assert_eq!(weight.kind, EmbeddedValueKind::Weight);
+        assert_eq!(weight.weight_grams, Some(1250));
+
+        let price = parse_embedded_barcode("2500042150001", &layout).unwrap();
+        assert_eq!(price.item_code, "00042");
+        assert_eq!(price.price, Some(15000));
+
+        // Regular GTIN and bad check digit
+        assert!(parse_embedded_barcode("8992761111113", &layout).is_none());
+        assert!(parse_embedded_barcode("2012345012500", &layout).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scale_over_pseudo_terminal() {
+        use serialport::TTYPort;
+
+        let (mut master, slave) = TTYPort::pair().expect("failed to create pty pair");
+        let config = ScaleConfig {
+            port: slave.name().unwrap_or_default(),
+            stable_samples: 2,
+            ..ScaleConfig::default()
+        };
+
+        let mut scale = ScaleService::new();
+        scale.open_with_port(config, Box::new(slave)).unwrap();
+
+        master.write_all(b"US,GS,+  0.800kg\r\n").unwrap();
+        master.write_all(b"ST,GS,+  0.812kg\r\nST,GS,+  0.812kg\r\n").unwrap();
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let reading = runtime
+            .block_on(scale.weight_handle().read_stable(Duration::from_secs(2)))
+            .unwrap();
+        assert_eq!(reading.grams, 812);
+
+        scale.tare().unwrap();
+        let mut echoed = [0u8; 3];
+        master.read_exact(&mut echoed).unwrap();
+        assert_eq!(&echoed, b"T\r\n");
+
+        let handle = scale.weight_handle();
+        scale.close();
+        assert!(matches!(scale.get_status(), ScaleStatus::Closed));
+
+        let started = Instant::now();
+        let closed = runtime.block_on(handle.read_stable(Duration::from_secs(2))).unwrap_err();
+        assert_eq!(closed.to_string(), "Scale is not open");
+        assert!(started.elapsed() < Duration::from_millis(500));
+    }
+}
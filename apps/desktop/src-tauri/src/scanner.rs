@@ -0,0 +1,600 @@
+// ======================================================================
+// BARCODE SCANNER
+// Membaca barcode scanner serial (CDC/COM) langsung tanpa keyboard-wedge
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use log::{debug, error, info, warn};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serialport::SerialPort;
+use std::{
+    io::{ErrorKind, Read},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ScannerStatus {
+    Stopped,
+    Running {
+        port: String,
+        baud_rate: u32,
+        scans: u64,
+        rejected: u64,
+        last_scan: Option<String>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Symbology {
+    Ean13,
+    Ean8,
+    UpcA,
+    Itf14,
+    Code39,
+    Code128,
+    Qr,
+    Unknown,
+}
+
+/// Payload of the `scanner://scan` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanEvent {
+    pub code: String,
+    pub symbology: Symbology,
+    pub port: String,
+    pub duration_ms: u64,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SerialPortEntry {
+    pub name: String,
+    pub kind: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScannerConfig {
+    pub port: String,
+    pub baud_rate: u32,
+    pub terminators: Vec<u8>,
+    /// Largest gap between two bytes that still counts as one scanner burst
+    pub max_inter_char_gap: Duration,
+    /// Frame the buffer after this much silence, for scanners without a suffix
+    pub idle_frame_timeout: Duration,
+    pub min_length: usize,
+    pub max_length: usize,
+}
+
+impl Default for ScannerConfig {
+    fn default() -> Self {
+        Self {
+            port: String::new(),
+            baud_rate: 9600,
+            terminators: vec![b'\r', b'\n', 0x03],
+            max_inter_char_gap: Duration::from_millis(50),
+            idle_frame_timeout: Duration::from_millis(150),
+            min_length: 4,
+            max_length: 512,
+        }
+    }
+}
+
+// ======================================================================
+// FRAMER
+// ======================================================================
+
+/// A complete frame cut from the serial byte stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub code: String,
+    pub duration: Duration,
+    /// `true` when every byte arrived within `max_inter_char_gap`
+    pub is_burst: bool,
+}
+
+/// Splits the raw byte stream into scans and tells scanner bursts apart
+/// from slow, human-typed input.
+#[derive(Debug)]
+pub struct ScanFramer {
+    config: ScannerConfig,
+    buffer: Vec<u8>,
+    first_byte_at: Option<Instant>,
+    last_byte_at: Option<Instant>,
+    max_gap: Duration,
+}
+
+impl ScanFramer {
+    pub fn new(config: ScannerConfig) -> Self {
+        Self {
+            config,
+            buffer: Vec::new(),
+            first_byte_at: None,
+            last_byte_at: None,
+            max_gap: Duration::ZERO,
+        }
+    }
+
+    /// Feed one byte received at `at`; returns a frame when a terminator closes one
+    pub fn push(&mut self, byte: u8, at: Instant) -> Option<Frame> {
+        if self.config.terminators.contains(&byte) {
+            return self.take_frame();
+        }
+
+        if let Some(last) = self.last_byte_at {
+            let gap = at.saturating_duration_since(last);
+            if gap > self.max_gap {
+                self.max_gap = gap;
+            }
+        } else {
+            self.first_byte_at = Some(at);
+        }
+        self.last_byte_at = Some(at);
+
+        if self.buffer.len() >= self.config.max_length {
+            warn!("Scanner frame exceeded {} bytes, discarding", self.config.max_length);
+            self.reset();
+            return None;
+        }
+
+        self.buffer.push(byte);
+        None
+    }
+
+    /// Close the pending frame if the line has been silent long enough
+    pub fn flush_idle(&mut self, now: Instant) -> Option<Frame> {
+        match self.last_byte_at {
+            Some(last) if now.saturating_duration_since(last) >= self.config.idle_frame_timeout => {
+                self.take_frame()
+            }
+            _ => None,
+        }
+    }
+
+    fn take_frame(&mut self) -> Option<Frame> {
+        let duration = match (self.first_byte_at, self.last_byte_at) {
+            (Some(first), Some(last)) => last.saturating_duration_since(first),
+            _ => Duration::ZERO,
+        };
+        let is_burst = self.max_gap <= self.config.max_inter_char_gap;
+        let raw = std::mem::take(&mut self.buffer);
+        self.reset();
+
+        let code = String::from_utf8_lossy(&raw)
+            .chars()
+            .filter(|c| !c.is_control())
+            .collect::<String>()
+            .trim()
+            .to_string();
+
+        if code.len() < self.config.min_length {
+            if !code.is_empty() {
+                debug!("Ignoring short scanner frame: {:?}", code);
+            }
+            return None;
+        }
+
+        Some(Frame {
+            code,
+            duration,
+            is_burst,
+        })
+    }
+
+    fn reset(&mut self) {
+        self.buffer.clear();
+        self.first_byte_at = None;
+        self.last_byte_at = None;
+        self.max_gap = Duration::ZERO;
+    }
+}
+
+// ======================================================================
+// SYMBOLOGY
+// ======================================================================
+
+/// Validate the GS1 mod-10 check digit used by EAN/UPC/ITF-14
+pub fn is_valid_gtin(code: &str) -> bool {
+    if code.len() < 2 || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return false;
+    }
+
+    let digits: Vec<u32> = code.bytes().map(|b| (b - b'0') as u32).collect();
+    let (body, check) = digits.split_at(digits.len() - 1);
+    let sum: u32 = body
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { d * 3 } else { *d })
+        .sum();
+
+    (10 - sum % 10) % 10 == check[0]
+}
+
+/// Best-effort guess of the symbology from the decoded payload
+pub fn guess_symbology(code: &str) -> Symbology {
+    if code.is_empty() {
+        return Symbology::Unknown;
+    }
+
+    if code.bytes().all(|b| b.is_ascii_digit()) {
+        let symbology = match code.len() {
+            13 => Symbology::Ean13,
+            8 => Symbology::Ean8,
+            12 => Symbology::UpcA,
+            14 => Symbology::Itf14,
+            _ => Symbology::Code128,
+        };
+        if symbology != Symbology::Code128 && is_valid_gtin(code) {
+            return symbology;
+        }
+        return Symbology::Code128;
+    }
+
+    // QRIS payloads start with the EMVCo format indicator "000201"
+    if code.starts_with("000201") || code.len() > 80 || code.contains("://") {
+        return Symbology::Qr;
+    }
+
+    let is_code39 = code
+        .chars()
+        .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || " -.$/+%".contains(c));
+    if is_code39 {
+        Symbology::Code39
+    } else {
+        Symbology::Code128
+    }
+}
+
+// ======================================================================
+// SCANNER SERVICE
+// ======================================================================
+
+#[derive(Debug, Default)]
+struct ScannerStats {
+    scans: u64,
+    rejected: u64,
+    last_scan: Option<String>,
+    error: Option<String>,
+}
+
+pub struct ScannerService {
+    config: Option<ScannerConfig>,
+    stats: Arc<Mutex<ScannerStats>>,
+    stop_flag: Arc<AtomicBool>,
+    reader: Option<JoinHandle<()>>,
+    scan_callback: Option<Arc<dyn Fn(ScanEvent) + Send + Sync>>,
+}
+
+impl ScannerService {
+    pub fn new() -> Self {
+        Self {
+            config: None,
+            stats: Arc::new(Mutex::new(ScannerStats::default())),
+            stop_flag: Arc::new(AtomicBool::new(false)),
+            reader: None,
+            scan_callback: None,
+        }
+    }
+
+    /// Register the handler invoked for every accepted scan
+    pub fn set_scan_callback<F>(&mut self, callback: F)
+    where
+        F: Fn(ScanEvent) + Send + Sync + 'static,
+    {
+        self.scan_callback = Some(Arc::new(callback));
+    }
+
+    /// List serial ports the OS currently exposes
+    pub fn list_ports() -> Result<Vec<SerialPortEntry>> {
+        let ports = serialport::available_ports()
+            .map_err(|e| anyhow!("Failed to enumerate serial ports: {}", e))?;
+
+        Ok(ports
+            .into_iter()
+            .map(|port| {
+                let (kind, description) = match port.port_type {
+                    serialport::SerialPortType::UsbPort(usb) => (
+                        "usb".to_string(),
+                        usb.product.or(usb.manufacturer),
+                    ),
+                    serialport::SerialPortType::PciPort => ("pci".to_string(), None),
+                    serialport::SerialPortType::BluetoothPort => ("bluetooth".to_string(), None),
+                    serialport::SerialPortType::Unknown => ("unknown".to_string(), None),
+                };
+                SerialPortEntry {
+                    name: port.port_name,
+                    kind,
+                    description,
+                }
+            })
+            .collect())
+    }
+
+    /// Open the configured serial port and start reading scans
+    pub fn start(&mut self, config: ScannerConfig) -> Result<()> {
+        info!("Opening scanner on {} @ {} baud", config.port, config.baud_rate);
+
+        let port = serialport::new(&config.port, config.baud_rate)
+            .timeout(Duration::from_millis(20))
+            .open()
+            .map_err(|e| anyhow!("Failed to open scanner port {}: {}", config.port, e))?;
+
+        self.start_with_port(config, port)
+    }
+
+    /// Start reading from an already opened port
+    pub fn start_with_port(&mut self, config: ScannerConfig, port: Box<dyn SerialPort>) -> Result<()> {
+        if self.is_running() {
+            return Err(anyhow!("Scanner is already running"));
+        }
+
+        *self.stats.lock() = ScannerStats::default();
+        self.stop_flag.store(false, Ordering::SeqCst);
+
+        let stats = Arc::clone(&self.stats);
+        let stop_flag = Arc::clone(&self.stop_flag);
+        let callback = self.scan_callback.clone();
+        let loop_config = config.clone();
+
+        let handle = std::thread::Builder::new()
+            .name("scanner-reader".to_string())
+            .spawn(move || Self::reader_loop(port, loop_config, stats, stop_flag, callback))
+            .map_err(|e| anyhow!("Failed to spawn scanner thread: {}", e))?;
+
+        self.reader = Some(handle);
+        self.config = Some(config);
+
+        info!("Scanner started");
+        Ok(())
+    }
+
+    /// Stop the reader thread and release the port
+    pub fn stop(&mut self) -> Result<()> {
+        info!("Stopping scanner...");
+
+        self.stop_flag.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.reader.take() {
+            if handle.join().is_err() {
+                warn!("Scanner reader thread panicked");
+            }
+        }
+        self.config = None;
+
+        info!("Scanner stopped");
+        Ok(())
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.reader
+            .as_ref()
+            .map(|handle| !handle.is_finished())
+            .unwrap_or(false)
+    }
+
+    /// Get current status
+    pub fn get_status(&self) -> ScannerStatus {
+        let stats = self.stats.lock();
+
+        if let Some(message) = &stats.error {
+            return ScannerStatus::Error {
+                message: message.clone(),
+            };
+        }
+
+        match (&self.config, self.is_running()) {
+            (Some(config), true) => ScannerStatus::Running {
+                port: config.port.clone(),
+                baud_rate: config.baud_rate,
+                scans: stats.scans,
+                rejected: stats.rejected,
+                last_scan: stats.last_scan.clone(),
+            },
+            _ => ScannerStatus::Stopped,
+        }
+    }
+
+    // ======================================================================
+    // PRIVATE METHODS
+    // ======================================================================
+
+    fn reader_loop(
+        mut port: Box<dyn SerialPort>,
+        config: ScannerConfig,
+        stats: Arc<Mutex<ScannerStats>>,
+        stop_flag: Arc<AtomicBool>,
+        callback: Option<Arc<dyn Fn(ScanEvent) + Send + Sync>>,
+    ) {
+        let port_name = config.port.clone();
+        let mut framer = ScanFramer::new(config);
+        let mut buf = [0u8; 256];
+
+        while !stop_flag.load(Ordering::SeqCst) {
+            let frames: Vec<Frame> = match port.read(&mut buf) {
+                Ok(0) => framer.flush_idle(Instant::now()).into_iter().collect(),
+                Ok(n) => {
+                    let now = Instant::now();
+                    buf[..n].iter().filter_map(|b| framer.push(*b, now)).collect()
+                }
+                Err(e) if e.kind() == ErrorKind::TimedOut || e.kind() == ErrorKind::WouldBlock => {
+                    framer.flush_idle(Instant::now()).into_iter().collect()
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    error!("Scanner port {} read failed: {}", port_name, e);
+                    stats.lock().error = Some(format!("Read failed: {}", e));
+                    break;
+                }
+            };
+
+            for frame in frames {
+                Self::dispatch_frame(frame, &port_name, &stats, &callback);
+            }
+        }
+
+        debug!("Scanner reader loop ended");
+    }
+
+    fn dispatch_frame(
+        frame: Frame,
+        port_name: &str,
+        stats: &Arc<Mutex<ScannerStats>>,
+        callback: &Option<Arc<dyn Fn(ScanEvent) + Send + Sync>>,
+    ) {
+        if !frame.is_burst {
+            debug!("Rejecting typed input on {}: {:?}", port_name, frame.code);
+            stats.lock().rejected += 1;
+            return;
+        }
+
+        let event = ScanEvent {
+            symbology: guess_symbology(&frame.code),
+            code: frame.code,
+            port: port_name.to_string(),
+            duration_ms: frame.duration.as_millis() as u64,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+
+        debug!("Scanned {:?} ({:?})", event.code, event.symbology);
+
+        {
+            let mut stats = stats.lock();
+            stats.scans += 1;
+            stats.last_scan = Some(event.code.clone());
+        }
+
+        if let Some(callback) = callback {
+            callback(event);
+        }
+    }
+}
+
+// ======================================================================
+// DROP IMPLEMENTATION
+// ======================================================================
+
+impl Drop for ScannerService {
+    fn drop(&mut self) {
+        // Signal the reader thread; it exits on its next read timeout
+        self.stop_flag.store(true, Ordering::SeqCst);
+    }
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(framer: &mut ScanFramer, bytes: &[u8], start: Instant, gap_ms: u64) -> Vec<Frame> {
+        bytes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, b)| framer.push(*b, start + Duration::from_millis(i as u64 * gap_ms)))
+            .collect()
+    }
+
+    #[test]
+    fn test_is_valid_gtin() {
+        assert!(is_valid_gtin("8992761111113"));
+        assert!(is_valid_gtin("96385074"));
+        assert!(is_valid_gtin("036000291452"));
+        assert!(!is_valid_gtin("8992761111114"));
+        assert!(!is_valid_gtin("89927611A1113"));
+    }
+
+    #[test]
+    fn test_guess_symbology() {
+        assert_eq!(guess_symbology("8992761111113"), Symbology::Ean13);
+        assert_eq!(guess_symbology("96385074"), Symbology::Ean8);
+        assert_eq!(guess_symbology("036000291452"), Symbology::UpcA);
+        assert_eq!(guess_symbology("8992761111114"), Symbology::Code128);
+        assert_eq!(guess_symbology("BRG-001"), Symbology::Code39);
+        assert_eq!(guess_symbology("brg-001"), Symbology::Code128);
+        assert_eq!(guess_symbology("00020101021126570011ID.DANA.WWW"), Symbology::Qr);
+    }
+
+    #[test]
+    fn test_framer_splits_on_terminators() {
+        let mut framer = ScanFramer::new(ScannerConfig::default());
+        let frames = feed(&mut framer, b"8992761111113\r\n96385074\r", Instant::now(), 1);
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].code, "8992761111113");
+        assert_eq!(frames[1].code, "96385074");
+        assert!(frames.iter().all(|f| f.is_burst));
+    }
+
+    #[test]
+    fn test_framer_flags_typed_input() {
+        let mut framer = ScanFramer::new(ScannerConfig::default());
+        let frames = feed(&mut framer, b"12345\r", Instant::now(), 180);
+
+        assert_eq!(frames.len(), 1);
+        assert!(!frames[0].is_burst);
+    }
+
+    #[test]
+    fn test_framer_flushes_on_idle() {
+        let mut framer = ScanFramer::new(ScannerConfig::default());
+        let start = Instant::now();
+        assert!(feed(&mut framer, b"BRG001", start, 2).is_empty());
+
+        assert!(framer.flush_idle(start + Duration::from_millis(20)).is_none());
+        let frame = framer.flush_idle(start + Duration::from_millis(500)).unwrap();
+        assert_eq!(frame.code, "BRG001");
+    }
+
+    #[test]
+    fn test_framer_drops_short_frames() {
+        let mut framer = ScanFramer::new(ScannerConfig::default());
+        assert!(feed(&mut framer, b"12\r", Instant::now(), 1).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scanner_over_pseudo_terminal() {
+        use serialport::TTYPort;
+        use std::io::Write;
+        use std::sync::mpsc;
+
+        let (mut master, slave) = TTYPort::pair().expect("failed to create pty pair");
+        let config = ScannerConfig {
+            port: slave.name().unwrap_or_default(),
+            ..ScannerConfig::default()
+        };
+
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+        let mut scanner = ScannerService::new();
+        scanner.set_scan_callback(move |event| {
+            let _ = tx.lock().send(event);
+        });
+        scanner.start_with_port(config, Box::new(slave)).unwrap();
+
+        master.write_all(b"8992761111113\r\n").unwrap();
+        let event = rx.recv_timeout(Duration::from_secs(2)).expect("no scan received");
+
+        assert_eq!(event.code, "8992761111113");
+        assert_eq!(event.symbology, Symbology::Ean13);
+        assert!(matches!(scanner.get_status(), ScannerStatus::Running { scans: 1, .. }));
+
+        scanner.stop().unwrap();
+        assert!(matches!(scanner.get_status(), ScannerStatus::Stopped));
+    }
+}
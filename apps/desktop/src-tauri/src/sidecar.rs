@@ -7,9 +7,13 @@ use anyhow::{anyhow, Result};
 use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashMap,
     path::PathBuf,
     process::{Child, Command, Stdio},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU16, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 use tokio::{
@@ -45,6 +49,9 @@ pub struct SidecarManager {
     process: Arc<Mutex<Option<SidecarProcess>>>,
     status: Arc<Mutex<SidecarStatus>>,
     config: SidecarConfig,
+    env: HashMap<String, String>,
+    /// Port while running, 0 otherwise; readable without the async locks
+    port: Arc<AtomicU16>,
 }
 
 #[derive(Debug, Clone)]
@@ -76,6 +83,8 @@ impl SidecarManager {
             process: Arc::new(Mutex::new(None)),
             status: Arc::new(Mutex::new(SidecarStatus::Stopped)),
             config: SidecarConfig::default(),
+            env: HashMap::new(),
+            port: Arc::new(AtomicU16::new(0)),
         }
     }
     
@@ -84,9 +93,19 @@ impl SidecarManager {
             process: Arc::new(Mutex::new(None)),
             status: Arc::new(Mutex::new(SidecarStatus::Stopped)),
             config,
+            env: HashMap::new(),
+            port: Arc::new(AtomicU16::new(0)),
         }
     }
     
+    /// Extra environment for the next spawn (takes effect on start/restart)
+    pub fn set_env<I>(&mut self, vars: I)
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        self.env.extend(vars);
+    }
+    
     /// Start the sidecar server
     pub async fn start(&mut self) -> Result<u16> {
         info!("Starting sidecar server...");
@@ -141,6 +160,7 @@ impl SidecarManager {
                                 uptime: 0,
                             };
                         }
+                        self.port.store(port, Ordering::SeqCst);
                         
                         Ok(port)
                     }
@@ -232,9 +252,15 @@ impl SidecarManager {
     
     /// Get the current port (if running)
     pub fn get_port(&self) -> Option<u16> {
-        // This is a synchronous method for convenience
-        // In a real implementation, you might want to store the port separately
-        None // Placeholder - would need async access to get the actual port
+        match self.port.load(Ordering::SeqCst) {
+            0 => None,
+            port => Some(port),
+        }
+    }
+    
+    /// Shared port cell, for callers that cannot wait on the manager lock
+    pub fn port_handle(&self) -> Arc<AtomicU16> {
+        Arc::clone(&self.port)
     }
     
     /// Check if the server is healthy
@@ -289,7 +315,8 @@ impl SidecarManager {
         
         // Additional environment variables
         cmd.env("FORCE_COLOR", "0") // Disable colors in output
-            .env("CI", "true"); // Prevent interactive prompts
+            .env("CI", "true") // Prevent interactive prompts
+            .envs(&self.env);
         
         let child = cmd.spawn()
             .map_err(|e| anyhow!("Failed to spawn process: {}", e))?;
@@ -333,6 +360,7 @@ impl SidecarManager {
     }
     
     async fn kill_process(&mut self) {
+        self.port.store(0, Ordering::SeqCst);
         let mut process_guard = self.process.lock().await;
         
         if let Some(mut process) = process_guard.take() {
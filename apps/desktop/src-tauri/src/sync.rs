@@ -0,0 +1,864 @@
+// ======================================================================
+// SYNC ENGINE
+// Mengirim ulang transaksi jurnal offline ke server secara berurutan
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    sync::{Mutex, Notify, RwLock},
+    task::JoinHandle,
+    time::timeout,
+};
+
+use crate::{
+    journal::{Journal, JournalOrder, OrderStatus, PaymentMethod, SyncStatus},
+    order_no,
+    rollback::{RollbackStore, UpdateFailure},
+    secrets::SecretString,
+    terminal::RequestSigner,
+};
+
+/// Endpoint receiving replayed orders
+const TRANSACTIONS_PATH: &str = "/api/transactions";
+
+/// Endpoint receiving updates this terminal had to roll back
+const UPDATE_FAILURES_PATH: &str = "/api/terminals/update-failures";
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyncState {
+    Stopped,
+    Idle,
+    Syncing,
+    /// Waiting before the next retry after a transient failure
+    Backoff { retry_in_secs: u64 },
+    /// The server refused this terminal's signature or token; needs re-provisioning or a clock fix
+    Unauthorized { message: String, retry_in_secs: u64 },
+}
+
+/// Payload of the `sync://progress` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncProgress {
+    pub state: SyncState,
+    pub pending: i64,
+    pub conflicts: i64,
+    pub synced_this_run: u64,
+    pub last_synced_order_no: Option<String>,
+    pub last_error: Option<String>,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncConfig {
+    pub base_url: String,
+    pub batch_size: usize,
+    pub request_timeout: Duration,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// How often to look for new pending orders when nothing triggers a run
+    pub idle_interval: Duration,
+    /// Signs each request with the terminal key once the till is provisioned
+    pub signer: Option<RequestSigner>,
+    /// Sent as a bearer token when set in the credential store
+    pub token: Option<SecretString>,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            base_url: String::new(),
+            batch_size: 20,
+            request_timeout: Duration::from_secs(15),
+            initial_backoff: Duration::from_secs(2),
+            max_backoff: Duration::from_secs(300),
+            idle_interval: Duration::from_secs(30),
+            signer: None,
+            token: None,
+        }
+    }
+}
+
+/// What to do with an order after the server answered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncOutcome {
+    Accepted { server_id: Option<String> },
+    /// Needs a human, e.g. duplicate order_no or stock gone negative
+    Conflict(String),
+    /// The server will never accept this payload as-is
+    Rejected(String),
+    /// Try again later; stops the run so orders stay in sequence
+    Retry(String),
+    /// The terminal itself is refused, so every order would be; stops the run like `Retry`
+    Unauthorized(String),
+}
+
+/// Error ending a batch when the server refuses the terminal's credentials
+#[derive(Debug)]
+struct Unauthorized(String);
+
+impl std::fmt::Display for Unauthorized {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Terminal not authorized: {}", self.0)
+    }
+}
+
+impl std::error::Error for Unauthorized {}
+
+// ======================================================================
+// PAYLOAD & RESPONSE MAPPING
+// ======================================================================
+
+/// Map a journaled order onto the `POST /api/transactions` schema
+pub fn transaction_payload(order: &JournalOrder) -> Value {
+    let items: Vec<Value> = order
+        .lines
+        .iter()
+        .map(|line| {
+            json!({
+                "produkId": line.product_id,
+                "produkKode": line.product_code,
+                "produkNama": line.product_name,
+                "hargaSatuan": line.price,
+                "jumlah": line.quantity,
+                "diskon": line.discount_amount,
+            })
+        })
+        .collect();
+
+    let payments: Vec<Value> = order
+        .payments
+        .iter()
+        .map(|payment| {
+            let mut value = json!({
+                "metode": payment_method_code(payment.method),
+                "jumlah": payment.amount,
+            });
+            if let Some(reference) = &payment.reference {
+                value["referensi"] = json!(reference);
+            }
+            value
+        })
+        .collect();
+
+    let mut payload = json!({
+        "tanggal": order.paid_at,
+        "kasirId": order.cashier_id,
+        "kasirNama": order.cashier_name,
+        "items": items,
+        "payments": payments,
+        "diskon": order.discount_amount,
+        "pajak": order.tax_amount,
+        "pembulatan": order.rounding_amount,
+        "status": order_status_code(order.status),
+    });
+
+    // Only terminal numbers are kept by the server; it assigns its own to anything else
+    if order_no::parse_order_no(&order.order_no).is_some() {
+        payload["nomorTransaksi"] = json!(order.order_no);
+    }
+    if let Some(customer_id) = &order.customer_id {
+        payload["pelangganId"] = json!(customer_id);
+    }
+    if let Some(customer_name) = &order.customer_name {
+        payload["pelangganNama"] = json!(customer_name);
+    }
+    if let Some(notes) = &order.notes {
+        payload["catatan"] = json!(notes);
+    }
+
+    payload
+}
+
+/// Map a rolled-back update onto the `POST /api/terminals/update-failures` schema
+pub fn update_failure_payload(failure: &UpdateFailure) -> Value {
+    json!({
+        "id": failure.id,
+        "version": failure.version,
+        "rolledBackTo": failure.rolled_back_to,
+        "reason": failure.reason,
+        "failedAt": failure.failed_at,
+    })
+}
+
+fn payment_method_code(method: PaymentMethod) -> &'static str {
+    match method {
+        PaymentMethod::Cash => "tunai",
+        PaymentMethod::Card => "kartu",
+        PaymentMethod::Ewallet | PaymentMethod::Transfer => "digital",
+        PaymentMethod::Voucher => "voucher",
+    }
+}
+
+fn order_status_code(status: OrderStatus) -> &'static str {
+    match status {
+        OrderStatus::Paid => "selesai",
+        OrderStatus::Void => "dibatalkan",
+        OrderStatus::Refund => "refund",
+    }
+}
+
+/// Decide the fate of an order from the HTTP status and JSON body
+pub fn classify_response(status: u16, body: &Value) -> SyncOutcome {
+    let message = body
+        .get("error")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("HTTP {}", status));
+
+    match status {
+        200..=299 => SyncOutcome::Accepted {
+            server_id: body
+                .pointer("/data/id")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        },
+        409 => SyncOutcome::Conflict(match body.get("code").and_then(Value::as_str) {
+            Some(code) => format!("{}: {}", code, message),
+            None => message,
+        }),
+        // Auth failures need the terminal (re)registered or its clock fixed, so surface them
+        401 | 403 => SyncOutcome::Unauthorized(message),
+        408 | 425 | 429 => SyncOutcome::Retry(message),
+        400..=499 => SyncOutcome::Rejected(message),
+        _ => SyncOutcome::Retry(message),
+    }
+}
+
+/// Exponential backoff: initial × 2^(failures-1), capped
+pub fn backoff_delay(failures: u32, config: &SyncConfig) -> Duration {
+    let exponent = failures.saturating_sub(1).min(16);
+    config
+        .initial_backoff
+        .saturating_mul(1u32 << exponent)
+        .min(config.max_backoff)
+}
+
+// ======================================================================
+// SYNC ENGINE
+// ======================================================================
+
+pub struct SyncEngine {
+    journal: Arc<Mutex<Journal>>,
+    config: Arc<RwLock<SyncConfig>>,
+    client: reqwest::Client,
+    progress: Arc<RwLock<SyncProgress>>,
+    wake: Arc<Notify>,
+    task_handle: Option<JoinHandle<()>>,
+    progress_callback: Option<Arc<dyn Fn(SyncProgress) + Send + Sync>>,
+    rollback: Option<Arc<RollbackStore>>,
+}
+
+impl SyncEngine {
+    pub fn new(journal: Arc<Mutex<Journal>>) -> Self {
+        Self {
+            journal,
+            config: Arc::new(RwLock::new(SyncConfig::default())),
+            client: reqwest::Client::new(),
+            progress: Arc::new(RwLock::new(SyncProgress {
+                state: SyncState::Stopped,
+                pending: 0,
+                conflicts: 0,
+                synced_this_run: 0,
+                last_synced_order_no: None,
+                last_error: None,
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            })),
+            wake: Arc::new(Notify::new()),
+            task_handle: None,
+            progress_callback: None,
+            rollback: None,
+        }
+    }
+
+    /// Report rolled-back updates upstream ahead of each batch
+    pub fn set_rollback(&mut self, rollback: Arc<RollbackStore>) {
+        self.rollback = Some(rollback);
+    }
+
+    /// Register the handler invoked on every progress change
+    pub fn set_progress_callback<F>(&mut self, callback: F)
+    where
+        F: Fn(SyncProgress) + Send + Sync + 'static,
+    {
+        self.progress_callback = Some(Arc::new(callback));
+    }
+
+    /// Start the background worker replaying to `config.base_url`
+    pub async fn start(&mut self, config: SyncConfig) -> Result<()> {
+        if config.base_url.is_empty() {
+            return Err(anyhow!("Sync base URL is not configured"));
+        }
+
+        self.stop().await;
+        info!("Starting sync engine towards {}", config.base_url);
+        *self.config.write().await = config;
+
+        let worker = self.worker();
+        self.task_handle = Some(tokio::spawn(async move {
+            worker.run().await;
+        }));
+
+        Ok(())
+    }
+
+    pub async fn stop(&mut self) {
+        if let Some(task) = self.task_handle.take() {
+            info!("Stopping sync engine");
+            task.abort();
+            let _ = task.await;
+        }
+
+        let worker = self.worker();
+        worker.publish(|progress| progress.state = SyncState::Stopped).await;
+    }
+
+    /// Run now instead of waiting for the next interval or backoff
+    pub fn trigger(&self) {
+        self.wake.notify_one();
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.task_handle.as_ref().is_some_and(|task| !task.is_finished())
+    }
+
+    pub async fn get_progress(&self) -> SyncProgress {
+        self.progress.read().await.clone()
+    }
+
+    /// Replay one batch in the foreground; returns the number of orders accepted
+    pub async fn sync_once(&self) -> Result<u64> {
+        self.worker().sync_batch().await
+    }
+
+    // ======================================================================
+    // PRIVATE METHODS
+    // ======================================================================
+
+    fn worker(&self) -> SyncWorker {
+        SyncWorker {
+            journal: Arc::clone(&self.journal),
+            config: Arc::clone(&self.config),
+            client: self.client.clone(),
+            progress: Arc::clone(&self.progress),
+            wake: Arc::clone(&self.wake),
+            callback: self.progress_callback.clone(),
+            rollback: self.rollback.clone(),
+        }
+    }
+}
+
+/// State shared with the background task
+struct SyncWorker {
+    journal: Arc<Mutex<Journal>>,
+    config: Arc<RwLock<SyncConfig>>,
+    client: reqwest::Client,
+    progress: Arc<RwLock<SyncProgress>>,
+    wake: Arc<Notify>,
+    callback: Option<Arc<dyn Fn(SyncProgress) + Send + Sync>>,
+    rollback: Option<Arc<RollbackStore>>,
+}
+
+impl SyncWorker {
+    async fn run(self) {
+        let mut failures: u32 = 0;
+
+        loop {
+            let config = self.config.read().await.clone();
+
+            let wait = match self.sync_batch().await {
+                Ok(_) => {
+                    failures = 0;
+                    self.publish(|progress| progress.state = SyncState::Idle).await;
+
+                    // A full batch means more orders may be waiting
+                    if self.progress.read().await.pending > 0 {
+                        continue;
+                    }
+                    config.idle_interval
+                }
+                Err(e) if e.is::<Unauthorized>() => {
+                    // Retrying fast won't help; wait the longest backoff or a manual trigger
+                    let delay = config.max_backoff;
+                    error!("Sync paused, server refused this terminal: {}", e);
+
+                    self.publish(|progress| {
+                        progress.state = SyncState::Unauthorized {
+                            message: e.to_string(),
+                            retry_in_secs: delay.as_secs(),
+                        };
+                        progress.last_error = Some(e.to_string());
+                    })
+                    .await;
+                    delay
+                }
+                Err(e) => {
+                    failures += 1;
+                    let delay = backoff_delay(failures, &config);
+                    warn!("Sync attempt {} failed, retrying in {:?}: {}", failures, delay, e);
+
+                    self.publish(|progress| {
+                        progress.state = SyncState::Backoff {
+                            retry_in_secs: delay.as_secs(),
+                        };
+                        progress.last_error = Some(e.to_string());
+                    })
+                    .await;
+                    delay
+                }
+            };
+
+            // Either the timer elapses or someone triggers a run
+            let _ = timeout(wait, self.wake.notified()).await;
+        }
+    }
+
+    async fn sync_batch(&self) -> Result<u64> {
+        let config = self.config.read().await.clone();
+        self.report_update_failures(&config).await;
+        let pending = self.journal.lock().await.pending_orders(config.batch_size)?;
+
+        if pending.is_empty() {
+            self.refresh_counts().await;
+            return Ok(0);
+        }
+
+        self.publish(|progress| {
+            progress.state = SyncState::Syncing;
+            progress.synced_this_run = 0;
+        })
+        .await;
+
+        let mut accepted = 0;
+
+        for order in pending {
+            let outcome = match self.post_order(&config, &order).await {
+                Ok(outcome) => outcome,
+                Err(e) => SyncOutcome::Retry(e.to_string()),
+            };
+
+            debug!("Sync outcome for {}: {:?}", order.order_no, outcome);
+            let journal = self.journal.lock().await;
+
+            match outcome {
+                SyncOutcome::Accepted { server_id } => {
+                    journal.mark_synced(&order.idempotency_key, server_id.as_deref())?;
+                    drop(journal);
+                    accepted += 1;
+                    self.publish(|progress| {
+                        progress.synced_this_run += 1;
+                        progress.last_synced_order_no = Some(order.order_no.clone());
+                        progress.last_error = None;
+                    })
+                    .await;
+                }
+                SyncOutcome::Conflict(message) => {
+                    warn!("Order {} conflicts upstream: {}", order.order_no, message);
+                    journal.mark_unsynced(&order.idempotency_key, SyncStatus::Conflict, &message)?;
+                    drop(journal);
+                    self.publish(|progress| {
+                        progress.last_error = Some(format!("{}: {}", order.order_no, message));
+                    })
+                    .await;
+                }
+                SyncOutcome::Rejected(message) => {
+                    error!("Order {} rejected upstream: {}", order.order_no, message);
+                    journal.mark_unsynced(&order.idempotency_key, SyncStatus::Failed, &message)?;
+                    drop(journal);
+                    self.publish(|progress| {
+                        progress.last_error = Some(format!("{}: {}", order.order_no, message));
+                    })
+                    .await;
+                }
+                SyncOutcome::Retry(message) => {
+                    journal.record_attempt_error(&order.idempotency_key, &message)?;
+                    drop(journal);
+                    self.refresh_counts().await;
+                    return Err(anyhow!("{}: {}", order.order_no, message));
+                }
+                SyncOutcome::Unauthorized(message) => {
+                    journal.record_attempt_error(&order.idempotency_key, &message)?;
+                    drop(journal);
+                    self.refresh_counts().await;
+                    return Err(Unauthorized(message).into());
+                }
+            }
+        }
+
+        self.refresh_counts().await;
+        info!("Synced {} journaled orders", accepted);
+        Ok(accepted)
+    }
+
+    async fn post_order(&self, config: &SyncConfig, order: &JournalOrder) -> Result<SyncOutcome> {
+        self.post_signed(config, TRANSACTIONS_PATH, &order.idempotency_key, &transaction_payload(order))
+            .await
+    }
+
+    /// POST JSON with the idempotency key, bearer token and terminal signature
+    async fn post_signed(
+        &self,
+        config: &SyncConfig,
+        path: &str,
+        idempotency_key: &str,
+        payload: &Value,
+    ) -> Result<SyncOutcome> {
+        let url = format!("{}{}", config.base_url.trim_end_matches('/'), path);
+
+        let body = serde_json::to_vec(payload)?;
+
+        let mut request = self
+            .client
+            .post(&url)
+            .timeout(config.request_timeout)
+            .header("Idempotency-Key", idempotency_key)
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+
+        if let Some(token) = &config.token {
+            request = request.bearer_auth(token.expose());
+        }
+        if let Some(signer) = &config.signer {
+            for (name, value) in signer.headers("POST", path, &body) {
+                request = request.header(name, value);
+            }
+        }
+
+        let response = request.body(body).send().await?;
+
+        let status = response.status().as_u16();
+        let body = response.json::<Value>().await.unwrap_or(Value::Null);
+        Ok(classify_response(status, &body))
+    }
+
+    /// Best effort: an unreachable server never holds up the order replay
+    async fn report_update_failures(&self, config: &SyncConfig) {
+        let Some(rollback) = &self.rollback else {
+            return;
+        };
+
+        for failure in rollback.unreported() {
+            let payload = update_failure_payload(&failure);
+            let outcome = match self.post_signed(config, UPDATE_FAILURES_PATH, &failure.id, &payload).await {
+                Ok(outcome) => outcome,
+                Err(e) => SyncOutcome::Retry(e.to_string()),
+            };
+
+            match outcome {
+                SyncOutcome::Accepted { .. } | SyncOutcome::Conflict(_) => {
+                    info!("Reported failed update {} upstream", failure.version);
+                    rollback.mark_reported(&failure.id);
+                }
+                SyncOutcome::Rejected(message) => {
+                    error!("Failed update report for {} rejected upstream: {}", failure.version, message);
+                    rollback.mark_reported(&failure.id);
+                }
+                SyncOutcome::Retry(message) | SyncOutcome::Unauthorized(message) => {
+                    debug!("Failed update report for {} deferred: {}", failure.version, message);
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn refresh_counts(&self) {
+        let stats = self.journal.lock().await.stats();
+
+        match stats {
+            Ok(stats) => {
+                self.publish(|progress| {
+                    progress.pending = stats.pending;
+                    progress.conflicts = stats.conflict + stats.failed;
+                })
+                .await
+            }
+            Err(e) => error!("Failed to read journal stats: {}", e),
+        }
+    }
+
+    async fn publish<F>(&self, update: F)
+    where
+        F: FnOnce(&mut SyncProgress),
+    {
+        let snapshot = {
+            let mut progress = self.progress.write().await;
+            update(&mut progress);
+            progress.timestamp = chrono::Utc::now().to_rfc3339();
+            progress.clone()
+        };
+
+        if let Some(callback) = &self.callback {
+            callback(snapshot);
+        }
+    }
+}
+
+// ======================================================================
+// DROP IMPLEMENTATION
+// ======================================================================
+
+impl Drop for SyncEngine {
+    fn drop(&mut self) {
+        if let Some(task) = self.task_handle.take() {
+            task.abort();
+        }
+    }
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        journal::{NewOrder, NewOrderLine, NewPayment},
+        order_no::OrderNumberAllocator,
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    fn numbered_journal() -> Journal {
+        let mut journal = Journal::open_in_memory().unwrap();
+        journal.set_numbering(Some(OrderNumberAllocator::in_memory("JKT01", "T02").unwrap()));
+        journal
+    }
+
+    fn new_order(key: &str) -> NewOrder {
+        NewOrder {
+            idempotency_key: Some(key.to_string()),
+            branch_id: "branch-1".to_string(),
+            session_id: None,
+            customer_id: None,
+            customer_name: None,
+            cashier_id: "usr_001".to_string(),
+            cashier_name: "Siti Aminah".to_string(),
+            discount_amount: 0,
+            rounding_amount: 0,
+            notes: None,
+            lines: vec![NewOrderLine {
+                product_id: "prod_001".to_string(),
+                product_code: "BRG001".to_string(),
+                product_name: "Indomie Goreng".to_string(),
+                quantity: 2,
+                price: 3_500,
+                discount_amount: 0,
+                tax_amount: 0,
+            }],
+            payments: vec![NewPayment {
+                method: PaymentMethod::Ewallet,
+                amount: 7_000,
+                reference: Some("QRIS-1".to_string()),
+            }],
+        }
+    }
+
+    /// Minimal HTTP/1.1 server answering with canned responses, recording idempotency keys
+    async fn mock_server(responses: Vec<(u16, &'static str)>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let keys = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&keys);
+
+        tokio::spawn(async move {
+            for (status, body) in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+
+                // Read headers, then the announced body
+                loop {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let header = |name: &str| {
+                            text.lines().find_map(|line| {
+                                let line = line.to_ascii_lowercase();
+                                line.strip_prefix(name).map(|value| value.trim().to_string())
+                            })
+                        };
+                        let length: usize = header("content-length:").map_or(0, |v| v.parse().unwrap());
+                        if request.len() >= end + 4 + length {
+                            seen.lock().await.push(header("idempotency-key:").unwrap_or_default());
+                            break;
+                        }
+                    }
+                }
+
+                let response = format!(
+                    "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        (base_url, keys)
+    }
+
+    #[test]
+    fn test_classify_response() {
+        let ok = serde_json::json!({ "success": true, "data": { "id": "trx_9" } });
+        assert_eq!(
+            classify_response(201, &ok),
+            SyncOutcome::Accepted { server_id: Some("trx_9".to_string()) }
+        );
+
+        let duplicate = serde_json::json!({ "error": "Nomor transaksi sudah ada", "code": "DUPLICATE_ORDER_NO" });
+        assert!(matches!(classify_response(409, &duplicate), SyncOutcome::Conflict(m) if m.starts_with("DUPLICATE_ORDER_NO")));
+        assert!(matches!(classify_response(400, &Value::Null), SyncOutcome::Rejected(_)));
+        assert!(matches!(classify_response(429, &Value::Null), SyncOutcome::Retry(_)));
+        assert!(matches!(classify_response(401, &Value::Null), SyncOutcome::Unauthorized(_)));
+        assert!(matches!(classify_response(403, &Value::Null), SyncOutcome::Unauthorized(_)));
+        assert!(matches!(classify_response(503, &Value::Null), SyncOutcome::Retry(_)));
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        let config = SyncConfig::default();
+        assert_eq!(backoff_delay(1, &config), Duration::from_secs(2));
+        assert_eq!(backoff_delay(3, &config), Duration::from_secs(8));
+        assert_eq!(backoff_delay(30, &config), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn test_transaction_payload() {
+        let mut journal = numbered_journal();
+        // Journaled by an older version with a number the server does not keep
+        let mut server_numbered = journal.record_order(new_order("p")).unwrap();
+        server_numbered.order_no = "JKT01-POS-20240501-000042".to_string();
+        assert!(transaction_payload(&server_numbered).get("nomorTransaksi").is_none());
+
+        let order = journal.record_order(new_order("k")).unwrap();
+        let payload = transaction_payload(&order);
+
+        assert_eq!(payload["nomorTransaksi"], order.order_no.as_str());
+        assert_eq!(payload["items"][0]["hargaSatuan"], 3_500);
+        assert_eq!(payload["payments"][0]["metode"], "digital");
+        assert_eq!(payload["payments"][0]["referensi"], "QRIS-1");
+        assert_eq!(payload["pajak"], order.tax_amount);
+        assert_eq!(payload["pembulatan"], order.rounding_amount);
+        assert_eq!(payload["status"], "selesai");
+        assert!(payload.get("pelangganId").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sync_replays_in_order_against_mock_server() {
+        let journal = Arc::new(Mutex::new(numbered_journal()));
+        for key in ["a", "b", "c", "d"] {
+            journal.lock().await.record_order(new_order(key)).unwrap();
+        }
+
+        let (base_url, keys) = mock_server(vec![
+            (201, r#"{"success":true,"data":{"id":"trx_a"}}"#),
+            (409, r#"{"success":false,"error":"Stok tidak cukup","code":"STOCK_NEGATIVE"}"#),
+            (503, r#"{"success":false,"error":"Database unavailable"}"#),
+            (201, r#"{"success":true,"data":{"id":"trx_c"}}"#),
+            (201, r#"{"success":true,"data":{"id":"trx_d"}}"#),
+        ])
+        .await;
+
+        let engine = SyncEngine::new(Arc::clone(&journal));
+        *engine.config.write().await = SyncConfig {
+            base_url,
+            ..SyncConfig::default()
+        };
+
+        // "c" hits a transient error: the run stops so "d" is not sent ahead of it
+        assert!(engine.sync_once().await.is_err());
+        {
+            let journal = journal.lock().await;
+            assert_eq!(journal.get_order("a").unwrap().unwrap().server_id.as_deref(), Some("trx_a"));
+            assert_eq!(journal.get_order("b").unwrap().unwrap().sync_status, SyncStatus::Conflict);
+            assert_eq!(journal.get_order("c").unwrap().unwrap().sync_status, SyncStatus::Pending);
+        }
+
+        assert_eq!(engine.sync_once().await.unwrap(), 2);
+        assert_eq!(*keys.lock().await, vec!["a", "b", "c", "c", "d"]);
+
+        let progress = engine.get_progress().await;
+        assert_eq!(progress.pending, 0);
+        assert_eq!(progress.conflicts, 1);
+        assert_eq!(progress.last_synced_order_no, journal.lock().await.get_order("d").unwrap().map(|o| o.order_no));
+    }
+
+    #[tokio::test]
+    async fn test_refused_terminal_stops_the_run_as_unauthorized() {
+        let journal = Arc::new(Mutex::new(numbered_journal()));
+        for key in ["a", "b"] {
+            journal.lock().await.record_order(new_order(key)).unwrap();
+        }
+
+        let (base_url, keys) = mock_server(vec![
+            (401, r#"{"success":false,"error":"Terminal tidak terdaftar atau nonaktif"}"#),
+        ])
+        .await;
+
+        let engine = SyncEngine::new(Arc::clone(&journal));
+        *engine.config.write().await = SyncConfig {
+            base_url,
+            ..SyncConfig::default()
+        };
+
+        // Neither order is dead-lettered; both wait for the terminal to be fixed
+        let error = engine.sync_once().await.unwrap_err();
+        assert!(error.is::<Unauthorized>());
+        assert!(error.to_string().contains("Terminal tidak terdaftar"));
+        assert_eq!(*keys.lock().await, vec!["a"]);
+
+        let journal = journal.lock().await;
+        assert_eq!(journal.get_order("a").unwrap().unwrap().sync_status, SyncStatus::Pending);
+        assert_eq!(journal.get_order("b").unwrap().unwrap().sync_status, SyncStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn test_rolled_back_update_is_reported_until_accepted() {
+        let dir = std::env::temp_dir().join(format!("pos-sync-rollback-{}", uuid::Uuid::new_v4()));
+        let rollback = Arc::new(RollbackStore::open(dir.clone()));
+        let installer = dir.join("pos-1.0.0.msi");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&installer, b"msi").unwrap();
+        rollback.keep_installer("1.0.0", &installer).unwrap();
+        rollback.prepare("1.1.0", &dir.join("pos-1.1.0.msi"), "1.0.0").unwrap();
+        // Fails to reinstall without touching this machine, and is still reported
+        std::fs::remove_dir_all(dir.join("installed")).unwrap();
+        assert!(rollback.fail("crash loop").is_err());
+        let failure_id = rollback.unreported()[0].id.clone();
+
+        let journal = Arc::new(Mutex::new(numbered_journal()));
+        journal.lock().await.record_order(new_order("a")).unwrap();
+
+        let (base_url, keys) = mock_server(vec![
+            (503, r#"{"success":false,"error":"Database unavailable"}"#),
+            (201, r#"{"success":true,"data":{"id":"trx_a"}}"#),
+            (201, r#"{"success":true}"#),
+        ])
+        .await;
+
+        let mut engine = SyncEngine::new(Arc::clone(&journal));
+        engine.set_rollback(Arc::clone(&rollback));
+        *engine.config.write().await = SyncConfig {
+            base_url,
+            ..SyncConfig::default()
+        };
+
+        // A deferred report does not hold up the orders
+        assert_eq!(engine.sync_once().await.unwrap(), 1);
+        assert_eq!(rollback.unreported().len(), 1);
+
+        assert_eq!(engine.sync_once().await.unwrap(), 0);
+        assert!(rollback.unreported().is_empty());
+        assert_eq!(*keys.lock().await, vec![failure_id.as_str(), "a", failure_id.as_str()]);
+    }
+}
@@ -0,0 +1,352 @@
+// ======================================================================
+// TERMINAL IDENTITY
+// Registrasi sekali per PC kasir: UUID terminal, keypair, dan cabang
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use ed25519_dalek::{Signer, SigningKey};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::utils;
+
+/// File name of the private key inside the app data directory
+pub const TERMINAL_KEY_FILE: &str = "terminal.key";
+
+/// Endpoint binding a new terminal to a branch
+pub const REGISTER_PATH: &str = "/api/terminals/register";
+
+pub const TERMINAL_ID_HEADER: &str = "X-Terminal-Id";
+pub const TERMINAL_TIMESTAMP_HEADER: &str = "X-Terminal-Timestamp";
+pub const TERMINAL_SIGNATURE_HEADER: &str = "X-Terminal-Signature";
+pub const TERMINAL_NONCE_HEADER: &str = "X-Terminal-Nonce";
+pub const ENROLLMENT_TOKEN_HEADER: &str = "X-Enrollment-Token";
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+/// Public half of the identity, stored in the app config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerminalIdentity {
+    pub terminal_id: String,
+    pub branch_id: String,
+    pub branch_code: String,
+    pub terminal_code: String,
+    pub name: String,
+    /// Raw Ed25519 public key, base64
+    pub public_key: String,
+    pub registered_at: String,
+}
+
+impl TerminalIdentity {
+    /// Environment handed to the sidecar so it can attribute its own requests
+    pub fn sidecar_env(&self) -> Vec<(String, String)> {
+        vec![
+            ("POS_TERMINAL_ID".to_string(), self.terminal_id.clone()),
+            ("POS_TERMINAL_CODE".to_string(), self.terminal_code.clone()),
+            ("POS_BRANCH_ID".to_string(), self.branch_id.clone()),
+            ("POS_BRANCH_CODE".to_string(), self.branch_code.clone()),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistrationRequest {
+    /// `branches.code` this till belongs to
+    pub branch_code: String,
+    /// Requested till code; the server assigns the next free one when absent
+    pub terminal_code: Option<String>,
+    pub name: Option<String>,
+    /// One-time token an admin created for this branch; never stored
+    pub enrollment_token: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RegistrationData {
+    terminal_id: String,
+    branch_id: String,
+    branch_code: String,
+    terminal_code: String,
+    name: String,
+    registered_at: String,
+}
+
+#[derive(Deserialize)]
+struct RegistrationResponse {
+    success: bool,
+    data: Option<RegistrationData>,
+    error: Option<String>,
+}
+
+// ======================================================================
+// TERMINAL KEY
+// ======================================================================
+
+pub struct TerminalKey {
+    signing: SigningKey,
+}
+
+impl fmt::Debug for TerminalKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TerminalKey")
+            .field("public_key", &self.public_key())
+            .finish_non_exhaustive()
+    }
+}
+
+impl TerminalKey {
+    pub fn generate() -> Self {
+        Self {
+            signing: SigningKey::generate(&mut rand_core::OsRng),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let encoded = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read terminal key {:?}: {}", path, e))?;
+        let bytes = BASE64
+            .decode(encoded.trim())
+            .map_err(|e| anyhow!("Corrupt terminal key {:?}: {}", path, e))?;
+        let secret: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| anyhow!("Corrupt terminal key {:?}: wrong length", path))?;
+
+        Ok(Self {
+            signing: SigningKey::from_bytes(&secret),
+        })
+    }
+
+    pub fn load_default() -> Result<Self> {
+        Self::load(&default_key_path())
+    }
+
+    /// Write the secret key readable by the current user only
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let encoded = BASE64.encode(self.signing.to_bytes());
+        utils::write_private(path, encoded.as_bytes()).map_err(|e| anyhow!(e))
+    }
+
+    pub fn public_key(&self) -> String {
+        BASE64.encode(self.signing.verifying_key().to_bytes())
+    }
+
+    pub fn sign(&self, message: &[u8]) -> String {
+        BASE64.encode(self.signing.sign(message).to_bytes())
+    }
+}
+
+pub fn default_key_path() -> PathBuf {
+    utils::get_app_data_dir().join(TERMINAL_KEY_FILE)
+}
+
+// ======================================================================
+// REQUEST SIGNING
+// ======================================================================
+
+/// Canonical bytes signed for a request: method, path, timestamp, nonce, then the body.
+/// The server accepts each nonce once, so a captured request can't be replayed inside the skew window.
+pub fn signing_message(method: &str, path: &str, timestamp: i64, nonce: &str, body: &[u8]) -> Vec<u8> {
+    let mut message = format!("{}\n{}\n{}\n{}\n", method.to_uppercase(), path, timestamp, nonce).into_bytes();
+    message.extend_from_slice(body);
+    message
+}
+
+/// Signs outgoing requests so the server can attribute them to this till
+#[derive(Clone)]
+pub struct RequestSigner {
+    terminal_id: String,
+    key: Arc<TerminalKey>,
+}
+
+impl fmt::Debug for RequestSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestSigner")
+            .field("terminal_id", &self.terminal_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RequestSigner {
+    pub fn new(terminal_id: &str, key: TerminalKey) -> Self {
+        Self {
+            terminal_id: terminal_id.to_string(),
+            key: Arc::new(key),
+        }
+    }
+
+    /// Headers to attach to a request with this exact body
+    pub fn headers(&self, method: &str, path: &str, body: &[u8]) -> Vec<(&'static str, String)> {
+        let nonce = uuid::Uuid::new_v4().simple().to_string();
+        self.headers_at(method, path, body, chrono::Utc::now().timestamp(), &nonce)
+    }
+
+    pub fn headers_at(
+        &self,
+        method: &str,
+        path: &str,
+        body: &[u8],
+        timestamp: i64,
+        nonce: &str,
+    ) -> Vec<(&'static str, String)> {
+        let signature = self.key.sign(&signing_message(method, path, timestamp, nonce, body));
+
+        vec![
+            (TERMINAL_ID_HEADER, self.terminal_id.clone()),
+            (TERMINAL_TIMESTAMP_HEADER, timestamp.to_string()),
+            (TERMINAL_NONCE_HEADER, nonce.to_string()),
+            (TERMINAL_SIGNATURE_HEADER, signature),
+        ]
+    }
+}
+
+// ======================================================================
+// PROVISIONING
+// ======================================================================
+
+/// One-time registration: create the identity, bind it to a branch, keep the key
+pub async fn register(
+    client: &reqwest::Client,
+    base_url: &str,
+    request: &RegistrationRequest,
+    key_path: &Path,
+) -> Result<TerminalIdentity> {
+    let terminal_id = uuid::Uuid::new_v4().to_string();
+    let key = TerminalKey::generate();
+
+    let body = serde_json::to_vec(&serde_json::json!({
+        "terminalId": terminal_id,
+        "branchCode": request.branch_code,
+        "terminalCode": request.terminal_code,
+        "name": request.name,
+        "publicKey": key.public_key(),
+    }))?;
+
+    // Signed with the new key to prove possession of it
+    let signer = RequestSigner::new(&terminal_id, key);
+    let mut http = client
+        .post(format!("{}{}", base_url.trim_end_matches('/'), REGISTER_PATH))
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(ENROLLMENT_TOKEN_HEADER, &request.enrollment_token);
+    for (name, value) in signer.headers("POST", REGISTER_PATH, &body) {
+        http = http.header(name, value);
+    }
+
+    let response: RegistrationResponse = http
+        .body(body)
+        .send()
+        .await?
+        .json()
+        .await
+        .map_err(|e| anyhow!("Invalid registration response: {}", e))?;
+
+    let data = match (response.success, response.data) {
+        (true, Some(data)) => data,
+        _ => {
+            return Err(anyhow!(
+                "Terminal registration failed: {}",
+                response.error.unwrap_or_else(|| "unknown error".to_string())
+            ))
+        }
+    };
+
+    if data.terminal_id != terminal_id {
+        return Err(anyhow!("Server returned a different terminal id"));
+    }
+
+    // Only keep the key once the server knows about it
+    signer.key.save(key_path)?;
+
+    info!(
+        "Registered terminal {} as {}-{}",
+        data.terminal_id, data.branch_code, data.terminal_code
+    );
+
+    Ok(TerminalIdentity {
+        terminal_id: data.terminal_id,
+        branch_id: data.branch_id,
+        branch_code: data.branch_code,
+        terminal_code: data.terminal_code,
+        name: data.name,
+        public_key: signer.key.public_key(),
+        registered_at: data.registered_at,
+    })
+}
+
+/// Signer for a provisioned identity, if its key is still on disk
+pub fn load_signer(identity: &TerminalIdentity) -> Option<RequestSigner> {
+    match TerminalKey::load_default() {
+        Ok(key) if key.public_key() == identity.public_key => Some(RequestSigner::new(&identity.terminal_id, key)),
+        Ok(_) => {
+            warn!("Terminal key does not match the registered identity; requests stay unsigned");
+            None
+        }
+        Err(e) => {
+            warn!("{}; requests stay unsigned", e);
+            None
+        }
+    }
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    fn verify(public_key: &str, message: &[u8], signature: &str) -> bool {
+        let public: [u8; 32] = BASE64.decode(public_key).unwrap().try_into().unwrap();
+        let signature: [u8; 64] = BASE64.decode(signature).unwrap().try_into().unwrap();
+
+        VerifyingKey::from_bytes(&public)
+            .unwrap()
+            .verify(message, &Signature::from_bytes(&signature))
+            .is_ok()
+    }
+
+    #[test]
+    fn test_key_round_trip_and_signed_headers() {
+        let dir = std::env::temp_dir().join(format!("terminal-{}", uuid::Uuid::new_v4()));
+        let path = dir.join(TERMINAL_KEY_FILE);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let key = TerminalKey::generate();
+        key.save(&path).unwrap();
+        let loaded = TerminalKey::load(&path).unwrap();
+        assert_eq!(loaded.public_key(), key.public_key());
+
+        let public_key = key.public_key();
+        let signer = RequestSigner::new("terminal-1", loaded);
+        let body = br#"{"total":10000}"#;
+        let headers = signer.headers_at("post", "/api/transactions", body, 1_714_500_000, "n1");
+
+        assert_eq!(headers[0], (TERMINAL_ID_HEADER, "terminal-1".to_string()));
+        assert_eq!(headers[1].1, "1714500000");
+        assert_eq!(headers[2], (TERMINAL_NONCE_HEADER, "n1".to_string()));
+
+        let message = signing_message("POST", "/api/transactions", 1_714_500_000, "n1", body);
+        assert!(verify(&public_key, &message, &headers[3].1));
+
+        // Any change to the body or nonce invalidates the signature
+        let tampered = signing_message("POST", "/api/transactions", 1_714_500_000, "n1", br#"{"total":1}"#);
+        assert!(!verify(&public_key, &tampered, &headers[3].1));
+        let renonced = signing_message("POST", "/api/transactions", 1_714_500_000, "n2", body);
+        assert!(!verify(&public_key, &renonced, &headers[3].1));
+
+        // Every request gets a fresh nonce
+        let nonce = |headers: Vec<(&'static str, String)>| headers[2].1.clone();
+        assert_ne!(nonce(signer.headers("POST", "/", body)), nonce(signer.headers("POST", "/", body)));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,1010 @@
+// ======================================================================
+// UPDATE MANAGER
+// Pembaruan aplikasi: feed per channel, tanda tangan minisign, rollout bertahap
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use log::{debug, error, info, warn};
+use minisign_verify::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tauri::{AppHandle, Manager};
+use tokio::{
+    io::AsyncWriteExt,
+    sync::{Mutex, RwLock},
+    task::JoinHandle,
+};
+
+use crate::{
+    audit::{AuditEvent, AuditOutcome},
+    commands::AppConfig,
+    postgres,
+    rollback::RollbackStore,
+    terminal::TerminalIdentity,
+    utils,
+    window_manager::CustomerView,
+    AppState,
+};
+
+/// Downloaded bundles inside the app data directory
+pub const UPDATES_DIR: &str = "updates";
+
+const PARTIAL_EXTENSION: &str = ".partial";
+
+/// Appended to the feed path for its detached signature, as `tauri signer sign` names it
+const FEED_SIGNATURE_EXTENSION: &str = ".sig";
+
+const FEED_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often a downloaded update looks for a quiet moment to install
+const INSTALL_POLL: Duration = Duration::from_secs(60);
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    /// Also offered stable releases that are newer than the latest beta
+    Beta,
+}
+
+impl UpdateChannel {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Stable => "stable",
+            Self::Beta => "beta",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateConfig {
+    pub channel: UpdateChannel,
+    /// JSON feed with the latest release per channel, over https; signed like the bundles
+    pub feed_url: Option<String>,
+    /// Minisign public key from `tauri signer generate`, as in tauri.conf.json
+    pub public_key: Option<String>,
+    pub check_interval_hours: u32,
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        Self {
+            channel: UpdateChannel::Stable,
+            feed_url: None,
+            public_key: None,
+            check_interval_hours: 6,
+        }
+    }
+}
+
+/// `{ "channels": { "stable": Release, "beta": Release }, "releases": [Release] }`
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateFeed {
+    pub channels: HashMap<String, Release>,
+    /// Earlier releases, so a till installed by hand can fetch the installer of the version it
+    /// runs before updating away from it
+    #[serde(default)]
+    pub releases: Vec<Release>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Release {
+    pub version: String,
+    #[serde(default)]
+    pub notes: String,
+    pub pub_date: Option<String>,
+    /// Share of terminals offered this release, 0–100
+    #[serde(default = "full_rollout")]
+    pub rollout_percent: u8,
+    /// Keyed by target, e.g. `windows-x86_64` or `linux-x86_64`
+    pub platforms: HashMap<String, ReleaseAsset>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseAsset {
+    /// `.msi` on Windows, `.AppImage` on Linux
+    pub url: String,
+    /// Base64 of the minisign `.sig` file, as written by `tauri signer sign`
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AvailableUpdate {
+    pub version: String,
+    pub channel: UpdateChannel,
+    pub notes: String,
+    pub pub_date: Option<String>,
+    #[serde(skip)]
+    pub asset: ReleaseAsset,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum UpdateStatus {
+    Idle,
+    Checking,
+    UpToDate { checked_at: String },
+    Downloading { version: String, downloaded: u64, total: Option<u64> },
+    /// Verified and waiting for no open shift and an empty cart
+    Ready { version: String, notes: String, path: PathBuf },
+    Installing { version: String },
+    Failed { error: String },
+}
+
+// ======================================================================
+// FEED
+// ======================================================================
+
+/// Feed key for this build, e.g. `windows-x86_64`
+pub fn current_target() -> String {
+    let os = match std::env::consts::OS {
+        "macos" => "darwin",
+        os => os,
+    };
+    format!("{}-{}", os, std::env::consts::ARCH)
+}
+
+/// Stable per terminal, so a staged release reaches the same tills first
+pub fn rollout_key(terminal: Option<&TerminalIdentity>) -> String {
+    match terminal {
+        Some(identity) => identity.terminal_id.clone(),
+        None => std::env::var("COMPUTERNAME")
+            .or_else(|_| std::env::var("HOSTNAME"))
+            .unwrap_or_default(),
+    }
+}
+
+/// 0–99; salted with the version so each release picks a different first wave
+pub fn rollout_bucket(key: &str, version: &str) -> u8 {
+    let digest = Sha256::digest(format!("{}:{}", key, version).as_bytes());
+    (u16::from_be_bytes([digest[0], digest[1]]) % 100) as u8
+}
+
+/// The newest release for `channel` that is newer than `current` and rolled out to `key`
+pub fn select_release(
+    feed: &UpdateFeed,
+    channel: UpdateChannel,
+    target: &str,
+    current: &str,
+    key: &str,
+) -> Result<Option<AvailableUpdate>> {
+    let current = semver::Version::parse(current).map_err(|e| anyhow!("Invalid current version {}: {}", current, e))?;
+
+    let mut channels = vec![channel];
+    if channel == UpdateChannel::Beta {
+        channels.push(UpdateChannel::Stable);
+    }
+
+    let mut best: Option<(semver::Version, AvailableUpdate)> = None;
+    for channel in channels {
+        let Some(release) = feed.channels.get(channel.as_str()) else {
+            continue;
+        };
+        let version = match semver::Version::parse(&release.version) {
+            Ok(version) => version,
+            Err(e) => {
+                warn!("Ignoring {} release with invalid version {:?}: {}", channel.as_str(), release.version, e);
+                continue;
+            }
+        };
+        if version <= current || best.as_ref().is_some_and(|(best, _)| version <= *best) {
+            continue;
+        }
+        if rollout_bucket(key, &release.version) >= release.rollout_percent.min(100) {
+            debug!("Release {} not rolled out to this terminal yet", release.version);
+            continue;
+        }
+        let Some(asset) = release.platforms.get(target) else {
+            continue;
+        };
+
+        best = Some((
+            version,
+            AvailableUpdate {
+                version: release.version.clone(),
+                channel,
+                notes: release.notes.clone(),
+                pub_date: release.pub_date.clone(),
+                asset: asset.clone(),
+            },
+        ));
+    }
+
+    Ok(best.map(|(_, update)| update))
+}
+
+/// Accepts the key either as in tauri.conf.json (base64 of the whole `.pub` file) or as its `RW…` line
+pub fn parse_public_key(public_key: &str) -> Result<PublicKey> {
+    let public_key = public_key.trim();
+    if let Ok(key) = PublicKey::from_base64(public_key) {
+        return Ok(key);
+    }
+
+    let text = BASE64
+        .decode(public_key)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .ok_or_else(|| anyhow!("Update public key is not valid base64"))?;
+    PublicKey::decode(&text).map_err(|e| anyhow!("Invalid update public key: {}", e))
+}
+
+/// The feed, once its detached signature checks out; versions, rollout and bundle URLs are all trusted from here
+pub fn verify_feed(bytes: &[u8], signature: &str, public_key: &PublicKey) -> Result<UpdateFeed> {
+    let signature = parse_signature(signature)?;
+    public_key
+        .verify(bytes, &signature, false)
+        .map_err(|e| anyhow!("Update feed failed signature verification: {}", e))?;
+    serde_json::from_slice(bytes).map_err(|e| anyhow!("Invalid update feed: {}", e))
+}
+
+/// Update traffic goes over https; plain http is only accepted from a mirror on this machine
+pub fn validate_update_url(url: &str) -> Result<reqwest::Url> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| anyhow!("Invalid update URL {}: {}", url, e))?;
+    let loopback = matches!(parsed.host_str(), Some("127.0.0.1" | "localhost" | "[::1]"));
+    match parsed.scheme() {
+        "https" => Ok(parsed),
+        "http" if loopback => Ok(parsed),
+        _ => Err(anyhow!("Update URL {} must use https", url)),
+    }
+}
+
+pub fn parse_signature(signature: &str) -> Result<Signature> {
+    let text = BASE64
+        .decode(signature.trim())
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .ok_or_else(|| anyhow!("Update signature is not valid base64"))?;
+    Signature::decode(&text).map_err(|e| anyhow!("Invalid update signature: {}", e))
+}
+
+// ======================================================================
+// UPDATE MANAGER
+// ======================================================================
+
+pub struct UpdateManager {
+    dir: PathBuf,
+    /// Versions rolled back here are skipped
+    rollback: Arc<RollbackStore>,
+    status: Arc<RwLock<UpdateStatus>>,
+    /// Held while checking, downloading or installing
+    busy: Arc<Mutex<()>>,
+    client: reqwest::Client,
+    task_handle: Option<JoinHandle<()>>,
+    status_callback: Option<Arc<dyn Fn(UpdateStatus) + Send + Sync>>,
+}
+
+impl UpdateManager {
+    pub fn new() -> Self {
+        Self::with_dir(utils::get_app_data_dir().join(UPDATES_DIR))
+    }
+
+    pub fn with_dir(dir: PathBuf) -> Self {
+        Self {
+            rollback: Arc::new(RollbackStore::open(dir.clone())),
+            dir,
+            status: Arc::new(RwLock::new(UpdateStatus::Idle)),
+            busy: Arc::new(Mutex::new(())),
+            client: reqwest::Client::new(),
+            task_handle: None,
+            status_callback: None,
+        }
+    }
+
+    /// Probation and failure history of installed updates, kept beside the downloads
+    pub fn rollback(&self) -> Arc<RollbackStore> {
+        Arc::clone(&self.rollback)
+    }
+
+    /// Register the handler invoked on every status change
+    pub fn set_status_callback<F>(&mut self, callback: F)
+    where
+        F: Fn(UpdateStatus) + Send + Sync + 'static,
+    {
+        self.status_callback = Some(Arc::new(callback));
+    }
+
+    /// (Re)start the periodic check and background download; `auto` is `AppConfig.auto_update`
+    pub async fn start(&mut self, config: UpdateConfig, auto: bool, key: String) {
+        self.stop().await;
+
+        if !auto || config.feed_url.is_none() {
+            debug!("Automatic updates are disabled");
+            return;
+        }
+
+        info!("Checking for {} updates every {} hour(s)", config.channel.as_str(), config.check_interval_hours.max(1));
+        let worker = self.worker();
+        let every = Duration::from_secs(u64::from(config.check_interval_hours.max(1)) * 3600);
+        self.task_handle = Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                interval.tick().await;
+                if let Err(e) = worker.check_and_download(&config, &key).await {
+                    warn!("Update check failed: {}", e);
+                }
+            }
+        }));
+    }
+
+    pub async fn stop(&mut self) {
+        if let Some(task) = self.task_handle.take() {
+            task.abort();
+            let _ = task.await;
+        }
+    }
+
+    pub async fn get_status(&self) -> UpdateStatus {
+        self.status.read().await.clone()
+    }
+
+    /// The verified bundle waiting to be installed
+    pub async fn ready(&self) -> Option<(String, PathBuf)> {
+        match &*self.status.read().await {
+            UpdateStatus::Ready { version, path, .. } => Some((version.clone(), path.clone())),
+            _ => None,
+        }
+    }
+
+    /// Hand the bundle to the installer. On Windows the MSI relaunches the app after
+    /// it exits; on Linux the AppImage is replaced and the caller restarts.
+    pub async fn install(&self) -> Result<()> {
+        let _guard = self.busy.try_lock().map_err(|_| anyhow!("An update check or install is running"))?;
+        let (version, path) = self.ready().await.ok_or_else(|| anyhow!("No update has been downloaded"))?;
+
+        let worker = self.worker();
+        worker.set_status(UpdateStatus::Installing { version: version.clone() }).await;
+
+        let bundle = path.clone();
+        let result = tokio::task::spawn_blocking(move || install_bundle(&bundle))
+            .await
+            .map_err(|e| anyhow!(e))
+            .and_then(|result| result);
+
+        match result {
+            Ok(()) => {
+                info!("Update {} handed to the installer", version);
+                Ok(())
+            }
+            Err(e) => {
+                error!("Installing update {} failed: {}", version, e);
+                worker.set_status(UpdateStatus::Failed { error: e.to_string() }).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Handle for checking and downloading without holding the manager lock
+    pub fn worker(&self) -> UpdateWorker {
+        UpdateWorker {
+            dir: self.dir.clone(),
+            rollback: Arc::clone(&self.rollback),
+            status: Arc::clone(&self.status),
+            busy: Arc::clone(&self.busy),
+            client: self.client.clone(),
+            callback: self.status_callback.clone(),
+        }
+    }
+}
+
+// ======================================================================
+// UPDATE WORKER
+// ======================================================================
+
+#[derive(Clone)]
+pub struct UpdateWorker {
+    dir: PathBuf,
+    rollback: Arc<RollbackStore>,
+    status: Arc<RwLock<UpdateStatus>>,
+    busy: Arc<Mutex<()>>,
+    client: reqwest::Client,
+    callback: Option<Arc<dyn Fn(UpdateStatus) + Send + Sync>>,
+}
+
+impl UpdateWorker {
+    /// Check the feed now and download a newer release; `None` when up to date
+    pub async fn check_and_download(&self, config: &UpdateConfig, key: &str) -> Result<Option<AvailableUpdate>> {
+        let _guard = self.busy.try_lock().map_err(|_| anyhow!("An update check or install is running"))?;
+
+        let result = self.try_check_and_download(config, key).await;
+        if let Err(e) = &result {
+            self.set_status(UpdateStatus::Failed { error: e.to_string() }).await;
+        }
+        result
+    }
+
+    async fn try_check_and_download(&self, config: &UpdateConfig, key: &str) -> Result<Option<AvailableUpdate>> {
+        let feed_url = config.feed_url.as_deref().ok_or_else(|| anyhow!("No update feed configured"))?;
+        let feed_url = validate_update_url(feed_url)?;
+        // Refuse to fetch anything that could not be verified afterwards
+        let public_key = parse_public_key(config.public_key.as_deref().ok_or_else(|| anyhow!("No update public key configured"))?)?;
+
+        self.set_status(UpdateStatus::Checking).await;
+        let mut signature_url = feed_url.clone();
+        signature_url.set_path(&format!("{}{}", feed_url.path(), FEED_SIGNATURE_EXTENSION));
+        let bytes = self.fetch(feed_url).await?;
+        let signature = self.fetch(signature_url).await?;
+        let signature = String::from_utf8(signature).map_err(|_| anyhow!("Invalid update feed signature"))?;
+        let feed = verify_feed(&bytes, &signature, &public_key)?;
+
+        let update = select_release(&feed, config.channel, &current_target(), env!("CARGO_PKG_VERSION"), key)?;
+        let update = update.filter(|update| {
+            let failed = self.rollback.is_failed(&update.version);
+            if failed {
+                debug!("Skipping update {}; it was rolled back on this terminal", update.version);
+            }
+            !failed
+        });
+        let Some(update) = update else {
+            self.set_status(UpdateStatus::UpToDate { checked_at: chrono::Utc::now().to_rfc3339() }).await;
+            return Ok(None);
+        };
+
+        let running = env!("CARGO_PKG_VERSION");
+        if !self.rollback.has_target(running) {
+            self.fetch_rollback_target(&feed, running, &public_key).await?;
+        }
+
+        let already_ready = matches!(
+            &*self.status.read().await,
+            UpdateStatus::Ready { version, path, .. } if *version == update.version && path.exists()
+        );
+        if !already_ready {
+            info!("Downloading update {} ({})", update.version, update.channel.as_str());
+            let path = self.download(&update, &public_key).await?;
+            self.set_status(UpdateStatus::Ready {
+                version: update.version.clone(),
+                notes: update.notes.clone(),
+                path,
+            })
+            .await;
+        }
+        Ok(Some(update))
+    }
+
+    /// Download and keep the running version's installer, so the update can be undone
+    async fn fetch_rollback_target(&self, feed: &UpdateFeed, running: &str, public_key: &PublicKey) -> Result<()> {
+        let target = current_target();
+        let asset = feed
+            .channels
+            .values()
+            .chain(&feed.releases)
+            .filter(|release| release.version == running)
+            .find_map(|release| release.platforms.get(&target))
+            .ok_or_else(|| {
+                anyhow!("Update held back: the feed has no {} installer of the running version {} to roll back to", target, running)
+            })?;
+
+        info!("Downloading the installer of {} to roll back to", running);
+        let installer = AvailableUpdate {
+            version: running.to_string(),
+            channel: UpdateChannel::Stable,
+            notes: String::new(),
+            pub_date: None,
+            asset: asset.clone(),
+        };
+        let path = self.download(&installer, public_key).await?;
+        self.rollback.keep_installer(running, &path)
+    }
+
+    async fn fetch(&self, url: reqwest::Url) -> Result<Vec<u8>> {
+        let response = self.client.get(url).timeout(FEED_TIMEOUT).send().await?.error_for_status()?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    /// Stream the bundle to disk, verifying the signature over the same bytes
+    async fn download(&self, update: &AvailableUpdate, public_key: &PublicKey) -> Result<PathBuf> {
+        validate_update_url(&update.asset.url)?;
+        let signature = parse_signature(&update.asset.signature)?;
+        let mut verifier = public_key
+            .verify_stream(&signature)
+            .map_err(|e| anyhow!("Update signature cannot be checked: {}", e))?;
+
+        let file_name = bundle_file_name(&update.asset.url)?;
+        utils::ensure_directory_exists(&self.dir).map_err(|e| anyhow!(e))?;
+        let target = self.dir.join(&file_name);
+        let partial = self.dir.join(format!("{}{}", file_name, PARTIAL_EXTENSION));
+
+        let mut response = self.client.get(&update.asset.url).send().await?.error_for_status()?;
+        let total = response.content_length();
+        let mut file = tokio::fs::File::create(&partial).await?;
+        let mut downloaded = 0u64;
+
+        let result: Result<()> = async {
+            while let Some(chunk) = response.chunk().await? {
+                verifier.update(&chunk);
+                file.write_all(&chunk).await?;
+                downloaded += chunk.len() as u64;
+                self.set_status(UpdateStatus::Downloading {
+                    version: update.version.clone(),
+                    downloaded,
+                    total,
+                })
+                .await;
+            }
+            file.sync_all().await?;
+            verifier
+                .finalize()
+                .map_err(|e| anyhow!("Update {} failed signature verification: {}", update.version, e))
+        }
+        .await;
+        drop(file);
+
+        if let Err(e) = result {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(e);
+        }
+
+        tokio::fs::rename(&partial, &target).await?;
+        info!("Update {} verified at {:?}", update.version, target);
+        Ok(target)
+    }
+
+    async fn set_status(&self, status: UpdateStatus) {
+        *self.status.write().await = status.clone();
+        if let Some(callback) = &self.callback {
+            callback(status);
+        }
+    }
+}
+
+// ======================================================================
+// INSTALL
+// ======================================================================
+
+/// Install the downloaded update once no shift is open and no sale is on the screen
+pub async fn install_when_idle(app: AppHandle) {
+    let mut interval = tokio::time::interval(INSTALL_POLL);
+    loop {
+        interval.tick().await;
+
+        let Some(state) = app.try_state::<AppState>() else {
+            continue;
+        };
+        if !AppConfig::load().auto_update || state.updater.lock().await.ready().await.is_none() {
+            continue;
+        }
+
+        match install_blocker(&state).await {
+            Some(reason) => debug!("Update waiting: {}", reason),
+            None => {
+                let version = state.updater.lock().await.ready().await.map(|(version, _)| version);
+                state.audit.record(AuditEvent::new(
+                    "install_update",
+                    AuditOutcome::Allowed,
+                    serde_json::json!({ "version": version, "source": "background" }),
+                ));
+                if let Err(e) = install_and_restart(&app, &state).await {
+                    error!("{}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Why installing now would interrupt the till, if it would
+pub async fn install_blocker(state: &AppState) -> Option<String> {
+    if sale_in_progress(&*state.customer_view.lock().await) {
+        return Some("a transaction is in progress".to_string());
+    }
+
+    let Some(database_url) = state.database_url().await else {
+        return Some("no database to check for open shifts".to_string());
+    };
+    let config = AppConfig::load();
+    let terminal_id = config.terminal.as_ref().map(|terminal| terminal.terminal_id.as_str());
+    match open_shift_count(&database_url, terminal_id, config.branch_id.as_deref()).await {
+        Ok(0) => None,
+        Ok(count) => Some(format!("{} shift(s) still open", count)),
+        Err(e) => Some(format!("cannot check for open shifts: {}", e)),
+    }
+}
+
+/// Stop the sidecar, run the installer, then restart; the sidecar comes back on failure
+pub async fn install_and_restart(app: &AppHandle, state: &AppState) -> Result<()> {
+    let updater = state.updater.lock().await;
+    let (version, bundle) = updater.ready().await.ok_or_else(|| anyhow!("No update has been downloaded"))?;
+    info!("Installing update {}", version);
+
+    // Keep the running version's installer so a broken release can be undone
+    state.rollback.prepare(&version, &bundle, env!("CARGO_PKG_VERSION"))?;
+
+    // The installer replaces files the sidecar has open
+    let _ = state.watchdog.lock().await.stop().await;
+    let _ = state.sidecar_manager.lock().await.stop().await;
+
+    match updater.install().await {
+        Ok(()) => {
+            relaunch(app);
+            Ok(())
+        }
+        Err(e) => {
+            state.rollback.cancel();
+            if let Err(restart) = state.sidecar_manager.lock().await.start().await {
+                error!("Failed to restart sidecar after a failed update: {}", restart);
+            }
+            Err(anyhow!("Update {} not installed: {}", version, e))
+        }
+    }
+}
+
+/// Start the installer for a bundle; also used to reinstall the previous version
+pub fn install_bundle(bundle: &Path) -> Result<()> {
+    let name = bundle.to_string_lossy().to_ascii_lowercase();
+
+    if name.ends_with(".msi") {
+        if !cfg!(windows) {
+            return Err(anyhow!("MSI bundles only install on Windows"));
+        }
+        std::process::Command::new("msiexec")
+            .arg("/i")
+            .arg(bundle)
+            .args(["/passive", "/norestart", "AUTOLAUNCHAPP=True"])
+            .spawn()
+            .map_err(|e| anyhow!("Failed to start msiexec: {}", e))?;
+        return Ok(());
+    }
+
+    if name.ends_with(".appimage") {
+        let target = std::env::var_os("APPIMAGE")
+            .map(PathBuf::from)
+            .ok_or_else(|| anyhow!("Not running from an AppImage"))?;
+        let staged = target.with_extension("update");
+        std::fs::copy(bundle, &staged)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))?;
+        }
+        std::fs::rename(&staged, &target)?;
+        return Ok(());
+    }
+
+    Err(anyhow!("Unsupported update bundle {:?}", bundle))
+}
+
+/// Leave the installer to it: on Windows msiexec waits for this process to exit and
+/// relaunches the app, a replaced AppImage is started again right away
+pub fn relaunch(app: &AppHandle) {
+    if cfg!(windows) {
+        app.exit(0);
+    } else {
+        app.restart();
+    }
+}
+
+// ======================================================================
+// HELPER FUNCTIONS
+// ======================================================================
+
+fn full_rollout() -> u8 {
+    100
+}
+
+/// Lines on the customer view mean a cart is being rung up
+fn sale_in_progress(view: &CustomerView) -> bool {
+    !view.lines.is_empty() && view.change.is_none()
+}
+
+/// Shifts open on this till; the whole branch when the till is not provisioned and cannot tell its own apart
+async fn open_shift_count(database_url: &str, terminal_id: Option<&str>, branch_id: Option<&str>) -> Result<i64> {
+    let client = postgres::connect(database_url).await?;
+
+    let row = match (terminal_id, branch_id) {
+        (Some(terminal_id), _) => {
+            client
+                .query_one(
+                    "SELECT COUNT(*) FROM pos_sessions WHERE status = 'open' AND terminal_id::text = $1",
+                    &[&terminal_id],
+                )
+                .await?
+        }
+        (None, Some(branch_id)) => {
+            client
+                .query_one(
+                    "SELECT COUNT(*) FROM pos_sessions WHERE status = 'open' AND branch_id::text = $1",
+                    &[&branch_id],
+                )
+                .await?
+        }
+        (None, None) => client.query_one("SELECT COUNT(*) FROM pos_sessions WHERE status = 'open'", &[]).await?,
+    };
+    Ok(row.get(0))
+}
+
+/// Last path segment of the bundle URL, limited to installers we know how to run
+fn bundle_file_name(url: &str) -> Result<String> {
+    let url = reqwest::Url::parse(url).map_err(|e| anyhow!("Invalid bundle URL {}: {}", url, e))?;
+    let name = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .map(utils::sanitize_filename)
+        .unwrap_or_default();
+
+    let lower = name.to_ascii_lowercase();
+    if lower.ends_with(".msi") || lower.ends_with(".appimage") {
+        Ok(name)
+    } else {
+        Err(anyhow!("Unsupported update bundle {:?}; expected .msi or .AppImage", name))
+    }
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    /// Key and prehashed signature over `b"test"`, from the minisign-verify documentation
+    const PUBLIC_KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1633700835\tfile:test\tprehashed
+wLMDjy9FLAuxZ3q4NlEvkgtyhrr0gtTu6KC4KBJdITbbOeAi1zBIYo0v4iTgt8jJpIidRJnp94ABQkJAgAooBQ==";
+
+    const TEST_KEY_ID: [u8; 8] = *b"pos-test";
+
+    /// Throwaway minisign key and its public half, so tests can sign feeds and bundles
+    fn test_key() -> (ed25519_dalek::SigningKey, String) {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[9u8; 32]);
+        let public_key = [b"Ed".as_slice(), &TEST_KEY_ID, key.verifying_key().as_bytes()].concat();
+        (key, BASE64.encode(public_key))
+    }
+
+    /// Prehashed minisign signature over `data`, base64 like a `tauri signer sign` `.sig` file
+    fn sign(key: &ed25519_dalek::SigningKey, data: &[u8]) -> String {
+        use ed25519_dalek::Signer;
+
+        let signature = key.sign(&blake2::Blake2b512::digest(data)).to_bytes();
+        let trusted_comment = "timestamp:1700000000\tfile:test";
+        let global = key.sign(&[signature.as_slice(), trusted_comment.as_bytes()].concat()).to_bytes();
+        let line = [b"ED".as_slice(), &TEST_KEY_ID, &signature].concat();
+        BASE64.encode(format!(
+            "untrusted comment: test\n{}\ntrusted comment: {}\n{}\n",
+            BASE64.encode(line),
+            trusted_comment,
+            BASE64.encode(global)
+        ))
+    }
+
+    fn release(version: &str, rollout_percent: u8, url: &str) -> Release {
+        Release {
+            version: version.to_string(),
+            notes: format!("Versi {}", version),
+            pub_date: None,
+            rollout_percent,
+            platforms: HashMap::from([(
+                "windows-x86_64".to_string(),
+                ReleaseAsset {
+                    url: url.to_string(),
+                    signature: BASE64.encode(SIGNATURE),
+                },
+            )]),
+        }
+    }
+
+    /// Serves fixed bodies by path, as a stand-in for the release host
+    async fn release_host(routes: Vec<(String, Vec<u8>)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                }
+                let text = String::from_utf8_lossy(&request);
+                let path = text.split_whitespace().nth(1).unwrap_or("/").to_string();
+
+                let (status, body) = match routes.iter().find(|(route, _)| *route == path) {
+                    Some((_, body)) => (200, body.clone()),
+                    None => (404, Vec::new()),
+                };
+                let head = format!(
+                    "HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                let _ = stream.write_all(head.as_bytes()).await;
+                let _ = stream.write_all(&body).await;
+            }
+        });
+
+        base_url
+    }
+
+    #[test]
+    fn test_select_release_by_channel_and_rollout() {
+        let feed = UpdateFeed {
+            channels: HashMap::from([
+                ("stable".to_string(), release("1.2.0", 100, "https://updates.example/pos-1.2.0.msi")),
+                ("beta".to_string(), release("1.3.0-beta.1", 100, "https://updates.example/pos-1.3.0-beta.1.msi")),
+            ]),
+            releases: Vec::new(),
+        };
+        let pick = |channel, current: &str| {
+            select_release(&feed, channel, "windows-x86_64", current, "T01")
+                .unwrap()
+                .map(|update| update.version)
+        };
+
+        assert_eq!(pick(UpdateChannel::Stable, "1.0.0").as_deref(), Some("1.2.0"));
+        assert_eq!(pick(UpdateChannel::Beta, "1.0.0").as_deref(), Some("1.3.0-beta.1"));
+        assert_eq!(pick(UpdateChannel::Stable, "1.2.0"), None);
+        assert!(select_release(&feed, UpdateChannel::Stable, "linux-x86_64", "1.0.0", "T01").unwrap().is_none());
+
+        // A stable release past the beta wins on the beta channel too
+        let mut feed = feed;
+        feed.channels.insert("stable".to_string(), release("1.3.0", 100, "https://updates.example/pos-1.3.0.msi"));
+        let update = select_release(&feed, UpdateChannel::Beta, "windows-x86_64", "1.0.0", "T01").unwrap().unwrap();
+        assert_eq!((update.version.as_str(), update.channel), ("1.3.0", UpdateChannel::Stable));
+
+        // A staged rollout reaches its share of terminals, the same ones on every check
+        feed.channels.insert("stable".to_string(), release("1.4.0", 30, "https://updates.example/pos-1.4.0.msi"));
+        let offered = (0..1000)
+            .filter(|n| {
+                let key = format!("T{}", n);
+                let update = select_release(&feed, UpdateChannel::Stable, "windows-x86_64", "1.0.0", &key).unwrap();
+                assert_eq!(update.is_some(), rollout_bucket(&key, "1.4.0") < 30);
+                update.is_some()
+            })
+            .count();
+        assert!((200..400).contains(&offered), "offered to {} of 1000", offered);
+    }
+
+    #[test]
+    fn test_signature_formats() {
+        let signature = parse_signature(&BASE64.encode(SIGNATURE)).unwrap();
+        let tauri_style = BASE64.encode(format!("untrusted comment: minisign public key\n{}\n", PUBLIC_KEY));
+
+        for key in [PUBLIC_KEY.to_string(), tauri_style] {
+            let key = parse_public_key(&key).unwrap();
+            assert!(key.verify(b"test", &signature, false).is_ok());
+            assert!(key.verify(b"tesT", &signature, false).is_err());
+        }
+        assert!(parse_public_key("not a key").is_err());
+        assert!(bundle_file_name("https://updates.example/pos.exe").is_err());
+        assert_eq!(bundle_file_name("https://updates.example/a/POS_1.2.0.AppImage").unwrap(), "POS_1.2.0.AppImage");
+
+        assert!(validate_update_url("https://updates.example/feed.json").is_ok());
+        assert!(validate_update_url("http://127.0.0.1:8080/feed.json").is_ok());
+        for url in ["http://updates.example/feed.json", "ftp://updates.example/feed.json", "feed.json"] {
+            assert!(validate_update_url(url).is_err(), "{}", url);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_verifies_before_ready() {
+        let (key, public_key) = test_key();
+        let base_url = release_host(vec![
+            ("/pos.msi".to_string(), b"test".to_vec()),
+            ("/tampered.msi".to_string(), b"tesT".to_vec()),
+            ("/running.msi".to_string(), b"test".to_vec()),
+        ])
+        .await;
+        let asset = |bundle: &str| serde_json::json!({ "url": format!("{}{}", base_url, bundle), "signature": sign(&key, b"test") });
+        let feed = |bundle: &str, releases: serde_json::Value| {
+            serde_json::json!({
+                "channels": {
+                    "stable": { "version": "99.0.0", "platforms": { current_target(): asset(bundle) } }
+                },
+                "releases": releases
+            })
+            .to_string()
+            .into_bytes()
+        };
+        let dir = std::env::temp_dir().join(format!("pos-updates-{}", uuid::Uuid::new_v4()));
+
+        // Routes are fixed at bind time, so serve the feeds from a second host
+        let running = serde_json::json!([
+            { "version": env!("CARGO_PKG_VERSION"), "platforms": { current_target(): asset("/running.msi") } }
+        ]);
+        let good = feed("/pos.msi", running.clone());
+        let tampered = feed("/tampered.msi", running);
+        let forged = String::from_utf8(good.clone()).unwrap().replace("99.0.0", "99.0.1").into_bytes();
+        let no_rollback = feed("/pos.msi", serde_json::json!([]));
+        let feeds = release_host(vec![
+            ("/no-rollback.json".to_string(), no_rollback.clone()),
+            ("/no-rollback.json.sig".to_string(), sign(&key, &no_rollback).into_bytes()),
+            ("/good.json".to_string(), good.clone()),
+            ("/good.json.sig".to_string(), sign(&key, &good).into_bytes()),
+            ("/tampered.json".to_string(), tampered.clone()),
+            ("/tampered.json.sig".to_string(), sign(&key, &tampered).into_bytes()),
+            ("/forged.json".to_string(), forged),
+            ("/forged.json.sig".to_string(), sign(&key, &good).into_bytes()),
+        ])
+        .await;
+
+        let manager = UpdateManager::with_dir(dir.clone());
+        let mut config = UpdateConfig {
+            feed_url: Some(format!("{}/no-rollback.json", feeds)),
+            public_key: Some(public_key),
+            ..UpdateConfig::default()
+        };
+
+        // Nothing to roll back to and no installer of the running version in the feed
+        let error = manager.worker().check_and_download(&config, "T01").await.unwrap_err();
+        assert!(error.to_string().contains("held back"), "{}", error);
+        assert!(!dir.exists());
+
+        config.feed_url = Some(format!("{}/tampered.json", feeds));
+        let error = manager.worker().check_and_download(&config, "T01").await.unwrap_err();
+        assert!(error.to_string().contains("signature"), "{}", error);
+        assert!(matches!(manager.get_status().await, UpdateStatus::Failed { .. }));
+        assert!(!dir.join("tampered.msi").exists() && !dir.join("tampered.msi.partial").exists());
+        // The running version's installer was fetched first and is kept
+        assert!(manager.rollback().has_target(env!("CARGO_PKG_VERSION")));
+
+        // A feed edited after signing is refused before any version in it is believed
+        config.feed_url = Some(format!("{}/forged.json", feeds));
+        let error = manager.worker().check_and_download(&config, "T01").await.unwrap_err();
+        assert!(error.to_string().contains("feed failed signature"), "{}", error);
+
+        config.feed_url = Some(format!("{}/good.json", feeds));
+        let update = manager.worker().check_and_download(&config, "T01").await.unwrap().unwrap();
+        assert_eq!(update.version, "99.0.0");
+        let (version, path) = manager.ready().await.unwrap();
+        assert_eq!(version, "99.0.0");
+        assert_eq!(std::fs::read(path).unwrap(), b"test");
+
+        config.feed_url = Some("http://updates.example/feed.json".to_string());
+        let error = manager.worker().check_and_download(&config, "T01").await.unwrap_err();
+        assert!(error.to_string().contains("https"), "{}", error);
+
+        config.feed_url = Some(format!("{}/good.json", feeds));
+        config.public_key = None;
+        assert!(manager.worker().check_and_download(&config, "T01").await.is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_sale_in_progress() {
+        let mut view = CustomerView::default();
+        assert!(!sale_in_progress(&view));
+        view.lines.push(Default::default());
+        assert!(sale_in_progress(&view));
+        view.change = Some(0);
+        assert!(!sale_in_progress(&view));
+
+        // As pushed by the POS page while ringing up, then once paid
+        let ringing: CustomerView = serde_json::from_value(serde_json::json!({
+            "lines": [{ "name": "Kopi Arabica 250g", "quantity": 2, "price": 45000, "total": 99900 }],
+            "subtotal": 90000, "discount": 0, "tax": 9900, "total": 99900, "paid": null, "change": null
+        }))
+        .unwrap();
+        assert!(sale_in_progress(&ringing));
+        let paid = CustomerView { paid: Some(100_000), change: Some(100), ..ringing };
+        assert!(!sale_in_progress(&paid));
+        let cleared: CustomerView = serde_json::from_value(serde_json::json!({
+            "lines": [], "subtotal": 0, "discount": 0, "tax": 0, "total": 0, "paid": null, "change": null
+        }))
+        .unwrap();
+        assert!(!sale_in_progress(&cleared));
+    }
+}
@@ -0,0 +1,200 @@
+// ======================================================================
+// URL POLICY
+// Validasi URL eksternal: allowlist skema dan domain, normalisasi IDN
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use log::warn;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+/// Longer URLs are refused outright
+const MAX_URL_LENGTH: usize = 2048;
+
+/// Handlers that are never opened, whatever the config says
+const BLOCKED_SCHEMES: &[&str] = &["file", "smb", "javascript", "vbscript", "data", "ms-msdt", "search-ms"];
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UrlPolicyConfig {
+    pub allowed_schemes: Vec<String>,
+    /// Each entry also allows its subdomains; Unicode and punycode forms are equivalent
+    pub allowed_domains: Vec<String>,
+}
+
+impl Default for UrlPolicyConfig {
+    fn default() -> Self {
+        Self {
+            allowed_schemes: vec!["https".to_string()],
+            allowed_domains: Vec::new(),
+        }
+    }
+}
+
+// ======================================================================
+// URL POLICY
+// ======================================================================
+
+pub struct UrlPolicy {
+    schemes: Vec<String>,
+    /// ASCII (punycode) form, lowercase, without a trailing dot
+    domains: Vec<String>,
+}
+
+impl UrlPolicy {
+    pub fn new(config: &UrlPolicyConfig) -> Self {
+        let schemes = config
+            .allowed_schemes
+            .iter()
+            .map(|scheme| scheme.trim().trim_end_matches(':').to_ascii_lowercase())
+            .filter(|scheme| !BLOCKED_SCHEMES.contains(&scheme.as_str()))
+            .collect();
+
+        let domains = config
+            .allowed_domains
+            .iter()
+            .filter_map(|domain| match normalize_domain(domain) {
+                Some(domain) => Some(domain),
+                None => {
+                    warn!("Ignoring invalid allowed domain {:?}", domain);
+                    None
+                }
+            })
+            .collect();
+
+        Self { schemes, domains }
+    }
+
+    /// The normalised URL when it may be handed to the OS, otherwise why not
+    pub fn check(&self, raw: &str) -> Result<Url> {
+        let raw = raw.trim_matches(' ');
+        if raw.len() > MAX_URL_LENGTH {
+            return Err(anyhow!("URL is longer than {} characters", MAX_URL_LENGTH));
+        }
+        // The parser silently drops tabs and newlines, so refuse them before it can
+        if raw.chars().any(|c| c.is_control() || c.is_whitespace()) {
+            return Err(anyhow!("URL contains whitespace or control characters"));
+        }
+
+        let url = Url::parse(raw).map_err(|e| anyhow!("Invalid URL: {}", e))?;
+
+        let scheme = url.scheme();
+        if BLOCKED_SCHEMES.contains(&scheme) || !self.schemes.iter().any(|allowed| allowed == scheme) {
+            return Err(anyhow!("Scheme {}: is not allowed", scheme));
+        }
+
+        // `mailto:` and `tel:` have no host to check
+        if url.cannot_be_a_base() {
+            return Ok(url);
+        }
+
+        // `https://trusted.example@evil.example` reads as the trusted host
+        if !url.username().is_empty() || url.password().is_some() {
+            return Err(anyhow!("URL must not carry credentials"));
+        }
+
+        // Already IDNA-normalised by the parser
+        let host = url
+            .host_str()
+            .map(|host| host.trim_end_matches('.').to_string())
+            .ok_or_else(|| anyhow!("URL has no host"))?;
+
+        let allowed = self
+            .domains
+            .iter()
+            .any(|domain| host == *domain || host.strip_suffix(domain.as_str()).is_some_and(|sub| sub.ends_with('.')));
+        if !allowed {
+            return Err(anyhow!("Domain {} is not on the allowlist", host));
+        }
+
+        Ok(url)
+    }
+}
+
+// ======================================================================
+// HELPER FUNCTIONS
+// ======================================================================
+
+/// `Bücher.DE.` → `xn--bcher-kva.de`, using the same IDNA mapping as URL parsing
+fn normalize_domain(domain: &str) -> Option<String> {
+    let domain = domain.trim().trim_start_matches("*.");
+    if domain.is_empty() || domain.contains(['/', ':', '@', '?', '#']) {
+        return None;
+    }
+
+    let url = Url::parse(&format!("https://{}/", domain)).ok()?;
+    url.host_str().map(|host| host.trim_end_matches('.').to_string())
+}
+
+// ======================================================================
+// TESTS
+// ======================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(schemes: &[&str], domains: &[&str]) -> UrlPolicy {
+        UrlPolicy::new(&UrlPolicyConfig {
+            allowed_schemes: schemes.iter().map(|s| s.to_string()).collect(),
+            allowed_domains: domains.iter().map(|d| d.to_string()).collect(),
+        })
+    }
+
+    #[test]
+    fn test_schemes() {
+        let policy = policy(&["https", "mailto", "file", "JavaScript:"], &["qris.id"]);
+
+        assert!(policy.check("https://qris.id/pay").is_ok());
+        assert_eq!(policy.check("HTTPS://QRIS.ID/pay").unwrap().as_str(), "https://qris.id/pay");
+        assert!(policy.check("mailto:support@qris.id").is_ok());
+
+        // Blocked even when configured
+        for url in [
+            "file:///etc/passwd",
+            "FILE://C:/Windows/System32/calc.exe",
+            "javascript:alert(1)",
+            "smb://fileserver/share",
+            "http://qris.id/",
+            "ms-msdt:/id PCWDiagnostic",
+            "qris.id/pay",
+            "",
+        ] {
+            assert!(policy.check(url).is_err(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_domains_and_idn() {
+        let policy = policy(&["https"], &["qris.id", "*.Bücher.de", "xn--mnchen-3ya.de", "http://bad/entry"]);
+
+        assert!(policy.check("https://pay.qris.id/x").is_ok());
+        assert!(policy.check("https://qris.id./x").is_ok());
+        assert!(policy.check("https://bücher.de/").is_ok());
+        assert!(policy.check("https://shop.xn--bcher-kva.de/").is_ok());
+        assert!(policy.check("https://münchen.de/").is_ok());
+        assert_eq!(policy.domains.len(), 3);
+
+        for url in [
+            "https://evilqris.id/",
+            "https://qris.id.evil.example/",
+            "https://qris.id@evil.example/",
+            "https://user:pw@qris.id/",
+            // Cyrillic "і" in place of the Latin one
+            "https://qrіs.id/",
+            "https://127.0.0.1/",
+            "https://0x7f000001/",
+            "https://qris.id\n.evil.example/",
+            "https://qr\tis.id/",
+        ] {
+            assert!(policy.check(url).is_err(), "{:?}", url);
+        }
+
+        assert!(policy.check(&format!("https://qris.id/{}", "a".repeat(MAX_URL_LENGTH))).is_err());
+        assert!(UrlPolicy::new(&UrlPolicyConfig::default()).check("https://qris.id/").is_err());
+    }
+}
@@ -7,6 +7,7 @@ use anyhow::{anyhow, Result};
 use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::VecDeque,
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -16,6 +17,8 @@ use tokio::{
     time::{interval, sleep, timeout},
 };
 
+use crate::tunnel::TunnelStatus;
+
 // ======================================================================
 // TYPES
 // ======================================================================
@@ -29,6 +32,10 @@ pub enum WatchdogStatus {
         last_check: Option<String>,
         consecutive_failures: u32,
         uptime: u64,
+        /// `None` until the first probe, or when no database is configured
+        database_online: Option<bool>,
+        /// `None` when the database is reached without an SSH tunnel
+        tunnel: Option<TunnelStatus>,
     },
     Error {
         message: String,
@@ -51,6 +58,10 @@ struct WatchdogConfig {
     pub max_consecutive_failures: u32,
     pub restart_delay: Duration,
     pub health_endpoint: String,
+    pub database_probe_timeout: Duration,
+    /// Restarts within `crash_loop_window` that count as a crash loop
+    pub crash_loop_restarts: u32,
+    pub crash_loop_window: Duration,
 }
 
 impl Default for WatchdogConfig {
@@ -61,6 +72,9 @@ impl Default for WatchdogConfig {
             max_consecutive_failures: 3,
             restart_delay: Duration::from_secs(5),
             health_endpoint: "/api/health".to_string(),
+            database_probe_timeout: Duration::from_secs(3),
+            crash_loop_restarts: 3,
+            crash_loop_window: Duration::from_secs(10 * 60),
         }
     }
 }
@@ -73,6 +87,20 @@ struct WatchdogState {
     last_check_result: Option<HealthCheckResult>,
     started_at: Instant,
     is_running: bool,
+    database_online: Option<bool>,
+    restarts: VecDeque<Instant>,
+}
+
+impl WatchdogState {
+    /// Note a restart and return how many happened within `window`, this one included
+    fn record_restart(&mut self, window: Duration) -> u32 {
+        let now = Instant::now();
+        self.restarts.push_back(now);
+        while self.restarts.front().is_some_and(|at| now.duration_since(*at) > window) {
+            self.restarts.pop_front();
+        }
+        self.restarts.len() as u32
+    }
 }
 
 // ======================================================================
@@ -84,6 +112,10 @@ pub struct Watchdog {
     task_handle: Arc<Mutex<Option<JoinHandle<()>>>>,
     config: WatchdogConfig,
     restart_callback: Option<Arc<dyn Fn() + Send + Sync>>,
+    crash_loop_callback: Option<Arc<dyn Fn(u32) + Send + Sync>>,
+    database_probe: Option<(String, u16)>,
+    database_callback: Option<Arc<dyn Fn(bool) + Send + Sync>>,
+    tunnel_status: Option<Arc<Mutex<TunnelStatus>>>,
 }
 
 impl Watchdog {
@@ -93,6 +125,10 @@ impl Watchdog {
             task_handle: Arc::new(Mutex::new(None)),
             config: WatchdogConfig::default(),
             restart_callback: None,
+            crash_loop_callback: None,
+            database_probe: None,
+            database_callback: None,
+            tunnel_status: None,
         }
     }
     
@@ -102,6 +138,10 @@ impl Watchdog {
             task_handle: Arc::new(Mutex::new(None)),
             config,
             restart_callback: None,
+            crash_loop_callback: None,
+            database_probe: None,
+            database_callback: None,
+            tunnel_status: None,
         }
     }
     
@@ -113,6 +153,53 @@ impl Watchdog {
         self
     }
     
+    /// Register the handler that restarts the sidecar after repeated failed checks
+    pub fn set_restart_callback<F>(&mut self, callback: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.restart_callback = Some(Arc::new(callback));
+    }
+    
+    /// Register the handler invoked instead of a restart once the sidecar is crash-looping;
+    /// it receives the number of restarts within the window
+    pub fn set_crash_loop_callback<F>(&mut self, callback: F)
+    where
+        F: Fn(u32) + Send + Sync + 'static,
+    {
+        self.crash_loop_callback = Some(Arc::new(callback));
+    }
+    
+    /// Follow the sidecar to the port it came back on after a restart
+    pub async fn set_target_port(&self, target_port: u16) {
+        if let Some(state) = self.state.write().await.as_mut() {
+            state.target_port = target_port;
+        }
+    }
+    
+    /// Probe the database server named by `database_url` on every check
+    pub fn set_database_probe(&mut self, database_url: Option<&str>) {
+        self.database_probe = database_url.and_then(|url| crate::utils::parse_host_port(url, 5432));
+        
+        match &self.database_probe {
+            Some((host, port)) => info!("Watchdog will probe database at {}:{}", host, port),
+            None => debug!("No database configured for the watchdog"),
+        }
+    }
+    
+    /// Register the handler invoked when the database goes offline or comes back
+    pub fn set_database_callback<F>(&mut self, callback: F)
+    where
+        F: Fn(bool) + Send + Sync + 'static,
+    {
+        self.database_callback = Some(Arc::new(callback));
+    }
+    
+    /// Report the database tunnel alongside the sidecar health
+    pub fn set_tunnel_status(&mut self, status: Option<Arc<Mutex<TunnelStatus>>>) {
+        self.tunnel_status = status;
+    }
+    
     /// Start the watchdog
     pub async fn start(&mut self, target_port: u16) -> Result<()> {
         info!("Starting watchdog for port {}", target_port);
@@ -135,6 +222,8 @@ impl Watchdog {
                 last_check_result: None,
                 started_at: Instant::now(),
                 is_running: true,
+                database_online: None,
+                restarts: VecDeque::new(),
             });
         }
         
@@ -142,9 +231,20 @@ impl Watchdog {
         let state_clone = Arc::clone(&self.state);
         let config = self.config.clone();
         let restart_callback = self.restart_callback.clone();
+        let crash_loop_callback = self.crash_loop_callback.clone();
+        let database_probe = self.database_probe.clone();
+        let database_callback = self.database_callback.clone();
         
         let task = tokio::spawn(async move {
-            Self::monitoring_loop(state_clone, config, restart_callback).await;
+            Self::monitoring_loop(
+                state_clone,
+                config,
+                restart_callback,
+                crash_loop_callback,
+                database_probe,
+                database_callback,
+            )
+            .await;
         });
         
         // Store task handle
@@ -199,6 +299,10 @@ impl Watchdog {
         
         match state.as_ref() {
             Some(state) => {
+                let tunnel = match &self.tunnel_status {
+                    Some(status) => Some(status.lock().await.clone()),
+                    None => None,
+                };
                 let uptime = state.started_at.elapsed().as_secs();
                 let last_check = state.last_check_result.as_ref()
                     .map(|result| result.timestamp.clone());
@@ -209,6 +313,8 @@ impl Watchdog {
                     last_check,
                     consecutive_failures: state.consecutive_failures,
                     uptime,
+                    database_online: state.database_online,
+                    tunnel,
                 }
             }
             None => WatchdogStatus::Stopped,
@@ -267,6 +373,9 @@ impl Watchdog {
         state: Arc<RwLock<Option<WatchdogState>>>,
         config: WatchdogConfig,
         restart_callback: Option<Arc<dyn Fn() + Send + Sync>>,
+        crash_loop_callback: Option<Arc<dyn Fn(u32) + Send + Sync>>,
+        database_probe: Option<(String, u16)>,
+        database_callback: Option<Arc<dyn Fn(bool) + Send + Sync>>,
     ) {
         let mut interval = interval(config.check_interval);
         
@@ -293,6 +402,14 @@ impl Watchdog {
             debug!("Health check result: success={}, response_time={}ms", 
                    health_result.success, health_result.response_time_ms);
             
+            // Probe the database so the shell knows when to journal offline
+            let database_online = match &database_probe {
+                Some((host, port)) => {
+                    Some(crate::utils::is_host_reachable(host, *port, config.database_probe_timeout).await)
+                }
+                None => None,
+            };
+            
             // Update state
             {
                 let mut state_guard = state.write().await;
@@ -300,6 +417,21 @@ impl Watchdog {
                     state.checks_performed += 1;
                     state.last_check_result = Some(health_result.clone());
                     
+                    if let Some(online) = database_online {
+                        if state.database_online != Some(online) {
+                            if online {
+                                info!("Database is reachable");
+                            } else {
+                                warn!("Database is unreachable, orders will be journaled offline");
+                            }
+                            
+                            state.database_online = Some(online);
+                            if let Some(callback) = &database_callback {
+                                callback(online);
+                            }
+                        }
+                    }
+                    
                     if health_result.success {
                         // Reset failure counter on success
                         if state.consecutive_failures > 0 {
@@ -316,11 +448,24 @@ impl Watchdog {
                         
                         // Check if we should trigger restart
                         if state.consecutive_failures >= config.max_consecutive_failures {
-                            error!("Maximum consecutive failures reached, triggering restart");
+                            let restarts = state.record_restart(config.crash_loop_window);
                             
-                            // Call restart callback if available
-                            if let Some(callback) = &restart_callback {
-                                callback();
+                            match &crash_loop_callback {
+                                // Restarting again will not help; let the shell decide
+                                Some(callback) if restarts >= config.crash_loop_restarts => {
+                                    error!("Sidecar restarted {} times within {:?}, crash loop detected",
+                                           restarts, config.crash_loop_window);
+                                    state.restarts.clear();
+                                    callback(restarts);
+                                }
+                                _ => {
+                                    error!("Maximum consecutive failures reached, triggering restart");
+                                    
+                                    // Call restart callback if available
+                                    if let Some(callback) = &restart_callback {
+                                        callback();
+                                    }
+                                }
                             }
                             
                             // Reset failure counter to prevent immediate re-triggering
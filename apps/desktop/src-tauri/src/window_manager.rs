@@ -0,0 +1,185 @@
+// ======================================================================
+// WINDOW MANAGER
+// Mengelola jendela utama dan jendela tampilan pelanggan (monitor kedua)
+// ======================================================================
+
+use anyhow::{anyhow, Result};
+use log::{debug, info};
+use qrcode::{render::svg, EcLevel, QrCode};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, PhysicalPosition, Window, WindowBuilder, WindowUrl};
+
+// ======================================================================
+// CONSTANTS
+// ======================================================================
+
+pub const MAIN_WINDOW_LABEL: &str = "main";
+pub const CUSTOMER_WINDOW_LABEL: &str = "customer";
+
+/// Route of the customer-facing page served by the sidecar
+const CUSTOMER_WINDOW_PATH: &str = "/customer-display";
+
+/// Event carrying a [`CustomerView`] to the customer window
+pub const CUSTOMER_VIEW_EVENT: &str = "customer-display://update";
+
+// ======================================================================
+// TYPES
+// ======================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisplayInfo {
+    pub index: usize,
+    pub name: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub x: i32,
+    pub y: i32,
+    pub scale_factor: f64,
+    pub is_primary: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CustomerViewLine {
+    pub name: String,
+    pub quantity: f64,
+    pub price: i64,
+    pub total: i64,
+}
+
+/// Everything the customer window renders; amounts are integer Rupiah
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CustomerView {
+    pub lines: Vec<CustomerViewLine>,
+    pub subtotal: i64,
+    pub discount: i64,
+    pub tax: i64,
+    pub total: i64,
+    pub paid: Option<i64>,
+    pub change: Option<i64>,
+    pub qris_payload: Option<String>,
+    /// Rendered from `qris_payload` by the shell
+    #[serde(default, skip_deserializing)]
+    pub qris_svg: Option<String>,
+    pub message: Option<String>,
+}
+
+// ======================================================================
+// WINDOW LOOKUP
+// ======================================================================
+
+pub fn main_window(app: &AppHandle) -> Option<Window> {
+    app.get_window(MAIN_WINDOW_LABEL)
+}
+
+pub fn customer_window(app: &AppHandle) -> Option<Window> {
+    app.get_window(CUSTOMER_WINDOW_LABEL)
+}
+
+/// Show and focus the main window
+pub fn show_main_window(app: &AppHandle) -> Result<()> {
+    let window = main_window(app).ok_or_else(|| anyhow!("Main window not found"))?;
+    window.show()?;
+    let _ = window.set_focus();
+    Ok(())
+}
+
+// ======================================================================
+// CUSTOMER WINDOW
+// ======================================================================
+
+/// List monitors attached to this till
+pub fn list_displays(app: &AppHandle) -> Result<Vec<DisplayInfo>> {
+    let window = main_window(app).ok_or_else(|| anyhow!("Main window not found"))?;
+
+    let primary_position = window
+        .primary_monitor()?
+        .map(|monitor| *monitor.position());
+
+    let displays = window
+        .available_monitors()?
+        .into_iter()
+        .enumerate()
+        .map(|(index, monitor)| DisplayInfo {
+            index,
+            name: monitor.name().cloned(),
+            width: monitor.size().width,
+            height: monitor.size().height,
+            x: monitor.position().x,
+            y: monitor.position().y,
+            scale_factor: monitor.scale_factor(),
+            is_primary: primary_position == Some(*monitor.position()),
+        })
+        .collect();
+
+    Ok(displays)
+}
+
+/// Open (or move) the borderless customer window onto the given display
+pub fn open_customer_window(app: &AppHandle, display_index: usize) -> Result<Window> {
+    let main = main_window(app).ok_or_else(|| anyhow!("Main window not found"))?;
+    let monitors = main.available_monitors()?;
+    let monitor = monitors
+        .get(display_index)
+        .ok_or_else(|| anyhow!("Display {} not found ({} attached)", display_index, monitors.len()))?;
+
+    let window = match customer_window(app) {
+        Some(window) => window,
+        None => {
+            // Serve the customer page from the same origin as the main window
+            let mut url = main.url();
+            url.set_path(CUSTOMER_WINDOW_PATH);
+            url.set_query(None);
+            url.set_fragment(None);
+
+            info!("Opening customer window at {}", url);
+
+            WindowBuilder::new(app, CUSTOMER_WINDOW_LABEL, WindowUrl::External(url))
+                .title("POS Kasir Suite - Pelanggan")
+                .decorations(false)
+                .resizable(false)
+                .skip_taskbar(true)
+                .focused(false)
+                .visible(false)
+                .build()?
+        }
+    };
+
+    let position = monitor.position();
+    debug!("Placing customer window on display {} at {:?}", display_index, position);
+
+    window.set_fullscreen(false)?;
+    window.set_position(PhysicalPosition::new(position.x, position.y))?;
+    window.set_fullscreen(true)?;
+    window.show()?;
+
+    // Keep keyboard focus on the cashier's window
+    let _ = main.set_focus();
+
+    Ok(window)
+}
+
+pub fn close_customer_window(app: &AppHandle) -> Result<()> {
+    if let Some(window) = customer_window(app) {
+        info!("Closing customer window");
+        window.close()?;
+    }
+    Ok(())
+}
+
+/// Render a QRIS payload as an SVG image
+pub fn render_qris_svg(payload: &str) -> Result<String> {
+    let code = QrCode::with_error_correction_level(payload.as_bytes(), EcLevel::M)
+        .map_err(|e| anyhow!("Failed to encode QRIS payload: {}", e))?;
+
+    Ok(code
+        .render::<svg::Color>()
+        .min_dimensions(320, 320)
+        .quiet_zone(true)
+        .build())
+}
+
+/// Forward the latest cart snapshot to the customer window
+pub fn push_customer_view(app: &AppHandle, view: &CustomerView) -> Result<()> {
+    app.emit_to(CUSTOMER_WINDOW_LABEL, CUSTOMER_VIEW_EVENT, view.clone())?;
+    Ok(())
+}
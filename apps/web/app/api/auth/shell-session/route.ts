@@ -0,0 +1,43 @@
+// ======================================================================
+// API ROUTE: SESI SHELL DESKTOP
+// Menerbitkan token peran untuk command Tauri setelah login
+// ======================================================================
+
+import { NextRequest, NextResponse } from 'next/server';
+import { cookies } from 'next/headers';
+import { authenticate, verifyToken } from '@/lib/auth/middleware';
+import { issueShellSession } from '@/lib/auth/shell-session';
+
+// ======================================================================
+// HANDLER API
+// ======================================================================
+
+/**
+ * POST /api/auth/shell-session
+ * Token hanya berlaku untuk proses aplikasi desktop yang menjalankan sidecar ini
+ */
+export async function POST(request: NextRequest) {
+  const auth = await authenticate(request);
+  if (!auth.success || !auth.user) {
+    return NextResponse.json({
+      success: false,
+      error: auth.error || 'Unauthorized'
+    }, { status: auth.status || 401 });
+  }
+
+  const cookieToken = cookies().get('auth-token')?.value;
+  const payload = cookieToken ? verifyToken(cookieToken) : null;
+
+  const session = issueShellSession(auth.user, payload?.exp, payload?.iat);
+  if (!session) {
+    return NextResponse.json({
+      success: false,
+      error: 'Sesi shell tidak tersedia untuk pengguna atau server ini'
+    }, { status: 403 });
+  }
+
+  return NextResponse.json({
+    success: true,
+    data: session
+  });
+}
@@ -0,0 +1,89 @@
+// ======================================================================
+// CATALOG DELTA API ROUTE
+// Perubahan katalog sejak sinkronisasi terakhir untuk cache kasir offline
+// ======================================================================
+
+import { NextRequest, NextResponse } from 'next/server';
+import { z } from 'zod';
+import { and, eq, gt, isNull, or } from 'drizzle-orm';
+import { db, sql } from '@/lib/db/config';
+import { productPrices, products, stocks, taxGroups, units } from '@/lib/db/schema';
+
+// ======================================================================
+// VALIDATION SCHEMAS
+// ======================================================================
+
+const CatalogDeltaQuerySchema = z.object({
+  branchId: z.string().uuid('ID cabang tidak valid'),
+  since: z.string().datetime({ offset: true }).optional()
+});
+
+// ======================================================================
+// API HANDLERS
+// ======================================================================
+
+/**
+ * GET /api/catalog/delta?branchId=...&since=...
+ * Tanpa `since` seluruh katalog cabang dikirim (snapshot awal)
+ */
+export async function GET(request: NextRequest) {
+  try {
+    const { searchParams } = new URL(request.url);
+    const query = CatalogDeltaQuerySchema.parse(Object.fromEntries(searchParams));
+    const since = query.since ? new Date(query.since) : new Date(0);
+
+    // Diambil sebelum query agar perubahan yang terjadi selama query ikut di delta berikutnya
+    const [{ server_time: serverTime }] = await sql`SELECT NOW() AS server_time`;
+
+    const changedProducts = await db
+      .select()
+      .from(products)
+      .where(gt(products.updatedAt, since));
+
+    const changedPrices = await db
+      .select({ price: productPrices })
+      .from(productPrices)
+      .innerJoin(products, eq(productPrices.productId, products.id))
+      .where(and(
+        or(isNull(productPrices.branchId), eq(productPrices.branchId, query.branchId)),
+        or(gt(productPrices.updatedAt, since), gt(products.updatedAt, since))
+      ));
+
+    const changedStocks = await db
+      .select()
+      .from(stocks)
+      .where(and(eq(stocks.branchId, query.branchId), gt(stocks.updatedAt, since)));
+
+    // Tabel kecil, selalu dikirim utuh
+    const allTaxGroups = await db.select().from(taxGroups);
+    const allUnits = await db.select().from(units);
+
+    return NextResponse.json({
+      success: true,
+      data: {
+        serverTime: new Date(serverTime).toISOString(),
+        products: changedProducts,
+        prices: changedPrices.map(row => row.price),
+        taxGroups: allTaxGroups,
+        units: allUnits,
+        stocks: changedStocks
+      }
+    });
+
+  } catch (error) {
+    console.error('Error fetching catalog delta:', error);
+
+    if (error instanceof z.ZodError) {
+      return NextResponse.json({
+        success: false,
+        error: 'Invalid query parameters',
+        details: error.errors
+      }, { status: 400 });
+    }
+
+    return NextResponse.json({
+      success: false,
+      error: 'Internal server error'
+    }, { status: 500 });
+  }
+}
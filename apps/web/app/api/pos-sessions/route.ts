@@ -5,6 +5,7 @@
 
 import { NextRequest, NextResponse } from 'next/server';
 import { z } from 'zod';
+import { authenticateTerminal } from '@/lib/auth/terminal';
 
 // ======================================================================
 // TYPES
@@ -17,6 +18,7 @@ interface PosSession {
   userId: string;
   userName: string;
   sessionNumber: string;
+  terminalId?: string; // PC kasir tempat sesi dibuka
   startTime: Date;
   endTime?: Date;
   startingCash: number;
@@ -377,8 +379,17 @@ export async function GET(request: NextRequest) {
 // POST /api/pos-sessions - Start new session
 export async function POST(request: NextRequest) {
   try {
-    const body = await request.json();
-    const data = SessionStartSchema.parse(body);
+    const rawBody = await request.text();
+
+    const auth = await authenticateTerminal(request, rawBody);
+    if (!auth.ok) {
+      return NextResponse.json({
+        success: false,
+        message: auth.error
+      }, { status: 401 });
+    }
+
+    const data = SessionStartSchema.parse(JSON.parse(rawBody));
 
     // Check if user already has an active session
     const activeSession = mockSessions.find(session => 
@@ -410,6 +421,7 @@ export async function POST(request: NextRequest) {
       userId: data.userId,
       userName: 'Nama Pengguna', // In real implementation, get from users table
       sessionNumber: generateSessionNumber(),
+      terminalId: auth.terminal.terminalId,
       startTime: new Date(),
       startingCash: data.startingCash,
       totalSales: 0,
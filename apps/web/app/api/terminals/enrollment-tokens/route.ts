@@ -0,0 +1,93 @@
+// ======================================================================
+// TERMINAL ENROLLMENT TOKENS API ROUTE
+// Admin membuat token sekali pakai untuk meregistrasi PC kasir ke cabang
+// ======================================================================
+
+import { NextRequest, NextResponse } from 'next/server';
+import { z } from 'zod';
+import { randomBytes } from 'crypto';
+import { and, eq } from 'drizzle-orm';
+import { db } from '@/lib/db/config';
+import { branches, terminalEnrollmentTokens } from '@/lib/db/schema';
+import { authorize } from '@/lib/auth/middleware';
+import { hashEnrollmentToken } from '@/lib/auth/terminal';
+
+// ======================================================================
+// VALIDATION SCHEMAS
+// ======================================================================
+
+const EnrollmentTokenSchema = z.object({
+  branchCode: z.string().min(1, 'Kode cabang wajib diisi'),
+  expiresInMinutes: z.number().int().min(5).max(7 * 24 * 60).default(60)
+});
+
+// ======================================================================
+// API HANDLERS
+// ======================================================================
+
+/**
+ * POST /api/terminals/enrollment-tokens
+ * Token asli hanya dikembalikan sekali; server menyimpan hash-nya saja
+ */
+export async function POST(request: NextRequest) {
+  try {
+    const auth = await authorize(request, ['settings.update']);
+    if (!auth.success || !auth.user) {
+      return NextResponse.json({
+        success: false,
+        error: auth.error || 'Unauthorized'
+      }, { status: auth.status || 401 });
+    }
+
+    const data = EnrollmentTokenSchema.parse(await request.json());
+
+    const [branch] = await db
+      .select()
+      .from(branches)
+      .where(and(eq(branches.code, data.branchCode), eq(branches.isActive, true)))
+      .limit(1);
+
+    if (!branch) {
+      return NextResponse.json({
+        success: false,
+        error: 'Cabang tidak ditemukan'
+      }, { status: 404 });
+    }
+
+    const token = randomBytes(24).toString('base64url');
+    const expiresAt = new Date(Date.now() + data.expiresInMinutes * 60 * 1000);
+
+    await db.insert(terminalEnrollmentTokens).values({
+      tokenHash: hashEnrollmentToken(token),
+      branchId: branch.id,
+      createdBy: auth.user.user_id,
+      expiresAt
+    });
+
+    return NextResponse.json({
+      success: true,
+      data: {
+        token,
+        branchCode: branch.code,
+        expiresAt: expiresAt.toISOString()
+      },
+      message: 'Token pendaftaran dibuat, masukkan di PC kasir sebelum kedaluwarsa'
+    }, { status: 201 });
+
+  } catch (error) {
+    console.error('Error creating enrollment token:', error);
+
+    if (error instanceof z.ZodError) {
+      return NextResponse.json({
+        success: false,
+        error: 'Invalid enrollment token data',
+        details: error.errors
+      }, { status: 400 });
+    }
+
+    return NextResponse.json({
+      success: false,
+      error: 'Internal server error'
+    }, { status: 500 });
+  }
+}
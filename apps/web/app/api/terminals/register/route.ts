@@ -0,0 +1,236 @@
+// ======================================================================
+// TERMINAL REGISTRATION API ROUTE
+// Registrasi sekali PC kasir ke cabang (branches.code)
+// Hanya dengan token pendaftaran sekali pakai atau sesi admin
+// ======================================================================
+
+import { NextRequest, NextResponse } from 'next/server';
+import { z } from 'zod';
+import { and, eq, gt, isNull } from 'drizzle-orm';
+import { db, withTransaction } from '@/lib/db/config';
+import { branches, posTerminals, terminalEnrollmentTokens } from '@/lib/db/schema';
+import { authorize } from '@/lib/auth/middleware';
+import {
+  ENROLLMENT_TOKEN_HEADER,
+  TERMINAL_ID_HEADER,
+  TERMINAL_NONCE_HEADER,
+  TERMINAL_SIGNATURE_HEADER,
+  TERMINAL_TIMESTAMP_HEADER,
+  hashEnrollmentToken,
+  isFreshTimestamp,
+  signingMessage,
+  verifyTerminalSignature
+} from '@/lib/auth/terminal';
+
+/** Permission admin yang boleh mendaftarkan terminal tanpa token */
+const REGISTER_PERMISSIONS = ['settings.update'];
+
+/** Kode error PostgreSQL untuk pelanggaran unique/primary key */
+const UNIQUE_VIOLATION = '23505';
+
+class EnrollmentTokenError extends Error {}
+
+// ======================================================================
+// VALIDATION SCHEMAS
+// ======================================================================
+
+const TerminalRegisterSchema = z.object({
+  terminalId: z.string().uuid('ID terminal tidak valid'),
+  branchCode: z.string().min(1, 'Kode cabang wajib diisi'),
+  terminalCode: z.string().regex(/^T\d{2,4}$/, 'Kode terminal harus T01..T9999').nullish(),
+  name: z.string().max(100).nullish(),
+  publicKey: z.string().min(1, 'Kunci publik wajib diisi')
+});
+
+// ======================================================================
+// API HANDLERS
+// ======================================================================
+
+/**
+ * POST /api/terminals/register
+ * Request ditandatangani dengan kunci baru terminal sebagai bukti kepemilikan,
+ * dan diotorisasi dengan header X-Enrollment-Token atau cookie sesi admin
+ */
+export async function POST(request: NextRequest) {
+  try {
+    const body = await request.text();
+    const data = TerminalRegisterSchema.parse(JSON.parse(body));
+
+    const timestamp = request.headers.get(TERMINAL_TIMESTAMP_HEADER) ?? '';
+    const nonce = request.headers.get(TERMINAL_NONCE_HEADER) ?? '';
+    const signature = request.headers.get(TERMINAL_SIGNATURE_HEADER) ?? '';
+    const message = signingMessage('POST', new URL(request.url).pathname, timestamp, nonce, body);
+
+    if (
+      request.headers.get(TERMINAL_ID_HEADER) !== data.terminalId ||
+      !isFreshTimestamp(timestamp) ||
+      !verifyTerminalSignature(data.publicKey, message, signature)
+    ) {
+      return NextResponse.json({
+        success: false,
+        error: 'Tanda tangan registrasi tidak valid'
+      }, { status: 401 });
+    }
+
+    // Registrasi ulang dengan kunci yang sama mengembalikan data lama (token sudah terpakai)
+    const [existing] = await db
+      .select()
+      .from(posTerminals)
+      .where(eq(posTerminals.id, data.terminalId))
+      .limit(1);
+
+    if (existing) {
+      const [existingBranch] = await db
+        .select()
+        .from(branches)
+        .where(eq(branches.id, existing.branchId))
+        .limit(1);
+
+      if (!existingBranch || existingBranch.code !== data.branchCode || existing.publicKey !== data.publicKey) {
+        return NextResponse.json({
+          success: false,
+          error: 'Terminal sudah terdaftar dengan kunci atau cabang lain'
+        }, { status: 409 });
+      }
+      return NextResponse.json({ success: true, data: registrationData(existing, existingBranch.code) });
+    }
+
+    const enrollmentToken = request.headers.get(ENROLLMENT_TOKEN_HEADER);
+    if (!enrollmentToken) {
+      const admin = await authorize(request, REGISTER_PERMISSIONS);
+      if (!admin.success) {
+        return NextResponse.json({
+          success: false,
+          error: 'Registrasi terminal memerlukan token pendaftaran atau sesi admin'
+        }, { status: admin.status === 403 ? 403 : 401 });
+      }
+    }
+
+    const matchingBranches = await db
+      .select()
+      .from(branches)
+      .where(and(eq(branches.code, data.branchCode), eq(branches.isActive, true)));
+
+    if (matchingBranches.length !== 1) {
+      return NextResponse.json({
+        success: false,
+        error: matchingBranches.length === 0
+          ? 'Cabang tidak ditemukan'
+          : 'Kode cabang tidak unik, hubungi admin'
+      }, { status: matchingBranches.length === 0 ? 404 : 409 });
+    }
+    const branch = matchingBranches[0];
+
+    const branchTerminals = await db
+      .select()
+      .from(posTerminals)
+      .where(eq(posTerminals.branchId, branch.id));
+
+    const terminalCode = data.terminalCode ?? nextTerminalCode(branchTerminals.map(t => t.terminalCode));
+    if (branchTerminals.some(t => t.terminalCode === terminalCode)) {
+      return NextResponse.json({
+        success: false,
+        error: `Kode terminal ${terminalCode} sudah dipakai di cabang ini`,
+        code: 'DUPLICATE_TERMINAL_CODE'
+      }, { status: 409 });
+    }
+
+    const terminal = await withTransaction(async (tx) => {
+      // Token dipakai dalam transaksi yang sama, jadi gagal insert tidak menghabiskannya
+      const tokenHash = enrollmentToken ? hashEnrollmentToken(enrollmentToken) : null;
+      if (tokenHash) {
+        const claimed = await tx
+          .update(terminalEnrollmentTokens)
+          .set({ usedAt: new Date() })
+          .where(and(
+            eq(terminalEnrollmentTokens.tokenHash, tokenHash),
+            eq(terminalEnrollmentTokens.branchId, branch.id),
+            isNull(terminalEnrollmentTokens.usedAt),
+            gt(terminalEnrollmentTokens.expiresAt, new Date())
+          ))
+          .returning();
+        if (claimed.length === 0) {
+          throw new EnrollmentTokenError('Token pendaftaran tidak valid, kedaluwarsa, atau sudah dipakai');
+        }
+      }
+
+      const [inserted] = await tx
+        .insert(posTerminals)
+        .values({
+          id: data.terminalId,
+          branchId: branch.id,
+          terminalCode,
+          name: data.name || `Kasir ${terminalCode}`,
+          publicKey: data.publicKey
+        })
+        .returning();
+
+      if (tokenHash) {
+        await tx
+          .update(terminalEnrollmentTokens)
+          .set({ usedByTerminalId: inserted.id })
+          .where(eq(terminalEnrollmentTokens.tokenHash, tokenHash));
+      }
+      return inserted;
+    });
+
+    return NextResponse.json({
+      success: true,
+      data: registrationData(terminal, branch.code),
+      message: 'Terminal berhasil didaftarkan'
+    }, { status: 201 });
+
+  } catch (error) {
+    console.error('Error registering terminal:', error);
+
+    if (error instanceof EnrollmentTokenError) {
+      return NextResponse.json({
+        success: false,
+        error: error.message
+      }, { status: 401 });
+    }
+
+    // Registrasi bersamaan dengan ID atau kode terminal yang sama
+    if ((error as { code?: string })?.code === UNIQUE_VIOLATION) {
+      return NextResponse.json({
+        success: false,
+        error: 'Terminal atau kode terminal sudah terdaftar'
+      }, { status: 409 });
+    }
+
+    if (error instanceof z.ZodError) {
+      return NextResponse.json({
+        success: false,
+        error: 'Invalid registration data',
+        details: error.errors
+      }, { status: 400 });
+    }
+
+    return NextResponse.json({
+      success: false,
+      error: 'Internal server error'
+    }, { status: 500 });
+  }
+}
+
+// ======================================================================
+// UTILITY FUNCTIONS
+// ======================================================================
+
+function nextTerminalCode(used: string[]): string {
+  const numbers = new Set(used.map(code => Number(code.slice(1))));
+  let next = 1;
+  while (numbers.has(next)) next++;
+  return `T${String(next).padStart(2, '0')}`;
+}
+
+function registrationData(terminal: typeof posTerminals.$inferSelect, branchCode: string) {
+  return {
+    terminalId: terminal.id,
+    branchId: terminal.branchId,
+    branchCode,
+    terminalCode: terminal.terminalCode,
+    name: terminal.name,
+    registeredAt: terminal.registeredAt.toISOString()
+  };
+}
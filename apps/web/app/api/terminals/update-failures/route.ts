@@ -0,0 +1,80 @@
+// ======================================================================
+// TERMINAL UPDATE FAILURES API ROUTE
+// Laporan pembaruan aplikasi yang gagal dan di-rollback oleh PC kasir
+// ======================================================================
+
+import { NextRequest, NextResponse } from 'next/server';
+import { z } from 'zod';
+import { db } from '@/lib/db/config';
+import { terminalUpdateFailures } from '@/lib/db/schema';
+import { authenticateTerminal } from '@/lib/auth/terminal';
+
+// ======================================================================
+// VALIDATION SCHEMAS
+// ======================================================================
+
+const UpdateFailureSchema = z.object({
+  id: z.string().uuid('ID laporan tidak valid'),
+  version: z.string().min(1, 'Versi wajib diisi').max(50),
+  rolledBackTo: z.string().max(50).nullish(),
+  reason: z.string().min(1, 'Alasan wajib diisi').max(1000),
+  failedAt: z.string().datetime({ offset: true })
+});
+
+// ======================================================================
+// API HANDLERS
+// ======================================================================
+
+/**
+ * POST /api/terminals/update-failures
+ * Dikirim oleh sync engine aplikasi desktop; id laporan membuat kiriman ulang aman
+ */
+export async function POST(request: NextRequest) {
+  try {
+    const body = await request.text();
+
+    const auth = await authenticateTerminal(request, body);
+    if (!auth.ok) {
+      return NextResponse.json({
+        success: false,
+        error: auth.error
+      }, { status: 401 });
+    }
+
+    const data = UpdateFailureSchema.parse(JSON.parse(body));
+
+    await db
+      .insert(terminalUpdateFailures)
+      .values({
+        id: data.id,
+        terminalId: auth.terminal.terminalId,
+        branchId: auth.terminal.branchId,
+        version: data.version,
+        rolledBackTo: data.rolledBackTo ?? null,
+        reason: data.reason,
+        failedAt: new Date(data.failedAt)
+      })
+      .onConflictDoNothing();
+
+    return NextResponse.json({
+      success: true,
+      message: 'Laporan pembaruan gagal tercatat'
+    }, { status: 201 });
+
+  } catch (error) {
+    console.error('Error recording update failure:', error);
+
+    if (error instanceof z.ZodError) {
+      return NextResponse.json({
+        success: false,
+        error: 'Invalid update failure data',
+        details: error.errors
+      }, { status: 400 });
+    }
+
+    return NextResponse.json({
+      success: false,
+      error: 'Internal server error'
+    }, { status: 500 });
+  }
+}
@@ -5,6 +5,7 @@
 
 import { NextRequest, NextResponse } from 'next/server';
 import { z } from 'zod';
+import { supervisorApprovalRequired, verifySupervisorApproval } from '@/lib/auth/supervisor-approval';
 
 // ======================================================================
 // TYPES
@@ -34,6 +35,8 @@ interface RefundTransaction {
   metodePengembalian: 'tunai' | 'kartu' | 'digital' | 'kredit';
   alasan: string;
   status: 'pending' | 'disetujui' | 'ditolak' | 'selesai';
+  supervisorId?: string;
+  supervisorNama?: string;
   createdAt: Date;
   updatedAt: Date;
 }
@@ -50,7 +53,8 @@ const RefundRequestSchema = z.object({
   alasan: z.string().min(1, 'Alasan refund wajib diisi'),
   metodePengembalian: z.enum(['tunai', 'kartu', 'digital', 'kredit']).default('tunai'),
   kasirId: z.string().min(1, 'ID kasir wajib diisi'),
-  kasirNama: z.string().min(1, 'Nama kasir wajib diisi')
+  kasirNama: z.string().min(1, 'Nama kasir wajib diisi'),
+  approvalToken: z.string().optional()
 });
 
 // ======================================================================
@@ -209,6 +213,42 @@ export async function POST(
     
     totalRefund = totalRefund - discountRefund + taxRefund;
 
+    // Validate supervisor approval (PIN/OTP dari aplikasi desktop)
+    let supervisor: { id: string; nama: string } | undefined;
+    
+    if (refundData.approvalToken) {
+      const approval = await verifySupervisorApproval(refundData.approvalToken, 'refund', { transaction_id: id });
+      
+      if (!approval.valid) {
+        return NextResponse.json({
+          success: false,
+          error: approval.error
+        }, { status: 401 });
+      }
+      
+      // Supervisor menyetujui nominal tertentu; refund tidak boleh melebihinya
+      const approvedAmount = approval.claims.context.amount;
+      if (typeof approvedAmount !== 'number') {
+        return NextResponse.json({
+          success: false,
+          error: 'Persetujuan refund tidak mencantumkan nominal'
+        }, { status: 401 });
+      }
+      if (totalRefund > approvedAmount) {
+        return NextResponse.json({
+          success: false,
+          error: 'Total refund melebihi nominal yang disetujui supervisor'
+        }, { status: 401 });
+      }
+      
+      supervisor = { id: approval.claims.supervisor_id, nama: approval.claims.supervisor_name };
+    } else if (supervisorApprovalRequired()) {
+      return NextResponse.json({
+        success: false,
+        error: 'Persetujuan supervisor (PIN/OTP) wajib untuk refund'
+      }, { status: 401 });
+    }
+
     // Create refund transaction
     const refundTransaction: RefundTransaction = {
       id: generateId('ref'),
@@ -222,7 +262,10 @@ export async function POST(
       totalRefund,
       metodePengembalian: refundData.metodePengembalian,
       alasan: refundData.alasan,
-      status: 'pending',
+      // Tanpa persetujuan supervisor, refund menunggu persetujuan
+      status: supervisor ? 'disetujui' : 'pending',
+      supervisorId: supervisor?.id,
+      supervisorNama: supervisor?.nama,
       createdAt: new Date(),
       updatedAt: new Date()
     };
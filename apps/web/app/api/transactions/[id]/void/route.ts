@@ -5,6 +5,7 @@
 
 import { NextRequest, NextResponse } from 'next/server';
 import { z } from 'zod';
+import { supervisorApprovalRequired, verifySupervisorApproval } from '@/lib/auth/supervisor-approval';
 
 // ======================================================================
 // TYPES
@@ -32,7 +33,8 @@ interface VoidTransaction {
 
 const VoidRequestSchema = z.object({
   alasan: z.string().min(1, 'Alasan void wajib diisi'),
-  supervisorPassword: z.string().min(1, 'Password supervisor wajib diisi'),
+  supervisorPassword: z.string().optional(),
+  approvalToken: z.string().optional(),
   kasirId: z.string().min(1, 'ID kasir wajib diisi'),
   kasirNama: z.string().min(1, 'Nama kasir wajib diisi')
 });
@@ -166,14 +168,36 @@ export async function POST(
       }, { status: 400 });
     }
 
-    // Validate supervisor password
-    const supervisorValidation = validateSupervisorPassword(voidData.supervisorPassword);
+    // Validate supervisor approval: token PIN/OTP dari aplikasi desktop, atau password di web
+    let supervisor: { id: string; nama: string };
     
-    if (!supervisorValidation.isValid) {
+    if (voidData.approvalToken) {
+      const approval = await verifySupervisorApproval(voidData.approvalToken, 'void', { transaction_id: id });
+      
+      if (!approval.valid) {
+        return NextResponse.json({
+          success: false,
+          error: approval.error
+        }, { status: 401 });
+      }
+      
+      supervisor = { id: approval.claims.supervisor_id, nama: approval.claims.supervisor_name };
+    } else if (supervisorApprovalRequired()) {
       return NextResponse.json({
         success: false,
-        error: 'Password supervisor tidak valid'
+        error: 'Persetujuan supervisor (PIN/OTP) wajib untuk void'
       }, { status: 401 });
+    } else {
+      const supervisorValidation = validateSupervisorPassword(voidData.supervisorPassword || '');
+      
+      if (!supervisorValidation.isValid) {
+        return NextResponse.json({
+          success: false,
+          error: 'Password supervisor tidak valid'
+        }, { status: 401 });
+      }
+      
+      supervisor = supervisorValidation.supervisor;
     }
 
     // Check if transaction has already been voided
@@ -196,11 +220,11 @@ export async function POST(
       tanggal: new Date(),
       kasirId: voidData.kasirId,
       kasirNama: voidData.kasirNama,
-      supervisorId: supervisorValidation.supervisor.id,
-      supervisorNama: supervisorValidation.supervisor.nama,
+      supervisorId: supervisor.id,
+      supervisorNama: supervisor.nama,
       alasan: voidData.alasan,
       totalVoid: transaction.total,
-      status: 'disetujui', // Auto-approve since supervisor password is validated
+      status: 'disetujui', // Auto-approve since supervisor approval is validated
       createdAt: new Date(),
       updatedAt: new Date()
     };
@@ -5,6 +5,11 @@
 
 import { NextRequest, NextResponse } from 'next/server';
 import { z } from 'zod';
+import { and, eq } from 'drizzle-orm';
+import { db } from '@/lib/db/config';
+import { branches, idempotencyKeys } from '@/lib/db/schema';
+import { authenticateTerminal } from '@/lib/auth/terminal';
+import type { TerminalContext } from '@/lib/auth/terminal';
 
 // ======================================================================
 // TYPES
@@ -33,6 +38,7 @@ interface Transaction {
   id: string;
   nomorTransaksi: string;
   tanggal: Date;
+  terminalId?: string; // PC kasir asal transaksi
   kasirId: string;
   kasirNama: string;
   pelangganId?: string;
@@ -41,6 +47,7 @@ interface Transaction {
   subtotal: number;
   diskon: number;
   pajak: number;
+  pembulatan: number;
   total: number;
   dibayar: number;
   kembalian: number;
@@ -98,7 +105,15 @@ const PaymentSchema = z.object({
   referensi: z.string().optional()
 });
 
+// Nomor struk dari terminal; server memakai prefix huruf sehingga tidak pernah bentrok
+const TERMINAL_ORDER_NO_PATTERN = /^[A-Z0-9]{2,10}-T\d{2,4}-(\d{8})-\d{5}$/;
+
 const TransactionCreateSchema = z.object({
+  // Diisi oleh kasir offline saat jurnal lokal dikirim ulang (CABANG-Txx-YYYYMMDD-NNNNN)
+  nomorTransaksi: z.string()
+    .regex(TERMINAL_ORDER_NO_PATTERN, 'Nomor transaksi terminal tidak valid')
+    .optional(),
+  tanggal: z.string().datetime({ offset: true }).optional(),
   kasirId: z.string().min(1, 'ID kasir wajib diisi'),
   kasirNama: z.string().min(1, 'Nama kasir wajib diisi'),
   pelangganId: z.string().optional(),
@@ -106,8 +121,16 @@ const TransactionCreateSchema = z.object({
   items: z.array(TransactionItemSchema).min(1, 'Minimal satu item harus ada'),
   payments: z.array(PaymentSchema).min(1, 'Minimal satu pembayaran harus ada'),
   diskon: z.number().min(0, 'Diskon tidak boleh negatif').default(0),
+  // Dikirim oleh terminal sesuai struk yang sudah dicetak; bila kosong dihitung server
+  pajak: z.number().min(0, 'Pajak tidak boleh negatif').optional(),
+  pembulatan: z.number().default(0),
+  status: z.enum(['selesai', 'dibatalkan', 'refund']).optional(),
   catatan: z.string().optional()
-});
+}).refine(
+  data => !data.nomorTransaksi || !data.tanggal ||
+    data.nomorTransaksi.match(TERMINAL_ORDER_NO_PATTERN)?.[1] === wibDate(new Date(data.tanggal)),
+  { message: 'Tanggal pada nomor transaksi tidak sesuai tanggal transaksi', path: ['nomorTransaksi'] }
+);
 
 const TransactionUpdateSchema = z.object({
   status: z.enum(['draft', 'selesai', 'dibatalkan', 'refund']).optional(),
@@ -152,6 +175,7 @@ let mockTransactions: Transaction[] = [
     subtotal: 10000,
     diskon: 0,
     pajak: 1000,
+    pembulatan: 0,
     total: 11000,
     dibayar: 15000,
     kembalian: 4000,
@@ -189,6 +213,7 @@ let mockTransactions: Transaction[] = [
     subtotal: 50000,
     diskon: 5000,
     pajak: 0,
+    pembulatan: 0,
     total: 45000,
     dibayar: 45000,
     kembalian: 0,
@@ -207,20 +232,64 @@ let mockTransactions: Transaction[] = [
   }
 ];
 
+// Scope Idempotency-Key di tabel idempotency_keys
+const IDEMPOTENCY_SCOPE = 'transactions';
+
 // ======================================================================
 // UTILITY FUNCTIONS
 // ======================================================================
 
-function calculateTransactionTotals(items: TransactionItem[], globalDiskon: number = 0) {
+function calculateTransactionTotals(
+  items: TransactionItem[],
+  globalDiskon: number = 0,
+  pajakStruk?: number,
+  pembulatan: number = 0
+) {
   const subtotal = items.reduce((sum, item) => sum + item.subtotal, 0);
   const diskon = globalDiskon;
   const subtotalAfterDiskon = subtotal - diskon;
   
-  // Calculate tax (10% for taxable items)
-  const pajak = Math.round(subtotalAfterDiskon * 0.1);
-  const total = subtotalAfterDiskon + pajak;
+  // Calculate tax (10% for taxable items) unless the receipt already fixed it
+  const pajak = pajakStruk ?? Math.round(subtotalAfterDiskon * 0.1);
+  const total = subtotalAfterDiskon + pajak + pembulatan;
   
-  return { subtotal, diskon, pajak, total };
+  return { subtotal, diskon, pajak, pembulatan, total };
+}
+
+/**
+ * Transaksi yang sudah dibuat untuk Idempotency-Key ini, bila ada
+ */
+async function findIdempotentTransaction(key: string) {
+  const [row] = await db
+    .select({ resourceId: idempotencyKeys.resourceId })
+    .from(idempotencyKeys)
+    .where(and(eq(idempotencyKeys.scope, IDEMPOTENCY_SCOPE), eq(idempotencyKeys.key, key)))
+    .limit(1);
+  if (!row) {
+    return null;
+  }
+  return mockTransactions.find(t => t.id === row.resourceId) ?? { id: row.resourceId };
+}
+
+/**
+ * Nomor struk terminal hanya boleh dari rentang terminal pengirimnya (CABANG-Txx)
+ */
+async function isOwnOrderNumber(nomorTransaksi: string, terminal: TerminalContext): Promise<boolean> {
+  const [branchCode, terminalCode] = nomorTransaksi.split('-');
+  if (terminalCode !== terminal.terminalCode) {
+    return false;
+  }
+  const [branch] = await db
+    .select({ code: branches.code })
+    .from(branches)
+    .where(eq(branches.id, terminal.branchId))
+    .limit(1);
+  return branch?.code === branchCode;
+}
+
+function wibDate(date: Date): string {
+  const wib = new Date(date.getTime() + 7 * 60 * 60 * 1000);
+  return wib.toISOString().slice(0, 10).replace(/-/g, '');
 }
 
 function generateTransactionNumber(): string {
@@ -403,8 +472,52 @@ export async function GET(request: NextRequest) {
 // POST /api/transactions - Create new transaction
 export async function POST(request: NextRequest) {
   try {
-    const body = await request.json();
-    const transactionData = TransactionCreateSchema.parse(body);
+    const rawBody = await request.text();
+
+    // Request dari terminal kasir wajib bertanda tangan valid
+    const auth = await authenticateTerminal(request, rawBody);
+    if (!auth.ok) {
+      return NextResponse.json({
+        success: false,
+        error: auth.error
+      }, { status: 401 });
+    }
+
+    const idempotencyKey = request.headers.get('idempotency-key');
+    if (idempotencyKey) {
+      const existing = await findIdempotentTransaction(idempotencyKey);
+      if (existing) {
+        return NextResponse.json({
+          success: true,
+          data: existing,
+          message: 'Transaksi sudah tercatat'
+        });
+      }
+    }
+
+    const transactionData = TransactionCreateSchema.parse(JSON.parse(rawBody));
+
+    if (
+      transactionData.nomorTransaksi &&
+      !(await isOwnOrderNumber(transactionData.nomorTransaksi, auth.terminal))
+    ) {
+      return NextResponse.json({
+        success: false,
+        error: 'Nomor transaksi bukan milik terminal ini',
+        code: 'FOREIGN_ORDER_NO'
+      }, { status: 403 });
+    }
+
+    if (
+      transactionData.nomorTransaksi &&
+      mockTransactions.some(t => t.nomorTransaksi === transactionData.nomorTransaksi)
+    ) {
+      return NextResponse.json({
+        success: false,
+        error: 'Nomor transaksi sudah digunakan',
+        code: 'DUPLICATE_ORDER_NO'
+      }, { status: 409 });
+    }
 
     // Calculate item subtotals
     const items: TransactionItem[] = transactionData.items.map(item => ({
@@ -414,7 +527,12 @@ export async function POST(request: NextRequest) {
     }));
 
     // Calculate totals
-    const totals = calculateTransactionTotals(items, transactionData.diskon);
+    const totals = calculateTransactionTotals(
+      items,
+      transactionData.diskon,
+      transactionData.pajak,
+      transactionData.pembulatan
+    );
     
     // Calculate payments
     const totalPaid = transactionData.payments.reduce((sum, payment) => sum + payment.jumlah, 0);
@@ -427,11 +545,34 @@ export async function POST(request: NextRequest) {
       status: 'berhasil' as const
     }));
 
+    // Reserve the key before recording, so a concurrent resend cannot book it twice
+    const id = generateId('trx');
+    if (idempotencyKey) {
+      const reserved = await db
+        .insert(idempotencyKeys)
+        .values({
+          scope: IDEMPOTENCY_SCOPE,
+          key: idempotencyKey,
+          terminalId: auth.terminal.terminalId,
+          resourceId: id
+        })
+        .onConflictDoNothing()
+        .returning({ resourceId: idempotencyKeys.resourceId });
+      if (reserved.length === 0) {
+        return NextResponse.json({
+          success: true,
+          data: await findIdempotentTransaction(idempotencyKey),
+          message: 'Transaksi sudah tercatat'
+        });
+      }
+    }
+
     // Create new transaction
     const newTransaction: Transaction = {
-      id: generateId('trx'),
-      nomorTransaksi: generateTransactionNumber(),
-      tanggal: new Date(),
+      id,
+      nomorTransaksi: transactionData.nomorTransaksi ?? generateTransactionNumber(),
+      terminalId: auth.terminal.terminalId,
+      tanggal: transactionData.tanggal ? new Date(transactionData.tanggal) : new Date(),
       kasirId: transactionData.kasirId,
       kasirNama: transactionData.kasirNama,
       pelangganId: transactionData.pelangganId,
@@ -440,11 +581,12 @@ export async function POST(request: NextRequest) {
       subtotal: totals.subtotal,
       diskon: totals.diskon,
       pajak: totals.pajak,
+      pembulatan: totals.pembulatan,
       total: totals.total,
       dibayar: totalPaid,
       kembalian,
       payments,
-      status: totalPaid >= totals.total ? 'selesai' : 'draft',
+      status: transactionData.status ?? (totalPaid >= totals.total ? 'selesai' : 'draft'),
       catatan: transactionData.catatan,
       createdAt: new Date(),
       updatedAt: new Date()
@@ -0,0 +1,139 @@
+// ======================================================================
+// HALAMAN TAMPILAN PELANGGAN
+// Jendela monitor kedua: keranjang, total dan QRIS untuk pelanggan
+// ======================================================================
+
+'use client';
+
+import { useEffect, useState } from 'react';
+import {
+  Text,
+  Title1,
+  Title2,
+  Body1,
+  Divider
+} from '@fluentui/react-components';
+import { formatCurrency } from '@/lib/utils/format';
+
+// ======================================================================
+// TIPE DATA
+// ======================================================================
+
+interface CustomerViewLine {
+  name: string;
+  quantity: number;
+  price: number;
+  total: number;
+}
+
+interface CustomerView {
+  lines: CustomerViewLine[];
+  subtotal: number;
+  discount: number;
+  tax: number;
+  total: number;
+  paid?: number | null;
+  change?: number | null;
+  qris_payload?: string | null;
+  qris_svg?: string | null;
+  message?: string | null;
+}
+
+interface CommandResult<T> {
+  success: boolean;
+  data?: T;
+  error?: string;
+}
+
+const CUSTOMER_VIEW_EVENT = 'customer-display://update';
+
+const EMPTY_VIEW: CustomerView = {
+  lines: [],
+  subtotal: 0,
+  discount: 0,
+  tax: 0,
+  total: 0
+};
+
+// ======================================================================
+// KOMPONEN UTAMA
+// ======================================================================
+
+export default function CustomerDisplayPage() {
+  const [view, setView] = useState<CustomerView>(EMPTY_VIEW);
+
+  useEffect(() => {
+    const tauri = (window as any).__TAURI__;
+    if (!tauri) return;
+
+    let unlisten: (() => void) | undefined;
+
+    tauri.invoke('get_customer_view').then((result: CommandResult<CustomerView>) => {
+      if (result.success && result.data) setView(result.data);
+    });
+
+    tauri.event
+      .listen(CUSTOMER_VIEW_EVENT, (event: { payload: CustomerView }) => setView(event.payload))
+      .then((fn: () => void) => { unlisten = fn; });
+
+    return () => unlisten?.();
+  }, []);
+
+  const lastLines = view.lines.slice(-8);
+
+  return (
+    <div className="flex h-screen w-screen bg-white select-none cursor-none">
+      <div className="flex flex-1 flex-col p-8">
+        <Title2>Belanjaan Anda</Title2>
+        <Divider className="my-4" />
+
+        <div className="flex-1 space-y-3 overflow-hidden">
+          {lastLines.length === 0 && (
+            <Body1>{view.message || 'Selamat datang, silakan berbelanja'}</Body1>
+          )}
+          {lastLines.map((line, index) => (
+            <div key={index} className="flex justify-between">
+              <div className="flex flex-col">
+                <Text size={500}>{line.name}</Text>
+                <Text size={300}>{line.quantity} x {formatCurrency(line.price)}</Text>
+              </div>
+              <Text size={500} weight="semibold">{formatCurrency(line.total)}</Text>
+            </div>
+          ))}
+        </div>
+
+        <Divider className="my-4" />
+        <div className="space-y-1">
+          <div className="flex justify-between"><Text>Subtotal</Text><Text>{formatCurrency(view.subtotal)}</Text></div>
+          {view.discount > 0 && (
+            <div className="flex justify-between"><Text>Diskon</Text><Text>-{formatCurrency(view.discount)}</Text></div>
+          )}
+          {view.tax > 0 && (
+            <div className="flex justify-between"><Text>PPN</Text><Text>{formatCurrency(view.tax)}</Text></div>
+          )}
+          <div className="flex justify-between">
+            <Title1>Total</Title1>
+            <Title1>{formatCurrency(view.total)}</Title1>
+          </div>
+          {view.paid != null && (
+            <div className="flex justify-between"><Text size={500}>Bayar</Text><Text size={500}>{formatCurrency(view.paid)}</Text></div>
+          )}
+          {view.change != null && (
+            <div className="flex justify-between"><Text size={500}>Kembali</Text><Text size={500}>{formatCurrency(view.change)}</Text></div>
+          )}
+        </div>
+      </div>
+
+      {view.qris_svg && (
+        <div className="flex w-[420px] flex-col items-center justify-center border-l p-8">
+          <Title2>Scan QRIS</Title2>
+          <div
+            className="mt-6 w-[320px]"
+            dangerouslySetInnerHTML={{ __html: view.qris_svg }}
+          />
+          <Body1 className="mt-4">{formatCurrency(view.total)}</Body1>
+        </div>
+      )}
+    </div>
+  );
+}
@@ -225,6 +225,51 @@ const paymentMethods: PaymentMethod[] = [
   }
 ];
 
+// ======================================================================
+// TAMPILAN PELANGGAN
+// ======================================================================
+
+/** Sama dengan `CustomerView` di window_manager.rs; nominal dalam Rupiah bulat */
+interface CustomerView {
+  lines: { name: string; quantity: number; price: number; total: number }[];
+  subtotal: number;
+  discount: number;
+  tax: number;
+  total: number;
+  paid: number | null;
+  change: number | null;
+}
+
+function customerView(items: CartItem[], paid: number | null = null, change: number | null = null): CustomerView {
+  return {
+    lines: items.map(item => ({
+      name: item.product.name,
+      quantity: item.quantity,
+      price: Math.round(item.unitPrice),
+      total: Math.round(item.total)
+    })),
+    subtotal: Math.round(items.reduce((sum, item) => sum + item.subtotal, 0)),
+    discount: Math.round(items.reduce((sum, item) => sum + item.discount * item.quantity, 0)),
+    tax: Math.round(items.reduce((sum, item) => sum + item.tax, 0)),
+    total: Math.round(items.reduce((sum, item) => sum + item.total, 0)),
+    paid: paid === null ? null : Math.round(paid),
+    change: change === null ? null : Math.round(change)
+  };
+}
+
+/**
+ * Kirim keranjang ke aplikasi desktop: tampil di layar pelanggan, dan selama ada
+ * item tanpa kembalian pembaruan aplikasi tidak dipasang
+ */
+function pushCustomerView(view: CustomerView) {
+  const tauri = (window as any).__TAURI__;
+  if (!tauri) return;
+
+  tauri.invoke('update_customer_view', { view }).catch((error: unknown) => {
+    console.error('Failed to update customer view:', error);
+  });
+}
+
 // ======================================================================
 // MAIN COMPONENT
 // ======================================================================
@@ -569,6 +614,22 @@ function POSPageContent() {
     barcodeInputRef.current?.focus();
   }, []);
 
+  // Keranjang yang sedang diinput, atau transaksi terakhir beserta kembaliannya
+  useEffect(() => {
+    if (cart.length > 0) {
+      pushCustomerView(customerView(cart));
+    } else if (currentTransaction) {
+      pushCustomerView(customerView(currentTransaction.items, currentTransaction.amountPaid, currentTransaction.change));
+    } else {
+      pushCustomerView(customerView([]));
+    }
+  }, [cart, currentTransaction]);
+
+  // Keranjang hilang saat halaman ditinggalkan, jadi layar pelanggan ikut dikosongkan
+  useEffect(() => {
+    return () => pushCustomerView(customerView([]));
+  }, []);
+
   // ======================================================================
   // RENDER
   // ======================================================================
@@ -68,6 +68,7 @@ export const PUBLIC_ROUTES = [
 export const PROTECTED_ROUTES = [
   '/api/auth/me',
   '/api/auth/logout',
+  '/api/auth/shell-session',
   '/dashboard',
   '/profile'
 ];
@@ -0,0 +1,69 @@
+// ======================================================================
+// SESI SHELL DESKTOP
+// Token peran yang ditandatangani untuk command Tauri yang sensitif
+// ======================================================================
+
+import { createHmac } from 'crypto';
+import type { AuthUser } from '@/lib/auth/middleware';
+
+// ======================================================================
+// KONSTANTA
+// ======================================================================
+
+/** Kunci HMAC dari aplikasi desktop, berganti setiap kali aplikasi dijalankan */
+export const SHELL_SESSION_SECRET_ENV = 'POS_SHELL_SESSION_SECRET';
+
+/** Masa berlaku maksimum token, satu shift kerja */
+const SHELL_SESSION_TTL_SECONDS = 12 * 60 * 60;
+
+// ======================================================================
+// TIPE DATA
+// ======================================================================
+
+export type ShellRole = 'cashier' | 'supervisor' | 'admin';
+
+export interface ShellSession {
+  token: string;
+  role: ShellRole;
+  expires_at: string;
+}
+
+// ======================================================================
+// FUNGSI
+// ======================================================================
+
+/**
+ * Peran shell dari permission pengguna; null bila tidak boleh memakai kasir
+ */
+export function shellRoleFor(permissions: string[]): ShellRole | null {
+  if (permissions.includes('*') || permissions.includes('settings.update')) return 'admin';
+  if (permissions.includes('transactions.update')) return 'supervisor';
+  if (permissions.includes('transactions.create')) return 'cashier';
+  return null;
+}
+
+/**
+ * Token `base64url(klaim).base64url(hmac)` yang diverifikasi oleh shell (ipc.rs)
+ * `authTime` adalah waktu login dengan password (iat JWT); shell hanya membuka kunci layar
+ * untuk login yang lebih baru dari kuncinya, bukan untuk token yang diterbitkan ulang saat reload
+ * Null bila web app tidak berjalan di dalam aplikasi desktop
+ */
+export function issueShellSession(user: AuthUser, tokenExpiresAt?: number, authTime?: number): ShellSession | null {
+  const secret = process.env[SHELL_SESSION_SECRET_ENV];
+  const role = shellRoleFor(user.permissions);
+  if (!secret || !role) return null;
+
+  const now = Math.floor(Date.now() / 1000);
+  const exp = Math.min(now + SHELL_SESSION_TTL_SECONDS, tokenExpiresAt ?? Number.MAX_SAFE_INTEGER);
+
+  const payload = Buffer.from(JSON.stringify({ sub: user.user_id, role, exp, auth_time: authTime ?? 0 })).toString('base64url');
+  const signature = createHmac('sha256', Buffer.from(secret, 'base64'))
+    .update(payload)
+    .digest('base64url');
+
+  return {
+    token: `${payload}.${signature}`,
+    role,
+    expires_at: new Date(exp * 1000).toISOString()
+  };
+}
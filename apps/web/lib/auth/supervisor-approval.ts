@@ -0,0 +1,126 @@
+// ======================================================================
+// PERSETUJUAN SUPERVISOR
+// Verifikasi token persetujuan (PIN/OTP) yang diterbitkan aplikasi desktop
+// ======================================================================
+
+import { createHmac, timingSafeEqual } from 'crypto';
+import { db } from '@/lib/db/config';
+import { idempotencyKeys } from '@/lib/db/schema';
+import { SHELL_SESSION_SECRET_ENV } from '@/lib/auth/shell-session';
+
+// ======================================================================
+// KONSTANTA
+// ======================================================================
+
+/** Harus sama dengan `APPROVAL_KEY_PURPOSE` di approval.rs */
+const APPROVAL_KEY_PURPOSE = 'supervisor-approval';
+
+/** Scope di `idempotency_keys` untuk `jti` yang sudah dipakai */
+const USED_APPROVAL_SCOPE = 'supervisor-approval';
+
+// ======================================================================
+// TIPE DATA
+// ======================================================================
+
+export type ApprovalAction = 'void' | 'refund';
+
+export interface ApprovalClaims {
+  jti: string;
+  action: ApprovalAction;
+  context: Record<string, unknown>;
+  supervisor_id: string;
+  supervisor_name: string;
+  requested_by: string | null;
+  iat: number;
+  exp: number;
+}
+
+export type ApprovalResult =
+  | { valid: true; claims: ApprovalClaims }
+  | { valid: false; error: string };
+
+// ======================================================================
+// FUNGSI
+// ======================================================================
+
+/**
+ * True bila web app berjalan di dalam aplikasi desktop, sehingga persetujuan
+ * supervisor wajib lewat token dari shell
+ */
+export function supervisorApprovalRequired(): boolean {
+  return !!process.env[SHELL_SESSION_SECRET_ENV];
+}
+
+/**
+ * Verifikasi token `base64url(klaim).base64url(hmac)` untuk `action`.
+ * Setiap field di `expected` harus sama dengan context yang disetujui.
+ * Token yang valid langsung ditandai terpakai di database, sehingga tidak bisa
+ * diulang meski sidecar di-restart sebelum token kedaluwarsa.
+ */
+export async function verifySupervisorApproval(
+  token: string,
+  action: ApprovalAction,
+  expected: Record<string, string | number>
+): Promise<ApprovalResult> {
+  const secret = process.env[SHELL_SESSION_SECRET_ENV];
+  if (!secret) {
+    return { valid: false, error: 'Persetujuan supervisor hanya tersedia di aplikasi desktop' };
+  }
+
+  const [payload, signature] = token.split('.');
+  if (!payload || !signature) {
+    return { valid: false, error: 'Token persetujuan tidak valid' };
+  }
+
+  const key = createHmac('sha256', Buffer.from(secret, 'base64'))
+    .update(APPROVAL_KEY_PURPOSE)
+    .digest();
+  const expectedSignature = createHmac('sha256', key).update(payload).digest();
+  const actualSignature = Buffer.from(signature, 'base64url');
+  if (actualSignature.length !== expectedSignature.length || !timingSafeEqual(actualSignature, expectedSignature)) {
+    return { valid: false, error: 'Tanda tangan token persetujuan tidak valid' };
+  }
+
+  let claims: ApprovalClaims;
+  try {
+    claims = JSON.parse(Buffer.from(payload, 'base64url').toString('utf-8'));
+  } catch {
+    return { valid: false, error: 'Token persetujuan tidak valid' };
+  }
+
+  const now = Math.floor(Date.now() / 1000);
+  if (claims.exp <= now) {
+    return { valid: false, error: 'Persetujuan supervisor sudah kedaluwarsa' };
+  }
+  if (claims.action !== action) {
+    return { valid: false, error: 'Persetujuan supervisor untuk aksi lain' };
+  }
+  const mismatch = Object.keys(expected).some(field => claims.context?.[field] !== expected[field]);
+  if (mismatch) {
+    return { valid: false, error: 'Persetujuan supervisor untuk transaksi lain' };
+  }
+
+  if (typeof claims.jti !== 'string' || !(await claimApproval(claims))) {
+    return { valid: false, error: 'Token persetujuan sudah dipakai' };
+  }
+
+  return { valid: true, claims };
+}
+
+/**
+ * Tandai `jti` terpakai; false bila token sudah pernah dipakai
+ */
+async function claimApproval(claims: ApprovalClaims): Promise<boolean> {
+  const transactionId = claims.context?.transaction_id;
+  const claimed = await db
+    .insert(idempotencyKeys)
+    .values({
+      scope: USED_APPROVAL_SCOPE,
+      key: claims.jti,
+      resourceId: typeof transactionId === 'string' ? transactionId : claims.jti
+    })
+    .onConflictDoNothing()
+    .returning({ key: idempotencyKeys.key });
+
+  return claimed.length > 0;
+}
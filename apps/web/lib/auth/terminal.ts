@@ -0,0 +1,165 @@
+// ======================================================================
+// AUTENTIKASI TERMINAL
+// Verifikasi request bertanda tangan Ed25519 dari PC kasir terdaftar
+// ======================================================================
+
+import { NextRequest } from 'next/server';
+import { createHash, createPublicKey, verify } from 'crypto';
+import { and, eq, lt } from 'drizzle-orm';
+import { db } from '@/lib/db/config';
+import { posTerminals, terminalRequestNonces } from '@/lib/db/schema';
+
+// ======================================================================
+// KONSTANTA
+// ======================================================================
+
+export const TERMINAL_ID_HEADER = 'x-terminal-id';
+export const TERMINAL_TIMESTAMP_HEADER = 'x-terminal-timestamp';
+export const TERMINAL_SIGNATURE_HEADER = 'x-terminal-signature';
+export const TERMINAL_NONCE_HEADER = 'x-terminal-nonce';
+export const ENROLLMENT_TOKEN_HEADER = 'x-enrollment-token';
+
+/** Selisih jam maksimum antara terminal dan server */
+const MAX_CLOCK_SKEW_SECONDS = 300;
+
+/** Nonce: 16-64 karakter alfanumerik atau tanda hubung (uuid) */
+const NONCE_PATTERN = /^[A-Za-z0-9-]{16,64}$/;
+
+/** Header DER SubjectPublicKeyInfo untuk kunci Ed25519 mentah 32 byte */
+const ED25519_SPKI_PREFIX = Buffer.from('302a300506032b6570032100', 'hex');
+
+// ======================================================================
+// TIPE DATA
+// ======================================================================
+
+export interface TerminalContext {
+  terminalId: string;
+  branchId: string;
+  terminalCode: string;
+}
+
+export type TerminalAuthResult =
+  | { ok: true; terminal: TerminalContext }
+  | { ok: false; error: string };
+
+// ======================================================================
+// FUNGSI
+// ======================================================================
+
+/**
+ * Pesan yang ditandatangani: METHOD, path, timestamp, nonce, lalu body apa adanya
+ */
+export function signingMessage(method: string, path: string, timestamp: string, nonce: string, body: string): Buffer {
+  return Buffer.concat([
+    Buffer.from(`${method.toUpperCase()}\n${path}\n${timestamp}\n${nonce}\n`),
+    Buffer.from(body)
+  ]);
+}
+
+/**
+ * Timestamp request (detik Unix) masih dalam jendela selisih jam
+ */
+export function isFreshTimestamp(timestamp: string): boolean {
+  const skew = Math.abs(Date.now() / 1000 - Number(timestamp));
+  return Number.isFinite(skew) && skew <= MAX_CLOCK_SKEW_SECONDS;
+}
+
+/**
+ * Token pendaftaran hanya disimpan sebagai hash SHA-256
+ */
+export function hashEnrollmentToken(token: string): string {
+  return createHash('sha256').update(token).digest('hex');
+}
+
+export function verifyTerminalSignature(publicKey: string, message: Buffer, signature: string): boolean {
+  try {
+    const raw = Buffer.from(publicKey, 'base64');
+    if (raw.length !== 32) return false;
+
+    const key = createPublicKey({
+      key: Buffer.concat([ED25519_SPKI_PREFIX, raw]),
+      format: 'der',
+      type: 'spki'
+    });
+    return verify(null, message, key, Buffer.from(signature, 'base64'));
+  } catch {
+    return false;
+  }
+}
+
+/**
+ * Periksa header tanda tangan pada request
+ * Request tanpa tanda tangan ditolak; setiap nonce hanya diterima sekali
+ */
+export async function authenticateTerminal(request: NextRequest, body: string): Promise<TerminalAuthResult> {
+  const terminalId = request.headers.get(TERMINAL_ID_HEADER);
+  const timestamp = request.headers.get(TERMINAL_TIMESTAMP_HEADER);
+  const nonce = request.headers.get(TERMINAL_NONCE_HEADER);
+  const signature = request.headers.get(TERMINAL_SIGNATURE_HEADER);
+
+  if (!terminalId || !timestamp || !nonce || !signature) {
+    return { ok: false, error: 'Request harus ditandatangani oleh terminal terdaftar' };
+  }
+  if (!NONCE_PATTERN.test(nonce)) {
+    return { ok: false, error: 'Nonce terminal tidak valid' };
+  }
+
+  if (!isFreshTimestamp(timestamp)) {
+    return { ok: false, error: 'Waktu terminal tidak sinkron dengan server' };
+  }
+
+  const [terminal] = await db
+    .select()
+    .from(posTerminals)
+    .where(eq(posTerminals.id, terminalId))
+    .limit(1);
+
+  if (!terminal || !terminal.isActive) {
+    return { ok: false, error: 'Terminal tidak terdaftar atau nonaktif' };
+  }
+
+  const path = new URL(request.url).pathname;
+  const message = signingMessage(request.method, path, timestamp, nonce, body);
+  if (!verifyTerminalSignature(terminal.publicKey, message, signature)) {
+    return { ok: false, error: 'Tanda tangan terminal tidak valid' };
+  }
+
+  if (!(await consumeNonce(terminal.id, nonce))) {
+    return { ok: false, error: 'Request terminal sudah pernah diterima' };
+  }
+
+  await db
+    .update(posTerminals)
+    .set({ lastSeenAt: new Date() })
+    .where(eq(posTerminals.id, terminal.id));
+
+  return {
+    ok: true,
+    terminal: {
+      terminalId: terminal.id,
+      branchId: terminal.branchId,
+      terminalCode: terminal.terminalCode
+    }
+  };
+}
+
+/**
+ * Catat nonce sekali pakai; false bila nonce sudah pernah dipakai (replay)
+ * Nonce yang lebih tua dari dua kali jendela skew dibuang karena timestamp-nya sudah ditolak
+ */
+async function consumeNonce(terminalId: string, nonce: string): Promise<boolean> {
+  await db
+    .delete(terminalRequestNonces)
+    .where(and(
+      eq(terminalRequestNonces.terminalId, terminalId),
+      lt(terminalRequestNonces.seenAt, new Date(Date.now() - 2 * MAX_CLOCK_SKEW_SECONDS * 1000))
+    ));
+
+  const inserted = await db
+    .insert(terminalRequestNonces)
+    .values({ terminalId, nonce })
+    .onConflictDoNothing()
+    .returning();
+
+  return inserted.length > 0;
+}
@@ -86,6 +86,35 @@ export function useAuth(): AuthContextType {
   return context;
 }
 
+// ======================================================================
+// SESI SHELL DESKTOP
+// ======================================================================
+
+/**
+ * Lampirkan token peran ke window Tauri agar command sensitif diizinkan
+ */
+async function beginShellSession(): Promise<void> {
+  const tauri = (window as any).__TAURI__;
+  if (!tauri) return;
+
+  try {
+    const response = await fetch('/api/auth/shell-session', { method: 'POST' });
+    const data = await response.json();
+    if (data.success && data.data) {
+      await tauri.invoke('begin_shell_session', { token: data.data.token });
+    }
+  } catch (error) {
+    console.error('Shell session error:', error);
+  }
+}
+
+async function endShellSession(): Promise<void> {
+  const tauri = (window as any).__TAURI__;
+  if (!tauri) return;
+
+  await tauri.invoke('end_shell_session').catch(() => undefined);
+}
+
 // ======================================================================
 // PROVIDER COMPONENT
 // ======================================================================
@@ -161,6 +190,7 @@ export function AuthProvider({ children }: { children: React.ReactNode }) {
         };
 
         setUser(authUser);
+        await beginShellSession();
         toast.success('Login berhasil!');
         return true;
       }
@@ -181,6 +211,7 @@ export function AuthProvider({ children }: { children: React.ReactNode }) {
     try {
       setLoading(true);
       
+      await endShellSession();
       await fetch('/api/auth/logout', {
         method: 'POST',
       });
@@ -192,6 +223,7 @@ export function AuthProvider({ children }: { children: React.ReactNode }) {
     } catch (error) {
       console.error('Logout error:', error);
       // Tetap logout meskipun ada error
+      await endShellSession();
       setUser(null);
       router.push('/login');
     } finally {
@@ -209,6 +241,7 @@ export function AuthProvider({ children }: { children: React.ReactNode }) {
       if (!response.ok) {
         if (response.status === 401) {
           // Token expired atau tidak valid
+          await endShellSession();
           setUser(null);
           router.push('/login');
           return;
@@ -225,6 +258,7 @@ export function AuthProvider({ children }: { children: React.ReactNode }) {
         };
 
         setUser(authUser);
+        await beginShellSession();
       }
 
     } catch (error) {
@@ -1,7 +1,7 @@
 // ======================================================================
 // DATABASE SCHEMA
 // Drizzle ORM Schema untuk Aplikasi Kasir
-// Sesuai dengan pos_database.sql
+// Sesuai dengan pos_database.sql ditambah database/migrations
 // ======================================================================
 
 import {
@@ -11,6 +11,7 @@ import {
   text,
   boolean,
   timestamp,
+  date,
   integer,
   decimal,
   pgEnum,
@@ -19,7 +20,7 @@ import {
   foreignKey,
   primaryKey
 } from 'drizzle-orm/pg-core';
-import { relations } from 'drizzle-orm';
+import { relations, sql } from 'drizzle-orm';
 import { createInsertSchema, createSelectSchema } from 'drizzle-zod';
 import { z } from 'zod';
 
@@ -274,13 +275,15 @@ export const productPrices = pgTable('product_prices', {
   effectiveFrom: timestamp('effective_from', { withTimezone: true }).notNull().defaultNow(),
   effectiveTo: timestamp('effective_to', { withTimezone: true }),
   isActive: boolean('is_active').notNull().default(true),
-  createdAt: timestamp('created_at', { withTimezone: true }).notNull().defaultNow()
+  createdAt: timestamp('created_at', { withTimezone: true }).notNull().defaultNow(),
+  updatedAt: timestamp('updated_at', { withTimezone: true }).notNull().defaultNow()
 }, (table) => {
   return {
     productLevelIdx: index('product_prices_product_level_idx').on(table.productId, table.priceLevel),
     branchIdx: index('product_prices_branch_idx').on(table.branchId),
     effectiveIdx: index('product_prices_effective_idx').on(table.effectiveFrom, table.effectiveTo),
-    activeIdx: index('product_prices_active_idx').on(table.isActive)
+    activeIdx: index('product_prices_active_idx').on(table.isActive),
+    updatedAtIdx: index('idx_product_prices_updated_at').on(table.updatedAt)
   };
 });
 
@@ -403,6 +406,7 @@ export const posSessions = pgTable('pos_sessions', {
   cashDifference: integer('cash_difference').notNull().default(0),
   status: sessionStatusEnum('status').notNull().default('open'),
   notes: text('notes'),
+  terminalId: uuid('terminal_id').references(() => posTerminals.id),
   createdAt: timestamp('created_at', { withTimezone: true }).notNull().defaultNow()
 }, (table) => {
   return {
@@ -413,6 +417,89 @@ export const posSessions = pgTable('pos_sessions', {
   };
 });
 
+/**
+ * Tabel POS Terminals - PC kasir terdaftar per cabang
+ */
+export const posTerminals = pgTable('pos_terminals', {
+  id: uuid('id').primaryKey(), // Dibuat oleh terminal saat registrasi
+  branchId: uuid('branch_id').notNull().references(() => branches.id),
+  terminalCode: varchar('terminal_code', { length: 10 }).notNull(),
+  name: varchar('name', { length: 100 }).notNull(),
+  publicKey: text('public_key').notNull(), // Kunci publik Ed25519 (base64)
+  isActive: boolean('is_active').notNull().default(true),
+  registeredAt: timestamp('registered_at', { withTimezone: true }).notNull().defaultNow(),
+  lastSeenAt: timestamp('last_seen_at', { withTimezone: true })
+}, (table) => {
+  return {
+    branchTerminalIdx: uniqueIndex('pos_terminals_branch_terminal_idx').on(table.branchId, table.terminalCode)
+  };
+});
+
+/**
+ * Tabel Terminal Update Failures - Pembaruan aplikasi yang di-rollback oleh terminal
+ */
+export const terminalUpdateFailures = pgTable('terminal_update_failures', {
+  id: uuid('id').primaryKey(), // Dibuat oleh terminal, sekaligus idempotency key laporan
+  terminalId: uuid('terminal_id').references(() => posTerminals.id),
+  branchId: uuid('branch_id').references(() => branches.id),
+  version: varchar('version', { length: 50 }).notNull(),
+  rolledBackTo: varchar('rolled_back_to', { length: 50 }),
+  reason: text('reason').notNull(),
+  failedAt: timestamp('failed_at', { withTimezone: true }).notNull(),
+  reportedAt: timestamp('reported_at', { withTimezone: true }).notNull().defaultNow()
+}, (table) => {
+  return {
+    versionIdx: index('idx_terminal_update_failures_version').on(table.version)
+  };
+});
+
+/**
+ * Tabel Idempotency Keys - Request terminal yang sudah diproses
+ */
+export const idempotencyKeys = pgTable('idempotency_keys', {
+  scope: varchar('scope', { length: 50 }).notNull(), // Endpoint penerima, mis. 'transactions'
+  key: varchar('key', { length: 100 }).notNull(), // Header Idempotency-Key
+  terminalId: uuid('terminal_id').references(() => posTerminals.id),
+  resourceId: varchar('resource_id', { length: 100 }).notNull(), // ID data dari request pertama
+  createdAt: timestamp('created_at', { withTimezone: true }).notNull().defaultNow()
+}, (table) => {
+  return {
+    pk: primaryKey({ columns: [table.scope, table.key] }),
+    createdAtIdx: index('idx_idempotency_keys_created_at').on(table.createdAt)
+  };
+});
+
+/**
+ * Tabel Terminal Enrollment Tokens - Token sekali pakai untuk registrasi PC kasir
+ */
+export const terminalEnrollmentTokens = pgTable('terminal_enrollment_tokens', {
+  tokenHash: varchar('token_hash', { length: 64 }).primaryKey(), // SHA-256 hex, token asli hanya ditampilkan sekali
+  branchId: uuid('branch_id').notNull().references(() => branches.id),
+  createdBy: uuid('created_by').references(() => users.id),
+  expiresAt: timestamp('expires_at', { withTimezone: true }).notNull(),
+  usedAt: timestamp('used_at', { withTimezone: true }),
+  usedByTerminalId: uuid('used_by_terminal_id').references(() => posTerminals.id),
+  createdAt: timestamp('created_at', { withTimezone: true }).notNull().defaultNow()
+}, (table) => {
+  return {
+    branchIdx: index('idx_terminal_enrollment_tokens_branch').on(table.branchId)
+  };
+});
+
+/**
+ * Tabel Terminal Request Nonces - Nonce request bertanda tangan yang sudah dipakai
+ */
+export const terminalRequestNonces = pgTable('terminal_request_nonces', {
+  terminalId: uuid('terminal_id').notNull().references(() => posTerminals.id),
+  nonce: varchar('nonce', { length: 64 }).notNull(), // Header X-Terminal-Nonce
+  seenAt: timestamp('seen_at', { withTimezone: true }).notNull().defaultNow()
+}, (table) => {
+  return {
+    pk: primaryKey({ columns: [table.terminalId, table.nonce] }),
+    seenAtIdx: index('idx_terminal_request_nonces_seen_at').on(table.seenAt)
+  };
+});
+
 /**
  * Tabel POS Orders - Transaksi penjualan
  */
@@ -436,10 +523,19 @@ export const posOrders = pgTable('pos_orders', {
   voidedAt: timestamp('voided_at', { withTimezone: true }),
   voidedBy: uuid('voided_by').references(() => users.id),
   voidReason: text('void_reason'),
+  terminalId: uuid('terminal_id').references(() => posTerminals.id),
+  terminalCode: varchar('terminal_code', { length: 10 }), // Kode terminal untuk nomor struk offline
+  terminalSeq: integer('terminal_seq'), // Nomor urut harian terminal
+  businessDate: date('business_date'), // Tanggal bisnis (WIB) pada nomor struk
+  issuedOffline: boolean('issued_offline').notNull().default(false),
   createdAt: timestamp('created_at', { withTimezone: true }).notNull().defaultNow()
 }, (table) => {
   return {
     orderNoIdx: uniqueIndex('pos_orders_order_no_idx').on(table.orderNo),
+    // Unik hanya untuk nomor terminal, sama dengan indeks parsial di migrasi 0002
+    terminalSeqIdx: uniqueIndex('idx_pos_orders_terminal_seq')
+      .on(table.branchId, table.terminalCode, table.businessDate, table.terminalSeq)
+      .where(sql`issued_offline`),
     branchSessionIdx: index('pos_orders_branch_session_idx').on(table.branchId, table.sessionId),
     customerIdx: index('pos_orders_customer_idx').on(table.customerId),
     cashierIdx: index('pos_orders_cashier_idx').on(table.cashierId),
@@ -101,7 +101,7 @@ export class ExcelExporter {
         type: 'array'
       });
       
-      this.downloadFile(excelBuffer, this.config.filename + '.xlsx', 'application/vnd.openxmlformats-officedocument.spreadsheetml.sheet');
+      await this.downloadFile(excelBuffer, this.config.filename + '.xlsx', 'application/vnd.openxmlformats-officedocument.spreadsheetml.sheet');
       
     } catch (error) {
       console.error('Error exporting to Excel:', error);
@@ -134,7 +134,7 @@ export class ExcelExporter {
       
       // Convert to blob and download
       const blob = new Blob([csvContent], { type: 'text/csv;charset=utf-8;' });
-      this.downloadBlob(blob, this.config.filename + '.csv');
+      await this.downloadBlob(blob, this.config.filename + '.csv');
       
     } catch (error) {
       console.error('Error exporting to CSV:', error);
@@ -153,7 +153,7 @@ export class ExcelExporter {
       
       // Convert to blob and download
       const blob = new Blob([jsonContent], { type: 'application/json;charset=utf-8;' });
-      this.downloadBlob(blob, this.config.filename + '.json');
+      await this.downloadBlob(blob, this.config.filename + '.json');
       
     } catch (error) {
       console.error('Error exporting to JSON:', error);
@@ -234,13 +234,23 @@ export class ExcelExporter {
   }
 
   // Download file as array buffer
-  private downloadFile(buffer: ArrayBuffer, filename: string, mimeType: string): void {
+  private async downloadFile(buffer: ArrayBuffer, filename: string, mimeType: string): Promise<void> {
     const blob = new Blob([buffer], { type: mimeType });
-    this.downloadBlob(blob, filename);
+    await this.downloadBlob(blob, filename);
   }
 
-  // Download blob as file
-  private downloadBlob(blob: Blob, filename: string): void {
+  // Download blob as file; di desktop disimpan shell ke Documents/POS/Exports
+  private async downloadBlob(blob: Blob, filename: string): Promise<void> {
+    const tauri = (window as any).__TAURI__;
+    if (tauri) {
+      const contents = await blobToBase64(blob);
+      const result = await tauri.invoke('export_file', { fileName: filename, contents });
+      if (!result.success) {
+        throw new Error(result.error || 'Gagal menyimpan file ekspor');
+      }
+      return;
+    }
+
     const url = window.URL.createObjectURL(blob);
     const link = document.createElement('a');
     link.href = url;
@@ -309,41 +319,7 @@ export class ExcelImporter {
       
       reader.onload = (e) => {
         try {
-          const csvText = e.target?.result as string;
-          const lines = csvText.split('\n').filter(line => line.trim());
-          
-          // Parse CSV lines
-          const csvData = lines.map(line => {
-            const values: string[] = [];
-            let current = '';
-            let inQuotes = false;
-            
-            for (let i = 0; i < line.length; i++) {
-              const char = line[i];
-              
-              if (char === '"') {
-                if (inQuotes && line[i + 1] === '"') {
-                  current += '"';
-                  i++; // Skip next quote
-                } else {
-                  inQuotes = !inQuotes;
-                }
-              } else if (char === ',' && !inQuotes) {
-                values.push(current.trim());
-                current = '';
-              } else {
-                current += char;
-              }
-            }
-            
-            values.push(current.trim());
-            return values;
-          });
-          
-          // Process imported data
-          const result = this.processImportedData(csvData);
-          resolve(result);
-          
+          resolve(this.parseCSV(e.target?.result as string));
         } catch (error) {
           reject(new Error('Gagal membaca file CSV: ' + (error as Error).message));
         }
@@ -357,6 +333,63 @@ export class ExcelImporter {
     });
   }
 
+  // Import CSV lewat shell desktop: dari Documents/POS/Imports atau dialog file native.
+  // Mengembalikan null bila dialog dibatalkan
+  async importFromShellCSV(fileName?: string): Promise<ImportResult | null> {
+    const tauri = (window as any).__TAURI__;
+    if (!tauri) {
+      throw new Error('Impor lewat shell hanya tersedia di aplikasi desktop');
+    }
+
+    const result = await tauri.invoke('import_csv', { fileName: fileName ?? null });
+    if (!result.success) {
+      throw new Error(result.error || 'Gagal membaca file CSV');
+    }
+    if (!result.data) return null;
+
+    try {
+      return this.parseCSV(result.data.contents);
+    } catch (error) {
+      throw new Error('Gagal membaca file CSV: ' + (error as Error).message);
+    }
+  }
+
+  // Parse CSV text
+  private parseCSV(csvText: string): ImportResult {
+    const lines = csvText.split('\n').filter(line => line.trim());
+    
+    // Parse CSV lines
+    const csvData = lines.map(line => {
+      const values: string[] = [];
+      let current = '';
+      let inQuotes = false;
+      
+      for (let i = 0; i < line.length; i++) {
+        const char = line[i];
+        
+        if (char === '"') {
+          if (inQuotes && line[i + 1] === '"') {
+            current += '"';
+            i++; // Skip next quote
+          } else {
+            inQuotes = !inQuotes;
+          }
+        } else if (char === ',' && !inQuotes) {
+          values.push(current.trim());
+          current = '';
+        } else {
+          current += char;
+        }
+      }
+      
+      values.push(current.trim());
+      return values;
+    });
+    
+    // Process imported data
+    return this.processImportedData(csvData);
+  }
+
   // Process imported data
   private processImportedData(rawData: any[][]): ImportResult {
     const result: ImportResult = {
@@ -844,4 +877,14 @@ export function createDownloadLink(data: any, filename: string, mimeType: string
 // Cleanup download link
 export function cleanupDownloadLink(url: string): void {
   window.URL.revokeObjectURL(url);
-}
\ No newline at end of file
+}
+
+// Encode blob for IPC (tanpa prefix data URL)
+function blobToBase64(blob: Blob): Promise<string> {
+  return new Promise((resolve, reject) => {
+    const reader = new FileReader();
+    reader.onload = () => resolve(String(reader.result).split(',')[1] || '');
+    reader.onerror = () => reject(new Error('Gagal membaca data ekspor'));
+    reader.readAsDataURL(blob);
+  });
+}
@@ -0,0 +1,65 @@
+-- Nomor struk per terminal untuk transaksi offline (CABANG-Txx-YYYYMMDD-NNNNN)
+ALTER TABLE pos_orders
+    ADD COLUMN IF NOT EXISTS terminal_code VARCHAR(10), -- Kode terminal untuk nomor struk offline (T01, T02, ...)
+    ADD COLUMN IF NOT EXISTS terminal_seq INTEGER, -- Nomor urut harian terminal
+    ADD COLUMN IF NOT EXISTS business_date DATE, -- Tanggal bisnis (WIB) pada nomor struk
+    ADD COLUMN IF NOT EXISTS issued_offline BOOLEAN NOT NULL DEFAULT FALSE; -- Nomor dibuat oleh terminal, bukan server
+
+ALTER TABLE pos_orders DROP CONSTRAINT IF EXISTS pos_orders_offline_no_check;
+ALTER TABLE pos_orders ADD CONSTRAINT pos_orders_offline_no_check CHECK (
+    (issued_offline = FALSE AND terminal_code IS NULL AND terminal_seq IS NULL)
+    OR (
+        issued_offline = TRUE
+        AND terminal_code ~ '^T[0-9]{2,4}$'
+        AND terminal_seq BETWEEN 1 AND 99999
+        AND business_date IS NOT NULL
+        AND order_no = split_part(order_no, '-', 1) || '-' || terminal_code || '-'
+            || to_char(business_date, 'YYYYMMDD') || '-' || lpad(terminal_seq::TEXT, 5, '0')
+    )
+);
+
+COMMENT ON COLUMN pos_orders.issued_offline IS 'Nomor struk dari terminal (CABANG-Txx-YYYYMMDD-NNNNN), disimpan apa adanya';
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_pos_orders_terminal_seq ON pos_orders (branch_id, terminal_code, business_date, terminal_seq)
+    WHERE issued_offline;
+
+-- Fungsi untuk generate nomor transaksi
+CREATE OR REPLACE FUNCTION generate_order_no(branch_code TEXT, prefix TEXT)
+RETURNS TEXT AS $$
+DECLARE
+    seq_num INTEGER;
+    date_part TEXT;
+BEGIN
+    -- Prefix T<angka> dicadangkan untuk nomor struk terminal offline
+    IF prefix ~ '^T[0-9]+$' THEN
+        RAISE EXCEPTION 'Prefix % dicadangkan untuk nomor terminal', prefix;
+    END IF;
+
+    seq_num := nextval('pos_order_seq');
+    date_part := to_char(NOW(), 'YYYYMMDD');
+    RETURN branch_code || '-' || prefix || '-' || date_part || '-' || lpad(seq_num::TEXT, 6, '0');
+END;
+$$ LANGUAGE plpgsql;
+
+COMMENT ON FUNCTION generate_order_no IS 'Generate nomor transaksi: CABANG-PREFIX-YYYYMMDD-XXXXXX';
+
+-- Nomor struk terminal yang hilang (dicetak offline tapi belum/tidak masuk server)
+CREATE OR REPLACE VIEW v_offline_receipt_gaps AS
+SELECT o.branch_id, o.terminal_code, o.business_date, missing.seq AS missing_seq
+FROM (
+    SELECT branch_id, terminal_code, business_date, MAX(terminal_seq) AS last_seq
+    FROM pos_orders
+    WHERE issued_offline
+    GROUP BY branch_id, terminal_code, business_date
+) o
+CROSS JOIN LATERAL generate_series(1, o.last_seq) AS missing(seq)
+WHERE NOT EXISTS (
+    SELECT 1 FROM pos_orders p
+    WHERE p.issued_offline
+      AND p.branch_id = o.branch_id
+      AND p.terminal_code = o.terminal_code
+      AND p.business_date = o.business_date
+      AND p.terminal_seq = missing.seq
+);
+
+COMMENT ON VIEW v_offline_receipt_gaps IS 'Celah nomor struk terminal untuk rekonsiliasi';
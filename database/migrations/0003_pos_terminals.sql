@@ -0,0 +1,25 @@
+-- Tabel Terminal Kasir
+CREATE TABLE IF NOT EXISTS pos_terminals (
+    id UUID PRIMARY KEY, -- Dibuat oleh terminal saat registrasi
+    branch_id UUID NOT NULL REFERENCES branches(id),
+    terminal_code VARCHAR(10) NOT NULL CHECK (terminal_code ~ '^T[0-9]{2,4}$'),
+    name VARCHAR(100) NOT NULL,
+    public_key TEXT NOT NULL, -- Kunci publik Ed25519 (base64)
+    is_active BOOLEAN DEFAULT TRUE,
+    registered_at TIMESTAMPTZ DEFAULT NOW(),
+    last_seen_at TIMESTAMPTZ,
+    UNIQUE (branch_id, terminal_code)
+);
+
+COMMENT ON TABLE pos_terminals IS 'PC kasir terdaftar per cabang';
+COMMENT ON COLUMN pos_terminals.public_key IS 'Kunci publik untuk verifikasi request bertanda tangan dari terminal';
+
+ALTER TABLE pos_sessions ADD COLUMN IF NOT EXISTS terminal_id UUID; -- Terminal tempat sesi dibuka
+ALTER TABLE pos_sessions DROP CONSTRAINT IF EXISTS pos_sessions_terminal_fk;
+ALTER TABLE pos_sessions ADD CONSTRAINT pos_sessions_terminal_fk
+    FOREIGN KEY (terminal_id) REFERENCES pos_terminals(id);
+
+ALTER TABLE pos_orders ADD COLUMN IF NOT EXISTS terminal_id UUID; -- Terminal asal transaksi
+ALTER TABLE pos_orders DROP CONSTRAINT IF EXISTS pos_orders_terminal_fk;
+ALTER TABLE pos_orders ADD CONSTRAINT pos_orders_terminal_fk
+    FOREIGN KEY (terminal_id) REFERENCES pos_terminals(id);
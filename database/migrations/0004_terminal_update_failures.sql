@@ -0,0 +1,15 @@
+-- Pembaruan aplikasi yang gagal berjalan dan dikembalikan (rollback) oleh terminal
+CREATE TABLE terminal_update_failures (
+    id UUID PRIMARY KEY, -- Dibuat oleh terminal, sekaligus idempotency key laporan
+    terminal_id UUID REFERENCES pos_terminals(id),
+    branch_id UUID REFERENCES branches(id),
+    version VARCHAR(50) NOT NULL, -- Versi yang gagal
+    rolled_back_to VARCHAR(50), -- Versi yang dipasang kembali; NULL bila installer lama tidak tersimpan
+    reason TEXT NOT NULL,
+    failed_at TIMESTAMPTZ NOT NULL,
+    reported_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX idx_terminal_update_failures_version ON terminal_update_failures(version);
+
+COMMENT ON TABLE terminal_update_failures IS 'Laporan rollback pembaruan dari PC kasir, untuk menghentikan rollout versi bermasalah';
@@ -0,0 +1,13 @@
+-- Idempotency-Key dari request terminal, agar kiriman ulang tidak tercatat ganda
+CREATE TABLE idempotency_keys (
+    scope VARCHAR(50) NOT NULL, -- Endpoint penerima, mis. 'transactions'
+    key VARCHAR(100) NOT NULL, -- Header Idempotency-Key
+    terminal_id UUID REFERENCES pos_terminals(id),
+    resource_id VARCHAR(100) NOT NULL, -- ID data yang dibuat oleh request pertama
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    PRIMARY KEY (scope, key)
+);
+
+CREATE INDEX idx_idempotency_keys_created_at ON idempotency_keys(created_at);
+
+COMMENT ON TABLE idempotency_keys IS 'Idempotency-Key yang sudah diproses, tahan restart server';
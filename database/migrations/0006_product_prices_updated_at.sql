@@ -0,0 +1,8 @@
+-- Perubahan harga yang sudah ada (harga, periode, status aktif) ikut terkirim di delta katalog kasir
+ALTER TABLE product_prices ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+UPDATE product_prices SET updated_at = COALESCE(created_at, updated_at);
+
+CREATE TRIGGER update_product_prices_updated_at BEFORE UPDATE ON product_prices
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE INDEX idx_product_prices_updated_at ON product_prices(updated_at);
@@ -0,0 +1,12 @@
+-- Nonce request bertanda tangan terminal, agar request yang disadap tidak bisa dikirim ulang
+-- selama jendela selisih jam (300 detik)
+CREATE TABLE terminal_request_nonces (
+    terminal_id UUID NOT NULL REFERENCES pos_terminals(id),
+    nonce VARCHAR(64) NOT NULL, -- Header X-Terminal-Nonce
+    seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (terminal_id, nonce)
+);
+
+CREATE INDEX idx_terminal_request_nonces_seen_at ON terminal_request_nonces(seen_at);
+
+COMMENT ON TABLE terminal_request_nonces IS 'Nonce request terminal yang sudah dipakai; baris lebih tua dari jendela skew boleh dihapus';
@@ -0,0 +1,14 @@
+-- Token pendaftaran sekali pakai yang dibuat admin untuk meregistrasi PC kasir ke cabang
+CREATE TABLE terminal_enrollment_tokens (
+    token_hash VARCHAR(64) PRIMARY KEY, -- SHA-256 hex dari token; token aslinya hanya ditampilkan sekali
+    branch_id UUID NOT NULL REFERENCES branches(id),
+    created_by UUID REFERENCES users(id),
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    used_by_terminal_id UUID REFERENCES pos_terminals(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_terminal_enrollment_tokens_branch ON terminal_enrollment_tokens(branch_id);
+
+COMMENT ON TABLE terminal_enrollment_tokens IS 'Token sekali pakai untuk POST /api/terminals/register';